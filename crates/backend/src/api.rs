@@ -1,8 +1,11 @@
 use common::cookie_manager::CookieManager;
+use common::endpoints::ApiEndpoints;
 use common::gen_cp::CTokenGenerator;
 use common::http_utils::request_get;
 use common::login::QrCodeLoginStatus;
+use common::redact;
 use common::ticket::*;
+use common::ticket_error::{ErrorClass, TicketApiError};
 use rand::{Rng, thread_rng};
 use reqwest::Client;
 use serde_json;
@@ -26,8 +29,10 @@ pub async fn get_countdown(
         sale_begin_sec /= 1000;
     }
 
-    let url = "https://api.bilibili.com/x/click-interface/click/now";
-    let response = cookie_manager.get(url).await;
+    let url = cookie_manager
+        .endpoints
+        .api_url("/x/click-interface/click/now");
+    let response = cookie_manager.get(&url).await;
     let now_sec = match response.send().await {
         Ok(data) => {
             let text = data.text().await.unwrap_or_default();
@@ -72,12 +77,38 @@ pub async fn get_countdown(
     Ok(countdown_sec as f64)
 }
 
+// 测量服务器时间与本地时间的偏差（毫秒，服务器 - 本地），以请求往返的中点作为本地时间；
+// 接口只精确到秒，按该秒的中点计算，结果有约半秒误差
+pub async fn get_clock_offset(cookie_manager: Arc<CookieManager>) -> Result<i64, String> {
+    let url = cookie_manager
+        .endpoints
+        .api_url("/x/click-interface/click/now");
+    let sent_at = chrono::Utc::now().timestamp_millis();
+    let response = cookie_manager
+        .get(&url)
+        .await
+        .send()
+        .await
+        .map_err(|e| format!("获取网络时间失败: {}", e))?;
+    let received_at = chrono::Utc::now().timestamp_millis();
+    let json_data: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("解析网络时间失败: {}", e))?;
+    let now_sec = json_data["data"]["now"]
+        .as_i64()
+        .filter(|now| *now > 0)
+        .ok_or_else(|| format!("网络时间无效: {}", json_data))?;
+    let offset = now_sec * 1000 + 500 - (sent_at + received_at) / 2;
+    log::debug!("时钟偏差：{}ms（往返 {}ms）", offset, received_at - sent_at);
+    Ok(offset)
+}
+
 pub async fn get_buyer_info(
     cookie_manager: Arc<CookieManager>,
 ) -> Result<BuyerInfoResponse, String> {
-    let req = cookie_manager
-        .get("https://show.bilibili.com/api/ticket/buyer/list")
-        .await;
+    let url = cookie_manager.endpoints.show_url("/api/ticket/buyer/list");
+    let req = cookie_manager.get(&url).await;
     let response = req.send().await;
     match response {
         Ok(resp) => {
@@ -113,15 +144,14 @@ pub async fn get_project(
     cookie_manager: Arc<CookieManager>,
     project_id: &str,
 ) -> Result<InfoResponse, String> {
-    let req = cookie_manager
-        .get(
-            format!(
-                "https://show.bilibili.com/api/ticket/project/getV2?id={}",
-                project_id
-            )
-            .as_str(),
-        )
-        .await;
+    let url = format!(
+        "{}?id={}",
+        cookie_manager
+            .endpoints
+            .show_url("/api/ticket/project/getV2"),
+        project_id
+    );
+    let req = cookie_manager.get(&url).await;
     let response = req.send().await;
     match response {
         Ok(resp) => {
@@ -170,7 +200,8 @@ pub async fn poll_qrcode_login(qrcode_key: &str, user_agent: Option<&str>) -> Qr
     let response = match request_get(
         &client,
         &format!(
-            "https://passport.bilibili.com/x/passport-login/web/qrcode/poll?qrcode_key={}",
+            "{}?qrcode_key={}",
+            ApiEndpoints::current().passport_url("/x/passport-login/web/qrcode/poll"),
             qrcode_key
         ),
         None,
@@ -261,7 +292,10 @@ pub async fn get_ticket_token(
     };
     log::debug!("获取票token参数：{:?}", params);
     let url = format!(
-        "https://show.bilibili.com/api/ticket/order/prepare?project_id={}",
+        "{}?project_id={}",
+        cookie_manager
+            .endpoints
+            .show_url("/api/ticket/order/prepare"),
         project_id
     );
     let response = cookie_manager.post(&url).await.json(&params).send().await;
@@ -289,7 +323,9 @@ pub async fn get_ticket_token(
                                 }
                                 return Ok((token.to_string(), String::new()));
                             }
-                            -401 | 401 => {
+                            code if TicketApiError::from_code(code).class()
+                                == ErrorClass::NeedsCaptcha =>
+                            {
                                 log::info!("需要进行人机验证");
                                 let mid = json["data"]["ga_data"]["riskParams"]["mid"]
                                     .as_str()
@@ -332,8 +368,8 @@ pub async fn get_ticket_token(
                             }
                             _ => {
                                 log::error!(
-                                    "获取token失败，未知错误码：{}，错误信息：{}，请提issue修复此问题",
-                                    code,
+                                    "获取token失败：{}，错误信息：{}",
+                                    TicketApiError::from_code(code),
                                     msg
                                 );
                                 log::error!("{:?}", json);
@@ -356,7 +392,7 @@ pub async fn get_ticket_token(
                     Err(e) => {
                         log::error!("解析票务token响应失败: {}", e);
                         return Err(TokenRiskParam {
-                            code: 999 as i32,
+                            code: TicketApiError::RequestFailed.code() as i32,
 
                             message: e.to_string(),
 
@@ -377,7 +413,7 @@ pub async fn get_ticket_token(
                     resp.status()
                 );
                 return Err(TokenRiskParam {
-                    code: 999 as i32,
+                    code: TicketApiError::RequestFailed.code() as i32,
 
                     message: resp.status().to_string(),
 
@@ -395,7 +431,7 @@ pub async fn get_ticket_token(
         Err(e) => {
             log::error!("获取票token失败，错误信息：{}", e);
             return Err(TokenRiskParam {
-                code: 999 as i32,
+                code: TicketApiError::RequestFailed.code() as i32,
 
                 message: e.to_string(),
 
@@ -412,40 +448,79 @@ pub async fn get_ticket_token(
     }
 }
 
+// 确认订单失败的原因，接口返回错误时带上错误码
+#[derive(Debug)]
+pub struct ConfirmError {
+    pub code: Option<i64>,
+    pub message: String,
+}
+
+impl ConfirmError {
+    fn request(message: String) -> Self {
+        Self {
+            code: None,
+            message,
+        }
+    }
+
+    pub fn error(&self) -> Option<TicketApiError> {
+        self.code.map(TicketApiError::from_code)
+    }
+}
+
+impl std::fmt::Display for ConfirmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.code {
+            Some(code) => write!(f, "{}（错误码 {}）", self.message, code),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
 pub async fn confirm_ticket_order(
     cookie_manager: Arc<CookieManager>,
     project_id: &str,
     token: &str,
-) -> Result<ConfirmTicketResult, String> {
+) -> Result<ConfirmTicketResult, ConfirmError> {
     let url = format!(
-        "https://show.bilibili.com/api/ticket/order/confirmInfo?token={}&voucher=&project_id={}&requestSource=neul-next",
-        token, project_id
+        "{}?token={}&voucher=&project_id={}&requestSource=neul-next",
+        cookie_manager
+            .endpoints
+            .show_url("/api/ticket/order/confirmInfo"),
+        token,
+        project_id
     );
     let response = cookie_manager
         .get(&url)
         .await
         .send()
         .await
-        .map_err(|e| format!("请求失败: {}", e))?;
+        .map_err(|e| ConfirmError::request(format!("请求失败: {}", e)))?;
 
     if !response.status().is_success() {
-        return Err(format!("请求失败: {}", response.status()));
+        return Err(ConfirmError::request(format!(
+            "请求失败: {}",
+            response.status()
+        )));
     }
     let text = response
         .text()
         .await
-        .map_err(|e| format!("获取响应文本失败: {}", e))?;
+        .map_err(|e| ConfirmError::request(format!("获取响应文本失败: {}", e)))?;
     log::debug!("确认订单响应：{}", text);
-    let json: serde_json::Value =
-        serde_json::from_str(&text).map_err(|e| format!("解析响应文本失败: {}", e))?;
+    let json: serde_json::Value = serde_json::from_str(&text)
+        .map_err(|e| ConfirmError::request(format!("解析响应文本失败: {}", e)))?;
     if json["errno"] != 0 {
-        return Err(format!(
-            "确认订单失败: {}",
-            json["msg"].as_str().unwrap_or("未知错误")
-        ));
+        return Err(ConfirmError {
+            code: json["errno"].as_i64(),
+            message: format!(
+                "确认订单失败: {}",
+                json["msg"].as_str().unwrap_or("未知错误")
+            ),
+        });
     }
     let confirm_result = serde_json::from_value(json["data"].clone())
-        .map_err(|e| format!("解析确认订单结果失败: {}", e))?;
+        .map_err(|e| ConfirmError::request(format!("解析确认订单结果失败: {}", e)))?;
     Ok(confirm_result)
 }
 
@@ -463,17 +538,14 @@ pub async fn create_order(
     need_retry: bool,
     fast_mode: bool,
     screen_size: Option<(u32, u32)>, // 可选参数：(宽度,高度)
-) -> Result<Value, i32> {
+) -> Result<Value, TicketApiError> {
+    let base_url = cookie_manager
+        .endpoints
+        .show_url("/api/ticket/order/createV2");
     let url = if !is_hot {
-        format!(
-            "https://show.bilibili.com/api/ticket/order/createV2?project_id={}",
-            project_id
-        )
+        format!("{}?project_id={}", base_url, project_id)
     } else {
-        format!(
-            "https://show.bilibili.com/api/ticket/order/createV2?project_id={}&ptoken={}",
-            project_id, ptoken
-        )
+        format!("{}?project_id={}&ptoken={}", base_url, project_id, ptoken)
     };
 
     // 选择适当的位置类型
@@ -516,9 +588,11 @@ pub async fn create_order(
 
     let ticket_id = match biliticket.select_ticket_id.clone() {
         Some(id) => id,
-        None => return Err(999),
+        None => return Err(TicketApiError::InvalidParams),
     };
-    let ticket_id_int = ticket_id.parse::<i64>().map_err(|_| 999)?;
+    let ticket_id_int = ticket_id
+        .parse::<i64>()
+        .map_err(|_| TicketApiError::InvalidParams)?;
 
     let data = match biliticket.id_bind {
         0 => {
@@ -582,7 +656,7 @@ pub async fn create_order(
         }
         _ => {
             log::error!("购票人信息错误，id_bind: {}", biliticket.id_bind);
-            return Err(919); // 错误的购票人信息
+            return Err(TicketApiError::BindTypeInvalid);
         }
     };
 
@@ -595,20 +669,20 @@ pub async fn create_order(
         .await
         .map_err(|e| {
             log::error!("请求失败: {}", e);
-            412
+            TicketApiError::RequestFailed
         })?;
     if response.status() != 200 {
         log::error!("请求失败: {}", response.status());
-        return Err(response.status().as_u16() as i32);
+        return Err(TicketApiError::from_code(response.status().as_u16() as i64));
     };
     let text = response.text().await.map_err(|e| {
         log::error!("获取响应文本失败: {}", e);
-        412
+        TicketApiError::RequestFailed
     })?;
     log::info!("{}", text);
     let value: Value = serde_json::from_str(&text).map_err(|e| {
         log::error!("解析响应文本失败: {}", e);
-        412
+        TicketApiError::RequestFailed
     })?;
 
     let errno_value = value.get("errno").and_then(|v| v.as_i64()).unwrap_or(-1);
@@ -616,11 +690,11 @@ pub async fn create_order(
 
     // 只要有一个错误码不是0，就认为有错误
     if errno_value != 0 || (errno_value == -1 && code_value != 0) {
-        return Err(if errno_value != -1 {
-            errno_value as i32
+        return Err(TicketApiError::from_code(if errno_value != -1 {
+            errno_value
         } else {
-            code_value as i32
-        });
+            code_value
+        }));
     }
 
     Ok(value)
@@ -637,13 +711,18 @@ pub async fn check_fake_ticket(
         .unwrap_or_default()
         .as_secs() as u32;
     let mut url = format!(
-        "https://show.bilibili.com/api/ticket/order/createstatus?project_id={}&token={}&timestamp={}",
-        project_id, pay_token, timestamp
+        "{}?project_id={}&token={}&timestamp={}",
+        cookie_manager
+            .endpoints
+            .show_url("/api/ticket/order/createstatus"),
+        project_id,
+        pay_token,
+        timestamp
     );
     if order_id != 0 {
         url = format!("{}&orderId={}", url, order_id);
     }
-    log::debug!("check_fake_ticket_url: {}", url);
+    log::debug!("check_fake_ticket_url: {}", redact::redact(&url));
     let response = cookie_manager
         .get(&url)
         .await
@@ -1,4 +1,5 @@
 pub mod api;
 pub mod dungeon;
+pub mod scheduler;
 pub mod show_orderlist;
 pub mod taskmanager;
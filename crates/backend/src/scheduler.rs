@@ -0,0 +1,223 @@
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::api::{get_buyer_info, get_clock_offset, get_project};
+use crate::taskmanager::TaskControlHandle;
+use common::account::Account;
+use common::captcha::LocalCaptcha;
+use common::config::BtrConfig;
+use common::cookie_manager::CookieManager;
+use common::schedule::{self, JobStatus, PreflightStep, ScheduledJob};
+use common::taskmanager::TaskRequest;
+use common::ticket::TicketInfo;
+use tokio::runtime::Runtime;
+
+// 读取当前配置（账号、抢票参数、推送设置），到点时用它重建抢票请求
+pub type ConfigSource = Arc<dyn Fn() -> Result<BtrConfig, String> + Send + Sync>;
+
+// 定时任务调度：每秒检查一次 schedule.json（文件未变时用缓存），执行到期的预检步骤，到触发时间后提交抢票任务
+pub struct Scheduler {
+    control: TaskControlHandle,
+    config: ConfigSource,
+    local_captcha: LocalCaptcha,
+    runtime: Runtime,
+}
+
+// 预检步骤得到的数据，写回定时任务
+enum Preflight {
+    Project(Box<TicketInfo>),
+    Account(String),
+    Clock(i64),
+}
+
+impl Scheduler {
+    pub fn new(
+        control: TaskControlHandle,
+        config: ConfigSource,
+        local_captcha: LocalCaptcha,
+    ) -> Self {
+        Self {
+            control,
+            config,
+            local_captcha,
+            runtime: Runtime::new().unwrap(),
+        }
+    }
+
+    // 在后台线程中运行，随程序退出
+    pub fn start(self) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            loop {
+                self.tick(chrono::Local::now().timestamp_millis());
+                thread::sleep(Duration::from_secs(1));
+            }
+        })
+    }
+
+    pub fn tick(&self, now_ms: i64) {
+        let jobs = match schedule::load() {
+            Ok(jobs) => jobs,
+            Err(e) => {
+                log::warn!("{}", e);
+                return;
+            }
+        };
+        for job in jobs {
+            if job.due_preflight(now_ms).is_empty() && !job.is_due(now_ms) {
+                continue;
+            }
+            let id = job.id.clone();
+            if let Err(e) = self.run_job(job, now_ms) {
+                log::warn!("定时任务 {} 执行失败: {}", id, e);
+            }
+        }
+    }
+
+    fn run_job(&self, mut job: ScheduledJob, now_ms: i64) -> Result<(), String> {
+        let account = self.account(job.grab.uid);
+
+        for step in job.due_preflight(now_ms) {
+            let outcome = match &account {
+                Ok((_, account)) => {
+                    let cookie_manager = account.cookie_manager.clone().unwrap();
+                    self.runtime
+                        .block_on(run_preflight(step, &job, cookie_manager))
+                }
+                Err(e) => Err(e.clone()),
+            };
+            match &outcome {
+                Ok(_) => log::info!("定时任务 {} {}完成", job.id, step.label()),
+                Err(e) => log::warn!("定时任务 {} {}失败: {}", job.id, step.label(), e),
+            }
+            job = schedule::modify(&job.id, |job| {
+                apply_preflight(job, step, outcome);
+                Ok(())
+            })?;
+        }
+
+        if !job.is_due(now_ms) {
+            return Ok(());
+        }
+        // 在跨进程的写锁内重新确认并标记为已提交，避免下一次检查或另一个进程重复提交
+        let job = schedule::modify(&job.id, |job| {
+            if !job.is_due(now_ms) {
+                return Err("定时任务已被修改".to_string());
+            }
+            job.status = JobStatus::Submitted;
+            Ok(())
+        })?;
+
+        let submitted = account.and_then(|(config, account)| {
+            let mut grab = job.grab.clone();
+            grab.task_id = format!("{}-{}", grab.uid, now_ms);
+            let request = grab.to_request(
+                &account,
+                &config.custom_config,
+                &config.push_config,
+                self.local_captcha.clone(),
+            )?;
+            self.control.submit(TaskRequest::GrabTicketRequest(request))
+        });
+        match &submitted {
+            Ok(task_id) => log::info!("定时任务 {} 已提交抢票任务 {}", job.id, task_id),
+            Err(e) => log::error!("定时任务 {} 提交抢票任务失败: {}", job.id, e),
+        }
+        schedule::modify(&job.id, |job| {
+            match submitted {
+                Ok(task_id) => {
+                    job.message = format!("已提交抢票任务 {}", task_id);
+                    job.task_id = Some(task_id);
+                }
+                Err(e) => {
+                    job.status = JobStatus::Failed;
+                    job.message = format!("提交抢票任务失败: {}", e);
+                }
+            }
+            Ok(())
+        })
+        .map(|_| ())
+    }
+
+    // 从最新配置中找到任务所属账号，并确保其 cookie 管理器已初始化
+    fn account(&self, uid: i64) -> Result<(BtrConfig, Account), String> {
+        let config = (self.config)()?;
+        let mut account = config
+            .accounts
+            .iter()
+            .find(|acc| acc.uid == uid)
+            .cloned()
+            .ok_or_else(|| format!("账号 {} 已不存在", uid))?;
+        account.ensure_client();
+        Ok((config, account))
+    }
+}
+
+async fn run_preflight(
+    step: PreflightStep,
+    job: &ScheduledJob,
+    cookie_manager: Arc<CookieManager>,
+) -> Result<Preflight, String> {
+    match step {
+        PreflightStep::RefreshProject => {
+            let project = get_project(cookie_manager, &job.grab.project_id).await?;
+            if project.errno != 0 {
+                return Err(format!("获取项目详情失败: {}", project.msg));
+            }
+            Ok(Preflight::Project(Box::new(project.data)))
+        }
+        PreflightStep::VerifyAccount => {
+            let buyers = get_buyer_info(cookie_manager).await?;
+            if buyers.errno != 0 || buyers.code != 0 {
+                return Err(format!("登录可能已失效: {}{}", buyers.msg, buyers.message));
+            }
+            let missing: Vec<String> = job
+                .grab
+                .buyer_info
+                .iter()
+                .filter(|b| !buyers.data.list.iter().any(|item| item.id == b.id))
+                .map(|b| b.name.clone())
+                .collect();
+            if !missing.is_empty() {
+                return Err(format!("购票人 {} 已不在账号中", missing.join("、")));
+            }
+            Ok(Preflight::Account(format!(
+                "登录有效，账号共 {} 位购票人",
+                buyers.data.list.len()
+            )))
+        }
+        PreflightStep::SyncClock => get_clock_offset(cookie_manager).await.map(Preflight::Clock),
+    }
+}
+
+fn apply_preflight(
+    job: &mut ScheduledJob,
+    step: PreflightStep,
+    outcome: Result<Preflight, String>,
+) {
+    let result = outcome.map(|data| match data {
+        Preflight::Project(info) => {
+            let sale_begin = chrono::DateTime::from_timestamp(info.sale_begin, 0)
+                .map(|dt| {
+                    dt.with_timezone(&chrono::Local)
+                        .format("%Y-%m-%d %H:%M:%S")
+                        .to_string()
+                })
+                .unwrap_or_else(|| info.sale_begin.to_string());
+            let message = format!("{}，开售时间 {}", info.name, sale_begin);
+            job.grab.is_hot = info.hot_project;
+            job.grab.project_info = Some(*info);
+            message
+        }
+        Preflight::Account(message) => message,
+        Preflight::Clock(offset) => {
+            job.clock_offset_ms = offset;
+            format!(
+                "服务器时间比本地{} {}ms",
+                if offset >= 0 { "快" } else { "慢" },
+                offset.abs()
+            )
+        }
+    });
+    job.record_preflight(step, result);
+}
@@ -4,12 +4,13 @@ use serde_json;
 use std::sync::Arc;
 
 pub async fn get_orderlist(cookie_manager: Arc<CookieManager>) -> Result<OrderResponse, String> {
-    match cookie_manager
-        .get("https://show.bilibili.com/api/ticket/ordercenter/ticketList?page=0&page_size=10")
-        .await
-        .send()
-        .await
-    {
+    let url = format!(
+        "{}?page=0&page_size=10",
+        cookie_manager
+            .endpoints
+            .show_url("/api/ticket/ordercenter/ticketList")
+    );
+    match cookie_manager.get(&url).await.send().await {
         Ok(resp) => {
             if resp.status().is_success() {
                 match tokio::task::block_in_place(|| {
@@ -0,0 +1,340 @@
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+
+use crate::api::{get_countdown, get_project};
+use crate::taskmanager::grab_ticket_handler::{GrabContext, enter_phase, sleep_or_cancelled};
+use common::log_record::LogPhase;
+use common::taskmanager::GrabTicketRequest;
+use common::ticket::{TicketInfo, TicketPreference};
+
+// 抢票模式由“何时开始”和“抢哪些票种”组合而成：
+// 定时 = 开售时开始 + 指定票种，直接 = 立即开始 + 指定票种，捡漏 = 立即开始 + 轮询第一个可售票种；
+// 设置了候选票种时，定时和直接模式的指定票种换成按顺序尝试的候选列表
+
+// 一个候选的场次和票种
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TicketTarget {
+    pub screen_id: String,
+    pub ticket_id: String,
+    pub label: String, //日志中显示的场次和票种名称
+}
+
+impl TicketTarget {
+    pub fn new(screen_id: impl Into<String>, ticket_id: impl Into<String>) -> Self {
+        let screen_id = screen_id.into();
+        let ticket_id = ticket_id.into();
+        Self {
+            label: format!("场次{} 票种{}", screen_id, ticket_id),
+            screen_id,
+            ticket_id,
+        }
+    }
+}
+
+// 一轮选出的票种，按顺序尝试；刷新项目得到的热门标记和实名类型会覆盖请求中的值
+#[derive(Clone, Debug, Default)]
+pub struct Selection {
+    pub targets: Vec<TicketTarget>,
+    pub is_hot: Option<bool>,
+    pub id_bind: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub enum SelectError {
+    Retry(String), //暂时失败，按刷新策略重试
+    Stop(String),  //无法继续，结束任务
+}
+
+// 何时开始抢票。等待结束返回 true，任务取消或无法开始时返回 false
+pub trait StartCondition: Send + Sync {
+    fn wait<'a>(&'a self, ctx: &'a GrabContext) -> BoxFuture<'a, bool>;
+}
+
+// 每一轮尝试哪些票种
+pub trait TicketSelector: Send + Sync {
+    fn select<'a>(
+        &'a mut self,
+        ctx: &'a GrabContext,
+    ) -> BoxFuture<'a, Result<Selection, SelectError>>;
+
+    // 轮询式选择：一轮都没抢到时按刷新间隔重新选择，某票种下单次数用尽就换下一轮。
+    // 非轮询式选择按顺序尝试列表中的票种，确认订单失败即结束任务
+    fn polling(&self) -> bool {
+        false
+    }
+
+    // 当前票种售罄/停售时是否换下一个票种，只有候选列表这样做
+    fn sold_out_fallback(&self) -> bool {
+        false
+    }
+}
+
+pub struct GrabPlan {
+    pub start: Box<dyn StartCondition>,
+    pub selector: Box<dyn TicketSelector>,
+}
+
+impl GrabPlan {
+    pub fn new(
+        start: impl StartCondition + 'static,
+        selector: impl TicketSelector + 'static,
+    ) -> Self {
+        Self {
+            start: Box::new(start),
+            selector: Box::new(selector),
+        }
+    }
+
+    // 按请求中的抢票模式组合：0 定时，1 直接，2 捡漏。
+    // 定时和直接模式设置了候选票种时按候选列表依次尝试
+    pub fn for_request(req: &GrabTicketRequest) -> Option<Self> {
+        let selector = || -> Box<dyn TicketSelector> {
+            if req.preferences.is_empty() {
+                Box::new(FixedTicket::from_request(req))
+            } else {
+                Box::new(PreferenceList::new(req.preferences.clone()))
+            }
+        };
+        match req.grab_mode {
+            0 => Some(Self {
+                start: Box::new(StartAt::new(req.biliticket.project_info.clone())),
+                selector: selector(),
+            }),
+            1 => Some(Self {
+                start: Box::new(StartNow),
+                selector: selector(),
+            }),
+            2 => Some(Self::new(
+                StartNow,
+                FirstAvailable::new(req.skip_words.clone()),
+            )),
+            _ => None,
+        }
+    }
+}
+
+// 立即开始
+pub struct StartNow;
+
+impl StartCondition for StartNow {
+    fn wait<'a>(&'a self, ctx: &'a GrabContext) -> BoxFuture<'a, bool> {
+        Box::pin(async move { !ctx.cancel.is_cancelled() })
+    }
+}
+
+// 等到项目开售时间，没有项目详情时先获取
+pub struct StartAt {
+    project_info: Option<TicketInfo>,
+}
+
+impl StartAt {
+    pub fn new(project_info: Option<TicketInfo>) -> Self {
+        Self { project_info }
+    }
+}
+
+impl StartCondition for StartAt {
+    fn wait<'a>(&'a self, ctx: &'a GrabContext) -> BoxFuture<'a, bool> {
+        Box::pin(async move {
+            let project_info = match &self.project_info {
+                Some(info) => Some(info.clone()),
+                None => {
+                    log::info!("后台项目信息缺失，正在自动获取以确定开始时间...");
+                    match get_project(ctx.cookie_manager.clone(), &ctx.project_id).await {
+                        Ok(resp) => Some(resp.data),
+                        Err(e) => {
+                            log::error!("自动获取项目详情失败: {}", e);
+                            None
+                        }
+                    }
+                }
+            };
+
+            enter_phase(LogPhase::Countdown);
+            let mut countdown = match get_countdown(ctx.cookie_manager.clone(), project_info).await
+            {
+                Ok(countdown) => countdown,
+                Err(e) => {
+                    log::error!("获取倒计时失败: {}", e);
+                    ctx.send_failure(format!("获取倒计时失败: {}", e)).await;
+                    return false;
+                }
+            };
+
+            if countdown > 0.0 {
+                log::info!("距离抢票时间还有{}秒", countdown);
+                while countdown > 20.0 {
+                    countdown -= 15.0;
+                    if sleep_or_cancelled(&ctx.cancel, Duration::from_secs(15)).await {
+                        log::info!("任务已取消，停止等待开售");
+                        return false;
+                    }
+                    log::info!("距离抢票时间还有{}秒", countdown);
+                }
+                while countdown > 1.3 {
+                    log::info!("距离抢票时间还有{}秒", countdown);
+                    countdown -= 1.0;
+                    if sleep_or_cancelled(&ctx.cancel, Duration::from_secs(1)).await {
+                        log::info!("任务已取消，停止等待开售");
+                        return false;
+                    }
+                }
+                if sleep_or_cancelled(&ctx.cancel, Duration::from_secs_f32(0.8)).await {
+                    log::info!("任务已取消，停止等待开售");
+                    return false;
+                }
+            }
+            log::info!("开始抢票！");
+            true
+        })
+    }
+}
+
+// 请求中指定的场次和票种
+pub struct FixedTicket {
+    target: TicketTarget,
+}
+
+impl FixedTicket {
+    pub fn from_request(req: &GrabTicketRequest) -> Self {
+        Self {
+            target: TicketTarget::new(req.screen_id.clone(), req.ticket_id.clone()),
+        }
+    }
+}
+
+impl TicketSelector for FixedTicket {
+    fn select<'a>(
+        &'a mut self,
+        _ctx: &'a GrabContext,
+    ) -> BoxFuture<'a, Result<Selection, SelectError>> {
+        Box::pin(async move {
+            Ok(Selection {
+                targets: vec![self.target.clone()],
+                ..Selection::default()
+            })
+        })
+    }
+}
+
+// 按偏好顺序依次尝试的候选票种。每轮刷新项目，跳过不可售或超出票价上限的票种，
+// 当前票种售罄时换下一个，一轮都没抢到时重新检查
+pub struct PreferenceList {
+    preferences: Vec<TicketPreference>,
+}
+
+impl PreferenceList {
+    pub fn new(preferences: Vec<TicketPreference>) -> Self {
+        Self { preferences }
+    }
+}
+
+impl TicketSelector for PreferenceList {
+    fn select<'a>(
+        &'a mut self,
+        ctx: &'a GrabContext,
+    ) -> BoxFuture<'a, Result<Selection, SelectError>> {
+        Box::pin(async move {
+            if self.preferences.is_empty() {
+                return Err(SelectError::Stop("没有设置候选票种".to_string()));
+            }
+            let project = get_project(ctx.cookie_manager.clone(), &ctx.project_id)
+                .await
+                .map_err(|e| SelectError::Retry(format!("获取项目数据失败，原因：{}", e)))?;
+            let targets = TicketPreference::rank(&self.preferences, &project.data)
+                .into_iter()
+                .map(|(screen, ticket)| TicketTarget {
+                    screen_id: screen.id.to_string(),
+                    ticket_id: ticket.id.to_string(),
+                    label: format!(
+                        "{} {}(¥{:.2})",
+                        screen.name,
+                        ticket.desc,
+                        ticket.price as f64 / 100.0
+                    ),
+                })
+                .collect();
+            Ok(Selection {
+                targets,
+                is_hot: Some(project.data.hot_project),
+                id_bind: None,
+            })
+        })
+    }
+
+    fn polling(&self) -> bool {
+        true
+    }
+    fn sold_out_fallback(&self) -> bool {
+        true
+    }
+}
+
+// 每轮刷新项目，依次尝试所有可售且不含过滤关键词的票种
+pub struct FirstAvailable {
+    skip_words: Option<Vec<String>>,
+}
+
+impl FirstAvailable {
+    pub fn new(skip_words: Option<Vec<String>>) -> Self {
+        Self { skip_words }
+    }
+
+    fn skipped(&self, text: &str) -> bool {
+        let text = text.to_lowercase();
+        self.skip_words
+            .as_ref()
+            .is_some_and(|words| words.iter().any(|word| text.contains(&word.to_lowercase())))
+    }
+}
+
+impl TicketSelector for FirstAvailable {
+    fn select<'a>(
+        &'a mut self,
+        ctx: &'a GrabContext,
+    ) -> BoxFuture<'a, Result<Selection, SelectError>> {
+        Box::pin(async move {
+            let project = get_project(ctx.cookie_manager.clone(), &ctx.project_id)
+                .await
+                .map_err(|e| SelectError::Retry(format!("获取项目数据失败，原因：{}", e)))?;
+            if ![1, 2].contains(&project.data.id_bind) {
+                return Err(SelectError::Stop("暂不支持抢非实名票捡漏模式".to_string()));
+            }
+
+            let mut targets = Vec::new();
+            for screen in project.data.screen_list {
+                if !screen.clickable {
+                    continue;
+                }
+                for ticket in screen.ticket_list {
+                    if !ticket.clickable {
+                        continue;
+                    }
+                    if self.skipped(&ticket.screen_name) {
+                        log::info!("跳过包含过滤关键词的场次: {}", ticket.screen_name);
+                        continue;
+                    }
+                    if self.skipped(&ticket.desc) {
+                        log::info!("跳过包含过滤关键词的票种: {}", ticket.desc);
+                        continue;
+                    }
+                    targets.push(TicketTarget {
+                        screen_id: screen.id.to_string(),
+                        ticket_id: ticket.id.to_string(),
+                        label: format!("{} {}", ticket.screen_name, ticket.desc),
+                    });
+                }
+            }
+            Ok(Selection {
+                targets,
+                is_hot: Some(project.data.hot_project),
+                id_bind: Some(project.data.id_bind),
+            })
+        })
+    }
+
+    fn polling(&self) -> bool {
+        true
+    }
+}
@@ -1,33 +1,61 @@
 use std::{
     sync::{Arc, Mutex},
-    time::{SystemTime, UNIX_EPOCH},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
-use crate::api::{
-    check_fake_ticket, confirm_ticket_order, create_order, get_countdown, get_ticket_token,
-};
+use crate::api::{check_fake_ticket, confirm_ticket_order, create_order, get_ticket_token};
 
 use common::{
-    captcha::handle_risk_verification,
+    captcha::{LocalCaptcha, handle_risk_verification},
     config::CustomConfig,
     cookie_manager::CookieManager,
     gen_cp::CTokenGenerator,
+    history::{self, HistoryEvent, HistoryRecord},
+    log_record::{self, LogPhase},
+    metrics,
+    push::{Notification, OrderSummary},
+    redact,
+    retry::{RetryStage, RetryState},
+    task_journal,
     taskmanager::{GrabTicketRequest, GrabTicketResult, TaskResult},
-    ticket::{BuyerInfo, CheckFakeResult, ConfirmTicketResult},
+    ticket::{BuyerInfo, CheckFakeResult, ConfirmTicketResult, TokenRiskParam},
+    ticket_error::{ErrorClass, TicketApiError},
 };
 use rand::{Rng, SeedableRng, rngs::StdRng};
-use serde_json::json;
+use serde_json::{Value, json};
 use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
 
-use crate::api::get_project;
+use crate::taskmanager::grab_mode::{GrabPlan, SelectError};
+use crate::taskmanager::status;
 
+// 取消任务后不再发起新的尝试，已发出的下单请求会等到支付检测结束再退出
 pub async fn handle_grab_ticket_request(
     grab_ticket_req: GrabTicketRequest,
     result_tx: mpsc::Sender<TaskResult>,
+    cancel: CancellationToken,
+) {
+    let plan = GrabPlan::for_request(&grab_ticket_req);
+    run_grab_task(grab_ticket_req, plan, result_tx, cancel).await;
+}
+
+// 按自定义的开始条件和票种选择运行抢票任务
+pub async fn handle_grab_ticket_plan(
+    grab_ticket_req: GrabTicketRequest,
+    plan: GrabPlan,
+    result_tx: mpsc::Sender<TaskResult>,
+    cancel: CancellationToken,
+) {
+    run_grab_task(grab_ticket_req, Some(plan), result_tx, cancel).await;
+}
+
+async fn run_grab_task(
+    grab_ticket_req: GrabTicketRequest,
+    plan: Option<GrabPlan>,
+    result_tx: mpsc::Sender<TaskResult>,
+    cancel: CancellationToken,
 ) {
     let project_id = grab_ticket_req.project_id.clone();
-    let screen_id = grab_ticket_req.screen_id.clone();
-    let ticket_id = grab_ticket_req.ticket_id.clone();
     let buyer_info = grab_ticket_req.buyer_info.clone();
     let cookie_manager = grab_ticket_req.cookie_manager.clone();
     let task_id = grab_ticket_req.task_id.clone();
@@ -38,9 +66,7 @@ pub async fn handle_grab_ticket_request(
     let local_captcha = grab_ticket_req.local_captcha.clone();
     let count = grab_ticket_req.count.clone();
     let project_info = grab_ticket_req.biliticket.project_info.clone();
-    let skip_words = grab_ticket_req.skip_words.clone();
     let mut rng = StdRng::from_entropy();
-    let is_hot = grab_ticket_req.is_hot.clone();
     let cpdd = if project_info.is_some() {
         Arc::new(Mutex::new(CTokenGenerator::new(
             project_info.clone().unwrap().sale_begin as i64,
@@ -59,965 +85,836 @@ pub async fn handle_grab_ticket_request(
     };
     log::debug!("开始分析抢票任务：{}", task_id);
 
-    match mode {
-        0 => {
-            timed_grab_ticket_mode(
-                cookie_manager,
-                cpdd,
-                project_id,
-                screen_id,
-                ticket_id,
-                count.try_into().unwrap(),
-                is_hot,
-                project_info,
-                task_id,
-                uid,
-                &result_tx,
-                grab_ticket_req,
-                buyer_info,
-                custon_config,
-                csrf,
-                Some(local_captcha),
-            )
-            .await;
-        }
-        1 => {
-            direct_grab_ticket_mode(
-                cookie_manager,
-                cpdd,
-                project_id,
-                screen_id,
-                ticket_id,
-                count.try_into().unwrap(),
-                is_hot,
-                task_id,
-                uid,
-                &result_tx,
-                grab_ticket_req,
-                buyer_info,
-                custon_config,
-                csrf,
-                Some(local_captcha),
-            )
-            .await;
-        }
-        2 => {
-            leak_grab_ticket_mode(
-                cookie_manager,
-                cpdd,
-                project_id,
-                screen_id,
-                ticket_id,
-                count.try_into().unwrap(),
-                is_hot,
-                skip_words,
-                rng,
-                task_id,
-                uid,
-                &result_tx,
-                grab_ticket_req,
-                buyer_info,
-                custon_config,
-                csrf,
-                Some(local_captcha),
-            )
-            .await;
-        }
-        _ => {
-            log::error!("未知模式");
+    let mut start_record = HistoryRecord::new(&task_id, uid, &project_id, HistoryEvent::TaskStart);
+    start_record.message = format!("抢票模式: {}", mode);
+    history::append(&start_record);
+    let (history_tx, history_forwarder) = forward_with_history(
+        result_tx,
+        task_id.clone(),
+        uid,
+        project_id.clone(),
+        cancel.clone(),
+    );
+
+    let ctx = GrabContext {
+        cookie_manager,
+        cpdd,
+        project_id,
+        count,
+        task_id,
+        uid,
+        result_tx: history_tx,
+        cancel,
+        buyer_info,
+        config: custon_config,
+        csrf,
+        local_captcha,
+    };
+    match plan {
+        Some(plan) => run_plan(&ctx, grab_ticket_req, plan).await,
+        None => {
+            log::error!("未知的抢票模式: {}", mode);
+            ctx.send_failure(format!("未知的抢票模式: {}", mode)).await;
         }
     }
+
+    // 结果通道全部关闭后转发任务才会结束
+    drop(ctx);
+    let _ = history_forwarder.await;
 }
 
-async fn timed_grab_ticket_mode(
-    cookie_manager: Arc<CookieManager>,
-    cpdd: Arc<Mutex<CTokenGenerator>>,
-    project_id: String,
-    screen_id: String,
-    ticket_id: String,
-    count: i16,
-    is_hot: bool,
-    project_info: Option<common::ticket::TicketInfo>,
+// 抢票结果先写入历史再转发；任务结束时没有结果也补一条结束记录和一条失败（或已取消）的抢票结果
+fn forward_with_history(
+    result_tx: mpsc::Sender<TaskResult>,
     task_id: String,
     uid: i64,
-    result_tx: &mpsc::Sender<TaskResult>,
-    grab_ticket_req: GrabTicketRequest,
-    buyer_info: Vec<BuyerInfo>,
-    custon_config: CustomConfig,
-    csrf: String,
-    local_captcha: Option<common::captcha::LocalCaptcha>,
-) {
-    log::debug!("定时抢票模式");
-
-    // 如果没有项目详情，尝试自动获取
-    let project_info = if project_info.is_none() {
-        log::info!("后台项目信息缺失，正在自动获取以确定开始时间...");
-        match get_project(cookie_manager.clone(), &project_id).await {
-            Ok(resp) => Some(resp.data),
-            Err(e) => {
-                log::error!("自动获取项目详情失败: {}", e);
-                None
+    project_id: String,
+    cancel: CancellationToken,
+) -> (mpsc::Sender<TaskResult>, tokio::task::JoinHandle<()>) {
+    let (history_tx, mut history_rx) = mpsc::channel::<TaskResult>(100);
+    let forwarder = tokio::spawn(async move {
+        let mut finished = false;
+        while let Some(result) = history_rx.recv().await {
+            if let TaskResult::GrabTicketResult(grab_result) = &result {
+                let mut record =
+                    HistoryRecord::new(&task_id, uid, &project_id, HistoryEvent::TaskEnd);
+                record.success = grab_result.success;
+                record.message = grab_result.message.clone();
+                record.order_id = grab_result.order_id.clone();
+                history::append(&record);
+                metrics::record_grab_result(grab_result.success);
+                finished = true;
+            }
+            if result_tx.send(result).await.is_err() {
+                log::debug!("抢票结果接收端已关闭");
             }
         }
-    } else {
-        project_info
-    };
-
-    let mut countdown = match get_countdown(cookie_manager.clone(), project_info).await {
-        Ok(countdown) => countdown,
-        Err(e) => {
-            log::error!("获取倒计时失败: {}", e);
+        // 任务已结束，不再需要重启后恢复
+        task_journal::remove(&task_id);
+        if finished {
             return;
         }
-    };
-
-    if countdown > 0.0 {
-        log::info!("距离抢票时间还有{}秒", countdown);
-        while countdown > 20.0 {
-            countdown -= 15.0;
-            tokio::time::sleep(tokio::time::Duration::from_secs(15)).await;
-            log::info!("距离抢票时间还有{}秒", countdown);
+        // 没有发送过结果时补发一条失败结果，等待结果的调用方（如 cli grab）才不会一直阻塞
+        let cancelled = cancel.is_cancelled();
+        let mut record = HistoryRecord::new(&task_id, uid, &project_id, HistoryEvent::TaskEnd);
+        record.success = false;
+        record.message = if cancelled {
+            "任务已取消".to_string()
+        } else {
+            "任务已退出，未返回抢票结果".to_string()
+        };
+        history::append(&record);
+        if !cancelled {
+            metrics::record_grab_result(false);
         }
-        while countdown > 1.3 {
-            log::info!("距离抢票时间还有{}秒", countdown);
-            countdown -= 1.0;
-            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+        let task_result = TaskResult::GrabTicketResult(GrabTicketResult {
+            task_id: task_id.clone(),
+            uid,
+            success: false,
+            message: record.message,
+            order_id: None,
+            pay_token: None,
+            pay_result: None,
+            confirm_result: None,
+            cancelled,
+            error_code: None,
+            error_class: None,
+        });
+        if result_tx.send(task_result).await.is_err() {
+            log::debug!("抢票结果接收端已关闭");
         }
-        tokio::time::sleep(tokio::time::Duration::from_secs_f32(0.8)).await;
+    });
+    (history_tx, forwarder)
+}
+
+// 任务已取消时不再发起新的尝试
+fn should_stop(cancel: &CancellationToken) -> bool {
+    if cancel.is_cancelled() {
+        log::info!("任务已取消，停止抢票");
+        return true;
     }
+    false
+}
 
-    log::info!("开始抢票！");
-    let mut token_retry_count = 0;
-    let max_token_retry = custon_config.max_token_retry as i8;
+// 等待指定时间，期间任务被取消则提前返回 true
+pub(crate) async fn sleep_or_cancelled(cancel: &CancellationToken, duration: Duration) -> bool {
+    tokio::select! {
+        _ = cancel.cancelled() => true,
+        _ = tokio::time::sleep(duration) => false,
+    }
+}
 
-    //抢票主循环
-    loop {
-        let token_result = get_ticket_token(
-            cookie_manager.clone(),
-            cpdd.clone(),
-            &project_id,
-            &screen_id,
-            &ticket_id,
-            count,
-            is_hot,
-        )
-        .await;
-        match token_result {
-            Ok((token, ptoken)) => {
-                log::info!("获取抢票token成功！:{} ptoken:{}", token, ptoken);
-                let mut confirm_retry_count = 0;
-                let max_confirm_retry = custon_config.max_confirm_retry as i8;
-
-                loop {
-                    let (success, _) = handle_grab_ticket(
-                        cookie_manager.clone(),
-                        cpdd.clone(),
-                        &project_id,
-                        &token,
-                        &ptoken,
-                        is_hot,
-                        &task_id,
-                        uid,
-                        &result_tx,
-                        &grab_ticket_req,
-                        &buyer_info,
-                    )
-                    .await;
-                    if success {
-                        log::info!("抢票流程结束，退出定时抢票模式");
-                        return;
-                    }
+// 进入新阶段：同时更新日志字段与任务状态
+pub(crate) fn enter_phase(phase: LogPhase) {
+    log_record::set_phase(phase);
+    status::report_phase(phase.into());
+}
 
-                    confirm_retry_count += 1;
-                    if confirm_retry_count >= max_confirm_retry {
-                        log::error!("确认订单失败，已达最大重试次数");
-                        let task_result = TaskResult::GrabTicketResult(GrabTicketResult {
-                            task_id: task_id.clone(),
-                            uid,
-                            success: false,
-                            message: "确认订单失败，已达最大重试次数".to_string(),
-                            order_id: None,
-                            pay_token: None,
-                            pay_result: None,
-                            confirm_result: None,
-                        });
-                        let _ = result_tx.send(task_result).await;
-                        return;
-                    }
-                }
-            }
-            Err(risk_param) => {
-                if risk_param.code == -401 || risk_param.code == 401 {
-                    log::warn!("需要验证码，开始处理验证码...");
-                    match handle_risk_verification(
-                        cookie_manager.clone(),
-                        risk_param,
-                        &custon_config,
-                        &csrf,
-                        local_captcha.clone().expect("REASON"),
-                    )
-                    .await
-                    {
-                        Ok(()) => log::info!("验证码处理成功！"),
-                        Err(e) => {
-                            log::error!("验证码处理失败: {}", e);
-                            token_retry_count += 1;
-                            if token_retry_count >= max_token_retry {
-                                let task_result = TaskResult::GrabTicketResult(GrabTicketResult {
-                                    task_id: task_id.clone(),
-                                    uid,
-                                    success: false,
-                                    message: format!("验证码处理失败，已达最大重试次数: {}", e),
-                                    order_id: None,
-                                    pay_token: None,
-                                    pay_result: None,
-                                    confirm_result: None,
-                                });
-                                let _ = result_tx.send(task_result).await;
-                                return;
-                            }
-                        }
-                    }
-                } else {
-                    match risk_param.code {
-                        100080 | 100082 => {
-                            log::error!("抢票失败，场次/项目/日期选择有误，请重新提交任务");
-                        }
-                        100039 => {
-                            log::error!("抢票失败，该场次已停售，请重新提交任务");
-                        }
-                        _ => {
-                            log::error!("抢票失败，未知错误，请重新提交任务");
-                        }
-                    }
-                    token_retry_count += 1;
-                    if token_retry_count >= max_token_retry {
-                        let task_result = TaskResult::GrabTicketResult(GrabTicketResult {
-                            task_id: task_id.clone(),
-                            uid,
-                            success: false,
-                            message: format!(
-                                "获取token失败，错误代码: {}，错误信息：{}",
-                                risk_param.code, risk_param.message
-                            ),
-                            order_id: None,
-                            pay_token: None,
-                            pay_result: None,
-                            confirm_result: None,
-                        });
-                        let _ = result_tx.send(task_result).await;
-                        return;
-                    }
-                }
-                tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-            }
-        }
+fn record_attempt(
+    task_id: &str,
+    uid: i64,
+    project_id: &str,
+    event: HistoryEvent,
+    code: Option<i64>,
+    started: Instant,
+    message: &str,
+) {
+    log_record::set_code(code);
+    let record = HistoryRecord::attempt(task_id, uid, project_id, event, code, started, message);
+    metrics::observe_request(
+        endpoint_name(event),
+        code,
+        Duration::from_millis(record.latency_ms.unwrap_or(0)),
+    );
+    history::append(&record);
+}
+
+// 指标中使用的接口名
+fn endpoint_name(event: HistoryEvent) -> &'static str {
+    match event {
+        HistoryEvent::Token => "order/prepare",
+        HistoryEvent::Confirm => "order/confirmInfo",
+        HistoryEvent::Create => "order/createV2",
+        HistoryEvent::CreateStatus => "order/createstatus",
+        HistoryEvent::TaskStart | HistoryEvent::TaskEnd => "task",
     }
 }
-async fn direct_grab_ticket_mode(
-    cookie_manager: Arc<CookieManager>,
+
+fn record_token_attempt(
+    task_id: &str,
+    uid: i64,
+    project_id: &str,
+    token_result: &Result<(String, String), TokenRiskParam>,
+    started: Instant,
+) {
+    let (code, message) = match token_result {
+        Ok(_) => (0, ""),
+        Err(risk_param) => (risk_param.code as i64, risk_param.message.as_str()),
+    };
+    record_attempt(
+        task_id,
+        uid,
+        project_id,
+        HistoryEvent::Token,
+        Some(code),
+        started,
+        message,
+    );
+}
+
+// 一个抢票任务在各阶段共用的参数
+pub struct GrabContext {
+    pub(crate) cookie_manager: Arc<CookieManager>,
     cpdd: Arc<Mutex<CTokenGenerator>>,
-    project_id: String,
-    screen_id: String,
-    ticket_id: String,
+    pub(crate) project_id: String,
     count: i16,
-    is_hot: bool,
     task_id: String,
     uid: i64,
-    result_tx: &mpsc::Sender<TaskResult>,
-    grab_ticket_req: GrabTicketRequest,
+    result_tx: mpsc::Sender<TaskResult>,
+    pub(crate) cancel: CancellationToken,
     buyer_info: Vec<BuyerInfo>,
-    custon_config: CustomConfig,
+    pub(crate) config: CustomConfig,
     csrf: String,
-    local_captcha: Option<common::captcha::LocalCaptcha>,
-) {
-    log::debug!("直接抢票模式");
-    let mut token_retry_count = 0;
-    let max_token_retry = custon_config.max_token_retry as i8;
+    local_captcha: LocalCaptcha,
+}
 
-    //抢票主循环
-    loop {
-        let token_result = get_ticket_token(
-            cookie_manager.clone(),
-            cpdd.clone(),
-            &project_id,
-            &screen_id,
-            &ticket_id,
-            count,
-            is_hot,
-        )
-        .await;
-        match token_result {
-            Ok((token, ptoken)) => {
-                log::info!("获取抢票token成功！:{} ptoken:{}", token, ptoken);
-                let mut confirm_retry_count = 0;
-                let max_confirm_retry = custon_config.max_confirm_retry as i8;
-
-                loop {
-                    let (success, _) = handle_grab_ticket(
-                        cookie_manager.clone(),
-                        cpdd.clone(),
-                        &project_id,
-                        &token,
-                        &ptoken,
-                        is_hot,
-                        &task_id,
-                        uid,
-                        &result_tx,
-                        &grab_ticket_req,
-                        &buyer_info,
-                    )
-                    .await;
-                    if success {
-                        log::info!("抢票流程结束，退出直接抢票模式");
-                        return;
-                    }
+impl GrabContext {
+    pub(crate) async fn send_failure(&self, message: String) {
+        self.send_failure_with(message, None).await;
+    }
 
-                    confirm_retry_count += 1;
-                    if confirm_retry_count >= max_confirm_retry {
-                        log::error!("确认订单失败，已达最大重试次数");
-                        let task_result = TaskResult::GrabTicketResult(GrabTicketResult {
-                            task_id: task_id.clone(),
-                            uid,
-                            success: false,
-                            message: "确认订单失败，已达最大重试次数".to_string(),
-                            order_id: None,
-                            pay_token: None,
-                            pay_result: None,
-                            confirm_result: None,
-                        });
-                        let _ = result_tx.send(task_result).await;
-                        return;
-                    }
-                }
+    // 因接口错误结束时带上错误码和分类
+    async fn send_error(&self, message: String, error: TicketApiError, class: ErrorClass) {
+        self.send_failure_with(message, Some((error, class))).await;
+    }
+
+    async fn send_failure_with(
+        &self,
+        message: String,
+        error: Option<(TicketApiError, ErrorClass)>,
+    ) {
+        let task_result = TaskResult::GrabTicketResult(GrabTicketResult {
+            task_id: self.task_id.clone(),
+            uid: self.uid,
+            success: false,
+            message,
+            order_id: None,
+            pay_token: None,
+            pay_result: None,
+            confirm_result: None,
+            cancelled: false,
+            error_code: error.map(|(e, _)| e.code()),
+            error_class: error.map(|(_, class)| class),
+        });
+        let _ = self.result_tx.send(task_result).await;
+    }
+}
+
+// 抢票流程的状态：获取 token -> 确认订单 -> 下单
+enum Stage {
+    Token,
+    Confirm {
+        token: String,
+        ptoken: String,
+    },
+    Create {
+        token: String,
+        ptoken: String,
+        confirm_result: Box<ConfirmTicketResult>,
+    },
+}
+
+enum PipelineOutcome {
+    Finished,              //已发送抢票结果（成功或无法重试的错误）
+    Cancelled,             //任务被取消
+    Exhausted(RetryStage), //该阶段重试次数或时限用尽，尚未发送结果
+    SoldOut,               //当前票种售罄，尚未发送结果
+}
+
+// 先等待开始条件，再按选择器给出的票种依次执行抢票流程
+async fn run_plan(ctx: &GrabContext, mut grab_ticket_req: GrabTicketRequest, mut plan: GrabPlan) {
+    if !plan.start.wait(ctx).await {
+        return;
+    }
+    let polling = plan.selector.polling();
+    let sold_out_fallback = plan.selector.sold_out_fallback();
+    let mut refresh_retry = RetryState::new(ctx.config.retry_policy(RetryStage::Refresh));
+
+    loop {
+        if should_stop(&ctx.cancel) {
+            return;
+        }
+        let selection = match plan.selector.select(ctx).await {
+            Ok(selection) => {
+                refresh_retry.reset();
+                selection
             }
-            Err(risk_param) => {
-                if risk_param.code == -401 || risk_param.code == 401 {
-                    log::warn!("需要验证码，开始处理验证码...");
-                    match handle_risk_verification(
-                        cookie_manager.clone(),
-                        risk_param,
-                        &custon_config,
-                        &csrf,
-                        local_captcha.clone().expect("REASON"),
-                    )
-                    .await
-                    {
-                        Ok(()) => log::info!("验证码处理成功！"),
-                        Err(e) => {
-                            log::error!("验证码处理失败: {}", e);
-                            token_retry_count += 1;
-                            if token_retry_count >= max_token_retry {
-                                let task_result = TaskResult::GrabTicketResult(GrabTicketResult {
-                                    task_id: task_id.clone(),
-                                    uid,
-                                    success: false,
-                                    message: format!("验证码处理失败，已达最大重试次数: {}", e),
-                                    order_id: None,
-                                    pay_token: None,
-                                    pay_result: None,
-                                    confirm_result: None,
-                                });
-                                let _ = result_tx.send(task_result).await;
-                                return;
-                            }
+            Err(SelectError::Retry(e)) => {
+                log::error!("{}", e);
+                match refresh_retry.failed(None) {
+                    Some(delay) => {
+                        if sleep_or_cancelled(&ctx.cancel, delay).await {
+                            return;
                         }
+                        continue;
                     }
-                } else {
-                    match risk_param.code {
-                        100080 | 100082 => {
-                            log::error!("抢票失败，场次/项目/日期选择有误，请重新提交任务")
-                        }
-                        100039 => log::error!("抢票失败，该场次已停售，请重新提交任务"),
-                        _ => log::error!("抢票失败，未知错误，请重新提交任务"),
-                    }
-                    token_retry_count += 1;
-                    if token_retry_count >= max_token_retry {
-                        let task_result = TaskResult::GrabTicketResult(GrabTicketResult {
-                            task_id: task_id.clone(),
-                            uid,
-                            success: false,
-                            message: format!(
-                                "获取token失败，错误代码: {}，错误信息：{}",
-                                risk_param.code, risk_param.message
-                            ),
-                            order_id: None,
-                            pay_token: None,
-                            pay_result: None,
-                            confirm_result: None,
-                        });
-                        let _ = result_tx.send(task_result).await;
+                    None => {
+                        ctx.send_failure(format!("{}，已达最大重试次数", e)).await;
                         return;
                     }
                 }
-                tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
             }
+            Err(SelectError::Stop(e)) => {
+                log::error!("{}", e);
+                ctx.send_failure(e).await;
+                return;
+            }
+        };
+        if !polling && selection.targets.is_empty() {
+            ctx.send_failure("没有可抢的票种".to_string()).await;
+            return;
         }
-    }
-}
-async fn leak_grab_ticket_mode(
-    cookie_manager: Arc<CookieManager>,
-    cpdd: Arc<Mutex<CTokenGenerator>>,
-    project_id: String,
-    _screen_id: String,
-    _ticket_id: String,
-    count: i16,
-    _is_hot: bool,
-    skip_words: Option<Vec<String>>,
-    _rng: StdRng,
-    task_id: String,
-    uid: i64,
-    result_tx: &mpsc::Sender<TaskResult>,
-    mut grab_ticket_req: GrabTicketRequest,
-    buyer_info: Vec<BuyerInfo>,
-    custon_config: CustomConfig,
-    csrf: String,
-    local_captcha: Option<common::captcha::LocalCaptcha>,
-) {
-    log::debug!("捡漏模式");
-    let mut token_retry_count = 0;
-    let max_token_retry = custon_config.max_token_retry as i8;
-    let mut is_hot;
-
-    'main_loop: loop {
-        let project_data =
-            match get_project(cookie_manager.clone(), project_id.clone().as_str()).await {
-                Ok(data) => data,
-                Err(e) => {
-                    log::error!("获取项目数据失败，原因：{}", e);
-                    tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-                    continue;
-                }
-            };
-        is_hot = project_data.data.hot_project;
-
-        if ![1, 2].contains(&project_data.data.id_bind) {
-            log::error!("暂不支持抢非实名票捡漏模式");
-            break 'main_loop;
+        let is_hot = selection.is_hot.unwrap_or(grab_ticket_req.is_hot);
+        if let Some(id_bind) = selection.id_bind {
+            grab_ticket_req.biliticket.id_bind = id_bind;
         }
-        grab_ticket_req.biliticket.id_bind = project_data.data.id_bind as usize;
 
-        'screen_loop: for screen_data in project_data.data.screen_list {
-            if !screen_data.clickable {
-                continue;
+        for target in selection.targets {
+            if should_stop(&ctx.cancel) {
+                return;
             }
-
-            grab_ticket_req.screen_id = screen_data.id.to_string();
-            grab_ticket_req.biliticket.screen_id = screen_data.id.to_string();
-            log::info!("当前项目有可抢票场次，开始抢票！");
-
-            for ticket_data in screen_data.ticket_list {
-                if !ticket_data.clickable {
-                    continue;
+            if polling {
+                log::info!("当前{}票种可售，开始抢票！", target.label);
+            }
+            grab_ticket_req.screen_id = target.screen_id.clone();
+            grab_ticket_req.biliticket.screen_id = target.screen_id;
+            grab_ticket_req.ticket_id = target.ticket_id.clone();
+            grab_ticket_req.biliticket.select_ticket_id = Some(target.ticket_id);
+
+            match run_pipeline(ctx, &grab_ticket_req, is_hot, polling, sold_out_fallback).await {
+                PipelineOutcome::Finished => {
+                    log::info!("抢票流程结束");
+                    return;
                 }
-                if let Some(ref skip_words) = skip_words {
-                    let title = ticket_data.screen_name.to_lowercase();
-                    if skip_words
-                        .iter()
-                        .any(|word| title.contains(&word.to_lowercase()))
-                    {
-                        log::info!("跳过包含过滤关键词的场次: {}", ticket_data.screen_name);
-                        continue;
-                    }
-                    let ticket_title = ticket_data.desc.to_lowercase();
-                    if skip_words
-                        .iter()
-                        .any(|word| ticket_title.contains(&word.to_lowercase()))
-                    {
-                        log::info!("跳过包含过滤关键词的票种: {}", ticket_data.desc);
-                        continue;
-                    }
+                PipelineOutcome::Cancelled => return,
+                PipelineOutcome::SoldOut => {
+                    log::info!("{}已售罄，尝试下一个票种", target.label);
+                }
+                PipelineOutcome::Exhausted(RetryStage::Order) if polling => {
+                    log::info!("该票种已达到最大重试次数，重新检查可售票种");
+                    break;
                 }
+                PipelineOutcome::Exhausted(RetryStage::Order) => {
+                    log::warn!("下单已达最大重试次数，重新获取token");
+                }
+                PipelineOutcome::Exhausted(_) if polling => {
+                    log::error!("确认订单失败，已达最大重试次数，尝试其他票种");
+                }
+                PipelineOutcome::Exhausted(_) => {
+                    log::error!("确认订单失败，已达最大重试次数");
+                    ctx.send_failure("确认订单失败，已达最大重试次数".to_string())
+                        .await;
+                    return;
+                }
+            }
+        }
 
-                log::info!(
-                    "当前{} {}票种可售，开始抢票！",
-                    ticket_data.screen_name,
-                    ticket_data.desc
-                );
-                grab_ticket_req.ticket_id = ticket_data.id.to_string();
-                grab_ticket_req.biliticket.select_ticket_id = Some(ticket_data.id.to_string());
+        if polling {
+            let delay = refresh_retry.policy().delay(1, None);
+            log::info!(
+                "所有场次和票种检查完毕，等待{:.1}秒后重新检查",
+                delay.as_secs_f32()
+            );
+            if sleep_or_cancelled(&ctx.cancel, delay).await {
+                return;
+            }
+        }
+    }
+}
 
+// 各模式共用的抢票流程，按各阶段的重试策略推进。
+// polling 为 true 时才套用 max_order_retry；sold_out_fallback 为 true 时，
+// 售罄/停售错误按重试策略分类后仍是重试或终止的，直接返回由调用方换下一个票种
+async fn run_pipeline(
+    ctx: &GrabContext,
+    grab_ticket_req: &GrabTicketRequest,
+    is_hot: bool,
+    polling: bool,
+    sold_out_fallback: bool,
+) -> PipelineOutcome {
+    let mut token_retry = RetryState::new(ctx.config.retry_policy(RetryStage::Token));
+    let mut confirm_retry = RetryState::new(ctx.config.retry_policy(RetryStage::Confirm));
+    let mut order_retry = RetryState::new(ctx.config.order_retry_policy(polling));
+    let mut stage = Stage::Token;
+
+    loop {
+        if should_stop(&ctx.cancel) {
+            return PipelineOutcome::Cancelled;
+        }
+        let (next, delay) = match stage {
+            Stage::Token => {
+                enter_phase(LogPhase::Token);
+                let started = Instant::now();
                 let token_result = get_ticket_token(
-                    cookie_manager.clone(),
-                    cpdd.clone(),
-                    &project_id,
+                    ctx.cookie_manager.clone(),
+                    ctx.cpdd.clone(),
+                    &ctx.project_id,
                     &grab_ticket_req.screen_id,
                     &grab_ticket_req.ticket_id,
-                    count,
+                    ctx.count,
                     is_hot,
                 )
                 .await;
-
+                record_token_attempt(
+                    &ctx.task_id,
+                    ctx.uid,
+                    &ctx.project_id,
+                    &token_result,
+                    started,
+                );
                 match token_result {
                     Ok((token, ptoken)) => {
-                        log::info!("获取抢票token成功！:{} ptoken:{}", token, ptoken);
-                        let mut confirm_retry_count = 0;
-                        let max_confirm_retry = custon_config.max_confirm_retry as i8;
-
-                        loop {
-                            let (success, retry_limit) = handle_grab_ticket(
-                                cookie_manager.clone(),
-                                cpdd.clone(),
-                                &project_id,
-                                &token,
-                                &ptoken,
-                                is_hot,
-                                &task_id,
-                                uid,
-                                &result_tx,
-                                &grab_ticket_req,
-                                &buyer_info,
-                            )
-                            .await;
-                            if success {
-                                log::info!("抢票流程结束，退出捡漏模式");
-                                break 'main_loop;
-                            }
-                            if retry_limit {
-                                log::info!("该票种已达到最大重试次数，恢复捡漏模式，尝试其他票种");
-                                break 'screen_loop;
-                            }
-
-                            confirm_retry_count += 1;
-                            if confirm_retry_count >= max_confirm_retry {
-                                log::error!("确认订单失败，已达最大重试次数，尝试其他票种");
-                                break;
-                            }
-
-                            tokio::time::sleep(tokio::time::Duration::from_secs_f32(0.3)).await;
-                        }
+                        log::info!(
+                            "获取抢票token成功！:{} ptoken:{}",
+                            redact::secret(&token),
+                            redact::secret(&ptoken)
+                        );
+                        confirm_retry.reset();
+                        (Stage::Confirm { token, ptoken }, None)
                     }
                     Err(risk_param) => {
-                        if risk_param.code == -401 || risk_param.code == 401 {
-                            log::warn!("需要验证码，开始处理验证码...");
-                            match handle_risk_verification(
-                                cookie_manager.clone(),
-                                risk_param,
-                                &custon_config,
-                                &csrf,
-                                local_captcha.clone().expect("REASON"),
-                            )
-                            .await
-                            {
-                                Ok(()) => log::info!("验证码处理成功！"),
-                                Err(e) => {
-                                    log::error!("验证码处理失败: {}", e);
-                                    token_retry_count += 1;
-                                    if token_retry_count >= max_token_retry {
-                                        let task_result =
-                                            TaskResult::GrabTicketResult(GrabTicketResult {
-                                                task_id: task_id.clone(),
-                                                uid,
-                                                success: false,
-                                                message: format!(
-                                                    "验证码处理失败，已达最大重试次数: {}",
-                                                    e
-                                                ),
-                                                order_id: None,
-                                                pay_token: None,
-                                                pay_result: None,
-                                                confirm_result: None,
-                                            });
-                                        let _ = result_tx.send(task_result).await;
-                                        break 'main_loop;
+                        let error = risk_param.error();
+                        let class = token_retry.policy().classify(error);
+                        if falls_back(sold_out_fallback, error, class) {
+                            log::warn!("获取token失败，{}", error);
+                            return PipelineOutcome::SoldOut;
+                        }
+                        // 开售前后 token 接口的返回并不稳定，除致命错误和登录失效外都按次数重试
+                        let failure = match class {
+                            ErrorClass::NeedsCaptcha => {
+                                log::warn!("需要验证码，开始处理验证码...");
+                                match handle_risk_verification(
+                                    ctx.cookie_manager.clone(),
+                                    risk_param,
+                                    &ctx.config,
+                                    &ctx.csrf,
+                                    ctx.local_captcha.clone(),
+                                )
+                                .await
+                                {
+                                    Ok(()) => {
+                                        log::info!("验证码处理成功！");
+                                        None
+                                    }
+                                    Err(e) => {
+                                        log::error!("验证码处理失败: {}", e);
+                                        Some(format!("验证码处理失败，已达最大重试次数: {}", e))
                                     }
                                 }
                             }
-                        } else {
-                            match risk_param.code {
-                                100080 | 100082 => log::error!("抢票失败，场次/项目/日期选择有误"),
-                                100039 => log::error!("抢票失败，该场次已停售"),
-                                _ => log::error!("抢票失败，未知错误"),
+                            ErrorClass::Fatal | ErrorClass::NeedsRelogin => {
+                                log::error!("抢票失败，{}", error);
+                                report_fatal_error(
+                                    ctx,
+                                    grab_ticket_req,
+                                    "获取token失败",
+                                    error,
+                                    class,
+                                )
+                                .await;
+                                return PipelineOutcome::Finished;
+                            }
+                            _ => {
+                                log::error!("抢票失败，{}", error);
+                                Some(format!(
+                                    "获取token失败：{}，错误信息：{}",
+                                    error, risk_param.message
+                                ))
+                            }
+                        };
+                        let delay = match failure {
+                            Some(message) => match token_retry.failed(Some(error.code())) {
+                                Some(delay) => delay,
+                                None => {
+                                    ctx.send_error(message, error, class).await;
+                                    return PipelineOutcome::Finished;
+                                }
+                            },
+                            None => token_retry.policy().delay(1, None),
+                        };
+                        (Stage::Token, Some(delay))
+                    }
+                }
+            }
+            Stage::Confirm { token, ptoken } => {
+                enter_phase(LogPhase::Confirm);
+                let started = Instant::now();
+                let confirm =
+                    confirm_ticket_order(ctx.cookie_manager.clone(), &ctx.project_id, &token).await;
+                record_attempt(
+                    &ctx.task_id,
+                    ctx.uid,
+                    &ctx.project_id,
+                    HistoryEvent::Confirm,
+                    confirm.as_ref().map_or_else(|e| e.code, |_| Some(0)),
+                    started,
+                    confirm.as_ref().err().map_or("", |e| e.message.as_str()),
+                );
+                match confirm {
+                    Ok(confirm_result) => {
+                        log::info!("确认订单成功！准备下单");
+                        order_retry.reset();
+                        (
+                            Stage::Create {
+                                token,
+                                ptoken,
+                                confirm_result: Box::new(confirm_result),
+                            },
+                            None,
+                        )
+                    }
+                    Err(e) => {
+                        // 请求本身失败时没有错误码，按临时错误重试
+                        let error = e.error();
+                        match error.map(|error| (error, confirm_retry.policy().classify(error))) {
+                            Some((
+                                error,
+                                class @ (ErrorClass::Fatal | ErrorClass::NeedsRelogin),
+                            )) => {
+                                log::error!("确认订单失败，{}", e);
+                                report_fatal_error(
+                                    ctx,
+                                    grab_ticket_req,
+                                    "确认订单失败",
+                                    error,
+                                    class,
+                                )
+                                .await;
+                                return PipelineOutcome::Finished;
                             }
-                            token_retry_count += 1;
-                            if token_retry_count >= max_token_retry {
-                                let task_result = TaskResult::GrabTicketResult(GrabTicketResult {
-                                    task_id: task_id.clone(),
-                                    uid,
-                                    success: false,
-                                    message: format!(
-                                        "获取token失败，错误代码: {}，错误信息：{}",
-                                        risk_param.code, risk_param.message
-                                    ),
-                                    order_id: None,
-                                    pay_token: None,
-                                    pay_result: None,
-                                    confirm_result: None,
-                                });
-                                let _ = result_tx.send(task_result).await;
-                                break 'main_loop;
+                            Some((_, ErrorClass::RefreshToken)) => {
+                                log::info!("确认订单失败，{}，重新获取token", e);
+                                token_retry.reset();
+                                stage = Stage::Token;
+                                continue;
                             }
+                            _ => log::error!("确认订单失败，原因：{}  正在重试...", e),
                         }
-                        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+                        let Some(delay) = confirm_retry.failed(e.code) else {
+                            return PipelineOutcome::Exhausted(RetryStage::Confirm);
+                        };
+                        (Stage::Confirm { token, ptoken }, Some(delay))
                     }
                 }
             }
-        }
-
-        log::info!("所有场次和票种检查完毕，等待2秒后重新检查");
-        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-    }
-    log::info!("捡漏模式任务已退出");
-}
-async fn handle_grab_ticket(
-    cookie_manager: Arc<CookieManager>,
-    cpdd: Arc<Mutex<CTokenGenerator>>,
-    project_id: &str,
-    token: &str,
-    ptoken: &str,
-    is_hot: bool,
-    task_id: &str,
-    uid: i64,
-    result_tx: &mpsc::Sender<TaskResult>,
-    grab_ticket_req: &GrabTicketRequest,
-    buyer_info: &Vec<BuyerInfo>,
-) -> (bool, bool) {
-    // 确认订单
-    match confirm_ticket_order(cookie_manager.clone(), project_id, token).await {
-        Ok(confirm_result) => {
-            log::info!("确认订单成功！准备下单");
-
-            if let Some((success, retry_limit)) = try_create_order(
-                cookie_manager.clone(),
-                cpdd,
-                project_id,
+            Stage::Create {
                 token,
                 ptoken,
-                &confirm_result,
-                is_hot,
-                grab_ticket_req,
-                buyer_info,
-                task_id,
-                uid,
-                result_tx,
-            )
-            .await
-            {
-                return (success, retry_limit);
+                confirm_result,
+            } => {
+                enter_phase(LogPhase::Create);
+                let started = Instant::now();
+                let create_result = create_order(
+                    ctx.cookie_manager.clone(),
+                    ctx.cpdd.clone(),
+                    &ctx.project_id,
+                    &token,
+                    &ptoken,
+                    &confirm_result,
+                    is_hot,
+                    &grab_ticket_req.biliticket,
+                    &ctx.buyer_info,
+                    true,
+                    order_retry.attempts() >= 3,
+                    false,
+                    None,
+                )
+                .await;
+                record_attempt(
+                    &ctx.task_id,
+                    ctx.uid,
+                    &ctx.project_id,
+                    HistoryEvent::Create,
+                    Some(create_result.as_ref().err().map_or(0, |e| e.code())),
+                    started,
+                    "",
+                );
+                let code = match create_result {
+                    // 已成功下单的订单会走完支付检测，取消只阻止新的下单请求
+                    Ok(order_result) => {
+                        log::info!("下单成功！订单信息{:?}", order_result);
+                        if check_order(ctx, grab_ticket_req, &confirm_result, &order_result).await {
+                            return PipelineOutcome::Finished;
+                        }
+                        // 假票，放弃当前订单继续下单
+                        None
+                    }
+                    Err(e)
+                        if falls_back(sold_out_fallback, e, order_retry.policy().classify(e)) =>
+                    {
+                        log::warn!("下单失败，{}", e);
+                        return PipelineOutcome::SoldOut;
+                    }
+                    Err(e) => match order_retry.policy().classify(e) {
+                        ErrorClass::Retryable => {
+                            match e {
+                                TicketApiError::NullResponse => log::error!(
+                                    "{}，自行决定是否继续，如果取消请关闭重新打开该应用",
+                                    e.message()
+                                ),
+                                TicketApiError::InvalidParams | TicketApiError::Unknown(_) => {
+                                    log::error!("下单失败，{}", e)
+                                }
+                                _ => log::info!("{}", e.message()),
+                            }
+                            Some(e.code())
+                        }
+                        ErrorClass::RefreshToken => {
+                            log::info!("{}", e.message());
+                            token_retry.reset();
+                            stage = Stage::Token;
+                            continue;
+                        }
+                        // 下单接口无法完成人机验证，按临时错误继续重试
+                        ErrorClass::NeedsCaptcha => {
+                            log::warn!("下单失败，{}", e);
+                            Some(e.code())
+                        }
+                        // 需要终止抢票的致命错误
+                        class @ (ErrorClass::Fatal | ErrorClass::NeedsRelogin) => {
+                            log::error!("下单失败，{}", e);
+                            report_fatal_error(ctx, grab_ticket_req, "下单失败", e, class).await;
+                            return PipelineOutcome::Finished;
+                        }
+                    },
+                };
+                let Some(delay) = order_retry.failed(code) else {
+                    return PipelineOutcome::Exhausted(RetryStage::Order);
+                };
+                (
+                    Stage::Create {
+                        token,
+                        ptoken,
+                        confirm_result,
+                    },
+                    Some(delay),
+                )
             }
-
-            (true, false) // 订单流程已完成
-        }
-        Err(e) => {
-            log::error!("确认订单失败，原因：{}  正在重试...", e);
-            (false, false) // 需要继续重试
+        };
+        if let Some(delay) = delay
+            && sleep_or_cancelled(&ctx.cancel, delay).await
+        {
+            return PipelineOutcome::Cancelled;
         }
+        stage = next;
     }
 }
 
-// 处理创建订单逻辑
-async fn try_create_order(
-    cookie_manager: Arc<CookieManager>,
-    cpdd: Arc<Mutex<CTokenGenerator>>,
-    project_id: &str,
-    token: &str,
-    ptoken: &str,
-    confirm_result: &ConfirmTicketResult,
-    is_hot: bool,
+// 售罄/停售且按配置仍会原样重试或终止时换下一个票种；配置改成其他处理方式的按配置处理
+fn falls_back(sold_out_fallback: bool, error: TicketApiError, class: ErrorClass) -> bool {
+    sold_out_fallback
+        && error.is_sold_out()
+        && matches!(class, ErrorClass::Retryable | ErrorClass::Fatal)
+}
+
+// 下单成功后检测假票并获取支付信息，发送抢票结果后返回 true；检测到假票时返回 false
+async fn check_order(
+    ctx: &GrabContext,
     grab_ticket_req: &GrabTicketRequest,
-    buyer_info: &Vec<BuyerInfo>,
-    task_id: &str,
-    uid: i64,
-    result_tx: &mpsc::Sender<TaskResult>,
-) -> Option<(
-    bool,
-    bool, // 第二个参数标记是因为达到重试上限
-)> {
-    let mut order_retry_count = 0;
-    let mut need_retry = false;
-
-    // 下单循环
+    confirm_result: &ConfirmTicketResult,
+    order_result: &Value,
+) -> bool {
+    let empty_json = json!({});
+    let order_data = order_result.get("data").unwrap_or(&empty_json);
+
+    let zero_json = json!(0);
+    let order_id = order_data
+        .get("orderId")
+        .unwrap_or(&zero_json)
+        .as_i64()
+        .unwrap_or(0);
+
+    let empty_string_json = json!("");
+    let pay_token = order_data
+        .get("token")
+        .unwrap_or(&empty_string_json)
+        .as_str()
+        .unwrap_or("");
+
+    log::info!("下单成功！正在检测是否假票！");
+
+    let mut fake_check_retry = RetryState::new(ctx.config.retry_policy(RetryStage::FakeCheck));
     loop {
-        if order_retry_count >= 3 {
-            need_retry = true;
-        }
-
-        match create_order(
-            cookie_manager.clone(),
-            cpdd.clone(),
-            project_id,
-            token,
-            ptoken,
-            confirm_result,
-            is_hot,
-            &grab_ticket_req.biliticket,
-            buyer_info,
-            true,
-            need_retry,
-            false,
-            None,
+        enter_phase(LogPhase::PayCheck);
+        let started = Instant::now();
+        let check_result = check_fake_ticket(
+            ctx.cookie_manager.clone(),
+            &ctx.project_id,
+            pay_token,
+            order_id,
         )
-        .await
-        {
-            Ok(order_result) => {
-                log::info!("下单成功！订单信息{:?}", order_result);
-                let empty_json = json!({});
-                let order_data = order_result.get("data").unwrap_or(&empty_json);
-
-                let zero_json = json!(0);
-                let order_id = order_data
-                    .get("orderId")
-                    .unwrap_or(&zero_json)
-                    .as_i64()
-                    .unwrap_or(0);
-
-                let empty_string_json = json!("");
-                let pay_token = order_data
-                    .get("token")
-                    .unwrap_or(&empty_string_json)
-                    .as_str()
-                    .unwrap_or("");
-
-                log::info!("下单成功！正在检测是否假票！");
-
-                // 假票检测重试循环
-                let mut fake_check_retry = 0;
-                let max_fake_check_retry =
-                    grab_ticket_req.biliticket.config.max_fake_check_retry as i32;
-
-                loop {
-                    let check_result = match check_fake_ticket(
-                        cookie_manager.clone(),
-                        project_id,
-                        pay_token,
-                        order_id,
-                    )
-                    .await
-                    {
-                        Ok(result) => result,
-                        Err(e) => {
-                            log::error!("检测假票失败，原因：{}", e);
-                            fake_check_retry += 1;
-                            if fake_check_retry >= max_fake_check_retry {
-                                log::error!("检测假票多次失败，默认下单成功，请前往订单中心支付");
-                                // 即使检测失败，也视为抢票成功，只是没有支付二维码
-                                let task_result = TaskResult::GrabTicketResult(GrabTicketResult {
-                                    task_id: task_id.to_string(),
-                                    uid,
-                                    success: true,
-                                    message: "抢票成功，但获取支付信息失败，请前往B站订单中心支付"
-                                        .to_string(),
-                                    order_id: Some(order_id.to_string()),
-                                    pay_token: Some(pay_token.to_string()),
-                                    confirm_result: Some(confirm_result.clone()),
-                                    pay_result: None,
-                                });
-                                let _ = result_tx.send(task_result).await;
-                                return Some((true, false));
-                            }
-                            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-                            continue;
-                        }
-                    };
-
-                    let errno = check_result
-                        .get("errno")
-                        .unwrap_or(&zero_json)
-                        .as_i64()
-                        .unwrap_or(0);
-
-                    if errno != 0 {
-                        log::error!("检测到假票(errno={})，放弃当前订单，继续抢票", errno);
-                        // 假票，跳出内层循环，继续外层 create_order 循环
-                        break;
-                    }
-
-                    let analyze_result = match serde_json::from_value::<CheckFakeResult>(
-                        check_result.clone(),
-                    ) {
-                        Ok(result) => result,
-                        Err(e) => {
-                            log::error!("解析假票结果失败，原因：{}", e);
-                            fake_check_retry += 1;
-                            if fake_check_retry >= max_fake_check_retry {
-                                log::error!("解析支付信息多次失败，默认下单成功");
-                                let task_result = TaskResult::GrabTicketResult(GrabTicketResult {
-                                    task_id: task_id.to_string(),
-                                    uid,
-                                    success: true,
-                                    message: "抢票成功，但解析支付信息失败，请前往B站订单中心支付"
-                                        .to_string(),
-                                    order_id: Some(order_id.to_string()),
-                                    pay_token: Some(pay_token.to_string()),
-                                    confirm_result: Some(confirm_result.clone()),
-                                    pay_result: None,
-                                });
-                                let _ = result_tx.send(task_result).await;
-
-                                let project_name = &confirm_result.project_name;
-                                let screen_name = &confirm_result.screen_name;
-                                let ticket_name = &confirm_result.ticket_info.name;
-                                let jump_url = Some(format!(
-                                    "bilibili://mall/web?url=https://mall.bilibili.com/neul-next/ticket/orderDetail.html?order_id={}",
-                                    order_id.to_string()
-                                ));
-                                let title = format!("抢票成功: {}", project_name);
-                                let message = format!(
-                                    "项目: {}\n场次: {}\n票种: {}\n订单号: {}\n状态: 解析支付信息失败，请前往订单中心支付",
-                                    project_name, screen_name, ticket_name, order_id
-                                );
-                                log::info!(
-                                    "准备发送推送通知(Fallback)... 启用渠道: {:?}",
-                                    grab_ticket_req.biliticket.push_self.enabled_methods
-                                );
-                                let (push_success, push_msg, _) = grab_ticket_req
-                                    .biliticket
-                                    .push_self
-                                    .push_all_async(
-                                        &title,
-                                        &message,
-                                        &jump_url,
-                                        Some(result_tx.clone()),
-                                    )
-                                    .await;
-                                log::info!(
-                                    "推送结果(Fallback): 成功={}, 信息={}",
-                                    push_success,
-                                    push_msg
-                                );
-
-                                return Some((true, false));
-                            }
-                            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-                            continue;
-                        }
-                    };
-
-                    let pay_result = analyze_result.data.pay_param;
-                    // 通知成功
-                    let task_result = TaskResult::GrabTicketResult(GrabTicketResult {
-                        task_id: task_id.to_string(),
-                        uid,
-                        success: true,
-                        message: "抢票成功".to_string(),
-                        order_id: Some(order_id.clone().to_string()),
-                        pay_token: Some(pay_token.to_string()),
-                        confirm_result: Some(confirm_result.clone()),
-                        pay_result: Some(pay_result.clone()),
-                    });
-                    let _ = result_tx.send(task_result.clone()).await;
-
-                    let project_name = &confirm_result.project_name;
-                    let screen_name = &confirm_result.screen_name;
-                    let ticket_name = &confirm_result.ticket_info.name;
-                    let jump_url = Some(format!(
-                        "bilibili://mall/web?url=https://mall.bilibili.com/neul-next/ticket/orderDetail.html?order_id={}",
-                        order_id.to_string()
-                    ));
-                    let title = format!("抢票成功: {}", project_name);
-                    let message = format!(
-                        "项目: {}\n场次: {}\n票种: {}\n订单号: {}\n请尽快支付！",
-                        project_name, screen_name, ticket_name, order_id
-                    );
-                    log::info!(
-                        "准备发送推送通知... 启用渠道: {:?}",
-                        grab_ticket_req.biliticket.push_self.enabled_methods
-                    );
-                    let (push_success, push_msg, _) = grab_ticket_req
-                        .biliticket
-                        .push_self
-                        .push_all_async(&title, &message, &jump_url, Some(result_tx.clone()))
-                        .await;
-                    log::info!("推送结果: 成功={}, 信息={}", push_success, push_msg);
-
-                    return Some((true, false)); // 成功，不需要继续重试
-                }
-
-                // 如果是从 break 跳出（假票），则继续外层循环（create_order）
-                // 这里不需要显式 continue，因为 break 后会执行到下面的 order_retry_count += 1
-            }
-
+        .await;
+        record_attempt(
+            &ctx.task_id,
+            ctx.uid,
+            &ctx.project_id,
+            HistoryEvent::CreateStatus,
+            check_result
+                .as_ref()
+                .ok()
+                .map(|v| v.get("errno").and_then(|e| e.as_i64()).unwrap_or(0)),
+            started,
+            check_result.as_ref().err().map_or("", |e| e.as_str()),
+        );
+        let check_result = match check_result {
+            Ok(result) => result,
             Err(e) => {
-                // 处理错误情况
-                match e {
-                    //需要继续重试的临时错误
-                    100001 | 429 | 900001 => log::info!("b站限速，正常现象"),
-                    100009 => {
-                        log::info!("当前票种库存不足");
-                        tokio::time::sleep(tokio::time::Duration::from_secs_f32(0.6)).await;
-                    }
-                    211 => log::info!("很遗憾，差一点点抢到票，继续加油吧！"),
-
-                    //需要暂停的情况
-                    3 => {
-                        log::info!("抢票速度过快，即将被硬控5秒");
-                        log::info!("暂停4.8秒");
-                        tokio::time::sleep(tokio::time::Duration::from_secs_f32(4.8)).await;
-                    }
-
-                    //需要重新获取token的情况
-                    100041 | 100050 | 900002 => {
-                        log::info!("token失效，即将重新获取token");
-                        return Some((true, true));
-                    }
-
-                    //需要终止抢票的致命错误
-                    100017 | 100016 => {
-                        log::info!("当前项目/类型/场次已停售");
-                        return Some((true, false));
-                    }
-                    1 => {
-                        log::error!(
-                            "超人 请慢一点，这是仅限1人抢票的项目，或抢票格式有误，请重新提交任务"
-                        );
-                        return Some((true, false));
-                    }
-                    83000004 => {
-                        log::error!("没有配置购票人信息！请重新配置");
-                        return Some((true, false));
+                log::error!("检测假票失败，原因：{}", e);
+                match fake_check_retry.failed(None) {
+                    Some(delay) => {
+                        tokio::time::sleep(delay).await;
+                        continue;
                     }
-                    100079 | 100003 | 100048 => {
-                        log::error!("购票人存在待付款订单，请前往支付或取消后重新下单");
+                    None => {
+                        log::error!("检测假票多次失败，默认下单成功，请前往订单中心支付");
+                        // 即使检测失败，也视为抢票成功，只是没有支付二维码
                         let task_result = TaskResult::GrabTicketResult(GrabTicketResult {
-                            task_id: task_id.to_string(),
-                            uid,
-                            success: false,
-                            message: "购票人存在待付款订单，请前往支付或取消后重新下单".to_string(),
-                            order_id: None,
-                            pay_token: None,
+                            task_id: ctx.task_id.clone(),
+                            uid: ctx.uid,
+                            success: true,
+                            message: "抢票成功，但获取支付信息失败，请前往B站订单中心支付"
+                                .to_string(),
+                            order_id: Some(order_id.to_string()),
+                            pay_token: Some(pay_token.to_string()),
+                            confirm_result: Some(confirm_result.clone()),
                             pay_result: None,
-                            confirm_result: None,
+                            cancelled: false,
+                            error_code: None,
+                            error_class: None,
                         });
-                        let _ = result_tx.send(task_result).await;
-                        return Some((true, false));
-                    }
-                    100039 => {
-                        log::error!("活动收摊啦,下次要快点哦");
-                        return Some((true, false));
-                    }
-                    209001 => {
-                        log::error!("当前项目只能选择一个购票人！不支持多选，请重新提交任务");
-                        return Some((true, false));
-                    }
-                    737 => {
-                        log::error!(
-                            "B站传了一个null回来，请看一下上一行的message提示信息，自行决定是否继续，如果取消请关闭重新打开该应用"
-                        );
+                        let _ = ctx.result_tx.send(task_result).await;
+                        return true;
                     }
-                    999 => log::error!("程序内部错误！传参错误"),
-                    919 => {
-                        log::error!(
-                            "程序内部错误！该项目区分绑定非绑定项目错误，传入意外值，请尝试重新下单以及提出issue"
-                        );
-                        return Some((true, false));
-                    }
-                    _ => log::error!("下单失败，未知错误码：{} 可以提出issue修复该问题", e),
                 }
             }
-        }
+        };
 
-        order_retry_count += 1;
-        if grab_ticket_req.grab_mode == 2
-            && order_retry_count >= grab_ticket_req.biliticket.config.max_order_retry as i32
-        {
-            log::error!(
-                "捡漏模式下单失败，已达最大重试次数，放弃该票种抢票，准备检测其他票种继续捡漏"
-            );
-            return Some((false, true));
+        let errno = check_result
+            .get("errno")
+            .unwrap_or(&zero_json)
+            .as_i64()
+            .unwrap_or(0);
+
+        if errno != 0 {
+            log::error!("检测到假票(errno={})，放弃当前订单，继续抢票", errno);
+            return false;
         }
-        tokio::time::sleep(tokio::time::Duration::from_millis(
-            grab_ticket_req.biliticket.config.retry_interval_ms,
-        ))
-        .await;
+
+        let order = OrderSummary::from_confirm(order_id, confirm_result);
+
+        let analyze_result = match serde_json::from_value::<CheckFakeResult>(check_result.clone()) {
+            Ok(result) => result,
+            Err(e) => {
+                log::error!("解析假票结果失败，原因：{}", e);
+                if let Some(delay) = fake_check_retry.failed(None) {
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+                log::error!("解析支付信息多次失败，默认下单成功");
+                let task_result = TaskResult::GrabTicketResult(GrabTicketResult {
+                    task_id: ctx.task_id.clone(),
+                    uid: ctx.uid,
+                    success: true,
+                    message: "抢票成功，但解析支付信息失败，请前往B站订单中心支付".to_string(),
+                    order_id: Some(order_id.to_string()),
+                    pay_token: Some(pay_token.to_string()),
+                    confirm_result: Some(confirm_result.clone()),
+                    pay_result: None,
+                    cancelled: false,
+                    error_code: None,
+                    error_class: None,
+                });
+                let _ = ctx.result_tx.send(task_result).await;
+
+                log::info!(
+                    "准备发送推送通知(Fallback)... 启用渠道: {:?}",
+                    grab_ticket_req.biliticket.push_self.enabled_methods
+                );
+                let notification = Notification::order_success(order, None);
+                let report = grab_ticket_req
+                    .biliticket
+                    .push_self
+                    .push_all_async(&notification, Some(ctx.result_tx.clone()))
+                    .await;
+                log::info!(
+                    "推送结果(Fallback): 成功={}, 信息={}",
+                    report.success(),
+                    report.summary()
+                );
+                return true;
+            }
+        };
+
+        let pay_result = analyze_result.data.pay_param;
+        // 通知成功
+        let task_result = TaskResult::GrabTicketResult(GrabTicketResult {
+            task_id: ctx.task_id.clone(),
+            uid: ctx.uid,
+            success: true,
+            message: "抢票成功".to_string(),
+            order_id: Some(order_id.to_string()),
+            pay_token: Some(pay_token.to_string()),
+            confirm_result: Some(confirm_result.clone()),
+            pay_result: Some(pay_result.clone()),
+            cancelled: false,
+            error_code: None,
+            error_class: None,
+        });
+        let _ = ctx.result_tx.send(task_result.clone()).await;
+
+        log::info!(
+            "准备发送推送通知... 启用渠道: {:?}",
+            grab_ticket_req.biliticket.push_self.enabled_methods
+        );
+        let notification = Notification::order_success(order, Some(&pay_result));
+        let report = grab_ticket_req
+            .biliticket
+            .push_self
+            .push_all_async(&notification, Some(ctx.result_tx.clone()))
+            .await;
+        log::info!(
+            "推送结果: 成功={}, 信息={}",
+            report.success(),
+            report.summary()
+        );
+
+        return true;
     }
 }
+
+// 遇到无法重试的错误时通知结果并推送
+async fn report_fatal_error(
+    ctx: &GrabContext,
+    grab_ticket_req: &GrabTicketRequest,
+    action: &str,
+    error: TicketApiError,
+    class: ErrorClass,
+) {
+    let message = format!("{}：{}", action, error);
+    ctx.send_error(message.clone(), error, class).await;
+
+    let title = format!("抢票终止: {}", error.message());
+    let report = grab_ticket_req
+        .biliticket
+        .push_self
+        .push_all_async(
+            &Notification::new(title, message),
+            Some(ctx.result_tx.clone()),
+        )
+        .await;
+    log::info!(
+        "推送结果: 成功={}, 信息={}",
+        report.success(),
+        report.summary()
+    );
+}
@@ -1,146 +1,202 @@
+pub mod grab_mode;
 pub mod grab_ticket_handler;
 pub mod login_handler;
 pub mod order_handler;
 pub mod push_handler;
+pub mod queue;
+pub mod status;
 pub mod ticket_handler;
 
 use std::collections::HashMap;
+use std::future::Future;
 use std::sync::Arc;
 use std::thread;
 
 use self::{
+    grab_mode::GrabPlan,
     grab_ticket_handler::handle_grab_ticket_request,
     login_handler::{
         handle_login_sms_request, handle_qrcode_login_request, handle_submit_login_sms_request,
     },
     order_handler::handle_get_all_order_request,
     push_handler::handle_push_request,
+    queue::TaskQueue,
+    status::{TaskGcPolicy, TaskTable},
     ticket_handler::{handle_get_buyer_info_request, handle_get_ticket_info_request},
 };
+use common::config::TaskLimits;
+use common::log_record;
+use common::metrics;
+use common::task_journal::{self, JournalEntry};
 use common::taskmanager::*;
 use tokio::runtime::Runtime;
 use tokio::sync::{Mutex, mpsc};
 use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
 pub struct TaskManagerImpl {
     task_sender: mpsc::Sender<TaskMessage>,
     result_receiver: Option<mpsc::Receiver<TaskResult>>,
-    running_tasks: HashMap<String, Task>,
+    tasks: TaskTable,
+    gc_policy: TaskGcPolicy,
     runtime: Arc<Runtime>,
+    limits: Arc<std::sync::Mutex<TaskLimits>>,
     _worker_thread: Option<thread::JoinHandle<()>>,
     pub dungeon_service: Arc<Mutex<Option<Arc<crate::dungeon::DungeonService>>>>,
 }
 
-enum TaskMessage {
-    SubmitTask((String, TaskRequest)),
-    CancelTask(String),
-    Shutdown,
-}
+// 启动任务并计入运行指标，任务结束或被取消时自动计为完成；
+// 任务结果经过一层转发，任务结束后据此更新状态表，并通知调度释放并发名额
+fn spawn_tracked<F, Fut>(
+    kind: &str,
+    task_id: String,
+    tasks: TaskTable,
+    result_tx: mpsc::Sender<TaskResult>,
+    done_tx: mpsc::UnboundedSender<String>,
+    task: F,
+) -> JoinHandle<()>
+where
+    F: FnOnce(mpsc::Sender<TaskResult>) -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let guard = metrics::track_task(kind);
+    let (task_tx, mut task_rx) = mpsc::channel::<TaskResult>(100);
+    let task = task(task_tx);
+    tokio::spawn(async move {
+        let _guard = guard;
+        tasks.set_status(&task_id, TaskStatus::Running);
 
-impl TaskManager for TaskManagerImpl {
-    fn new() -> Self {
-        let (task_tx, mut task_rx) = mpsc::channel(100);
-        let (result_tx, result_rx) = mpsc::channel(100);
-
-        let runtime = Arc::new(Runtime::new().unwrap());
-        let rt = runtime.clone();
-
-        let dungeon_service = Arc::new(Mutex::new(None));
-        let ds_clone = dungeon_service.clone();
+        let forward = async {
+            let mut outcome = None;
+            while let Some(result) = task_rx.recv().await {
+                if let Some(status) = result.final_status() {
+                    outcome = Some(status);
+                }
+                if result_tx.send(result).await.is_err() {
+                    log::debug!("任务结果接收端已关闭");
+                }
+            }
+            outcome
+        };
+        let ((), outcome) =
+            tokio::join!(status::scope(task_id.clone(), tasks.clone(), task), forward);
 
-        let worker = thread::spawn(move || {
-            let mut task_handles: HashMap<String, JoinHandle<()>> = HashMap::new();
+        let final_status =
+            outcome.unwrap_or_else(|| TaskStatus::Failed("任务已退出，未返回结果".to_string()));
+        tasks.set_status(&task_id, final_status);
+        let _ = done_tx.send(task_id);
+    })
+}
 
-            rt.block_on(async {
-                while let Some(msg) = task_rx.recv().await {
-                    match msg {
-                        TaskMessage::SubmitTask((task_id, request)) => {
-                            let result_tx = result_tx.clone();
-                            let ds_for_task = ds_clone.clone();
-
-                            let handle = match request {
-                                TaskRequest::QrCodeLoginRequest(qrcode_req) => {
-                                    tokio::spawn(handle_qrcode_login_request(qrcode_req, result_tx))
-                                }
-                                TaskRequest::LoginSmsRequest(login_sms_req) => {
-                                    tokio::spawn(handle_login_sms_request(login_sms_req, result_tx))
-                                }
-                                TaskRequest::PushRequest(push_req) => {
-                                    let ds_guard = ds_for_task.lock().await;
-                                    let ds_opt: Option<Arc<crate::dungeon::DungeonService>> =
-                                        ds_guard.clone();
-                                    tokio::spawn(handle_push_request(push_req, result_tx, ds_opt))
-                                }
-                                TaskRequest::SubmitLoginSmsRequest(login_sms_req) => tokio::spawn(
-                                    handle_submit_login_sms_request(login_sms_req, result_tx),
-                                ),
-                                TaskRequest::GetAllorderRequest(get_order_req) => tokio::spawn(
-                                    handle_get_all_order_request(get_order_req, result_tx),
-                                ),
-                                TaskRequest::GetTicketInfoRequest(get_ticketinfo_req) => {
-                                    tokio::spawn(handle_get_ticket_info_request(
-                                        get_ticketinfo_req,
-                                        result_tx,
-                                    ))
-                                }
-                                TaskRequest::GetBuyerInfoRequest(get_buyerinfo_req) => {
-                                    tokio::spawn(handle_get_buyer_info_request(
-                                        get_buyerinfo_req,
-                                        result_tx,
-                                    ))
-                                }
-                                TaskRequest::GrabTicketRequest(grab_ticket_req) => tokio::spawn(
-                                    handle_grab_ticket_request(grab_ticket_req, result_tx),
-                                ),
-                            };
-                            task_handles.insert(task_id, handle);
-                        }
-                        TaskMessage::CancelTask(task_id) => {
-                            if let Some(handle) = task_handles.remove(&task_id) {
-                                log::info!("正在取消任务: {}", &task_id);
-                                handle.abort();
-                            }
-                        }
-                        TaskMessage::Shutdown => break,
-                    }
-                }
+// 启动一个任务，抢票任务返回其取消令牌（未指定时新建）
+async fn start_task(
+    task_id: String,
+    request: TaskRequest,
+    cancel: Option<CancellationToken>,
+    tasks: &TaskTable,
+    result_tx: &mpsc::Sender<TaskResult>,
+    dungeon_service: &Arc<Mutex<Option<Arc<crate::dungeon::DungeonService>>>>,
+    done_tx: &mpsc::UnboundedSender<String>,
+) -> (JoinHandle<()>, Option<CancellationToken>) {
+    let kind = request.kind().as_str();
+    let tasks = tasks.clone();
+    let result_tx = result_tx.clone();
+    let done_tx = done_tx.clone();
+    match request {
+        TaskRequest::QrCodeLoginRequest(qrcode_req) => {
+            let handle = spawn_tracked(kind, task_id, tasks, result_tx, done_tx, |tx| {
+                handle_qrcode_login_request(qrcode_req, tx)
             });
-        });
+            (handle, None)
+        }
+        TaskRequest::LoginSmsRequest(login_sms_req) => {
+            let handle = spawn_tracked(kind, task_id, tasks, result_tx, done_tx, |tx| {
+                handle_login_sms_request(login_sms_req, tx)
+            });
+            (handle, None)
+        }
+        TaskRequest::PushRequest(push_req) => {
+            let ds_opt = dungeon_service.lock().await.clone();
+            let handle = spawn_tracked(kind, task_id, tasks, result_tx, done_tx, |tx| {
+                handle_push_request(push_req, tx, ds_opt)
+            });
+            (handle, None)
+        }
+        TaskRequest::SubmitLoginSmsRequest(login_sms_req) => {
+            let handle = spawn_tracked(kind, task_id, tasks, result_tx, done_tx, |tx| {
+                handle_submit_login_sms_request(login_sms_req, tx)
+            });
+            (handle, None)
+        }
+        TaskRequest::GetAllorderRequest(get_order_req) => {
+            let handle = spawn_tracked(kind, task_id, tasks, result_tx, done_tx, |tx| {
+                handle_get_all_order_request(get_order_req, tx)
+            });
+            (handle, None)
+        }
+        TaskRequest::GetTicketInfoRequest(get_ticketinfo_req) => {
+            let handle = spawn_tracked(kind, task_id, tasks, result_tx, done_tx, |tx| {
+                handle_get_ticket_info_request(get_ticketinfo_req, tx)
+            });
+            (handle, None)
+        }
+        TaskRequest::GetBuyerInfoRequest(get_buyerinfo_req) => {
+            let handle = spawn_tracked(kind, task_id, tasks, result_tx, done_tx, |tx| {
+                handle_get_buyer_info_request(get_buyerinfo_req, tx)
+            });
+            (handle, None)
+        }
+        TaskRequest::GrabTicketRequest(grab_ticket_req) => {
+            // 抢票任务内的日志自动带上任务 ID 与账号
+            let log_task_id = grab_ticket_req.task_id.clone();
+            let log_uid = grab_ticket_req.uid;
+            let token = cancel.unwrap_or_default();
+            let task_token = token.clone();
+            let handle = spawn_tracked(kind, task_id, tasks, result_tx, done_tx, |tx| {
+                log_record::with_grab_context(
+                    log_task_id,
+                    log_uid,
+                    handle_grab_ticket_request(grab_ticket_req, tx, task_token),
+                )
+            });
+            (handle, Some(token))
+        }
+    }
+}
 
-        Self {
-            task_sender: task_tx,
-            result_receiver: Some(result_rx),
-            running_tasks: HashMap::new(),
-            runtime,
-            _worker_thread: Some(worker),
-            dungeon_service,
+impl TaskManagerImpl {
+    // 调整已结束任务的回收策略
+    pub fn set_gc_policy(&mut self, policy: TaskGcPolicy) {
+        self.gc_policy = policy;
+    }
+
+    // 可跨线程共享的任务查询与取消句柄
+    pub fn control(&self) -> TaskControlHandle {
+        TaskControlHandle {
+            tasks: self.tasks.clone(),
+            task_sender: self.task_sender.clone(),
         }
     }
+}
 
-    fn submit_task(&mut self, request: TaskRequest) -> Result<String, String> {
-        let task_id = match &request {
-            TaskRequest::GetBuyerInfoRequest(req) => {
-                if !req.task_id.is_empty() {
-                    req.task_id.clone()
-                } else {
-                    uuid::Uuid::new_v4().to_string()
-                }
-            }
-            TaskRequest::GetTicketInfoRequest(req) => {
-                if !req.task_id.is_empty() {
-                    req.task_id.clone()
-                } else {
-                    uuid::Uuid::new_v4().to_string()
-                }
-            }
-            TaskRequest::GrabTicketRequest(req) => {
-                if !req.task_id.is_empty() {
-                    req.task_id.clone()
-                } else {
-                    uuid::Uuid::new_v4().to_string()
+#[derive(Clone)]
+pub struct TaskControlHandle {
+    tasks: TaskTable,
+    task_sender: mpsc::Sender<TaskMessage>,
+}
+
+impl TaskControlHandle {
+    // 登记并提交任务，供定时任务等其他线程使用
+    pub fn submit(&self, mut request: TaskRequest) -> Result<String, String> {
+        // 沿用请求自带的任务 ID，没有时生成一个并写回请求，保证结果中的 ID 与返回值一致
+        let task_id = match request.task_id_mut() {
+            Some(id) => {
+                if id.is_empty() {
+                    *id = uuid::Uuid::new_v4().to_string();
                 }
+                id.clone()
             }
-            _ => uuid::Uuid::new_v4().to_string(),
+            None => uuid::Uuid::new_v4().to_string(),
         };
 
         match &request {
@@ -153,7 +209,7 @@ impl TaskManager for TaskManagerImpl {
                     status: TaskStatus::Pending,
                     start_time: Some(std::time::Instant::now()),
                 };
-                self.running_tasks
+                self.tasks
                     .insert(task_id.clone(), Task::QrCodeLoginTask(task));
             }
             TaskRequest::LoginSmsRequest(login_sms_req) => {
@@ -168,7 +224,7 @@ impl TaskManager for TaskManagerImpl {
                     status: TaskStatus::Pending,
                     start_time: Some(std::time::Instant::now()),
                 };
-                self.running_tasks
+                self.tasks
                     .insert(task_id.clone(), Task::LoginSmsRequestTask(task));
             }
             TaskRequest::PushRequest(push_req) => {
@@ -181,8 +237,7 @@ impl TaskManager for TaskManagerImpl {
                     status: TaskStatus::Pending,
                     start_time: Some(std::time::Instant::now()),
                 };
-                self.running_tasks
-                    .insert(task_id.clone(), Task::PushTask(task));
+                self.tasks.insert(task_id.clone(), Task::PushTask(task));
             }
             TaskRequest::SubmitLoginSmsRequest(login_sms_req) => {
                 log::info!(
@@ -198,7 +253,7 @@ impl TaskManager for TaskManagerImpl {
                     status: TaskStatus::Pending,
                     start_time: Some(std::time::Instant::now()),
                 };
-                self.running_tasks
+                self.tasks
                     .insert(task_id.clone(), Task::SubmitLoginSmsRequestTask(task));
             }
             TaskRequest::GetAllorderRequest(get_order_req) => {
@@ -211,7 +266,7 @@ impl TaskManager for TaskManagerImpl {
                     account_id: get_order_req.account_id.clone(),
                     start_time: Some(std::time::Instant::now()),
                 };
-                self.running_tasks
+                self.tasks
                     .insert(task_id.clone(), Task::GetAllorderRequestTask(task));
             }
             TaskRequest::GetTicketInfoRequest(get_ticketinfo_req) => {
@@ -219,11 +274,11 @@ impl TaskManager for TaskManagerImpl {
                 let task = GetTicketInfoTask {
                     task_id: task_id.clone(),
                     project_id: get_ticketinfo_req.project_id.clone(),
-                    status: TaskStatus::Running,
+                    status: TaskStatus::Pending,
                     start_time: Some(std::time::Instant::now()),
                     cookie_manager: get_ticketinfo_req.cookie_manager.clone(),
                 };
-                self.running_tasks
+                self.tasks
                     .insert(task_id.clone(), Task::GetTicketInfoTask(task));
             }
             TaskRequest::GetBuyerInfoRequest(get_buyerinfo_req) => {
@@ -235,19 +290,27 @@ impl TaskManager for TaskManagerImpl {
                     status: TaskStatus::Pending,
                     start_time: Some(std::time::Instant::now()),
                 };
-                self.running_tasks
+                self.tasks
                     .insert(task_id.clone(), Task::GetBuyerInfoTask(task));
             }
             TaskRequest::GrabTicketRequest(grab_ticket_req) => {
+                if GrabPlan::for_request(grab_ticket_req).is_none() {
+                    return Err(format!("未知的抢票模式: {}", grab_ticket_req.grab_mode));
+                }
                 log::info!("提交抢票任务 ID: {}", task_id);
+                // 写入任务日志，程序重启后可恢复
+                let mut entry = JournalEntry::from_request(grab_ticket_req);
+                entry.task_id = task_id.clone();
+                task_journal::record(&entry);
                 let task = GrabTicketTask {
                     task_id: task_id.clone(),
                     biliticket: grab_ticket_req.biliticket.clone(),
                     status: TaskStatus::Pending,
+                    phase: None,
                     client: grab_ticket_req.cookie_manager.client.clone(),
                     start_time: Some(std::time::Instant::now()),
                 };
-                self.running_tasks
+                self.tasks
                     .insert(task_id.clone(), Task::GrabTicketTask(task));
             }
         }
@@ -261,6 +324,155 @@ impl TaskManager for TaskManagerImpl {
 
         Ok(task_id)
     }
+}
+
+impl TaskControl for TaskControlHandle {
+    fn list_tasks(&self) -> Vec<TaskSummary> {
+        self.tasks.list()
+    }
+
+    // 已结束的任务直接返回成功，状态保持不变；
+    // 取消是异步的，任务退出后状态才变为已取消（抢票任务在取消前已下单成功则为已完成）
+    fn cancel_task(&self, task_id: &str) -> Result<(), String> {
+        match self.tasks.status(task_id) {
+            None => Err("任务不存在".to_string()),
+            Some(status) if status.is_finished() => Ok(()),
+            Some(_) => self
+                .task_sender
+                .blocking_send(TaskMessage::CancelTask(task_id.to_owned()))
+                .map_err(|e| format!("无法取消任务: {}", e)),
+        }
+    }
+}
+
+enum TaskMessage {
+    SubmitTask((String, TaskRequest)),
+    CancelTask(String),
+    LimitsChanged, //并发上限已更新，按新上限启动排队中的任务
+    Shutdown,
+}
+
+impl TaskManager for TaskManagerImpl {
+    fn new() -> Self {
+        let (task_tx, mut task_rx) = mpsc::channel(100);
+        let (result_tx, result_rx) = mpsc::channel(100);
+
+        let runtime = Arc::new(Runtime::new().unwrap());
+        let rt = runtime.clone();
+
+        let dungeon_service = Arc::new(Mutex::new(None));
+        let ds_clone = dungeon_service.clone();
+        let tasks = TaskTable::default();
+        let worker_tasks = tasks.clone();
+
+        let limits = Arc::new(std::sync::Mutex::new(TaskLimits::default()));
+        let worker_limits = limits.clone();
+
+        let worker = thread::spawn(move || {
+            // 抢票任务带有取消令牌，取消时等待其自行退出；其他任务直接中止
+            let mut task_handles: HashMap<String, (JoinHandle<()>, Option<CancellationToken>)> =
+                HashMap::new();
+            let mut queue: TaskQueue<TaskRequest> = TaskQueue::default();
+            let (done_tx, mut done_rx) = mpsc::unbounded_channel::<String>();
+
+            rt.block_on(async {
+                loop {
+                    let mut submitted = None;
+                    tokio::select! {
+                        msg = task_rx.recv() => match msg {
+                            Some(TaskMessage::SubmitTask((task_id, request))) => {
+                                submitted = Some(task_id.clone());
+                                queue.push(task_id, request.kind(), request);
+                            }
+                            Some(TaskMessage::CancelTask(task_id)) => {
+                                if let Some(request) = queue.remove(&task_id) {
+                                    log::info!("取消排队中的任务: {}", &task_id);
+                                    if let TaskRequest::GrabTicketRequest(_) = request {
+                                        // 以已取消状态启动，由抢票流程统一发送取消结果并清理任务日志
+                                        let token = CancellationToken::new();
+                                        token.cancel();
+                                        let (handle, cancel) = start_task(
+                                            task_id.clone(),
+                                            request,
+                                            Some(token),
+                                            &worker_tasks,
+                                            &result_tx,
+                                            &ds_clone,
+                                            &done_tx,
+                                        )
+                                        .await;
+                                        task_handles.insert(task_id, (handle, cancel));
+                                    } else {
+                                        worker_tasks.set_status(&task_id, TaskStatus::Cancelled);
+                                    }
+                                } else {
+                                    match task_handles.remove(&task_id) {
+                                        Some((_, Some(cancel))) => {
+                                            log::info!(
+                                                "正在取消任务: {}，等待当前请求结束",
+                                                &task_id
+                                            );
+                                            cancel.cancel();
+                                        }
+                                        Some((handle, None)) => {
+                                            log::info!("正在取消任务: {}", &task_id);
+                                            handle.abort();
+                                            queue.finish(&task_id);
+                                            worker_tasks
+                                                .set_status(&task_id, TaskStatus::Cancelled);
+                                        }
+                                        None => {}
+                                    }
+                                }
+                            }
+                            Some(TaskMessage::LimitsChanged) => {}
+                            Some(TaskMessage::Shutdown) | None => break,
+                        },
+                        Some(task_id) = done_rx.recv() => {
+                            queue.finish(&task_id);
+                        }
+                    }
+
+                    let limits = worker_limits.lock().map(|l| l.clone()).unwrap_or_default();
+                    for (task_id, request) in queue.pop_ready(&limits) {
+                        let (handle, cancel) = start_task(
+                            task_id.clone(),
+                            request,
+                            None,
+                            &worker_tasks,
+                            &result_tx,
+                            &ds_clone,
+                            &done_tx,
+                        )
+                        .await;
+                        task_handles.retain(|_, (handle, _)| !handle.is_finished());
+                        task_handles.insert(task_id, (handle, cancel));
+                    }
+                    if let Some(task_id) = submitted
+                        && queue.is_waiting(&task_id)
+                    {
+                        log::info!("任务 {} 排队等待并发名额", task_id);
+                    }
+                }
+            });
+        });
+
+        Self {
+            task_sender: task_tx,
+            result_receiver: Some(result_rx),
+            tasks,
+            gc_policy: TaskGcPolicy::default(),
+            runtime,
+            limits,
+            _worker_thread: Some(worker),
+            dungeon_service,
+        }
+    }
+
+    fn submit_task(&mut self, request: TaskRequest) -> Result<String, String> {
+        self.tasks.gc(&self.gc_policy);
+        self.control().submit(request)
+    }
 
     fn set_result_sender(&mut self, sender: mpsc::Sender<TaskResult>) {
         if let Some(mut rx) = self.result_receiver.take() {
@@ -287,41 +499,30 @@ impl TaskManager for TaskManagerImpl {
     }
 
     fn cancel_task(&mut self, task_id: &str) -> Result<(), String> {
-        if let Some(task) = self.running_tasks.get_mut(task_id) {
-            if let Err(e) = self
-                .task_sender
-                .blocking_send(TaskMessage::CancelTask(task_id.to_owned()))
-            {
-                return Err(format!("无法取消任务: {}", e));
-            }
-            let new_status = TaskStatus::Cancelled;
-            match task {
-                Task::QrCodeLoginTask(t) => t.status = new_status,
-                Task::LoginSmsRequestTask(t) => t.status = new_status,
-                Task::PushTask(t) => t.status = new_status,
-                Task::SubmitLoginSmsRequestTask(t) => t.status = new_status,
-                Task::GetAllorderRequestTask(t) => t.status = new_status,
-                Task::GetTicketInfoTask(t) => t.status = new_status,
-                Task::GetBuyerInfoTask(t) => t.status = new_status,
-                Task::GrabTicketTask(t) => t.status = new_status,
-            }
-            Ok(())
-        } else {
-            Err("任务不存在".to_string())
-        }
+        self.control().cancel_task(task_id)
     }
 
     fn get_task_status(&self, task_id: &str) -> Option<TaskStatus> {
-        self.running_tasks.get(task_id).map(|task| match task {
-            Task::QrCodeLoginTask(t) => t.status.clone(),
-            Task::LoginSmsRequestTask(t) => t.status.clone(),
-            Task::PushTask(t) => t.status.clone(),
-            Task::SubmitLoginSmsRequestTask(t) => t.status.clone(),
-            Task::GetAllorderRequestTask(t) => t.status.clone(),
-            Task::GetTicketInfoTask(t) => t.status.clone(),
-            Task::GetBuyerInfoTask(t) => t.status.clone(),
-            Task::GrabTicketTask(t) => t.status.clone(),
-        })
+        self.tasks.gc(&self.gc_policy);
+        self.tasks.status(task_id)
+    }
+
+    fn get_task_phase(&self, task_id: &str) -> Option<TaskPhase> {
+        self.tasks.phase(task_id)
+    }
+
+    fn list_tasks(&self) -> Vec<TaskSummary> {
+        self.tasks.gc(&self.gc_policy);
+        self.tasks.list()
+    }
+
+    fn set_limits(&self, limits: TaskLimits) {
+        if let Ok(mut current) = self.limits.lock() {
+            *current = limits;
+        }
+        // 唤醒工作线程重新检查排队任务；可能在异步命令中调用，不能阻塞发送。
+        // 通道已满时工作线程处理完其他消息也会按新上限检查
+        let _ = self.task_sender.try_send(TaskMessage::LimitsChanged);
     }
 
     fn shutdown(&mut self) {
@@ -1,6 +1,9 @@
 use crate::dungeon::DungeonService;
+use common::metrics;
+use common::push::{ChannelReport, Notification, PushRegistry};
 use common::taskmanager::{PushRequest, PushRequestResult, PushType, TaskResult};
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::mpsc;
 
 pub async fn handle_push_request(
@@ -10,74 +13,74 @@ pub async fn handle_push_request(
 ) {
     let task_id = uuid::Uuid::new_v4().to_string();
     let push_config = push_req.push_config.clone();
-    let title = push_req.title.clone();
-    let message = push_req.message.clone();
-    let jump_url = push_req.jump_url.clone();
     let push_type = push_req.push_type.clone();
+    let notification = Notification {
+        jump_url: push_req.jump_url.clone(),
+        ..Notification::new(push_req.title.clone(), push_req.message.clone())
+    };
 
     log::info!("开始处理推送任务 ID: {}, 类型: {:?}", task_id, push_type);
 
-    let (success, result_message, dungeon_target_id) = match push_type {
-        PushType::All => {
-            let mut dungeon_handled = false;
-            let mut dungeon_res = (false, String::new(), None);
-
-            if push_config.enabled_methods.contains(&"dungeon".to_string())
-                && push_config.dungeon_config.enabled
-            {
-                if let Some(service) = dungeon_service {
-                    let dc = &push_config.dungeon_config;
-                    log::info!("使用持久连接发送 Dungeon 脉冲...");
-                    match service
-                        .send_pulse(
-                            dc.channel,
-                            dc.intensity,
-                            dc.frequency,
-                            dc.pulse_ms,
-                            dc.pause_ms,
-                            dc.count,
-                        )
-                        .await
-                    {
-                        Ok(_) => {
-                            dungeon_handled = true;
-                            let tid = service.target_id.lock().await.clone();
-                            dungeon_res = (true, "Dungeon 持久连接推送成功".to_string(), tid);
-                        }
-                        Err(e) => {
-                            log::warn!("Dungeon 持久连接推送失败，将尝试重新连接: {}", e);
-                        }
-                    }
-                }
-            }
+    let mut registry = PushRegistry::from_config(&push_config, Some(result_tx.clone()));
+    if let Some(channel) = push_type.channel() {
+        registry.retain(|name| name == channel);
+    }
 
-            let mut effective_config = push_config.clone();
-            if dungeon_handled {
-                effective_config.enabled_methods.retain(|m| m != "dungeon");
+    // 优先使用持久连接发送 Dungeon 脉冲，成功后不再单独建立连接
+    let mut dungeon_report = None;
+    let mut dungeon_target_id = None;
+    if matches!(push_type, PushType::All | PushType::Dungeon)
+        && registry.names().contains(&"dungeon")
+        && let Some(service) = dungeon_service
+    {
+        let dc = &push_config.dungeon_config;
+        log::info!("使用持久连接发送 Dungeon 脉冲...");
+        let started = Instant::now();
+        match service
+            .send_pulse(
+                dc.channel,
+                dc.intensity,
+                dc.frequency,
+                dc.pulse_ms,
+                dc.pause_ms,
+                dc.count,
+            )
+            .await
+        {
+            Ok(_) => {
+                metrics::record_push("dungeon", true);
+                registry.retain(|name| name != "dungeon");
+                dungeon_target_id = service.target_id.lock().await.clone();
+                dungeon_report = Some(ChannelReport {
+                    channel: "dungeon".to_string(),
+                    success: true,
+                    message: "Dungeon 持久连接推送成功".to_string(),
+                    elapsed_ms: started.elapsed().as_millis() as u64,
+                });
             }
-
-            let (mut succ, msg, mut tid) = effective_config
-                .push_all_async(&title, &message, &jump_url, Some(result_tx.clone()))
-                .await;
-
-            if dungeon_handled {
-                succ = true;
-                if tid.is_none() {
-                    tid = dungeon_res.2;
-                }
+            Err(e) => {
+                metrics::record_push("dungeon", false);
+                log::warn!("Dungeon 持久连接推送失败，将尝试重新连接: {}", e);
             }
-
-            (succ, msg, tid)
         }
-        _ => (false, "未实现的推送类型".to_string(), None),
-    };
+    }
+
+    let mut report = registry.send_all(&notification).await;
+    if let Some(dungeon_report) = dungeon_report {
+        report.channels.push(dungeon_report);
+    }
+    if report.dungeon_target_id.is_none() {
+        report.dungeon_target_id = dungeon_target_id;
+    }
+    let success = report.success();
 
     let task_result = TaskResult::PushResult(PushRequestResult {
         task_id: task_id.clone(),
         success,
-        message: result_message,
+        message: report.summary(),
         push_type: push_type.clone(),
-        dungeon_target_id,
+        dungeon_target_id: report.dungeon_target_id,
+        channels: report.channels,
     });
 
     if let Err(e) = result_tx.send(task_result).await {
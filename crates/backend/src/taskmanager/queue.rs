@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use common::config::TaskLimits;
+use common::taskmanager::TaskKind;
+
+// 任务优先级：抢票 > 登录、推送等交互任务 > 订单、项目、购票人等后台查询
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TaskPriority {
+    Grab,
+    Interactive,
+    Background,
+}
+
+impl TaskPriority {
+    pub fn of(kind: TaskKind) -> Self {
+        match kind {
+            TaskKind::GrabTicket => TaskPriority::Grab,
+            TaskKind::GetAllOrder | TaskKind::GetTicketInfo | TaskKind::GetBuyerInfo => {
+                TaskPriority::Background
+            }
+            TaskKind::QrCodeLogin
+            | TaskKind::LoginSms
+            | TaskKind::Push
+            | TaskKind::SubmitLoginSms => TaskPriority::Interactive,
+        }
+    }
+}
+
+struct Waiting<T> {
+    priority: TaskPriority,
+    seq: u64,
+    task_id: String,
+    kind: TaskKind,
+    item: T,
+}
+
+// 等待启动的任务与运行中任务的计数，按优先级和各类型上限决定启动顺序
+pub struct TaskQueue<T> {
+    waiting: Vec<Waiting<T>>,
+    running: HashMap<String, TaskKind>,
+    next_seq: u64,
+}
+
+impl<T> Default for TaskQueue<T> {
+    fn default() -> Self {
+        Self {
+            waiting: Vec::new(),
+            running: HashMap::new(),
+            next_seq: 0,
+        }
+    }
+}
+
+impl<T> TaskQueue<T> {
+    pub fn push(&mut self, task_id: String, kind: TaskKind, item: T) {
+        self.waiting.push(Waiting {
+            priority: TaskPriority::of(kind),
+            seq: self.next_seq,
+            task_id,
+            kind,
+            item,
+        });
+        self.next_seq += 1;
+        self.waiting.sort_by_key(|w| (w.priority, w.seq));
+    }
+
+    // 取出还没启动的任务（排队中被取消）
+    pub fn remove(&mut self, task_id: &str) -> Option<T> {
+        let index = self.waiting.iter().position(|w| w.task_id == task_id)?;
+        Some(self.waiting.remove(index).item)
+    }
+
+    pub fn is_waiting(&self, task_id: &str) -> bool {
+        self.waiting.iter().any(|w| w.task_id == task_id)
+    }
+
+    pub fn finish(&mut self, task_id: &str) {
+        self.running.remove(task_id);
+    }
+
+    pub fn running(&self, kind: TaskKind) -> usize {
+        self.running.values().filter(|k| **k == kind).count()
+    }
+
+    // 按优先级取出现在可以启动的任务，并记为运行中
+    pub fn pop_ready(&mut self, limits: &TaskLimits) -> Vec<(String, T)> {
+        let mut ready = Vec::new();
+        let mut index = 0;
+        while index < self.waiting.len() {
+            let waiting = &self.waiting[index];
+            if self.can_start(waiting.kind, waiting.priority, limits) {
+                let waiting = self.waiting.remove(index);
+                self.running.insert(waiting.task_id.clone(), waiting.kind);
+                ready.push((waiting.task_id, waiting.item));
+            } else {
+                index += 1;
+            }
+        }
+        ready
+    }
+
+    fn can_start(&self, kind: TaskKind, priority: TaskPriority, limits: &TaskLimits) -> bool {
+        let limit = limits.limit(kind);
+        if limit > 0 && self.running(kind) >= limit {
+            return false;
+        }
+        if priority != TaskPriority::Background || limits.background_while_grabbing == 0 {
+            return true;
+        }
+        // 有抢票任务运行或排队时，后台查询让出连接
+        let grabbing = self.running(TaskKind::GrabTicket) > 0
+            || self
+                .waiting
+                .iter()
+                .any(|w| w.priority == TaskPriority::Grab);
+        let background = self
+            .running
+            .values()
+            .filter(|k| TaskPriority::of(**k) == TaskPriority::Background)
+            .count();
+        !grabbing || background < limits.background_while_grabbing
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use common::taskmanager::{Task, TaskPhase, TaskStatus, TaskSummary};
+
+// 任务状态表：提交时登记，处理函数运行中上报状态与阶段，结束的任务按回收策略清理
+
+// 已结束任务的回收策略
+#[derive(Clone, Debug)]
+pub struct TaskGcPolicy {
+    pub retention: Duration, //结束后保留多久
+    pub max_finished: usize, //最多保留多少个已结束任务
+}
+
+impl Default for TaskGcPolicy {
+    fn default() -> Self {
+        Self {
+            retention: Duration::from_secs(10 * 60),
+            max_finished: 200,
+        }
+    }
+}
+
+#[derive(Default)]
+struct TaskTableInner {
+    tasks: HashMap<String, Task>,
+    finished_at: HashMap<String, Instant>,
+}
+
+#[derive(Clone, Default)]
+pub struct TaskTable {
+    inner: Arc<Mutex<TaskTableInner>>,
+}
+
+impl TaskTable {
+    pub fn insert(&self, task_id: String, task: Task) {
+        if let Ok(mut inner) = self.inner.lock() {
+            inner.finished_at.remove(&task_id);
+            inner.tasks.insert(task_id, task);
+        }
+    }
+
+    pub fn status(&self, task_id: &str) -> Option<TaskStatus> {
+        let inner = self.inner.lock().ok()?;
+        inner.tasks.get(task_id).map(|task| task.status().clone())
+    }
+
+    pub fn phase(&self, task_id: &str) -> Option<TaskPhase> {
+        let inner = self.inner.lock().ok()?;
+        inner.tasks.get(task_id).and_then(|task| task.phase())
+    }
+
+    // 已结束（完成、失败、取消）的任务状态不会再被覆盖
+    pub fn set_status(&self, task_id: &str, status: TaskStatus) -> bool {
+        let Ok(mut inner) = self.inner.lock() else {
+            return false;
+        };
+        let Some(task) = inner.tasks.get_mut(task_id) else {
+            return false;
+        };
+        if task.status().is_finished() {
+            return false;
+        }
+        let finished = status.is_finished();
+        task.set_status(status);
+        if finished {
+            inner
+                .finished_at
+                .insert(task_id.to_string(), Instant::now());
+        }
+        true
+    }
+
+    pub fn set_phase(&self, task_id: &str, phase: TaskPhase) {
+        if let Ok(mut inner) = self.inner.lock()
+            && let Some(Task::GrabTicketTask(task)) = inner.tasks.get_mut(task_id)
+        {
+            task.phase = Some(phase);
+        }
+    }
+
+    // 按开始时间从新到旧
+    pub fn list(&self) -> Vec<TaskSummary> {
+        let Ok(inner) = self.inner.lock() else {
+            return Vec::new();
+        };
+        let mut summaries: Vec<TaskSummary> = inner
+            .tasks
+            .iter()
+            .map(|(id, task)| task.summary(id, inner.finished_at.get(id).copied()))
+            .collect();
+        summaries.sort_by_key(|s| std::cmp::Reverse(s.started_at));
+        summaries
+    }
+
+    // 清理超过保留时间的已结束任务，数量超限时先清理最早结束的
+    pub fn gc(&self, policy: &TaskGcPolicy) -> usize {
+        let Ok(mut inner) = self.inner.lock() else {
+            return 0;
+        };
+        let mut finished: Vec<(String, Instant)> = inner
+            .finished_at
+            .iter()
+            .map(|(id, at)| (id.clone(), *at))
+            .collect();
+        finished.sort_by_key(|(_, at)| *at);
+
+        let over_limit = finished.len().saturating_sub(policy.max_finished);
+        let expired: Vec<String> = finished
+            .into_iter()
+            .enumerate()
+            .filter(|(i, (_, at))| *i < over_limit || at.elapsed() >= policy.retention)
+            .map(|(_, (id, _))| id)
+            .collect();
+        for id in &expired {
+            inner.tasks.remove(id);
+            inner.finished_at.remove(id);
+        }
+        expired.len()
+    }
+}
+
+tokio::task_local! {
+    static CURRENT_TASK: (String, TaskTable);
+}
+
+// 在任务上下文中运行，处理函数可直接上报阶段
+pub async fn scope<F: Future>(task_id: String, table: TaskTable, task: F) -> F::Output {
+    CURRENT_TASK.scope((task_id, table), task).await
+}
+
+pub fn report_phase(phase: TaskPhase) {
+    let _ = CURRENT_TASK.try_with(|(task_id, table)| table.set_phase(task_id, phase));
+}
@@ -0,0 +1,29 @@
+mod support;
+
+use backend::api::{get_buyer_info, get_countdown, get_project};
+use backend::show_orderlist::get_orderlist;
+use mock_server::{MockServer, Scenario, fixtures};
+use support::cookie_manager;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn fetches_project_buyers_and_orders() {
+    let server = MockServer::start(Scenario::on_sale()).await.unwrap();
+    let cookie_manager = cookie_manager(&server);
+
+    let project = get_project(cookie_manager.clone(), &fixtures::PROJECT_ID.to_string())
+        .await
+        .unwrap();
+    assert_eq!(project.data.id as i64, fixtures::PROJECT_ID);
+    assert_eq!(project.data.screen_list[0].ticket_list.len(), 1);
+
+    let buyers = get_buyer_info(cookie_manager.clone()).await.unwrap();
+    assert_eq!(buyers.data.list[0].id, fixtures::BUYER_ID);
+
+    let orders = get_orderlist(cookie_manager.clone()).await.unwrap();
+    assert_eq!(orders.data.total, 1);
+
+    let countdown = get_countdown(cookie_manager, Some(project.data))
+        .await
+        .unwrap();
+    assert!(countdown < 0.0);
+}
@@ -0,0 +1,336 @@
+mod support;
+
+use std::time::{Duration, SystemTime};
+
+use backend::taskmanager::grab_mode::GrabPlan;
+use common::history::{self, HistoryEvent, HistoryQuery};
+use common::metrics;
+use common::ticket::TicketPreference;
+use common::ticket_error::ErrorClass;
+use mock_server::{MockServer, Scenario, Script, fixtures};
+use support::{grab_request, run_grab, run_grab_plan, run_grab_with_cancel, test_config};
+use tokio_util::sync::CancellationToken;
+
+const PREPARE: &str = "/api/ticket/order/prepare";
+const CONFIRM: &str = "/api/ticket/order/confirmInfo";
+const CREATE: &str = "/api/ticket/order/createV2";
+const PROJECT: &str = "/api/ticket/project/getV2";
+const RISK_REGISTER: &str = "/x/gaia-vgate/v1/register";
+
+#[tokio::test(flavor = "multi_thread")]
+async fn timed_mode_waits_for_sale_start() {
+    let server = MockServer::start(Scenario::sale_starts_in(5))
+        .await
+        .unwrap();
+    let started = SystemTime::now();
+
+    let results = run_grab(grab_request(&server, 0, test_config())).await;
+
+    assert_eq!(results.len(), 1);
+    assert!(results[0].success, "{}", results[0].message);
+    assert_eq!(server.hit_count(PROJECT), 1);
+    let first_prepare = server.hits_for(PREPARE)[0].at;
+    assert!(first_prepare.duration_since(started).unwrap() >= Duration::from_secs(3));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn cancelled_while_waiting_reports_cancelled_result() {
+    let server = MockServer::start(Scenario::sale_starts_in(60))
+        .await
+        .unwrap();
+    let cancel = CancellationToken::new();
+    let trigger = cancel.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        trigger.cancel();
+    });
+
+    let results = run_grab_with_cancel(grab_request(&server, 0, test_config()), cancel).await;
+
+    assert_eq!(results.len(), 1);
+    assert!(results[0].cancelled);
+    assert!(!results[0].success);
+    assert_eq!(server.hit_count(PREPARE), 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn direct_mode_retries_stock_shortage() {
+    let server = MockServer::start(Scenario::stock_shortage_then_success(3))
+        .await
+        .unwrap();
+
+    let results = run_grab(grab_request(&server, 1, test_config())).await;
+
+    assert_eq!(results.len(), 1);
+    assert!(results[0].success, "{}", results[0].message);
+    assert_eq!(
+        results[0].order_id.as_deref(),
+        Some(fixtures::ORDER_ID.to_string().as_str())
+    );
+    assert_eq!(server.hit_count(PREPARE), 1);
+    assert_eq!(server.hit_count(CREATE), 4);
+
+    let records = history::query(&HistoryQuery {
+        task_id: Some(results[0].task_id.clone()),
+        ..HistoryQuery::default()
+    })
+    .unwrap();
+    let summary = &history::summarize(&records)[0];
+    assert_eq!(summary.success, Some(true));
+    assert_eq!(summary.error_codes.get(&100009), Some(&3));
+    assert_eq!(
+        records
+            .iter()
+            .filter(|r| r.event == HistoryEvent::Create)
+            .count(),
+        4
+    );
+
+    // 指标是进程内全局的，其他测试不会产生 100009
+    let stats = metrics::snapshot();
+    assert!(stats.error_codes.get(&100009).copied().unwrap_or(0) >= 3);
+    assert!(stats.grab_success >= 1);
+    assert!(
+        stats
+            .latencies
+            .iter()
+            .any(|l| l.endpoint == "order/createV2" && l.histogram.count >= 4)
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn direct_mode_recovers_from_risk() {
+    let server = MockServer::start(Scenario::risk_then_success())
+        .await
+        .unwrap();
+
+    let results = run_grab(grab_request(&server, 1, test_config())).await;
+
+    assert_eq!(results.len(), 1);
+    assert!(results[0].success, "{}", results[0].message);
+    assert_eq!(server.hit_count(RISK_REGISTER), 1);
+    assert_eq!(server.hit_count(PREPARE), 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn direct_mode_gives_up_after_token_retries() {
+    let scenario = Scenario {
+        prepare: Script::once(fixtures::prepare_err(100001)),
+        ..Scenario::default()
+    };
+    let server = MockServer::start(scenario).await.unwrap();
+    let config = common::config::CustomConfig {
+        max_token_retry: 2,
+        ..test_config()
+    };
+
+    let results = run_grab(grab_request(&server, 1, config)).await;
+
+    assert_eq!(results.len(), 1);
+    assert!(!results[0].success);
+    assert!(results[0].message.contains("100001"));
+    assert_eq!(results[0].error_code, Some(100001));
+    assert_eq!(results[0].error_class, Some(ErrorClass::Retryable));
+    assert_eq!(server.hit_count(PREPARE), 2);
+    assert_eq!(server.hit_count(CREATE), 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn direct_mode_stops_on_fatal_token_error() {
+    let scenario = Scenario {
+        prepare: Script::once(fixtures::prepare_err(100080)),
+        ..Scenario::default()
+    };
+    let server = MockServer::start(scenario).await.unwrap();
+
+    let results = run_grab(grab_request(&server, 1, test_config())).await;
+
+    assert_eq!(results.len(), 1);
+    assert!(!results[0].success);
+    assert_eq!(results[0].error_code, Some(100080));
+    assert_eq!(results[0].error_class, Some(ErrorClass::Fatal));
+    assert_eq!(server.hit_count(PREPARE), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn direct_mode_stops_when_confirm_needs_relogin() {
+    let scenario = Scenario {
+        confirm: Script::once(fixtures::confirm_err(-101)),
+        ..Scenario::default()
+    };
+    let server = MockServer::start(scenario).await.unwrap();
+
+    let results = run_grab(grab_request(&server, 1, test_config())).await;
+
+    assert_eq!(results.len(), 1);
+    assert!(!results[0].success);
+    assert_eq!(results[0].error_class, Some(ErrorClass::NeedsRelogin));
+    assert_eq!(server.hit_count(CONFIRM), 1);
+    assert_eq!(server.hit_count(CREATE), 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn direct_mode_stops_on_pending_order() {
+    let scenario = Scenario {
+        create: Script::once(fixtures::create_err(100079)),
+        ..Scenario::default()
+    };
+    let server = MockServer::start(scenario).await.unwrap();
+
+    let results = run_grab(grab_request(&server, 1, test_config())).await;
+
+    assert_eq!(results.len(), 1);
+    assert!(!results[0].success);
+    assert!(results[0].message.contains("待付款"));
+    assert_eq!(results[0].error_code, Some(100079));
+    assert_eq!(results[0].error_class, Some(ErrorClass::Fatal));
+    assert_eq!(server.hit_count(CREATE), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn direct_mode_refreshes_expired_token() {
+    let scenario = Scenario {
+        create: Script::repeat_then(fixtures::create_err(100041), 1, fixtures::create_ok()),
+        ..Scenario::default()
+    };
+    let server = MockServer::start(scenario).await.unwrap();
+
+    let results = run_grab(grab_request(&server, 1, test_config())).await;
+
+    assert_eq!(results.len(), 1);
+    assert!(results[0].success, "{}", results[0].message);
+    assert_eq!(server.hit_count(PREPARE), 2);
+    assert_eq!(server.hit_count(CREATE), 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn leak_mode_waits_for_restock() {
+    let server = MockServer::start(Scenario::sold_out_then_restock())
+        .await
+        .unwrap();
+
+    let results = run_grab(grab_request(&server, 2, test_config())).await;
+
+    assert_eq!(results.len(), 1);
+    assert!(results[0].success, "{}", results[0].message);
+    assert_eq!(server.hit_count(PROJECT), 2);
+    assert_eq!(server.hit_count(PREPARE), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn leak_mode_retries_sold_out_on_same_ticket() {
+    // 捡漏模式没有候选列表，下单售罄时按重试策略继续下单，不重新检查票种
+    let scenario = Scenario {
+        create: Script::repeat_then(fixtures::create_err(100009), 2, fixtures::create_ok()),
+        ..Scenario::default()
+    };
+    let server = MockServer::start(scenario).await.unwrap();
+
+    let results = run_grab(grab_request(&server, 2, test_config())).await;
+
+    assert_eq!(results.len(), 1);
+    assert!(results[0].success, "{}", results[0].message);
+    assert_eq!(server.hit_count(PROJECT), 1);
+    assert_eq!(server.hit_count(PREPARE), 1);
+    assert_eq!(server.hit_count(CREATE), 3);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn leak_mode_reports_unsupported_project() {
+    let mut project = fixtures::project(true, fixtures::now_secs() - 60, false);
+    project["data"]["id_bind"] = 0.into();
+    let server = MockServer::start(Scenario {
+        project: Script::once(project),
+        ..Scenario::default()
+    })
+    .await
+    .unwrap();
+
+    let results = run_grab(grab_request(&server, 2, test_config())).await;
+
+    // 无法开始的任务也要返回结果，等待结果的调用方才不会一直阻塞
+    assert_eq!(results.len(), 1);
+    assert!(!results[0].success);
+    assert!(!results[0].cancelled);
+    assert!(results[0].message.contains("非实名"));
+    assert_eq!(server.hit_count(PREPARE), 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn scheduled_plan_tries_available_preferences() {
+    // 定时模式设置了候选票种：开售后按候选顺序跳过不可售的 3001，直接抢 3003
+    let mut project = fixtures::project(false, fixtures::now_secs() - 60, false);
+    let tickets = project["data"]["screen_list"][0]["ticket_list"]
+        .as_array_mut()
+        .unwrap();
+    let mut ticket = tickets[0].clone();
+    ticket["id"] = 3003.into();
+    ticket["clickable"] = true.into();
+    tickets.push(ticket);
+    project["data"]["screen_list"][0]["clickable"] = true.into();
+    let server = MockServer::start(Scenario {
+        project: Script::once(project),
+        ..Scenario::default()
+    })
+    .await
+    .unwrap();
+    let mut request = grab_request(&server, 0, test_config());
+    request.preferences = ["3001", "3003"]
+        .into_iter()
+        .map(|ticket_id| TicketPreference {
+            screen_id: fixtures::SCREEN_ID.to_string(),
+            ticket_id: Some(ticket_id.to_string()),
+            max_price: None,
+        })
+        .collect();
+    let plan = GrabPlan::for_request(&request).unwrap();
+
+    let results = run_grab_plan(request, plan).await;
+
+    assert_eq!(results.len(), 1);
+    assert!(results[0].success, "{}", results[0].message);
+    let prepares = server.hits_for(PREPARE);
+    assert_eq!(prepares.len(), 1);
+    assert!(prepares[0].body.contains("3003"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn preference_list_falls_back_after_sold_out() {
+    // 同一场次再加两个票种：3002 超出票价上限，3003 作为备选
+    let mut project = fixtures::project(true, fixtures::now_secs() - 60, false);
+    let tickets = project["data"]["screen_list"][0]["ticket_list"]
+        .as_array_mut()
+        .unwrap();
+    for (id, price) in [(3002, 20000), (3003, 8000)] {
+        let mut ticket = tickets[0].clone();
+        ticket["id"] = id.into();
+        ticket["price"] = price.into();
+        tickets.push(ticket);
+    }
+    let server = MockServer::start(Scenario {
+        project: Script::once(project),
+        create: Script::repeat_then(fixtures::create_err(100009), 1, fixtures::create_ok()),
+        ..Scenario::default()
+    })
+    .await
+    .unwrap();
+    let mut request = grab_request(&server, 1, test_config());
+    request.preferences = [("3001", None), ("3002", Some(15000)), ("3003", Some(15000))]
+        .into_iter()
+        .map(|(ticket_id, max_price)| TicketPreference {
+            screen_id: fixtures::SCREEN_ID.to_string(),
+            ticket_id: Some(ticket_id.to_string()),
+            max_price,
+        })
+        .collect();
+
+    let results = run_grab(request).await;
+
+    assert_eq!(results.len(), 1);
+    assert!(results[0].success, "{}", results[0].message);
+    assert_eq!(server.hit_count(PROJECT), 1);
+    let prepares = server.hits_for(PREPARE);
+    assert_eq!(prepares.len(), 2);
+    assert!(prepares[0].body.contains("3001"));
+    assert!(prepares[1].body.contains("3003"));
+}
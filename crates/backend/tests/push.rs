@@ -0,0 +1,496 @@
+use common::config::{
+    GotifyConfig, MatrixConfig, NtfyConfig, PushConfig, SmtpConfig, SmtpSecurity, WebhookConfig,
+};
+use common::push::{Notification, OrderSummary, PushChannel, PushRegistry, render_template};
+use common::ticket::{CheckFakeResultData, ConfirmTicketInfo, ConfirmTicketResult};
+use futures_util::future::BoxFuture;
+use mock_server::{SmtpSink, WebhookSink};
+use std::time::{Duration, Instant};
+
+fn smtp_push_config(sink: &SmtpSink) -> PushConfig {
+    PushConfig {
+        enabled: true,
+        enabled_methods: vec!["smtp".to_string()],
+        smtp_config: SmtpConfig {
+            host: sink.addr().ip().to_string(),
+            port: sink.addr().port(),
+            security: SmtpSecurity::None,
+            username: "bot@example.com".to_string(),
+            password: "smtp-password".to_string(),
+            from: String::new(),
+            to: vec!["a@example.com".to_string(), "b@example.com".to_string()],
+        },
+        ..PushConfig::default()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn smtp_push_sends_html_mail_with_payment_qr() {
+    let sink = SmtpSink::start().await.unwrap();
+    let config = smtp_push_config(&sink);
+
+    let notification = Notification {
+        pay_url: Some("https://pay.example.com/qr?order=5001".to_string()),
+        ..Notification::new(
+            "抢票成功: 模拟演出",
+            "项目: 模拟演出\n订单号: 5001\n请尽快支付！",
+        )
+    };
+    let report = config.push_all_async(&notification, None).await;
+
+    assert!(report.success(), "{:?}", report.channels);
+    let mails = sink.mails();
+    assert_eq!(mails.len(), 1);
+    let mail = &mails[0];
+    assert_eq!(mail.from, "bot@example.com");
+    assert_eq!(mail.to, vec!["a@example.com", "b@example.com"]);
+    assert!(mail.auth.is_some());
+    for part in [
+        "multipart/alternative",
+        "multipart/related",
+        "text/html",
+        "image/png",
+        "Content-ID: <pay_qr>",
+    ] {
+        assert!(mail.data.contains(part), "缺少 {}:\n{}", part, mail.data);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn smtp_push_sends_pay_link_when_qr_cannot_be_rendered() {
+    let sink = SmtpSink::start().await.unwrap();
+    let config = smtp_push_config(&sink);
+
+    // 超出二维码容量的链接
+    let notification = Notification {
+        pay_url: Some(format!(
+            "https://pay.example.com/qr?order={}",
+            "9".repeat(8000)
+        )),
+        ..Notification::new("抢票成功: 模拟演出", "订单号: 5001")
+    };
+    let report = config.push_all_async(&notification, None).await;
+
+    assert!(report.success(), "{:?}", report.channels);
+    let mails = sink.mails();
+    assert_eq!(mails.len(), 1);
+    assert!(mails[0].data.contains("text/html"));
+    assert!(!mails[0].data.contains("image/png"));
+    assert!(!mails[0].data.contains("cid:pay_qr"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn smtp_push_reports_unreachable_server() {
+    let sink = SmtpSink::start().await.unwrap();
+    let mut config = smtp_push_config(&sink);
+    drop(sink);
+    config.smtp_config.port = 1;
+
+    let report = config
+        .push_all_async(&Notification::new("标题", "内容"), None)
+        .await;
+
+    assert!(!report.success());
+    assert_eq!(report.channels.len(), 1);
+    assert_eq!(report.channels[0].channel, "smtp");
+    assert!(
+        report.channels[0].message.contains("发送邮件失败"),
+        "{}",
+        report.channels[0].message
+    );
+}
+
+// 按固定延迟返回固定结果的测试渠道
+struct FakeChannel {
+    name: &'static str,
+    delay: Duration,
+    result: Result<String, String>,
+}
+
+impl PushChannel for FakeChannel {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn is_configured(&self) -> bool {
+        true
+    }
+
+    fn send<'a>(
+        &'a self,
+        _notification: &'a Notification,
+    ) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            tokio::time::sleep(self.delay).await;
+            self.result.clone()
+        })
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_millis(500)
+    }
+}
+
+#[tokio::test]
+async fn registry_sends_concurrently_and_reports_each_channel() {
+    let mut registry = PushRegistry::new();
+    for (name, delay, result) in [
+        ("ok", 300, Ok("已送达".to_string())),
+        ("broken", 300, Err("token 无效".to_string())),
+        ("slow", 5000, Ok("不会返回".to_string())),
+    ] {
+        registry.register(Box::new(FakeChannel {
+            name,
+            delay: Duration::from_millis(delay),
+            result,
+        }));
+    }
+
+    let started = Instant::now();
+    let report = registry.send_all(&Notification::new("标题", "内容")).await;
+
+    // 三个渠道并发执行，总耗时只取决于最慢渠道的超时
+    assert!(started.elapsed() < Duration::from_millis(1500));
+    assert!(report.success());
+    assert_eq!(report.summary(), "1 成功 / 2 失败");
+    let channels: Vec<_> = report
+        .channels
+        .iter()
+        .map(|c| (c.channel.as_str(), c.success))
+        .collect();
+    assert_eq!(
+        channels,
+        vec![("ok", true), ("broken", false), ("slow", false)]
+    );
+    assert_eq!(report.channels[0].message, "已送达");
+    assert_eq!(report.channels[1].message, "token 无效");
+    assert!(report.channels[2].message.contains("超时"));
+}
+
+#[test]
+fn registry_skips_disabled_and_unconfigured_channels() {
+    let config = PushConfig {
+        enabled: true,
+        enabled_methods: vec!["bark".to_string(), "pushplus".to_string()],
+        bark_token: "token".to_string(),
+        fangtang_token: "token".to_string(),
+        ..PushConfig::default()
+    };
+
+    let registry = PushRegistry::from_config(&config, None);
+
+    assert_eq!(registry.names(), vec!["bark"]);
+}
+
+fn order_notification() -> Notification {
+    Notification {
+        pay_url: Some("https://pay.example.com/qr?order=5001&sign=a b".to_string()),
+        order: Some(OrderSummary {
+            project: "模拟演出".to_string(),
+            screen: "第一场".to_string(),
+            ticket: "VIP \"内场\"".to_string(),
+            count: 2,
+            price: 68000,
+            pay_money: 136000,
+            order_id: "5001".to_string(),
+        }),
+        ..Notification::new("抢票成功: 模拟演出", "订单号: 5001\n请尽快支付！")
+    }
+}
+
+#[tokio::test]
+async fn webhook_renders_templates_into_url_headers_and_body() {
+    let sink = WebhookSink::start().await.unwrap();
+    let config = PushConfig {
+        enabled: true,
+        enabled_methods: vec!["webhook".to_string()],
+        webhooks: vec![WebhookConfig {
+            name: "relay".to_string(),
+            url: sink.url("/hook?order={{order_id}}&pay={{pay_url}}"),
+            method: "put".to_string(),
+            headers: [("X-Project".to_string(), "{{order_id}}".to_string())].into(),
+            body: r#"{"text": "{{message}}", "tier": "{{ticket}}", "price": "{{price}}", "screen": "{{screen}}"}"#
+                .to_string(),
+        }],
+        ..PushConfig::default()
+    };
+
+    let report = config.push_all_async(&order_notification(), None).await;
+
+    assert!(report.success(), "{:?}", report.channels);
+    assert_eq!(report.channels[0].channel, "webhook:relay");
+    let requests = sink.requests();
+    assert_eq!(requests.len(), 1);
+    let request = &requests[0];
+    assert_eq!(request.method, "PUT");
+    assert_eq!(request.path, "/hook");
+    assert_eq!(
+        request.query,
+        "order=5001&pay=https%3A%2F%2Fpay.example.com%2Fqr%3Forder%3D5001%26sign%3Da+b"
+    );
+    assert_eq!(request.headers["x-project"], "5001");
+    assert_eq!(request.headers["content-type"], "application/json");
+    let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+    assert_eq!(body["text"], "订单号: 5001\n请尽快支付！");
+    assert_eq!(body["tier"], "VIP \"内场\"");
+    assert_eq!(body["price"], "680.00");
+    assert_eq!(body["screen"], "第一场");
+}
+
+#[tokio::test]
+async fn each_webhook_is_reported_separately() {
+    let ok = WebhookSink::start().await.unwrap();
+    let broken = WebhookSink::with_status(500).await.unwrap();
+    let config = PushConfig {
+        enabled: true,
+        enabled_methods: vec!["webhook".to_string()],
+        webhooks: vec![
+            WebhookConfig {
+                url: ok.url("/a"),
+                body: "{{title}}".to_string(),
+                ..WebhookConfig::default()
+            },
+            WebhookConfig {
+                name: "backup".to_string(),
+                url: broken.url("/b"),
+                ..WebhookConfig::default()
+            },
+            WebhookConfig::default(),
+        ],
+        ..PushConfig::default()
+    };
+
+    let report = config.push_all_async(&order_notification(), None).await;
+
+    let channels: Vec<_> = report
+        .channels
+        .iter()
+        .map(|c| (c.channel.as_str(), c.success))
+        .collect();
+    assert_eq!(
+        channels,
+        vec![("webhook#1", true), ("webhook:backup", false)]
+    );
+    assert!(report.channels[1].message.contains("500"));
+    // 纯文本模板不做 JSON 转义
+    let request = &ok.requests()[0];
+    assert_eq!(request.body, "抢票成功: 模拟演出");
+    assert_ne!(
+        request.headers.get("content-type").map(String::as_str),
+        Some("application/json"),
+        "{:?}",
+        request.headers
+    );
+    // 默认模板是 JSON
+    let body: serde_json::Value = serde_json::from_str(&broken.requests()[0].body).unwrap();
+    assert_eq!(body["order_id"], "5001");
+}
+
+#[tokio::test]
+async fn ntfy_and_matrix_receive_the_notification() {
+    let sink = WebhookSink::start().await.unwrap();
+    let config = PushConfig {
+        enabled: true,
+        enabled_methods: vec!["ntfy".to_string(), "matrix".to_string()],
+        ntfy_config: NtfyConfig {
+            topic_url: sink.url("/tickets"),
+            token: "tk_ntfy".to_string(),
+            priority: 4,
+        },
+        matrix_config: MatrixConfig {
+            homeserver: sink.url("/"),
+            access_token: "syt_matrix".to_string(),
+            room_id: "!room:example.org".to_string(),
+        },
+        ..PushConfig::default()
+    };
+    let notification = Notification {
+        jump_url: Some("bilibili://mall/order/5001".to_string()),
+        ..Notification::new("抢票成功: 模拟演出", "订单号: 5001\n<请尽快支付>")
+    };
+
+    let report = config.push_all_async(&notification, None).await;
+
+    assert!(
+        report.channels.iter().all(|c| c.success),
+        "{:?}",
+        report.channels
+    );
+    let requests = sink.requests();
+    assert_eq!(requests.len(), 2);
+
+    let ntfy = requests.iter().find(|r| r.method == "POST").unwrap();
+    assert_eq!(ntfy.path, "/");
+    assert_eq!(ntfy.headers["authorization"], "Bearer tk_ntfy");
+    let body: serde_json::Value = serde_json::from_str(&ntfy.body).unwrap();
+    assert_eq!(body["topic"], "tickets");
+    assert_eq!(body["title"], "抢票成功: 模拟演出");
+    assert_eq!(body["priority"], 4);
+    assert_eq!(body["click"], "bilibili://mall/order/5001");
+
+    let matrix = requests.iter().find(|r| r.method == "PUT").unwrap();
+    assert!(
+        matrix
+            .path
+            .starts_with("/_matrix/client/v3/rooms/%21room%3Aexample.org/send/m.room.message/"),
+        "{}",
+        matrix.path
+    );
+    assert_eq!(matrix.headers["authorization"], "Bearer syt_matrix");
+    let body: serde_json::Value = serde_json::from_str(&matrix.body).unwrap();
+    assert_eq!(body["msgtype"], "m.text");
+    assert_eq!(
+        body["body"],
+        "抢票成功: 模拟演出\n订单号: 5001\n<请尽快支付>"
+    );
+    assert_eq!(
+        body["formatted_body"],
+        "<b>抢票成功: 模拟演出</b><br>订单号: 5001<br>&lt;请尽快支付&gt;"
+    );
+}
+
+fn confirmed_order() -> OrderSummary {
+    OrderSummary::from_confirm(
+        5001,
+        &ConfirmTicketResult {
+            count: 2,
+            pay_money: 136000,
+            project_name: "模拟演出".to_string(),
+            screen_name: "第一场".to_string(),
+            ticket_info: ConfirmTicketInfo {
+                name: "VIP".to_string(),
+                count: 2,
+                price: 68000,
+            },
+        },
+    )
+}
+
+#[test]
+fn template_values_are_not_expanded_again() {
+    let mut notification = Notification::order_success(confirmed_order(), None);
+    notification.title = "{{order_id}}".to_string();
+    notification.message = "{{title}}".to_string();
+
+    let rendered = render_template(
+        "{{title}}|{{message}}|{{tier}}|{{ticket}}|{{unknown}}|{{{{count}}",
+        &notification,
+        |v| v.to_string(),
+    );
+
+    assert_eq!(rendered, "{{order_id}}|{{title}}|VIP|VIP|{{unknown}}|{{2");
+}
+
+#[test]
+fn order_success_notification_carries_order_details() {
+    let pay = CheckFakeResultData {
+        sign: String::new(),
+        code_url: Some("https://pay.example.com/qr?order=5001".to_string()),
+    };
+
+    let paid = Notification::order_success(confirmed_order(), Some(&pay));
+    let unpaid = Notification::order_success(confirmed_order(), None);
+
+    assert_eq!(paid.title, "抢票成功: 模拟演出");
+    assert_eq!(
+        paid.message,
+        "项目: 模拟演出\n场次: 第一场\n票种: VIP x2\n金额: ¥1360.00\n订单号: 5001\n请尽快支付！"
+    );
+    assert_eq!(paid.pay_url, pay.code_url);
+    assert!(
+        paid.jump_url
+            .unwrap()
+            .ends_with("orderDetail.html?order_id=5001")
+    );
+    assert_eq!(unpaid.pay_url, None);
+    assert!(unpaid.message.ends_with("请前往订单中心支付"));
+}
+
+#[tokio::test]
+async fn gotify_and_webhook_get_the_payment_qr() {
+    let sink = WebhookSink::start().await.unwrap();
+    let config = PushConfig {
+        enabled: true,
+        enabled_methods: vec!["gotify".to_string(), "webhook".to_string()],
+        gotify_config: GotifyConfig {
+            gotify_url: sink.url(""),
+            gotify_token: "gotify-token".to_string(),
+        },
+        webhooks: vec![WebhookConfig {
+            url: sink.url("/hook"),
+            body: r#"{"amount": "{{pay_money}}", "count": "{{count}}", "qr": "{{pay_qr}}"}"#
+                .to_string(),
+            ..WebhookConfig::default()
+        }],
+        ..PushConfig::default()
+    };
+    let pay = CheckFakeResultData {
+        sign: String::new(),
+        code_url: Some("https://pay.example.com/qr?order=5001".to_string()),
+    };
+    let notification = Notification::order_success(confirmed_order(), Some(&pay));
+
+    let report = config.push_all_async(&notification, None).await;
+
+    assert!(
+        report.channels.iter().all(|c| c.success),
+        "{:?}",
+        report.channels
+    );
+    let requests = sink.requests();
+    let gotify = requests.iter().find(|r| r.path == "/message").unwrap();
+    let body: serde_json::Value = serde_json::from_str(&gotify.body).unwrap();
+    assert_eq!(
+        body["extras"]["client::display"]["contentType"],
+        "text/markdown"
+    );
+    assert_eq!(
+        body["extras"]["client::notification"]["click"]["url"],
+        notification.jump_url.clone().unwrap()
+    );
+    let message = body["message"].as_str().unwrap();
+    assert!(message.contains("![支付二维码](data:image/png;base64,"));
+    assert!(message.contains("(https://pay.example.com/qr?order=5001)"));
+
+    let webhook = requests.iter().find(|r| r.path == "/hook").unwrap();
+    let body: serde_json::Value = serde_json::from_str(&webhook.body).unwrap();
+    assert_eq!(body["amount"], "1360.00");
+    assert_eq!(body["count"], "2");
+    assert!(
+        body["qr"]
+            .as_str()
+            .unwrap()
+            .starts_with("data:image/png;base64,iVBORw0KGgo")
+    );
+}
+
+#[tokio::test]
+async fn gotify_sends_pay_link_when_qr_cannot_be_rendered() {
+    let sink = WebhookSink::start().await.unwrap();
+    let config = PushConfig {
+        enabled: true,
+        enabled_methods: vec!["gotify".to_string()],
+        gotify_config: GotifyConfig {
+            gotify_url: sink.url(""),
+            gotify_token: "gotify-token".to_string(),
+        },
+        ..PushConfig::default()
+    };
+    // 超出二维码容量的链接
+    let pay_url = format!("https://pay.example.com/qr?order={}", "9".repeat(8000));
+    let pay = CheckFakeResultData {
+        sign: String::new(),
+        code_url: Some(pay_url.clone()),
+    };
+    let notification = Notification::order_success(confirmed_order(), Some(&pay));
+
+    let report = config.push_all_async(&notification, None).await;
+
+    assert!(report.success(), "{:?}", report.channels);
+    let requests = sink.requests();
+    let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+    let message = body["message"].as_str().unwrap();
+    assert!(message.starts_with("项目: 模拟演出  \n"));
+    assert!(!message.contains("data:image/png"));
+    assert!(message.ends_with(&format!("[打开支付链接]({})", pay_url)));
+}
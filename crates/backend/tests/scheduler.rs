@@ -0,0 +1,153 @@
+mod support;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use backend::scheduler::Scheduler;
+use backend::taskmanager::TaskManagerImpl;
+use common::captcha::LocalCaptcha;
+use common::config::BtrConfig;
+use common::schedule::{
+    self, JobStatus, PreflightOffsets, PreflightStep, ScheduleTime, ScheduledJob,
+};
+use common::task_journal::JournalEntry;
+use common::taskmanager::{TaskManager, TaskStatus};
+use mock_server::{MockServer, Scenario, fixtures};
+use support::{cookie_manager, grab_request, test_account, test_config};
+
+#[test]
+fn scheduled_job_runs_preflight_then_submits() {
+    schedule::set_schedule_path(
+        std::env::temp_dir().join(format!("btr-test-schedule-{}.json", std::process::id())),
+    );
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let server = rt
+        .block_on(MockServer::start(Scenario::sale_starts_in(60)))
+        .unwrap();
+    let mut manager = TaskManagerImpl::new();
+
+    let account = test_account(cookie_manager(&server));
+    let scheduler = Scheduler::new(
+        manager.control(),
+        Arc::new(move || {
+            Ok(BtrConfig {
+                accounts: vec![account.clone()],
+                custom_config: test_config(),
+                ..BtrConfig::default()
+            })
+        }),
+        LocalCaptcha::disabled(),
+    );
+
+    let mut entry = JournalEntry::from_request(&grab_request(&server, 0, test_config()));
+    entry.project_info = None;
+    let job = ScheduledJob::new(
+        entry.clone(),
+        ScheduleTime::BeforeSale { minutes: 0 },
+        PreflightOffsets::default(),
+    );
+    let later = ScheduledJob {
+        id: "later".to_string(),
+        ..ScheduledJob::new(
+            entry,
+            ScheduleTime::At {
+                time: chrono::Local::now().timestamp_millis() + 86_400_000,
+            },
+            PreflightOffsets::default(),
+        )
+    };
+    schedule::add(job.clone()).unwrap();
+    schedule::add(later).unwrap();
+
+    // 没有开售时间时先刷新项目，之后开售前 60 秒内的预检全部到期
+    scheduler.tick(chrono::Local::now().timestamp_millis());
+    let job = schedule::find(&job.id).unwrap();
+    assert!(job.fire_at().is_some());
+    scheduler.tick(chrono::Local::now().timestamp_millis());
+    let job = schedule::find(&job.id).unwrap();
+    assert_eq!(job.status, JobStatus::Scheduled);
+    for step in PreflightStep::ALL {
+        assert!(
+            job.preflight_log
+                .iter()
+                .any(|r| r.step == step && r.success),
+            "{:?} 未执行: {:?}",
+            step,
+            job.preflight_log
+        );
+    }
+    assert!(job.clock_offset_ms.abs() < 5_000);
+    assert!(schedule::find("later").unwrap().preflight_log.is_empty());
+
+    // 改为开售前 2 分钟后已到触发时间，重新预检并提交抢票任务
+    schedule::modify(&job.id, |job| {
+        job.reschedule(ScheduleTime::BeforeSale { minutes: 2 }, job.preflight)
+    })
+    .unwrap();
+    scheduler.tick(chrono::Local::now().timestamp_millis());
+    let job = schedule::find(&job.id).unwrap();
+    assert_eq!(job.status, JobStatus::Submitted, "{}", job.message);
+    assert_eq!(job.preflight_log.len(), PreflightStep::ALL.len());
+    let task_id = job.task_id.clone().unwrap();
+    assert!(manager.get_task_status(&task_id).is_some());
+    assert_eq!(job.grab.uid, fixtures::UID);
+
+    // 已提交的任务不会再次提交，也不能再修改
+    scheduler.tick(chrono::Local::now().timestamp_millis());
+    assert_eq!(
+        schedule::find(&job.id).unwrap().task_id,
+        Some(task_id.clone())
+    );
+    assert!(
+        schedule::modify(&job.id, |job| {
+            job.reschedule(ScheduleTime::BeforeSale { minutes: 1 }, job.preflight)
+        })
+        .is_err()
+    );
+
+    manager.cancel_task(&task_id).unwrap();
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while manager.get_task_status(&task_id) != Some(TaskStatus::Cancelled) {
+        assert!(Instant::now() < deadline, "等待取消超时");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    schedule::remove(&job.id).unwrap();
+    schedule::remove("later").unwrap();
+    assert!(schedule::load().unwrap().is_empty());
+
+    // 另一个进程抢先认领了到期任务：本进程能读到文件的变化，也不会重复提交
+    let claimed = ScheduledJob {
+        id: "claimed".to_string(),
+        ..ScheduledJob::new(
+            job.grab.clone(),
+            ScheduleTime::At {
+                time: chrono::Local::now().timestamp_millis() - 1000,
+            },
+            PreflightOffsets {
+                refresh_project: 0,
+                verify_account: 0,
+                sync_clock: 0,
+            },
+        )
+    };
+    schedule::add(claimed.clone()).unwrap();
+    assert_eq!(schedule::load().unwrap()[0].status, JobStatus::Scheduled);
+    let other = ScheduledJob {
+        status: JobStatus::Submitted,
+        task_id: Some("other-process".to_string()),
+        ..claimed
+    };
+    std::fs::write(
+        schedule::schedule_path(),
+        serde_json::to_string(&vec![other]).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(schedule::load().unwrap()[0].status, JobStatus::Submitted);
+    scheduler.tick(chrono::Local::now().timestamp_millis());
+    assert_eq!(
+        schedule::find("claimed").unwrap().task_id.as_deref(),
+        Some("other-process")
+    );
+    schedule::remove("claimed").unwrap();
+    manager.shutdown();
+}
@@ -0,0 +1,163 @@
+#![allow(dead_code)]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use backend::taskmanager::grab_mode::GrabPlan;
+use backend::taskmanager::grab_ticket_handler::{
+    handle_grab_ticket_plan, handle_grab_ticket_request,
+};
+use common::account::Account;
+use common::captcha::LocalCaptcha;
+use common::config::{CustomConfig, PushConfig};
+use common::cookie_manager::CookieManager;
+use common::taskmanager::{GrabTicketRequest, GrabTicketResult, TaskResult, TaskStatus};
+use common::ticket::{BilibiliTicket, BuyerInfo, BuyerInfoResponse};
+use mock_server::{MockServer, fixtures};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+// 测试用配置：缩短重试间隔
+pub fn test_config() -> CustomConfig {
+    CustomConfig {
+        retry_interval_ms: 10,
+        ..CustomConfig::default()
+    }
+}
+
+pub fn cookie_manager(server: &MockServer) -> Arc<CookieManager> {
+    let client = Arc::new(reqwest::Client::new());
+    Arc::new(
+        CookieManager::from_client(
+            client,
+            &format!("DedeUserID={}; bili_jct=mock_csrf", fixtures::UID),
+        )
+        .with_endpoints(server.endpoints()),
+    )
+}
+
+pub fn test_account(cookie_manager: Arc<CookieManager>) -> Account {
+    Account {
+        uid: fixtures::UID,
+        name: "测试账号".to_string(),
+        level: "6".to_string(),
+        cookie: cookie_manager.get_all_cookies(),
+        csrf: "mock_csrf".to_string(),
+        is_login: true,
+        account_status: "空闲".to_string(),
+        vip_label: String::new(),
+        vip_type: 0,
+        vip_status: 0,
+        is_active: true,
+        avatar_url: None,
+        cookie_manager: Some(cookie_manager),
+    }
+}
+
+pub fn test_buyers() -> Vec<BuyerInfo> {
+    serde_json::from_value::<BuyerInfoResponse>(fixtures::buyer_list())
+        .unwrap()
+        .data
+        .list
+}
+
+// 历史记录写到临时目录，避免污染工作目录
+pub fn use_temp_history_dir() {
+    common::history::set_history_dir(
+        std::env::temp_dir().join(format!("btr-test-history-{}", std::process::id())),
+    );
+}
+
+// 任务日志同样写到临时文件
+pub fn use_temp_journal() {
+    common::task_journal::set_journal_path(
+        std::env::temp_dir().join(format!("btr-test-journal-{}.json", std::process::id())),
+    );
+}
+
+pub fn grab_request(server: &MockServer, mode: u8, config: CustomConfig) -> GrabTicketRequest {
+    use_temp_history_dir();
+    use_temp_journal();
+    let cookie_manager = cookie_manager(server);
+    let account = test_account(cookie_manager.clone());
+    let buyers = test_buyers();
+    let project_id = fixtures::PROJECT_ID.to_string();
+
+    let mut biliticket = BilibiliTicket::new(
+        &mode,
+        &"mock".to_string(),
+        &config,
+        &account,
+        &PushConfig::default(),
+        &0,
+        &project_id,
+    );
+    biliticket.cookie_manager = Some(cookie_manager.clone());
+    biliticket.screen_id = fixtures::SCREEN_ID.to_string();
+    biliticket.select_ticket_id = Some(fixtures::TICKET_ID.to_string());
+    biliticket.id_bind = 1;
+    biliticket.buyer_info = Some(buyers.clone());
+
+    GrabTicketRequest {
+        task_id: format!("test-{}-{}", mode, server.addr().port()),
+        uid: fixtures::UID,
+        project_id,
+        screen_id: fixtures::SCREEN_ID.to_string(),
+        ticket_id: fixtures::TICKET_ID.to_string(),
+        count: buyers.len() as i16,
+        buyer_info: buyers,
+        cookie_manager,
+        biliticket,
+        grab_mode: mode,
+        status: TaskStatus::Pending,
+        start_time: None,
+        is_hot: false,
+        local_captcha: LocalCaptcha::disabled(),
+        skip_words: None,
+        preferences: Vec::new(),
+    }
+}
+
+// 运行抢票任务直到结束，返回过程中产生的抢票结果
+pub async fn run_grab(request: GrabTicketRequest) -> Vec<GrabTicketResult> {
+    run_grab_with_cancel(request, CancellationToken::new()).await
+}
+
+pub async fn run_grab_with_cancel(
+    request: GrabTicketRequest,
+    cancel: CancellationToken,
+) -> Vec<GrabTicketResult> {
+    let (tx, rx) = mpsc::channel(100);
+    let handle = tokio::spawn(handle_grab_ticket_request(request, tx, cancel));
+    collect_results(handle, rx).await
+}
+
+// 按自定义的开始条件和票种选择运行抢票任务
+pub async fn run_grab_plan(request: GrabTicketRequest, plan: GrabPlan) -> Vec<GrabTicketResult> {
+    let (tx, rx) = mpsc::channel(100);
+    let handle = tokio::spawn(handle_grab_ticket_plan(
+        request,
+        plan,
+        tx,
+        CancellationToken::new(),
+    ));
+    collect_results(handle, rx).await
+}
+
+async fn collect_results(
+    handle: tokio::task::JoinHandle<()>,
+    mut rx: mpsc::Receiver<TaskResult>,
+) -> Vec<GrabTicketResult> {
+    tokio::time::timeout(Duration::from_secs(30), handle)
+        .await
+        .expect("抢票任务超时")
+        .expect("抢票任务异常退出");
+
+    let mut results = Vec::new();
+    while let Ok(result) = rx.try_recv() {
+        if let TaskResult::GrabTicketResult(r) = result {
+            results.push(r);
+        }
+    }
+    results
+}
@@ -0,0 +1,89 @@
+mod support;
+
+use std::time::{Duration, Instant};
+
+use backend::taskmanager::TaskManagerImpl;
+use common::captcha::LocalCaptcha;
+use common::config::PushConfig;
+use common::task_journal::{self, JournalEntry};
+use common::taskmanager::{TaskManager, TaskPhase, TaskRequest, TaskStatus};
+use mock_server::{MockServer, Scenario, fixtures};
+use support::{cookie_manager, grab_request, test_account, test_config};
+
+#[test]
+fn entry_rebuilds_request_from_account() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let server = rt.block_on(MockServer::start(Scenario::on_sale())).unwrap();
+    let request = grab_request(&server, 0, test_config());
+
+    let entry = JournalEntry::from_request(&request);
+    task_journal::record(&entry);
+    let saved = task_journal::find(&request.task_id).unwrap();
+    assert_eq!(saved.buyer_info.len(), request.buyer_info.len());
+
+    // 重启后账号的 cookie 管理器是新建的
+    let account = test_account(cookie_manager(&server));
+    let rebuilt = saved
+        .to_request(
+            &account,
+            &test_config(),
+            &PushConfig::default(),
+            LocalCaptcha::disabled(),
+        )
+        .unwrap();
+    assert_eq!(rebuilt.task_id, request.task_id);
+    assert_eq!(rebuilt.screen_id, fixtures::SCREEN_ID.to_string());
+    assert_eq!(rebuilt.ticket_id, fixtures::TICKET_ID.to_string());
+    assert_eq!(rebuilt.biliticket.id_bind, request.biliticket.id_bind);
+    assert_eq!(rebuilt.grab_mode, 0);
+
+    let mut other = account.clone();
+    other.uid += 1;
+    assert!(
+        saved
+            .to_request(
+                &other,
+                &test_config(),
+                &PushConfig::default(),
+                LocalCaptcha::disabled()
+            )
+            .is_err()
+    );
+
+    task_journal::remove(&request.task_id);
+    assert!(task_journal::find(&request.task_id).is_err());
+}
+
+#[test]
+fn journal_keeps_task_until_it_ends() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let server = rt
+        .block_on(MockServer::start(Scenario::sale_starts_in(60)))
+        .unwrap();
+    let mut manager = TaskManagerImpl::new();
+
+    let task_id = manager
+        .submit_task(TaskRequest::GrabTicketRequest(grab_request(
+            &server,
+            0,
+            test_config(),
+        )))
+        .unwrap();
+    assert!(task_journal::find(&task_id).is_ok());
+
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while manager.get_task_phase(&task_id) != Some(TaskPhase::WaitingForSale) {
+        assert!(Instant::now() < deadline, "等待开售阶段超时");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    // 等待开售期间仍在任务日志中
+    assert!(task_journal::find(&task_id).is_ok());
+
+    manager.cancel_task(&task_id).unwrap();
+    while manager.get_task_status(&task_id) != Some(TaskStatus::Cancelled) {
+        assert!(Instant::now() < deadline, "等待取消超时");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    assert!(task_journal::find(&task_id).is_err());
+    manager.shutdown();
+}
@@ -0,0 +1,252 @@
+mod support;
+
+use std::time::{Duration, Instant};
+
+use backend::taskmanager::TaskManagerImpl;
+use backend::taskmanager::status::TaskGcPolicy;
+use common::config::TaskLimits;
+use common::taskmanager::{
+    GetAllorderRequest, TaskManager, TaskPhase, TaskRequest, TaskResult, TaskStatus,
+};
+use mock_server::{MockServer, Scenario, fixtures};
+use support::{cookie_manager, grab_request, test_config};
+
+// 轮询任务状态直到满足条件
+fn wait_for(
+    manager: &TaskManagerImpl,
+    task_id: &str,
+    done: impl Fn(&TaskStatus, Option<TaskPhase>) -> bool,
+) -> (TaskStatus, Option<TaskPhase>) {
+    let deadline = Instant::now() + Duration::from_secs(30);
+    loop {
+        let status = manager.get_task_status(task_id).expect("任务不存在");
+        let phase = manager.get_task_phase(task_id);
+        if done(&status, phase) {
+            return (status, phase);
+        }
+        assert!(Instant::now() < deadline, "等待任务状态超时: {:?}", status);
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn grab_task_reports_progress_and_is_collected() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let server = rt
+        .block_on(MockServer::start(Scenario::stock_shortage_then_success(1)))
+        .unwrap();
+    let mut manager = TaskManagerImpl::new();
+
+    let task_id = manager
+        .submit_task(TaskRequest::GrabTicketRequest(grab_request(
+            &server,
+            1,
+            test_config(),
+        )))
+        .unwrap();
+    let (status, phase) = wait_for(&manager, &task_id, |status, _| status.is_finished());
+
+    assert_eq!(status, TaskStatus::Completed(true));
+    assert_eq!(phase, Some(TaskPhase::AwaitingPayment));
+    // 取消已结束的任务不报错，状态保持不变
+    manager.cancel_task(&task_id).unwrap();
+    assert_eq!(
+        manager.get_task_status(&task_id),
+        Some(TaskStatus::Completed(true))
+    );
+
+    let summary = manager
+        .list_tasks()
+        .into_iter()
+        .find(|t| t.task_id == task_id)
+        .expect("任务列表中没有该任务");
+    assert_eq!(summary.kind, "grab_ticket");
+    assert_eq!(summary.uid, Some(fixtures::UID));
+    assert_eq!(summary.project_id, Some(fixtures::PROJECT_ID.to_string()));
+    assert_eq!(summary.status, TaskStatus::Completed(true));
+
+    manager.set_gc_policy(TaskGcPolicy {
+        retention: Duration::ZERO,
+        max_finished: 0,
+    });
+    assert!(manager.get_task_status(&task_id).is_none());
+    assert!(manager.list_tasks().is_empty());
+    manager.shutdown();
+}
+
+#[test]
+fn raising_the_limit_starts_queued_tasks() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let server = rt
+        .block_on(MockServer::start(Scenario::sale_starts_in(60)))
+        .unwrap();
+    let mut manager = TaskManagerImpl::new();
+    manager.set_limits(TaskLimits {
+        grab_ticket: 1,
+        ..TaskLimits::default()
+    });
+
+    let first = manager
+        .submit_task(TaskRequest::GrabTicketRequest(grab_request(
+            &server,
+            0,
+            test_config(),
+        )))
+        .unwrap();
+    let mut request = grab_request(&server, 0, test_config());
+    request.task_id = format!("{}-raised", request.task_id);
+    let second = manager
+        .submit_task(TaskRequest::GrabTicketRequest(request))
+        .unwrap();
+    wait_for(&manager, &first, |_, phase| {
+        phase == Some(TaskPhase::WaitingForSale)
+    });
+    assert_eq!(manager.get_task_status(&second), Some(TaskStatus::Pending));
+
+    // 只调高上限，没有其他提交或结束的任务
+    manager.set_limits(TaskLimits {
+        grab_ticket: 2,
+        ..TaskLimits::default()
+    });
+    wait_for(&manager, &second, |_, phase| {
+        phase == Some(TaskPhase::WaitingForSale)
+    });
+
+    manager.cancel_task(&first).unwrap();
+    manager.cancel_task(&second).unwrap();
+    wait_for(&manager, &first, |status, _| status.is_finished());
+    wait_for(&manager, &second, |status, _| status.is_finished());
+    manager.shutdown();
+}
+
+#[test]
+fn result_carries_the_submitted_task_id() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let server = rt.block_on(MockServer::start(Scenario::default())).unwrap();
+    let mut manager = TaskManagerImpl::new();
+
+    let task_id = manager
+        .submit_task(TaskRequest::GetAllorderRequest(GetAllorderRequest {
+            task_id: String::new(),
+            cookie_manager: cookie_manager(&server),
+            status: TaskStatus::Pending,
+            cookies: String::new(),
+            account_id: fixtures::UID.to_string(),
+            start_time: None,
+        }))
+        .unwrap();
+
+    assert!(!task_id.is_empty());
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let received = manager
+            .get_results()
+            .into_iter()
+            .any(|r| matches!(r, TaskResult::GetAllorderRequestResult(o) if o.task_id == task_id));
+        if received {
+            break;
+        }
+        assert!(Instant::now() < deadline, "没有收到带同一任务 ID 的结果");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    manager.shutdown();
+}
+
+#[test]
+fn unknown_grab_mode_is_rejected_on_submit() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let server = rt.block_on(MockServer::start(Scenario::default())).unwrap();
+    let mut manager = TaskManagerImpl::new();
+
+    let result = manager.submit_task(TaskRequest::GrabTicketRequest(grab_request(
+        &server,
+        9,
+        test_config(),
+    )));
+
+    assert!(result.unwrap_err().contains("9"));
+    assert!(manager.list_tasks().is_empty());
+    manager.shutdown();
+}
+
+#[test]
+fn cancelled_task_stays_cancelled() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let server = rt
+        .block_on(MockServer::start(Scenario::sale_starts_in(60)))
+        .unwrap();
+    let mut manager = TaskManagerImpl::new();
+
+    let task_id = manager
+        .submit_task(TaskRequest::GrabTicketRequest(grab_request(
+            &server,
+            0,
+            test_config(),
+        )))
+        .unwrap();
+    wait_for(&manager, &task_id, |_, phase| {
+        phase == Some(TaskPhase::WaitingForSale)
+    });
+
+    // 取消后任务自行退出，最终状态为已取消
+    manager.cancel_task(&task_id).unwrap();
+    let (status, _) = wait_for(&manager, &task_id, |status, _| status.is_finished());
+    assert_eq!(status, TaskStatus::Cancelled);
+    std::thread::sleep(Duration::from_millis(200));
+    assert_eq!(
+        manager.get_task_status(&task_id),
+        Some(TaskStatus::Cancelled)
+    );
+    manager.shutdown();
+}
+
+#[test]
+fn queued_grab_task_waits_for_a_free_slot() {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let server = rt
+        .block_on(MockServer::start(Scenario::sale_starts_in(60)))
+        .unwrap();
+    let mut manager = TaskManagerImpl::new();
+    manager.set_limits(TaskLimits {
+        grab_ticket: 1,
+        ..TaskLimits::default()
+    });
+
+    let first = manager
+        .submit_task(TaskRequest::GrabTicketRequest(grab_request(
+            &server,
+            0,
+            test_config(),
+        )))
+        .unwrap();
+    let mut request = grab_request(&server, 0, test_config());
+    request.task_id = format!("{}-queued", request.task_id);
+    let second = manager
+        .submit_task(TaskRequest::GrabTicketRequest(request))
+        .unwrap();
+
+    wait_for(&manager, &first, |_, phase| {
+        phase == Some(TaskPhase::WaitingForSale)
+    });
+    assert_eq!(manager.get_task_status(&second), Some(TaskStatus::Pending));
+
+    // 排队中取消也会收到已取消的抢票结果
+    manager.cancel_task(&second).unwrap();
+    let (status, _) = wait_for(&manager, &second, |status, _| status.is_finished());
+    assert_eq!(status, TaskStatus::Cancelled);
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let cancelled = manager.get_results().into_iter().any(
+            |r| matches!(r, TaskResult::GrabTicketResult(g) if g.task_id == second && g.cancelled),
+        );
+        if cancelled {
+            break;
+        }
+        assert!(Instant::now() < deadline, "没有收到取消结果");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    manager.cancel_task(&first).unwrap();
+    wait_for(&manager, &first, |status, _| status.is_finished());
+    manager.shutdown();
+}
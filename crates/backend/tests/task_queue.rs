@@ -0,0 +1,67 @@
+use backend::taskmanager::queue::TaskQueue;
+use common::config::TaskLimits;
+use common::taskmanager::TaskKind;
+
+fn started(ready: &[(String, ())]) -> Vec<&str> {
+    ready.iter().map(|(id, _)| id.as_str()).collect()
+}
+
+#[test]
+fn grab_tasks_start_before_background_fetches() {
+    let limits = TaskLimits::default();
+    let mut queue = TaskQueue::default();
+    for i in 0..5 {
+        queue.push(format!("info-{}", i), TaskKind::GetTicketInfo, ());
+    }
+    queue.push("grab".to_string(), TaskKind::GrabTicket, ());
+    queue.push("login".to_string(), TaskKind::QrCodeLogin, ());
+
+    // 抢票期间后台查询只放行 background_while_grabbing 个
+    let ready = queue.pop_ready(&limits);
+    assert_eq!(started(&ready), ["grab", "login", "info-0"]);
+    assert!(queue.pop_ready(&limits).is_empty());
+
+    // 抢票结束后按类型上限放行
+    queue.finish("grab");
+    let ready = queue.pop_ready(&limits);
+    assert_eq!(started(&ready), ["info-1", "info-2", "info-3"]);
+    assert_eq!(
+        queue.running(TaskKind::GetTicketInfo),
+        limits.get_ticket_info
+    );
+
+    queue.finish("info-0");
+    assert_eq!(started(&queue.pop_ready(&limits)), ["info-4"]);
+}
+
+#[test]
+fn per_kind_limits_and_cancelled_waiting_tasks() {
+    let limits = TaskLimits {
+        get_all_order: 1,
+        ..TaskLimits::default()
+    };
+    let mut queue = TaskQueue::default();
+    queue.push("order-0".to_string(), TaskKind::GetAllOrder, ());
+    queue.push("order-1".to_string(), TaskKind::GetAllOrder, ());
+    queue.push("order-2".to_string(), TaskKind::GetAllOrder, ());
+    queue.push("buyer".to_string(), TaskKind::GetBuyerInfo, ());
+
+    // 一种任务达到上限不影响其他类型
+    assert_eq!(started(&queue.pop_ready(&limits)), ["order-0", "buyer"]);
+    assert!(queue.is_waiting("order-1"));
+    assert!(queue.remove("order-1").is_some());
+    assert!(!queue.is_waiting("order-1"));
+
+    queue.finish("order-0");
+    assert_eq!(started(&queue.pop_ready(&limits)), ["order-2"]);
+
+    // 0 表示不限
+    let unlimited = TaskLimits {
+        get_all_order: 0,
+        ..TaskLimits::default()
+    };
+    for i in 0..10 {
+        queue.push(format!("more-{}", i), TaskKind::GetAllOrder, ());
+    }
+    assert_eq!(queue.pop_ready(&unlimited).len(), 10);
+}
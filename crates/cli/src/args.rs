@@ -0,0 +1,236 @@
+use clap::{Args, Parser, Subcommand};
+
+#[derive(Parser, Debug)]
+#[command(version, about = "B站会员购抢票工具（命令行版）")]
+pub struct Cli {
+    /// 以 JSON 行输出任务结果，便于脚本处理
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    /// 在指定地址开启 Prometheus 指标接口（如 127.0.0.1:9464），覆盖配置文件中的设置
+    #[arg(long, global = true, value_name = "ADDR")]
+    pub metrics_addr: Option<String>,
+
+    /// 日志中保留 Cookie、token、手机号等原文（仅供排查问题，请勿外传日志）
+    #[arg(long, global = true)]
+    pub unsafe_debug: bool,
+
+    /// 日志文件按 JSON 行写入，每行包含任务 ID、阶段、错误码等字段
+    #[arg(long, global = true)]
+    pub log_json: bool,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// 账号管理
+    Accounts {
+        #[command(subcommand)]
+        command: AccountsCommand,
+    },
+    /// 项目信息
+    Project {
+        #[command(subcommand)]
+        command: ProjectCommand,
+    },
+    /// 获取账号下的购票人列表
+    Buyers {
+        /// 账号 UID
+        uid: i64,
+    },
+    /// 提交抢票任务，并持续输出任务结果直到结束
+    Grab(GrabArgs),
+    /// 获取账号的订单列表
+    Orders {
+        /// 账号 UID
+        uid: i64,
+    },
+    /// 查询本地保存的抢票历史
+    History(HistoryArgs),
+    /// 查看、跟踪或导出单个任务的日志
+    Logs(LogsArgs),
+    /// 列出、恢复或放弃上次退出前未结束的抢票任务
+    Resume(ResumeArgs),
+    /// 定时抢票：在指定时间或开售前 N 分钟自动提交抢票任务
+    Schedule {
+        #[command(subcommand)]
+        command: Option<ScheduleCommand>,
+    },
+    /// 查看或取消正在运行的程序（图形界面或 cli grab）中的任务
+    Tasks {
+        #[command(subcommand)]
+        command: Option<TasksCommand>,
+    },
+    /// 配置文件加密，口令通过环境变量 BTR_VAULT_PASSPHRASE 提供
+    Vault {
+        #[command(subcommand)]
+        command: VaultCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum VaultCommand {
+    /// 查看配置文件是否已加密
+    Status,
+    /// 把明文配置迁移为加密格式
+    Encrypt {
+        /// 不使用口令，生成随机密钥文件（默认为数据目录下的 config.key，可用 BTR_VAULT_KEYFILE 指定），不能与 BTR_VAULT_PASSPHRASE 同时使用
+        #[arg(long)]
+        keyfile: bool,
+    },
+    /// 解密配置并以明文写回
+    Decrypt,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TasksCommand {
+    /// 列出全部任务（默认）
+    List,
+    /// 取消指定任务
+    Cancel {
+        /// 任务 ID
+        task_id: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ScheduleCommand {
+    /// 列出全部定时任务（默认）
+    List,
+    /// 新建定时任务，抢票参数与 grab 相同
+    Add {
+        #[command(flatten)]
+        grab: GrabArgs,
+        #[command(flatten)]
+        time: ScheduleTimeArgs,
+    },
+    /// 修改定时任务的触发时间
+    Edit {
+        /// 定时任务 ID
+        id: String,
+        #[command(flatten)]
+        time: ScheduleTimeArgs,
+    },
+    /// 删除定时任务
+    Remove {
+        /// 定时任务 ID
+        id: String,
+    },
+    /// 在前台运行调度，到点提交抢票任务并输出结果，按 Ctrl+C 退出
+    Run,
+}
+
+#[derive(Args, Debug)]
+#[group(required = true, multiple = false)]
+pub struct ScheduleTimeArgs {
+    /// 在指定的本地时间提交，格式 "2024-07-06 18:00"
+    #[arg(long)]
+    pub at: Option<String>,
+    /// 在开售前 N 分钟提交
+    #[arg(long, value_name = "MINUTES")]
+    pub before_sale: Option<i64>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AccountsCommand {
+    /// 列出配置文件中的全部账号
+    List,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ProjectCommand {
+    /// 获取项目详情（场次与票种）
+    Info {
+        /// 项目 ID
+        project_id: String,
+        /// 用于请求的账号 UID，默认使用第一个启用的账号
+        #[arg(long)]
+        uid: Option<i64>,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct GrabArgs {
+    /// 账号 UID
+    #[arg(long)]
+    pub uid: i64,
+    /// 项目 ID
+    #[arg(long)]
+    pub project: String,
+    /// 场次 ID（捡漏模式可省略）
+    #[arg(long, default_value = "")]
+    pub screen: String,
+    /// 票种 ID（捡漏模式可省略）
+    #[arg(long, default_value = "")]
+    pub ticket: String,
+    /// 实名购票人 ID，可重复指定
+    #[arg(long = "buyer")]
+    pub buyers: Vec<i64>,
+    /// 非实名项目的联系人姓名
+    #[arg(long)]
+    pub name: Option<String>,
+    /// 非实名项目的联系人手机号
+    #[arg(long)]
+    pub tel: Option<String>,
+    /// 抢票模式 0:定时 1:直接 2:捡漏，默认使用配置文件中的模式
+    #[arg(long)]
+    pub mode: Option<u8>,
+    /// 购买数量，默认等于购票人数量
+    #[arg(long)]
+    pub count: Option<i16>,
+    /// 候选票种，可重复指定，按顺序尝试，前一个售罄后换下一个。
+    /// 格式 场次ID[:票种ID][:票价上限(元)]，如 2001:3001:680、2001::500
+    #[arg(long = "prefer", value_name = "SCREEN[:TICKET][:MAX_PRICE]")]
+    pub preferences: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct HistoryArgs {
+    /// 按任务 ID 过滤
+    #[arg(long)]
+    pub task: Option<String>,
+    /// 按账号 UID 过滤
+    #[arg(long)]
+    pub uid: Option<i64>,
+    /// 按项目 ID 过滤
+    #[arg(long)]
+    pub project: Option<String>,
+    /// 起始时间，格式 2024-07-06 或 "2024-07-06 18:00"
+    #[arg(long)]
+    pub since: Option<String>,
+    /// 截止时间，格式同上
+    #[arg(long)]
+    pub until: Option<String>,
+    /// 只显示最近的 N 条
+    #[arg(long)]
+    pub limit: Option<usize>,
+    /// 显示每次接口调用，而不是按任务汇总
+    #[arg(long)]
+    pub attempts: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct LogsArgs {
+    /// 任务 ID
+    pub task: String,
+    /// 只显示最后 N 条
+    #[arg(long)]
+    pub tail: Option<usize>,
+    /// 持续输出新日志，按 Ctrl+C 退出
+    #[arg(long, short)]
+    pub follow: bool,
+    /// 把任务日志完整导出到指定文件
+    #[arg(long, value_name = "FILE")]
+    pub export: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ResumeArgs {
+    /// 要恢复的任务 ID，省略时列出全部未结束的任务
+    pub task: Option<String>,
+    /// 放弃该任务，不再提示恢复
+    #[arg(long, requires = "task")]
+    pub discard: bool,
+}
@@ -0,0 +1,698 @@
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::time::Duration;
+
+use backend::scheduler::Scheduler;
+use common::captcha::LocalCaptcha;
+use common::config::BtrConfig;
+use common::history::{self, HistoryQuery};
+use common::paths;
+use common::schedule::{self, ScheduleTime, ScheduledJob};
+use common::task_api::{self, TaskApiEndpoint};
+use common::task_journal::{self, JournalEntry};
+use common::task_log;
+use common::taskmanager::{
+    GetAllorderRequest, GetBuyerInfoRequest, GetTicketInfoRequest, GrabTicketRequest, TaskRequest,
+    TaskResult, TaskStatus, TaskSummary,
+};
+use common::ticket::{BilibiliTicket, BuyerInfo, NoBindBuyerInfo, TicketInfo, TicketPreference};
+use common::vault;
+
+use crate::args::{
+    GrabArgs, HistoryArgs, LogsArgs, ResumeArgs, ScheduleTimeArgs, TasksCommand, VaultCommand,
+};
+use crate::output::{
+    print_buyers, print_history_records, print_log_record, print_project, print_result,
+    print_running_tasks, print_scheduled_jobs, print_task_summaries, print_unfinished_tasks,
+};
+use crate::session::CliSession;
+
+pub fn accounts_list(session: &CliSession, json: bool) -> Result<(), String> {
+    if json {
+        let accounts: Vec<_> = session
+            .config
+            .accounts
+            .iter()
+            .map(|a| {
+                serde_json::json!({
+                    "uid": a.uid,
+                    "name": a.name,
+                    "level": a.level,
+                    "is_login": a.is_login,
+                    "is_active": a.is_active,
+                    "vip_label": a.vip_label,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::Value::Array(accounts));
+        return Ok(());
+    }
+
+    if session.config.accounts.is_empty() {
+        println!("配置中没有账号，请先在图形界面中登录");
+        return Ok(());
+    }
+    for account in &session.config.accounts {
+        println!(
+            "  {} | {} | Lv{} | {}{}",
+            account.uid,
+            account.name,
+            account.level,
+            if account.is_active {
+                "启用"
+            } else {
+                "停用"
+            },
+            if account.vip_label.is_empty() {
+                String::new()
+            } else {
+                format!(" | {}", account.vip_label)
+            }
+        );
+    }
+    Ok(())
+}
+
+pub fn project_info(
+    session: &mut CliSession,
+    project_id: &str,
+    uid: Option<i64>,
+    json: bool,
+) -> Result<(), String> {
+    let account = session.account_or_active(uid)?;
+    let result = fetch_project(session, account.uid, project_id, json)?;
+    if json {
+        println!("{}", serde_json::to_string(&result).unwrap_or_default());
+    } else {
+        print_project(&result);
+    }
+    Ok(())
+}
+
+pub fn buyers(session: &mut CliSession, uid: i64, json: bool) -> Result<(), String> {
+    let buyers = fetch_buyers(session, uid, json)?;
+    if json {
+        println!("{}", serde_json::to_string(&buyers).unwrap_or_default());
+    } else {
+        print_buyers(&buyers);
+    }
+    Ok(())
+}
+
+pub fn orders(session: &mut CliSession, uid: i64, json: bool) -> Result<(), String> {
+    let account = session.account(uid)?;
+    let cookie_manager = CliSession::cookie_manager(&account)?;
+
+    let task_id = session.submit(TaskRequest::GetAllorderRequest(GetAllorderRequest {
+        task_id: String::new(),
+        cookie_manager,
+        status: TaskStatus::Pending,
+        cookies: account.cookie.clone(),
+        account_id: uid.to_string(),
+        start_time: None,
+    }))?;
+    log::debug!("提交获取订单任务: {}", task_id);
+
+    let result = session
+        .wait_for(
+            |r| matches!(r, TaskResult::GetAllorderRequestResult(o) if o.task_id == task_id),
+            |r| print_result(r, json),
+        )
+        .ok_or_else(|| "任务管理器已关闭".to_string())?;
+    print_result(&result, json);
+    Ok(())
+}
+
+pub fn grab(session: &mut CliSession, args: GrabArgs, json: bool) -> Result<(), String> {
+    let request = build_grab_request(session, &args, json)?;
+    let task_id = request.task_id.clone();
+    let mode = request.grab_mode;
+    session.submit(TaskRequest::GrabTicketRequest(request))?;
+    if !json {
+        println!("已提交抢票任务 {} (模式 {})，按 Ctrl+C 结束", task_id, mode);
+    }
+    wait_for_grab(session, &task_id, json)
+}
+
+// 按命令行参数获取项目和购票人，生成抢票请求
+fn build_grab_request(
+    session: &mut CliSession,
+    args: &GrabArgs,
+    json: bool,
+) -> Result<GrabTicketRequest, String> {
+    let account = session.account(args.uid)?;
+    let cookie_manager = CliSession::cookie_manager(&account)?;
+
+    let project_info = fetch_project(session, args.uid, &args.project, json)?;
+    let mode = args.mode.unwrap_or(session.config.grab_mode);
+    let preferences = args
+        .preferences
+        .iter()
+        .map(|p| parse_preference(p))
+        .collect::<Result<Vec<_>, _>>()?;
+    if mode != 2 && preferences.is_empty() && (args.screen.is_empty() || args.ticket.is_empty()) {
+        return Err("定时/直接模式需要指定 --screen 和 --ticket，或至少一个 --prefer".to_string());
+    }
+
+    let (buyer_info, no_bind_buyer_info) = if project_info.id_bind == 0 {
+        let (Some(name), Some(tel)) = (args.name.clone(), args.tel.clone()) else {
+            return Err("非实名项目需要指定 --name 和 --tel".to_string());
+        };
+        (
+            Vec::new(),
+            Some(NoBindBuyerInfo {
+                name,
+                tel,
+                uid: args.uid,
+            }),
+        )
+    } else {
+        if args.buyers.is_empty() {
+            return Err("实名项目需要至少指定一个 --buyer".to_string());
+        }
+        let all_buyers = fetch_buyers(session, args.uid, json)?;
+        let selected: Vec<BuyerInfo> = all_buyers
+            .into_iter()
+            .filter(|b| args.buyers.contains(&b.id))
+            .collect();
+        if selected.len() != args.buyers.len() {
+            return Err("部分购票人 ID 不存在，请先用 buyers 子命令确认".to_string());
+        }
+        (selected, None)
+    };
+
+    let count = args.count.unwrap_or_else(|| buyer_info.len().max(1) as i16);
+    let custom_config = session.config.custom_config.clone();
+    let ua = if custom_config.open_custom_ua && !custom_config.custom_ua.is_empty() {
+        custom_config.custom_ua.clone()
+    } else {
+        common::http_utils::get_random_ua()
+    };
+
+    let biliticket = BilibiliTicket {
+        uid: account.uid,
+        method: 0,
+        ua,
+        config: custom_config,
+        account: account.clone(),
+        push_self: session.config.push_config.clone(),
+        status_delay: session.config.delay_time as usize,
+        captcha_use_type: 0,
+        cookie_manager: account.cookie_manager.clone(),
+        project_id: args.project.clone(),
+        screen_id: args.screen.clone(),
+        id_bind: project_info.id_bind,
+        project_info: Some(project_info.clone()),
+        all_buyer_info: None,
+        buyer_info: Some(buyer_info.clone()),
+        no_bind_buyer_info,
+        select_ticket_id: (!args.ticket.is_empty()).then(|| args.ticket.clone()),
+        pay_money: None,
+        count: Some(count as i32),
+        device_id: String::new(),
+    };
+
+    let task_id = format!("{}-{}", account.uid, chrono::Utc::now().timestamp_millis());
+    Ok(GrabTicketRequest {
+        task_id,
+        uid: account.uid,
+        project_id: args.project.clone(),
+        screen_id: args.screen.clone(),
+        ticket_id: args.ticket.clone(),
+        count,
+        buyer_info,
+        cookie_manager,
+        biliticket,
+        grab_mode: mode,
+        status: TaskStatus::Pending,
+        start_time: None,
+        is_hot: project_info.hot_project,
+        local_captcha: LocalCaptcha::new(),
+        skip_words: session.config.skip_words.clone(),
+        preferences,
+    })
+}
+
+// 等待抢票任务结束，期间输出其他任务结果
+fn wait_for_grab(session: &mut CliSession, task_id: &str, json: bool) -> Result<(), String> {
+    let result = session
+        .wait_for(
+            |r| matches!(r, TaskResult::GrabTicketResult(g) if g.task_id == task_id),
+            |r| print_result(r, json),
+        )
+        .ok_or_else(|| "任务管理器已关闭".to_string())?;
+    print_result(&result, json);
+    match result {
+        TaskResult::GrabTicketResult(r) if !r.success => Err(r.message),
+        _ => Ok(()),
+    }
+}
+
+// 提交项目详情任务并等待结果
+fn fetch_project(
+    session: &mut CliSession,
+    uid: i64,
+    project_id: &str,
+    json: bool,
+) -> Result<TicketInfo, String> {
+    let account = session.account(uid)?;
+    let cookie_manager = CliSession::cookie_manager(&account)?;
+    let task_id = session.submit(TaskRequest::GetTicketInfoRequest(GetTicketInfoRequest {
+        uid,
+        task_id: String::new(),
+        project_id: project_id.to_string(),
+        cookie_manager,
+    }))?;
+
+    match session.wait_for(
+        |r| matches!(r, TaskResult::GetTicketInfoResult(t) if t.task_id == task_id),
+        |r| print_result(r, json),
+    ) {
+        Some(TaskResult::GetTicketInfoResult(r)) => match r.ticket_info {
+            Some(info) if r.success => Ok(info.data),
+            _ => Err(format!("获取项目详情失败: {}", r.message)),
+        },
+        _ => Err("任务管理器已关闭".to_string()),
+    }
+}
+
+// 提交购票人任务并等待结果
+fn fetch_buyers(session: &mut CliSession, uid: i64, json: bool) -> Result<Vec<BuyerInfo>, String> {
+    let account = session.account(uid)?;
+    let cookie_manager = CliSession::cookie_manager(&account)?;
+    let task_id = session.submit(TaskRequest::GetBuyerInfoRequest(GetBuyerInfoRequest {
+        uid,
+        task_id: String::new(),
+        cookie_manager,
+    }))?;
+
+    match session.wait_for(
+        |r| matches!(r, TaskResult::GetBuyerInfoResult(b) if b.task_id == task_id),
+        |r| print_result(r, json),
+    ) {
+        Some(TaskResult::GetBuyerInfoResult(r)) => match r.buyer_info {
+            Some(info) if r.success => Ok(info.data.list),
+            _ => Err(format!("获取购票人信息失败: {}", r.message)),
+        },
+        _ => Err("任务管理器已关闭".to_string()),
+    }
+}
+
+pub fn history(args: HistoryArgs, json: bool) -> Result<(), String> {
+    let query = HistoryQuery {
+        task_id: args.task,
+        uid: args.uid,
+        project_id: args.project,
+        since: args.since.as_deref().map(parse_local_time).transpose()?,
+        until: args.until.as_deref().map(parse_local_time).transpose()?,
+        limit: if args.attempts { args.limit } else { None },
+    };
+    let records = history::query(&query)?;
+
+    if args.attempts {
+        if json {
+            for record in &records {
+                println!("{}", serde_json::to_string(record).unwrap_or_default());
+            }
+        } else {
+            print_history_records(&records);
+        }
+        return Ok(());
+    }
+
+    let mut summaries = history::summarize(&records);
+    if let Some(limit) = args.limit {
+        let skip = summaries.len().saturating_sub(limit);
+        summaries.drain(..skip);
+    }
+    if json {
+        for summary in &summaries {
+            println!("{}", serde_json::to_string(summary).unwrap_or_default());
+        }
+    } else {
+        print_task_summaries(&summaries);
+    }
+    Ok(())
+}
+
+pub fn logs(args: LogsArgs, json: bool) -> Result<(), String> {
+    if let Some(path) = &args.export {
+        let count = task_log::export(&args.task, std::path::Path::new(path), json)?;
+        println!("已导出 {} 条日志到 {}", count, path);
+        return Ok(());
+    }
+
+    // 跟踪模式下任务可能还没有产生日志，先按空日志处理
+    let (records, mut next_seq) = match task_log::fetch(&args.task, 0) {
+        Ok(chunk) => (chunk.records, chunk.next_seq),
+        Err(e) if !args.follow => return Err(e),
+        Err(_) => (Vec::new(), 0),
+    };
+    let skip = args
+        .tail
+        .map_or(0, |tail| records.len().saturating_sub(tail));
+    for record in &records[skip..] {
+        print_log_record(record, json);
+    }
+    if !args.follow {
+        return Ok(());
+    }
+
+    loop {
+        std::thread::sleep(std::time::Duration::from_millis(500));
+        if let Ok(chunk) = task_log::fetch(&args.task, next_seq) {
+            for record in &chunk.records {
+                print_log_record(record, json);
+            }
+            next_seq = chunk.next_seq;
+        }
+    }
+}
+
+// 列出或放弃上次未结束的抢票任务，不需要创建任务管理器
+pub fn unfinished_tasks(args: &ResumeArgs, json: bool) -> Result<(), String> {
+    if let Some(task_id) = &args.task {
+        task_journal::find(task_id)?;
+        task_journal::remove(task_id);
+        task_journal::flush();
+        println!("已放弃任务 {}", task_id);
+        return Ok(());
+    }
+
+    let entries = task_journal::load()?;
+    if json {
+        for entry in &entries {
+            println!(
+                "{}",
+                serde_json::json!({
+                    "task_id": entry.task_id,
+                    "uid": entry.uid,
+                    "project_id": entry.project_id,
+                    "project_name": entry.project_name(),
+                    "grab_mode": entry.grab_mode,
+                    "saved_at": entry.saved_at,
+                })
+            );
+        }
+    } else {
+        print_unfinished_tasks(&entries);
+    }
+    Ok(())
+}
+
+// 用配置中的账号恢复上次未结束的抢票任务，并等待其结束
+pub fn resume(session: &mut CliSession, task_id: &str, json: bool) -> Result<(), String> {
+    let entry = task_journal::find(task_id)?;
+    let account = session.account(entry.uid)?;
+    let request = entry.to_request(
+        &account,
+        &session.config.custom_config,
+        &session.config.push_config,
+        LocalCaptcha::new(),
+    )?;
+    session.submit(TaskRequest::GrabTicketRequest(request))?;
+    if !json {
+        println!(
+            "已恢复抢票任务 {} ({}，模式 {})，按 Ctrl+C 结束",
+            task_id,
+            entry.project_name(),
+            entry.grab_mode
+        );
+    }
+    wait_for_grab(session, task_id, json)
+}
+
+fn schedule_time(args: &ScheduleTimeArgs) -> Result<ScheduleTime, String> {
+    match (&args.at, args.before_sale) {
+        (Some(at), _) => {
+            let time = parse_local_time(at)?;
+            if time <= chrono::Local::now().timestamp_millis() {
+                return Err("定时时间必须晚于当前时间".to_string());
+            }
+            Ok(ScheduleTime::At { time })
+        }
+        (None, Some(minutes)) if minutes >= 0 => Ok(ScheduleTime::BeforeSale { minutes }),
+        (None, Some(_)) => Err("开售前分钟数不能为负数".to_string()),
+        (None, None) => Err("请指定 --at 或 --before-sale".to_string()),
+    }
+}
+
+// 以下定时任务命令只读写 schedule.json，不需要任务管理器
+pub fn schedule_list(json: bool) -> Result<(), String> {
+    let jobs = schedule::load()?;
+    if json {
+        for job in &jobs {
+            match serde_json::to_string(job) {
+                Ok(line) => println!("{}", line),
+                Err(e) => eprintln!("序列化定时任务失败: {}", e),
+            }
+        }
+    } else {
+        print_scheduled_jobs(&jobs);
+    }
+    Ok(())
+}
+
+pub fn schedule_edit(id: &str, time: &ScheduleTimeArgs) -> Result<(), String> {
+    let when = schedule_time(time)?;
+    schedule::modify(id, |job| job.reschedule(when, job.preflight))?;
+    println!("已修改定时任务 {}", id);
+    Ok(())
+}
+
+pub fn schedule_remove(id: &str) -> Result<(), String> {
+    schedule::remove(id)?;
+    println!("已删除定时任务 {}", id);
+    Ok(())
+}
+
+pub fn schedule_add(
+    session: &mut CliSession,
+    grab: &GrabArgs,
+    time: &ScheduleTimeArgs,
+    json: bool,
+) -> Result<(), String> {
+    let when = schedule_time(time)?;
+    let request = build_grab_request(session, grab, json)?;
+    let job = ScheduledJob::new(
+        JournalEntry::from_request(&request),
+        when,
+        session.config.schedule_preflight,
+    );
+    let id = job.id.clone();
+    schedule::add(job)?;
+    println!(
+        "已添加定时任务 {}，需要运行 schedule run 或打开图形界面才会执行",
+        id
+    );
+    Ok(())
+}
+
+// 在本进程中运行调度，持续输出提交的抢票任务结果
+pub fn schedule_run(session: &mut CliSession, json: bool) -> Result<(), String> {
+    Scheduler::new(
+        session.task_manager.control(),
+        Arc::new(|| BtrConfig::load_config().map_err(|e| format!("加载配置失败: {}", e))),
+        LocalCaptcha::new(),
+    )
+    .start();
+    if !json {
+        println!("定时任务调度已启动，按 Ctrl+C 退出");
+    }
+    while let Some(result) = session.next_result() {
+        print_result(&result, json);
+    }
+    Ok(())
+}
+
+// 任务在另一个进程（图形界面或 grab 命令）中运行，通过其本地任务接口查询和取消
+pub fn tasks(command: Option<TasksCommand>, json: bool) -> Result<(), String> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .map_err(|e| e.to_string())?;
+    // 连不上的接口信息文件来自已退出的程序，顺手清理
+    let endpoints: Vec<TaskApiEndpoint> = task_api::discover()
+        .into_iter()
+        .filter(|endpoint| {
+            let alive = TcpStream::connect(&endpoint.addr).is_ok();
+            if !alive {
+                endpoint.remove();
+            }
+            alive
+        })
+        .collect();
+    if endpoints.is_empty() {
+        return Err("未找到正在运行的程序（图形界面或 cli grab）".to_string());
+    }
+
+    match command.unwrap_or(TasksCommand::List) {
+        TasksCommand::List => {
+            let mut tasks: Vec<TaskSummary> = Vec::new();
+            for endpoint in &endpoints {
+                let response = client
+                    .get(format!("http://{}/tasks", endpoint.addr))
+                    .header(task_api::TOKEN_HEADER, &endpoint.token)
+                    .send()
+                    .map_err(|e| format!("连接进程 {} 失败: {}", endpoint.pid, e))?;
+                if !response.status().is_success() {
+                    return Err(format!("任务接口不可用: {}", response.status()));
+                }
+                let mut listed: Vec<TaskSummary> = response
+                    .json()
+                    .map_err(|e| format!("解析任务列表失败: {}", e))?;
+                tasks.append(&mut listed);
+            }
+            if json {
+                for task in &tasks {
+                    println!("{}", serde_json::to_string(task).unwrap_or_default());
+                }
+            } else {
+                print_running_tasks(&tasks);
+            }
+        }
+        TasksCommand::Cancel { task_id } => {
+            // 任务 ID 全局唯一，依次询问各进程，直到有一个取消成功
+            let mut last_error = "任务不存在".to_string();
+            for endpoint in &endpoints {
+                let response = client
+                    .post(format!("http://{}/tasks/{}/cancel", endpoint.addr, task_id))
+                    .header(task_api::TOKEN_HEADER, &endpoint.token)
+                    .send()
+                    .map_err(|e| format!("连接进程 {} 失败: {}", endpoint.pid, e))?;
+                let status = response.status();
+                let body: serde_json::Value = response.json().unwrap_or_default();
+                if status.is_success() {
+                    println!("已取消任务 {}", task_id);
+                    return Ok(());
+                }
+                last_error = body["message"]
+                    .as_str()
+                    .unwrap_or(status.as_str())
+                    .to_string();
+            }
+            return Err(format!("取消任务失败: {}", last_error));
+        }
+    }
+    Ok(())
+}
+
+pub fn vault(command: &VaultCommand, json: bool) -> Result<(), String> {
+    let config_path = &paths::config_path();
+    match command {
+        VaultCommand::Status => {
+            let status = vault::status(config_path);
+            if json {
+                println!("{}", serde_json::to_string(&status).unwrap_or_default());
+            } else {
+                println!(
+                    "配置文件: {}",
+                    match status.kdf.as_deref() {
+                        Some("argon2id") => "已加密（口令）",
+                        Some(_) => "已加密（密钥文件）",
+                        None => "明文",
+                    }
+                );
+                println!(
+                    "密钥: {}",
+                    if status.key_available {
+                        "已提供"
+                    } else {
+                        "未提供"
+                    }
+                );
+            }
+            Ok(())
+        }
+        VaultCommand::Encrypt { keyfile } => {
+            if vault::status(config_path).encrypted {
+                return Err("配置文件已经是加密格式".to_string());
+            }
+            if *keyfile {
+                // 口令优先于密钥文件，同时提供时会用口令加密，直接拒绝
+                if vault::passphrase_set() {
+                    return Err(
+                        "已设置 BTR_VAULT_PASSPHRASE，不能同时使用 --keyfile，请先取消该环境变量"
+                            .to_string(),
+                    );
+                }
+                vault::generate_keyfile(&vault::keyfile_path())?;
+            } else if vault::current_key()?.is_none() {
+                return Err(
+                    "请设置环境变量 BTR_VAULT_PASSPHRASE，或使用 --keyfile 生成密钥文件"
+                        .to_string(),
+                );
+            }
+            let config = BtrConfig::load_config().map_err(|e| format!("加载配置失败: {}", e))?;
+            config
+                .save_config()
+                .map_err(|e| format!("保存配置失败: {}", e))?;
+            println!("配置文件已加密");
+            Ok(())
+        }
+        VaultCommand::Decrypt => {
+            let config = BtrConfig::load_config().map_err(|e| format!("加载配置失败: {}", e))?;
+            config
+                .save_config_plaintext()
+                .map_err(|e| format!("保存配置失败: {}", e))?;
+            println!("配置文件已解密为明文");
+            if vault::keyfile_path().exists() {
+                println!(
+                    "注意：密钥文件 {} 仍然存在，下次保存配置时会重新加密，如需保持明文请删除它",
+                    vault::keyfile_path().display()
+                );
+            }
+            Ok(())
+        }
+    }
+}
+
+// 解析本地时间为毫秒时间戳，只给日期时取当天 0 点
+fn parse_local_time(input: &str) -> Result<i64, String> {
+    let input = input.trim();
+    let naive = chrono::NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| chrono::NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M"))
+        .or_else(|_| {
+            chrono::NaiveDate::parse_from_str(input, "%Y-%m-%d")
+                .map(|d| d.and_hms_opt(0, 0, 0).unwrap_or_default())
+        })
+        .map_err(|_| format!("无法解析时间: {}", input))?;
+    naive
+        .and_local_timezone(chrono::Local)
+        .earliest()
+        .map(|t| t.timestamp_millis())
+        .ok_or_else(|| format!("无效的本地时间: {}", input))
+}
+
+// 解析候选票种：场次ID[:票种ID][:票价上限(元)]
+fn parse_preference(input: &str) -> Result<TicketPreference, String> {
+    let mut parts = input.trim().split(':');
+    let screen_id = parts.next().unwrap_or_default().trim().to_string();
+    if screen_id.is_empty() {
+        return Err(format!("候选票种缺少场次ID: {}", input));
+    }
+    let ticket_id = parts
+        .next()
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(str::to_string);
+    let max_price = match parts.next().map(str::trim).filter(|p| !p.is_empty()) {
+        Some(price) => {
+            let yuan: f64 = price
+                .parse()
+                .ok()
+                .filter(|yuan: &f64| *yuan >= 0.0)
+                .ok_or_else(|| format!("无效的票价上限: {}", price))?;
+            Some((yuan * 100.0).round() as usize)
+        }
+        None => None,
+    };
+    if parts.next().is_some() {
+        return Err(format!("候选票种格式有误: {}", input));
+    }
+    Ok(TicketPreference {
+        screen_id,
+        ticket_id,
+        max_price,
+    })
+}
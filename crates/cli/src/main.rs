@@ -1,3 +1,88 @@
+mod args;
+mod commands;
+mod output;
+mod session;
+
+use clap::Parser;
+
+use crate::args::{AccountsCommand, Cli, Command, ProjectCommand, ScheduleCommand};
+use crate::session::CliSession;
+
 fn main() {
-    println!("Hello, world!");
+    let cli = Cli::parse();
+
+    if let Err(e) = common::init_logger() {
+        eprintln!("初始化日志失败，原因: {}", e);
+    }
+    if cli.unsafe_debug {
+        common::redact::set_unsafe_debug(true);
+    }
+    if cli.log_json {
+        common::record_log::set_json_file(true);
+    }
+
+    if let Err(e) = run(cli) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+// 查询历史、日志、任务和加密等命令不依赖本进程的任务管理器，也不启动指标接口，
+// 避免与正在运行的程序争用端口；加密命令还需要在配置无法解密时运行
+fn run(cli: Cli) -> Result<(), String> {
+    let json = cli.json;
+    let metrics_addr = cli.metrics_addr;
+    match cli.command {
+        Command::Vault { command } => commands::vault(&command, json),
+        Command::History(history_args) => commands::history(history_args, json),
+        Command::Logs(logs_args) => commands::logs(logs_args, json),
+        Command::Tasks { command } => commands::tasks(command, json),
+        Command::Resume(args) => match &args.task {
+            Some(task_id) if !args.discard => with_session(metrics_addr, |session| {
+                commands::resume(session, task_id, json)
+            }),
+            _ => commands::unfinished_tasks(&args, json),
+        },
+        Command::Schedule { command } => match command.unwrap_or(ScheduleCommand::List) {
+            ScheduleCommand::List => commands::schedule_list(json),
+            ScheduleCommand::Edit { id, time } => commands::schedule_edit(&id, &time),
+            ScheduleCommand::Remove { id } => commands::schedule_remove(&id),
+            ScheduleCommand::Add { grab, time } => with_session(metrics_addr, |session| {
+                commands::schedule_add(session, &grab, &time, json)
+            }),
+            ScheduleCommand::Run => with_session(metrics_addr, |session| {
+                commands::schedule_run(session, json)
+            }),
+        },
+        Command::Accounts {
+            command: AccountsCommand::List,
+        } => with_session(metrics_addr, |session| {
+            commands::accounts_list(session, json)
+        }),
+        Command::Project {
+            command: ProjectCommand::Info { project_id, uid },
+        } => with_session(metrics_addr, |session| {
+            commands::project_info(session, &project_id, uid, json)
+        }),
+        Command::Buyers { uid } => {
+            with_session(metrics_addr, |session| commands::buyers(session, uid, json))
+        }
+        Command::Grab(grab_args) => with_session(metrics_addr, |session| {
+            commands::grab(session, grab_args, json)
+        }),
+        Command::Orders { uid } => {
+            with_session(metrics_addr, |session| commands::orders(session, uid, json))
+        }
+    }
+}
+
+// 需要本进程任务管理器的命令：创建会话，执行完后关闭
+fn with_session<F>(metrics_addr: Option<String>, run: F) -> Result<(), String>
+where
+    F: FnOnce(&mut CliSession) -> Result<(), String>,
+{
+    let mut session = CliSession::new(metrics_addr)?;
+    let result = run(&mut session);
+    session.shutdown();
+    result
 }
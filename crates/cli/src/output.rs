@@ -0,0 +1,358 @@
+use common::history::{HistoryRecord, TaskSummary};
+use common::log_record::LogRecord;
+use common::schedule::{JobStatus, ScheduleTime, ScheduledJob};
+use common::task_journal::JournalEntry;
+use common::taskmanager::{TaskPhase, TaskResult, TaskStatus};
+use common::ticket::{BuyerInfo, TicketInfo};
+use common::ticket_error::TicketApiError;
+
+// 按 --json 选项输出任务结果
+pub fn print_result(result: &TaskResult, json: bool) {
+    if json {
+        match serde_json::to_string(result) {
+            Ok(line) => println!("{}", line),
+            Err(e) => eprintln!("序列化任务结果失败: {}", e),
+        }
+        return;
+    }
+
+    match result {
+        TaskResult::GetTicketInfoResult(r) => match &r.ticket_info {
+            Some(info) if r.success => print_project(&info.data),
+            _ => println!("获取项目失败: {}", r.message),
+        },
+        TaskResult::GetBuyerInfoResult(r) => match &r.buyer_info {
+            Some(info) if r.success => print_buyers(&info.data.list),
+            _ => println!("获取购票人失败: {}", r.message),
+        },
+        TaskResult::GetAllorderRequestResult(r) => match &r.order_info {
+            Some(orders) if r.success => {
+                println!("共 {} 个订单", orders.data.total);
+                for order in &orders.data.list {
+                    println!(
+                        "  [{}] {} | {} | {} 张 | ¥{:.2} | {}",
+                        order.order_id,
+                        order.item_info.name,
+                        order.item_info.screen_name,
+                        order.count,
+                        order.pay_money as f64 / 100.0,
+                        order.sub_status_name
+                    );
+                }
+            }
+            _ => println!("获取订单失败: {}", r.message),
+        },
+        TaskResult::GrabTicketResult(r) => {
+            println!(
+                "[{}] 抢票{}: {}",
+                r.task_id,
+                if r.success {
+                    "成功"
+                } else if r.cancelled {
+                    "已取消"
+                } else {
+                    "结束"
+                },
+                r.message
+            );
+            if let Some(order_id) = &r.order_id {
+                println!("  订单号: {}", order_id);
+            }
+            if let Some(confirm) = &r.confirm_result {
+                println!(
+                    "  {} | {} | {} | ¥{:.2}",
+                    confirm.project_name,
+                    confirm.screen_name,
+                    confirm.ticket_info.name,
+                    confirm.pay_money as f64 / 100.0
+                );
+            }
+            if let Some(code_url) = r.pay_result.as_ref().and_then(|p| p.code_url.as_ref()) {
+                println!("  支付链接: {}", code_url);
+            }
+        }
+        TaskResult::PushResult(r) => {
+            println!(
+                "推送{}: {}",
+                if r.success { "成功" } else { "失败" },
+                r.message
+            );
+            for c in &r.channels {
+                println!(
+                    "  {}: {} {} ({}ms)",
+                    c.channel,
+                    if c.success { "成功" } else { "失败" },
+                    c.message,
+                    c.elapsed_ms
+                );
+            }
+        }
+        TaskResult::DungeonQrResult(r) => println!("Dungeon 绑定二维码: {}", r.qr_url),
+        TaskResult::QrCodeLoginResult(r) => println!("二维码登录状态: {:?}", r.status),
+        TaskResult::LoginSmsResult(r) => println!("短信验证码: {}", r.message),
+        TaskResult::SubmitSmsLoginResult(r) => println!("短信登录: {}", r.message),
+    }
+}
+
+pub fn print_project(info: &TicketInfo) {
+    println!("项目: {} (ID: {})", info.name, info.id);
+    println!(
+        "开售时间: {} | 实名类型: {} | 热门项目: {}",
+        format_timestamp(info.sale_begin),
+        info.id_bind,
+        info.hot_project
+    );
+    for screen in &info.screen_list {
+        println!(
+            "  场次 {} | {} | {}",
+            screen.id, screen.name, screen.sale_flag.display_name
+        );
+        for ticket in &screen.ticket_list {
+            println!(
+                "    票种 {} | {} | ¥{:.2} | {}{}",
+                ticket.id,
+                ticket.desc,
+                ticket.price as f64 / 100.0,
+                ticket.sale_flag.display_name,
+                if ticket.clickable { "" } else { " (不可选)" }
+            );
+        }
+    }
+}
+
+pub fn print_buyers(buyers: &[BuyerInfo]) {
+    if buyers.is_empty() {
+        println!("该账号没有购票人");
+        return;
+    }
+    for buyer in buyers {
+        println!(
+            "  {} | {} | {}{}",
+            buyer.id,
+            buyer.name,
+            buyer.tel,
+            if buyer.is_default == 1 {
+                " (默认)"
+            } else {
+                ""
+            }
+        );
+    }
+}
+
+pub fn print_task_summaries(summaries: &[TaskSummary]) {
+    if summaries.is_empty() {
+        println!("没有匹配的抢票记录");
+        return;
+    }
+    for summary in summaries {
+        let status = match summary.success {
+            Some(true) => "成功",
+            Some(false) => "失败",
+            None => "未结束",
+        };
+        println!(
+            "[{}] UID {} | 项目 {} | {} | {} 次请求",
+            summary.task_id, summary.uid, summary.project_id, status, summary.attempts
+        );
+        println!(
+            "  开始: {} | 结束: {}",
+            summary
+                .started_at
+                .map(format_millis)
+                .unwrap_or_else(|| "-".to_string()),
+            summary
+                .finished_at
+                .map(format_millis)
+                .unwrap_or_else(|| "-".to_string())
+        );
+        if !summary.message.is_empty() {
+            println!("  结果: {}", summary.message);
+        }
+        if let Some(order_id) = &summary.order_id {
+            println!("  订单号: {}", order_id);
+        }
+        if !summary.error_codes.is_empty() {
+            let codes: Vec<String> = summary
+                .error_codes
+                .iter()
+                .map(|(code, count)| match TicketApiError::from_code(*code) {
+                    TicketApiError::Unknown(_) => format!("{}×{}", code, count),
+                    error => format!("{}({})×{}", code, error.message(), count),
+                })
+                .collect();
+            println!("  错误码: {}", codes.join(", "));
+        }
+    }
+}
+
+pub fn print_running_tasks(tasks: &[common::taskmanager::TaskSummary]) {
+    if tasks.is_empty() {
+        println!("没有任务");
+        return;
+    }
+    for task in tasks {
+        let status = match &task.status {
+            TaskStatus::Pending => "等待中".to_string(),
+            TaskStatus::Running => "运行中".to_string(),
+            TaskStatus::Completed(true) => "已完成".to_string(),
+            TaskStatus::Completed(false) => "未成功".to_string(),
+            TaskStatus::Failed(e) => format!("失败: {}", e),
+            TaskStatus::Cancelled => "已取消".to_string(),
+        };
+        println!("[{}] {} | {}", task.task_id, task.kind, status);
+        let mut detail = Vec::new();
+        if let Some(uid) = task.uid {
+            detail.push(format!("UID {}", uid));
+        }
+        if let Some(project_id) = &task.project_id {
+            detail.push(format!("项目 {}", project_id));
+        }
+        if let Some(phase) = task.phase {
+            detail.push(format!("阶段 {}", phase_label(phase)));
+        }
+        detail.push(format!(
+            "开始 {}",
+            task.started_at
+                .map(format_millis)
+                .unwrap_or_else(|| "-".to_string())
+        ));
+        detail.push(format!("已运行 {:.1}s", task.elapsed_ms as f64 / 1000.0));
+        println!("  {}", detail.join(" | "));
+    }
+}
+
+pub fn print_unfinished_tasks(entries: &[JournalEntry]) {
+    if entries.is_empty() {
+        println!("没有未结束的抢票任务");
+        return;
+    }
+    for entry in entries {
+        println!(
+            "[{}] UID {} | {} | 模式 {}",
+            entry.task_id,
+            entry.uid,
+            entry.project_name(),
+            entry.grab_mode
+        );
+        let sale_begin = entry
+            .project_info
+            .as_ref()
+            .map(|info| format_timestamp(info.sale_begin))
+            .unwrap_or_else(|| "-".to_string());
+        println!(
+            "  开售: {} | 提交: {}",
+            sale_begin,
+            format_millis(entry.saved_at)
+        );
+    }
+}
+
+pub fn print_scheduled_jobs(jobs: &[ScheduledJob]) {
+    if jobs.is_empty() {
+        println!("没有定时任务");
+        return;
+    }
+    for job in jobs {
+        let status = match job.status {
+            JobStatus::Scheduled => "等待中",
+            JobStatus::Submitted => "已提交",
+            JobStatus::Failed => "失败",
+        };
+        println!(
+            "[{}] UID {} | {} | 模式 {} | {}",
+            job.id,
+            job.grab.uid,
+            job.grab.project_name(),
+            job.grab.grab_mode,
+            status
+        );
+        let when = match job.when {
+            ScheduleTime::At { time } => format!("指定时间 {}", format_millis(time)),
+            ScheduleTime::BeforeSale { minutes } => format!("开售前 {} 分钟", minutes),
+        };
+        println!(
+            "  {} | 触发: {}",
+            when,
+            job.fire_at()
+                .map(format_millis)
+                .unwrap_or_else(|| "等待获取开售时间".to_string())
+        );
+        for record in &job.preflight_log {
+            println!(
+                "  预检 {} {}: {}",
+                record.step.label(),
+                if record.success { "成功" } else { "失败" },
+                record.message
+            );
+        }
+        if !job.message.is_empty() {
+            println!("  {}", job.message);
+        }
+    }
+}
+
+fn phase_label(phase: TaskPhase) -> &'static str {
+    match phase {
+        TaskPhase::WaitingForSale => "等待开售",
+        TaskPhase::GettingToken => "获取token",
+        TaskPhase::Confirming => "确认订单",
+        TaskPhase::CreatingOrder => "创建订单",
+        TaskPhase::AwaitingPayment => "等待支付",
+    }
+}
+
+pub fn print_log_record(record: &LogRecord, json: bool) {
+    if json {
+        println!("{}", record.to_json());
+    } else {
+        println!("{}", record.to_line());
+    }
+}
+
+pub fn print_history_records(records: &[HistoryRecord]) {
+    if records.is_empty() {
+        println!("没有匹配的抢票记录");
+        return;
+    }
+    for record in records {
+        println!(
+            "{} [{}] {:?} | {} | code={} | {}ms{}",
+            format_millis(record.time),
+            record.task_id,
+            record.event,
+            if record.success { "成功" } else { "失败" },
+            record
+                .code
+                .map(|c| c.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            record.latency_ms.unwrap_or(0),
+            if record.message.is_empty() {
+                String::new()
+            } else {
+                format!(" | {}", record.message)
+            }
+        );
+    }
+}
+
+fn format_millis(ms: i64) -> String {
+    chrono::DateTime::from_timestamp_millis(ms)
+        .map(|dt| {
+            dt.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S%.3f")
+                .to_string()
+        })
+        .unwrap_or_else(|| ms.to_string())
+}
+
+fn format_timestamp(ts: i64) -> String {
+    let secs = if ts > 10_000_000_000 { ts / 1000 } else { ts };
+    chrono::DateTime::from_timestamp(secs, 0)
+        .map(|dt| {
+            dt.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_else(|| ts.to_string())
+}
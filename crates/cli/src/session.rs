@@ -0,0 +1,127 @@
+use std::sync::Arc;
+
+use backend::taskmanager::TaskManagerImpl;
+use common::account::Account;
+use common::config::BtrConfig;
+use common::cookie_manager::CookieManager;
+use common::endpoints::ApiEndpoints;
+use common::history;
+use common::metrics_exporter;
+use common::record_log;
+use common::task_api::{self, TaskApiEndpoint};
+use common::task_journal;
+use common::taskmanager::{TaskManager, TaskRequest, TaskResult};
+use tokio::sync::mpsc;
+
+// 命令行运行时：配置 + 任务管理器 + 结果通道
+pub struct CliSession {
+    pub config: BtrConfig,
+    pub task_manager: TaskManagerImpl,
+    result_rx: mpsc::Receiver<TaskResult>,
+    task_api: Option<TaskApiEndpoint>,
+}
+
+impl CliSession {
+    pub fn new(metrics_addr: Option<String>) -> Result<Self, String> {
+        let mut config = BtrConfig::load_config().map_err(|e| format!("加载配置失败: {}", e))?;
+        ApiEndpoints::set_current(config.api_endpoints.clone());
+        if let Some(addr) = metrics_addr {
+            config.metrics_exporter.enabled = true;
+            config.metrics_exporter.listen = addr;
+        }
+        // 指标接口启动失败（如图形界面已占用端口）不影响命令本身
+        if let Err(e) = metrics_exporter::start(&config.metrics_exporter) {
+            log::warn!("{}", e);
+        }
+        if config.json_log_file {
+            record_log::set_json_file(true);
+        }
+
+        let (tx, rx) = mpsc::channel(100);
+        let mut task_manager = TaskManagerImpl::new();
+        task_manager.set_result_sender(tx);
+        task_manager.set_limits(config.custom_config.task_limits.clone());
+        let task_api = match task_api::start(Arc::new(task_manager.control())) {
+            Ok(endpoint) => Some(endpoint),
+            Err(e) => {
+                log::warn!("{}", e);
+                None
+            }
+        };
+
+        Ok(Self {
+            config,
+            task_manager,
+            result_rx: rx,
+            task_api,
+        })
+    }
+
+    // 查找账号，并确保其 cookie 管理器已初始化
+    pub fn account(&mut self, uid: i64) -> Result<Account, String> {
+        let account = self
+            .config
+            .accounts
+            .iter_mut()
+            .find(|a| a.uid == uid)
+            .ok_or_else(|| format!("未找到账号 {}", uid))?;
+        account.ensure_client();
+        Ok(account.clone())
+    }
+
+    // 未指定 UID 时使用第一个启用的账号
+    pub fn account_or_active(&mut self, uid: Option<i64>) -> Result<Account, String> {
+        let uid = match uid {
+            Some(uid) => uid,
+            None => self
+                .config
+                .accounts
+                .iter()
+                .find(|a| a.is_active)
+                .map(|a| a.uid)
+                .ok_or_else(|| "配置中没有启用的账号".to_string())?,
+        };
+        self.account(uid)
+    }
+
+    pub fn cookie_manager(account: &Account) -> Result<Arc<CookieManager>, String> {
+        account
+            .cookie_manager
+            .clone()
+            .ok_or_else(|| "账号未初始化，请重新添加账号".to_string())
+    }
+
+    pub fn submit(&mut self, request: TaskRequest) -> Result<String, String> {
+        self.task_manager.submit_task(request)
+    }
+
+    // 阻塞等待下一条任务结果
+    pub fn next_result(&mut self) -> Option<TaskResult> {
+        self.result_rx.blocking_recv()
+    }
+
+    // 阻塞等待满足条件的结果，其余结果交给 on_other 处理
+    pub fn wait_for<F, O>(&mut self, mut matches: F, mut on_other: O) -> Option<TaskResult>
+    where
+        F: FnMut(&TaskResult) -> bool,
+        O: FnMut(&TaskResult),
+    {
+        while let Some(result) = self.next_result() {
+            if matches(&result) {
+                return Some(result);
+            }
+            on_other(&result);
+        }
+        None
+    }
+
+    pub fn shutdown(&mut self) {
+        self.task_manager.shutdown();
+        // 进程即将退出，等待后台线程写完任务日志和抢票历史
+        task_journal::flush();
+        history::flush();
+        if let Some(endpoint) = self.task_api.take() {
+            endpoint.remove();
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use crate::cookie_manager::{self, CookieManager};
+use crate::endpoints::ApiEndpoints;
 use crate::http_utils::request_get;
+use crate::redact;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json;
@@ -27,8 +29,8 @@ impl std::fmt::Debug for Account {
             .field("uid", &self.uid)
             .field("name", &self.name)
             .field("level", &self.level)
-            .field("cookie", &self.cookie)
-            .field("csrf", &self.csrf)
+            .field("cookie", &redact::secret(&self.cookie))
+            .field("csrf", &redact::secret(&self.csrf))
             .field("is_login", &self.is_login)
             .field("account_status", &self.account_status)
             .field("vip_label", &self.vip_label)
@@ -42,10 +44,10 @@ impl std::fmt::Debug for Account {
 }
 
 pub async fn add_account(cookie: &str, client: &Client, ua: &str) -> Result<Account, String> {
-    log::info!("添加账号: {}", cookie);
+    log::info!("添加账号: {}", redact::redact(cookie));
     let response = request_get(
         client,
-        "https://api.bilibili.com/x/web-interface/nav",
+        &ApiEndpoints::current().api_url("/x/web-interface/nav"),
         Some(cookie),
     )
     .await
@@ -96,11 +98,9 @@ pub fn signout_account(account: &Account) -> Result<bool, String> {
     });
     let rt = tokio::runtime::Runtime::new().unwrap();
     let response = rt.block_on(async {
-        account
-            .cookie_manager
-            .clone()
-            .unwrap()
-            .post("https://passport.bilibili.com/login/exit/v2")
+        let cookie_manager = account.cookie_manager.clone().unwrap();
+        cookie_manager
+            .post(&cookie_manager.endpoints.passport_url("/login/exit/v2"))
             .await
             .json(&data)
             .send()
@@ -118,7 +118,7 @@ pub fn signout_account(account: &Account) -> Result<bool, String> {
 //提取 csrf
 fn extract_csrf(cookie: &str) -> String {
     // 打印原始cookie用于调试
-    log::debug!("提取CSRF的原始cookie: {}", cookie);
+    log::debug!("提取CSRF的原始cookie: {}", redact::redact(cookie));
 
     for part in cookie.split(';') {
         let part = part.trim();
@@ -129,7 +129,7 @@ fn extract_csrf(cookie: &str) -> String {
                 let value = &part[pos + 1..];
                 // 去除可能的引号
                 let value = value.trim_matches('"').trim_matches('\'');
-                log::debug!("成功提取CSRF值: {}", value);
+                log::debug!("成功提取CSRF值: {}", redact::secret(value));
                 return value.to_string();
             }
         }
@@ -43,6 +43,14 @@ impl LocalCaptcha {
             slide: None, //暂时先不初始化滑块，疑似出现滑块概率极低
         }
     }
+
+    // 不加载本地打码模型（使用 ttocr 或测试时）
+    pub fn disabled() -> Self {
+        LocalCaptcha {
+            click: None,
+            slide: None,
+        }
+    }
 }
 pub async fn captcha(
     custom_config: CustomConfig,
@@ -187,9 +195,11 @@ pub async fn handle_risk_verification(
         None => return Err("风控参数为空".to_string()),
     };
     log::debug!("风控参数: {:?}", risk_params_value);
-    let url = "https://api.bilibili.com/x/gaia-vgate/v1/register";
+    let url = cookie_manager
+        .endpoints
+        .api_url("/x/gaia-vgate/v1/register");
     let response = cookie_manager
-        .post(url)
+        .post(&url)
         .await
         .json(&json!(risk_params_value))
         .send()
@@ -259,9 +269,11 @@ pub async fn handle_risk_verification(
             });
 
             log::debug!("发送验证请求: {:?}", params);
-            let validate_url = "https://api.bilibili.com/x/gaia-vgate/v1/validate";
+            let validate_url = cookie_manager
+                .endpoints
+                .api_url("/x/gaia-vgate/v1/validate");
             let validate_response = cookie_manager
-                .post(validate_url)
+                .post(&validate_url)
                 .await
                 .json(&params)
                 .send()
@@ -1,8 +1,14 @@
 use crate::account::Account;
+use crate::endpoints::ApiEndpoints;
+use crate::paths;
+use crate::retry::{RetryPolicies, RetryPolicy, RetryStage};
+use crate::schedule::PreflightOffsets;
+use crate::taskmanager::TaskKind;
+use crate::vault;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::io;
-use std::path::Path;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Project {
@@ -31,6 +37,14 @@ pub struct BtrConfig {
     pub max_attempts: u64,
     #[serde(default)]
     pub skip_words: Option<Vec<String>>,
+    #[serde(default)]
+    pub api_endpoints: ApiEndpoints, //接口地址，可指向本地 mock 服务
+    #[serde(default)]
+    pub metrics_exporter: MetricsExporterConfig,
+    #[serde(default)]
+    pub json_log_file: bool, //日志文件按 JSON 行写入
+    #[serde(default)]
+    pub schedule_preflight: PreflightOffsets, //新建定时任务的预检提前量
 }
 
 fn default_delay_time() -> u64 {
@@ -66,27 +80,45 @@ impl Default for BtrConfig {
             delay_time: default_delay_time(),
             max_attempts: default_max_attempts(),
             skip_words: None,
+            api_endpoints: ApiEndpoints::default(),
+            metrics_exporter: MetricsExporterConfig::default(),
+            json_log_file: false,
+            schedule_preflight: PreflightOffsets::default(),
         }
     }
 }
 
 impl BtrConfig {
     pub fn load_config() -> io::Result<Self> {
-        if !Path::new("./config").exists() {
+        let config_path = paths::config_path();
+        if !config_path.exists() {
             return Ok(BtrConfig::default());
         }
 
-        let raw_context = fs::read_to_string("./config")?;
+        let raw_context = vault::read_config(&config_path)
+            .map_err(|e| io::Error::new(io::ErrorKind::PermissionDenied, e))?;
         serde_json::from_str(&raw_context)
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
     }
 
+    // 有口令或密钥文件时写成加密格式，见 vault.rs
     pub fn save_config(&self) -> io::Result<()> {
         let json_str = serde_json::to_string_pretty(self)?;
+        let content = vault::seal_config(&paths::config_path(), &json_str)
+            .map_err(|e| io::Error::new(io::ErrorKind::PermissionDenied, e))?;
+        Self::write_config(&content)
+    }
+
+    // 解密后以明文写回（取消加密时使用）
+    pub fn save_config_plaintext(&self) -> io::Result<()> {
+        let json_str = serde_json::to_string_pretty(self)?;
+        Self::write_config(&json_str)
+    }
 
-        let temp_path = "./config.tmp";
-        fs::write(temp_path, json_str)?;
-        fs::rename(temp_path, "./config")
+    fn write_config(content: &str) -> io::Result<()> {
+        let temp_path = paths::data_path("config.tmp");
+        vault::write_private(&temp_path, content.as_bytes())?;
+        fs::rename(&temp_path, paths::config_path())
     }
 
     pub fn add_account(&mut self, account: Account) {
@@ -134,6 +166,14 @@ pub struct PushConfig {
     pub gotify_config: GotifyConfig,
     #[serde(default)]
     pub dungeon_config: DungeonConfig,
+    #[serde(default)]
+    pub smtp_config: SmtpConfig,
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+    #[serde(default)]
+    pub ntfy_config: NtfyConfig,
+    #[serde(default)]
+    pub matrix_config: MatrixConfig,
 }
 
 impl Default for PushConfig {
@@ -148,6 +188,10 @@ impl Default for PushConfig {
             wechat_token: String::new(),
             gotify_config: GotifyConfig::default(),
             dungeon_config: DungeonConfig::default(),
+            smtp_config: SmtpConfig::default(),
+            webhooks: Vec::new(),
+            ntfy_config: NtfyConfig::default(),
+            matrix_config: MatrixConfig::default(),
         }
     }
 }
@@ -180,12 +224,124 @@ impl Default for DungeonConfig {
     }
 }
 
+// 本地 Prometheus 指标接口，默认关闭
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MetricsExporterConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_metrics_listen")]
+    pub listen: String,
+}
+
+fn default_metrics_listen() -> String {
+    "127.0.0.1:9464".to_string()
+}
+
+impl Default for MetricsExporterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: default_metrics_listen(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct GotifyConfig {
     pub gotify_url: String,
     pub gotify_token: String,
 }
 
+// SMTP 连接加密方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    None, //明文，只用于本机或可信的中继
+    #[default]
+    StartTls, //明文连接后升级为 TLS，一般是 587 端口
+    Tls,  //直接使用 TLS 连接，一般是 465 端口
+}
+
+// 邮件推送
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub security: SmtpSecurity,
+    pub username: String, //为空时不登录
+    pub password: String,
+    pub from: String, //发件人，为空时使用 username
+    pub to: Vec<String>,
+}
+
+impl Default for SmtpConfig {
+    fn default() -> Self {
+        Self {
+            host: String::new(),
+            port: 587,
+            security: SmtpSecurity::StartTls,
+            username: String::new(),
+            password: String::new(),
+            from: String::new(),
+            to: Vec::new(),
+        }
+    }
+}
+
+// ntfy 推送
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NtfyConfig {
+    pub topic_url: String, //如 https://ntfy.sh/my-topic
+    pub token: String,     //访问令牌，公开主题留空
+    pub priority: u8,      //1-5，5 为最高
+}
+
+impl Default for NtfyConfig {
+    fn default() -> Self {
+        Self {
+            topic_url: String::new(),
+            token: String::new(),
+            priority: 5,
+        }
+    }
+}
+
+// Matrix 推送，以 access token 对应的账号向房间发送消息
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct MatrixConfig {
+    pub homeserver: String, //如 https://matrix.org
+    pub access_token: String,
+    pub room_id: String, //如 !abc:matrix.org
+}
+
+// 自定义 Webhook，URL、请求头和请求体中的 {{title}} 等占位符在发送时替换
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    pub name: String, //区分多个 Webhook 的推送结果
+    pub url: String,
+    pub method: String,
+    pub headers: BTreeMap<String, String>,
+    pub body: String, //请求体模板，为空时不发送请求体
+}
+
+pub const DEFAULT_WEBHOOK_BODY: &str = r#"{"title": "{{title}}", "message": "{{message}}", "order_id": "{{order_id}}", "pay_url": "{{pay_url}}"}"#;
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            url: String::new(),
+            method: "POST".to_string(),
+            headers: BTreeMap::new(),
+            body: DEFAULT_WEBHOOK_BODY.to_string(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CustomConfig {
     pub open_custom_ua: bool,
@@ -204,6 +360,10 @@ pub struct CustomConfig {
     pub max_order_retry: u32,
     #[serde(default = "default_retry_interval_ms")]
     pub retry_interval_ms: u64,
+    #[serde(default)]
+    pub task_limits: TaskLimits,
+    #[serde(default)]
+    pub retry_policies: RetryPolicies,
 }
 
 impl Default for CustomConfig {
@@ -222,6 +382,75 @@ impl Default for CustomConfig {
             max_fake_check_retry: default_max_fake_check_retry(),
             max_order_retry: default_max_order_retry(),
             retry_interval_ms: default_retry_interval_ms(),
+            task_limits: TaskLimits::default(),
+            retry_policies: RetryPolicies::default(),
+        }
+    }
+}
+
+impl CustomConfig {
+    // 某阶段实际使用的重试策略，未设置的最大次数和间隔取原有的重试设置；
+    // 下单阶段未设置最大次数时不限次数，轮询式选择请用 order_retry_policy
+    pub fn retry_policy(&self, stage: RetryStage) -> RetryPolicy {
+        let mut policy = self.retry_policies.get(stage).clone();
+        let max_attempts = match stage {
+            RetryStage::Token => self.max_token_retry as u32,
+            RetryStage::Confirm => self.max_confirm_retry as u32,
+            RetryStage::FakeCheck => self.max_fake_check_retry,
+            RetryStage::Order | RetryStage::Refresh => 0,
+        };
+        policy.max_attempts.get_or_insert(max_attempts);
+        policy.base_delay_ms.get_or_insert(self.retry_interval_ms);
+        policy
+    }
+
+    // 下单阶段的重试策略：与原来一样只有捡漏等轮询式选择按 max_order_retry 放弃当前票种，
+    // 在 retry_policies.order 中设置了 max_attempts 时各模式都以它为准
+    pub fn order_retry_policy(&self, polling: bool) -> RetryPolicy {
+        let mut policy = self.retry_policy(RetryStage::Order);
+        if polling && self.retry_policies.order.max_attempts.is_none() {
+            policy.max_attempts = Some(self.max_order_retry);
+        }
+        policy
+    }
+}
+
+// 各类任务同时运行的数量上限，0 表示不限；超出上限的任务排队，
+// 抢票任务优先启动，抢票期间后台查询任务再按 background_while_grabbing 合计限流
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TaskLimits {
+    pub grab_ticket: usize,
+    pub get_all_order: usize,
+    pub get_ticket_info: usize,
+    pub get_buyer_info: usize,
+    pub background_while_grabbing: usize,
+}
+
+impl Default for TaskLimits {
+    fn default() -> Self {
+        Self {
+            grab_ticket: 0,
+            get_all_order: 2,
+            get_ticket_info: 4,
+            get_buyer_info: 2,
+            background_while_grabbing: 1,
+        }
+    }
+}
+
+impl TaskLimits {
+    // 按任务类型取上限，登录、推送等交互任务不限
+    pub fn limit(&self, kind: TaskKind) -> usize {
+        match kind {
+            TaskKind::GrabTicket => self.grab_ticket,
+            TaskKind::GetAllOrder => self.get_all_order,
+            TaskKind::GetTicketInfo => self.get_ticket_info,
+            TaskKind::GetBuyerInfo => self.get_buyer_info,
+            TaskKind::QrCodeLogin
+            | TaskKind::LoginSms
+            | TaskKind::Push
+            | TaskKind::SubmitLoginSms => 0,
         }
     }
 }
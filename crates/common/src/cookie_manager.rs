@@ -1,3 +1,4 @@
+use crate::endpoints::ApiEndpoints;
 use crate::web_ck_obfuscated::*;
 use cookie::Cookie;
 use rand::seq::SliceRandom;
@@ -36,14 +37,30 @@ pub struct CookieManager {
     app_data: Option<AppData>,
     pub web_data: Option<WebData>,
     pub cookies: CookiesData,
+    pub endpoints: ApiEndpoints, //接口地址，默认取进程级配置
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct CookiesData {
     pub cookies_map: Arc<Mutex<HashMap<String, String>>>,
     pub cookie_jar: Arc<Mutex<Jar>>,
 }
 
+// 只输出 cookie 名称，不输出值
+impl std::fmt::Debug for CookiesData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut names: Vec<String> = self
+            .cookies_map
+            .lock()
+            .map(|map| map.keys().cloned().collect())
+            .unwrap_or_default();
+        names.sort();
+        f.debug_struct("CookiesData")
+            .field("cookies", &names)
+            .finish_non_exhaustive()
+    }
+}
+
 impl CookiesData {
     pub fn insert(&self, key: String, value: String) {
         self.cookies_map
@@ -249,6 +266,7 @@ impl CookieManager {
                     app_data: None,
                     web_data: Some(web_data),
                     cookies: cookies,
+                    endpoints: ApiEndpoints::current(),
                 }
             }
 
@@ -266,6 +284,7 @@ impl CookieManager {
                     app_data: None,
                     web_data: None,
                     cookies: cookies,
+                    endpoints: ApiEndpoints::current(),
                 }
             }
         }
@@ -317,9 +336,16 @@ impl CookieManager {
             app_data: None,
             web_data: None,
             cookies: cookies,
+            endpoints: ApiEndpoints::current(),
         }
     }
 
+    //替换接口地址（如指向本地 mock 服务）
+    pub fn with_endpoints(mut self, endpoints: ApiEndpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
     //更新单个字段
     pub fn update_cookie(&self, key: &str, value: &str) {
         self.cookies.insert(key.to_string(), value.to_string());
@@ -0,0 +1,89 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+
+// B站各接口域名，可整体指向本地 mock 服务（如 http://127.0.0.1:8080）
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ApiEndpoints {
+    #[serde(default = "default_show")]
+    pub show: String, //会员购 show.bilibili.com
+    #[serde(default = "default_api")]
+    pub api: String, //主站接口 api.bilibili.com
+    #[serde(default = "default_passport")]
+    pub passport: String, //登录 passport.bilibili.com
+    #[serde(default = "default_www")]
+    pub www: String, //主站 www.bilibili.com
+}
+
+fn default_show() -> String {
+    "https://show.bilibili.com".to_string()
+}
+fn default_api() -> String {
+    "https://api.bilibili.com".to_string()
+}
+fn default_passport() -> String {
+    "https://passport.bilibili.com".to_string()
+}
+fn default_www() -> String {
+    "https://www.bilibili.com".to_string()
+}
+
+impl Default for ApiEndpoints {
+    fn default() -> Self {
+        Self {
+            show: default_show(),
+            api: default_api(),
+            passport: default_passport(),
+            www: default_www(),
+        }
+    }
+}
+
+// 进程级默认值，由前端在加载配置后设置；CookieManager 创建时会复制一份
+static CURRENT: Lazy<RwLock<ApiEndpoints>> = Lazy::new(|| RwLock::new(ApiEndpoints::default()));
+
+impl ApiEndpoints {
+    // 所有域名指向同一个地址，用于本地 mock 服务
+    pub fn with_base(base: &str) -> Self {
+        let base = base.trim_end_matches('/').to_string();
+        Self {
+            show: base.clone(),
+            api: base.clone(),
+            passport: base.clone(),
+            www: base,
+        }
+    }
+
+    pub fn current() -> Self {
+        CURRENT.read().map(|e| e.clone()).unwrap_or_default()
+    }
+
+    pub fn set_current(endpoints: ApiEndpoints) {
+        if endpoints != ApiEndpoints::default() {
+            log::warn!("已覆盖B站接口地址: {:?}", endpoints);
+        }
+        if let Ok(mut current) = CURRENT.write() {
+            *current = endpoints;
+        }
+    }
+
+    pub fn show_url(&self, path: &str) -> String {
+        join(&self.show, path)
+    }
+
+    pub fn api_url(&self, path: &str) -> String {
+        join(&self.api, path)
+    }
+
+    pub fn passport_url(&self, path: &str) -> String {
+        join(&self.passport, path)
+    }
+
+    pub fn www_url(&self, path: &str) -> String {
+        join(&self.www, path)
+    }
+}
+
+fn join(base: &str, path: &str) -> String {
+    format!("{}{}", base.trim_end_matches('/'), path)
+}
@@ -0,0 +1,309 @@
+use crate::paths;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::{Mutex, mpsc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// 抢票历史记录，按天写入 history/YYYY-MM-DD.jsonl，每行一条
+static HISTORY_DIR: Lazy<Mutex<PathBuf>> = Lazy::new(|| Mutex::new(paths::data_path("history")));
+
+// 修改历史记录目录（测试或自定义数据目录时使用）
+pub fn set_history_dir(dir: impl Into<PathBuf>) {
+    if let Ok(mut current) = HISTORY_DIR.lock() {
+        *current = dir.into();
+    }
+}
+
+pub fn history_dir() -> PathBuf {
+    HISTORY_DIR
+        .lock()
+        .map(|d| d.clone())
+        .unwrap_or_else(|_| paths::data_path("history"))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryEvent {
+    TaskStart,    //任务开始
+    TaskEnd,      //任务结束
+    Token,        //order/prepare
+    Confirm,      //order/confirmInfo
+    Create,       //order/createV2
+    CreateStatus, //order/createstatus
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HistoryRecord {
+    pub time: i64, //毫秒时间戳
+    pub task_id: String,
+    pub uid: i64,
+    pub project_id: String,
+    pub event: HistoryEvent,
+    pub success: bool,
+    #[serde(default)]
+    pub code: Option<i64>, //接口错误码
+    #[serde(default)]
+    pub latency_ms: Option<u64>, //接口耗时
+    #[serde(default)]
+    pub message: String,
+    #[serde(default)]
+    pub order_id: Option<String>,
+}
+
+impl HistoryRecord {
+    pub fn new(task_id: &str, uid: i64, project_id: &str, event: HistoryEvent) -> Self {
+        Self {
+            time: chrono::Local::now().timestamp_millis(),
+            task_id: task_id.to_string(),
+            uid,
+            project_id: project_id.to_string(),
+            event,
+            success: true,
+            code: None,
+            latency_ms: None,
+            message: String::new(),
+            order_id: None,
+        }
+    }
+
+    // 一次接口调用，code 为 0 视为成功
+    pub fn attempt(
+        task_id: &str,
+        uid: i64,
+        project_id: &str,
+        event: HistoryEvent,
+        code: Option<i64>,
+        started: Instant,
+        message: &str,
+    ) -> Self {
+        let mut record = Self::new(task_id, uid, project_id, event);
+        record.success = code == Some(0);
+        record.code = code;
+        record.latency_ms = Some(started.elapsed().as_millis() as u64);
+        record.message = message.to_string();
+        record
+    }
+}
+
+static WRITE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+enum WriterMessage {
+    Record(HistoryRecord),
+    Flush(mpsc::Sender<()>),
+}
+
+// 由单独的线程按提交顺序写文件，抢票任务中调用 append 不会阻塞异步运行时
+static WRITER: Lazy<Option<mpsc::Sender<WriterMessage>>> = Lazy::new(|| {
+    let (tx, rx) = mpsc::channel::<WriterMessage>();
+    let spawned = thread::Builder::new()
+        .name("history-writer".to_string())
+        .spawn(move || {
+            for message in rx {
+                match message {
+                    WriterMessage::Record(record) => write_record(&record),
+                    WriterMessage::Flush(done) => {
+                        let _ = done.send(());
+                    }
+                }
+            }
+        });
+    match spawned {
+        Ok(_) => Some(tx),
+        Err(e) => {
+            log::warn!("启动抢票历史写入线程失败，改为直接写入: {}", e);
+            None
+        }
+    }
+});
+
+// 追加一条记录，失败只记日志不影响抢票
+pub fn append(record: &HistoryRecord) {
+    let queued = WRITER
+        .as_ref()
+        .is_some_and(|tx| tx.send(WriterMessage::Record(record.clone())).is_ok());
+    if !queued {
+        write_record(record);
+    }
+}
+
+// 等待已提交的记录写入文件
+pub fn flush() {
+    if let Some(tx) = WRITER.as_ref() {
+        let (done_tx, done_rx) = mpsc::channel();
+        if tx.send(WriterMessage::Flush(done_tx)).is_ok() {
+            let _ = done_rx.recv_timeout(Duration::from_secs(5));
+        }
+    }
+}
+
+fn write_record(record: &HistoryRecord) {
+    if let Err(e) = try_append(record) {
+        log::warn!("写入抢票历史失败: {}", e);
+    }
+}
+
+fn try_append(record: &HistoryRecord) -> Result<(), String> {
+    let line = serde_json::to_string(record).map_err(|e| e.to_string())?;
+    let dir = history_dir();
+    let _guard = WRITE_LOCK.lock().map_err(|e| e.to_string())?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let date = chrono::DateTime::from_timestamp_millis(record.time)
+        .map(|t| t.with_timezone(&chrono::Local))
+        .unwrap_or_else(chrono::Local::now)
+        .format("%Y-%m-%d");
+    let path = dir.join(format!("{}.jsonl", date));
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    writeln!(file, "{}", line).map_err(|e| e.to_string())
+}
+
+// 查询条件，字段均为可选
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct HistoryQuery {
+    #[serde(default)]
+    pub task_id: Option<String>,
+    #[serde(default)]
+    pub uid: Option<i64>,
+    #[serde(default)]
+    pub project_id: Option<String>,
+    #[serde(default)]
+    pub since: Option<i64>, //毫秒时间戳（含）
+    #[serde(default)]
+    pub until: Option<i64>, //毫秒时间戳（不含）
+    #[serde(default)]
+    pub limit: Option<usize>, //只返回最近的 N 条
+}
+
+impl HistoryQuery {
+    fn matches(&self, record: &HistoryRecord) -> bool {
+        self.task_id.as_ref().is_none_or(|id| &record.task_id == id)
+            && self.uid.is_none_or(|uid| record.uid == uid)
+            && self
+                .project_id
+                .as_ref()
+                .is_none_or(|id| &record.project_id == id)
+            && self.since.is_none_or(|t| record.time >= t)
+            && self.until.is_none_or(|t| record.time < t)
+    }
+
+    // 按文件名日期跳过不在范围内的文件（前后各放宽一天，避免时区误差）
+    fn file_in_range(&self, date: &str) -> bool {
+        let Ok(date) = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d") else {
+            return false;
+        };
+        let day_ms = 86_400_000;
+        let start = date
+            .and_hms_opt(0, 0, 0)
+            .map(|t| t.and_utc().timestamp_millis())
+            .unwrap_or(0);
+        self.since.is_none_or(|t| start + 2 * day_ms > t)
+            && self.until.is_none_or(|t| start - day_ms < t)
+    }
+}
+
+// 按时间顺序返回匹配的记录
+pub fn query(query: &HistoryQuery) -> Result<Vec<HistoryRecord>, String> {
+    flush();
+    let dir = history_dir();
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut files: Vec<(String, PathBuf)> = fs::read_dir(&dir)
+        .map_err(|e| format!("读取历史目录失败: {}", e))?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            let stem = path.file_stem()?.to_str()?.to_string();
+            (path.extension()? == "jsonl").then_some((stem, path))
+        })
+        .filter(|(date, _)| query.file_in_range(date))
+        .collect();
+    files.sort();
+
+    let mut records = Vec::new();
+    for (_, path) in files {
+        let file = fs::File::open(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        for line in BufReader::new(file).lines().map_while(Result::ok) {
+            match serde_json::from_str::<HistoryRecord>(&line) {
+                Ok(record) if query.matches(&record) => records.push(record),
+                Ok(_) => {}
+                Err(e) => log::debug!("跳过无法解析的历史记录: {}", e),
+            }
+        }
+    }
+    records.sort_by_key(|r| r.time);
+
+    if let Some(limit) = query.limit {
+        let skip = records.len().saturating_sub(limit);
+        records.drain(..skip);
+    }
+    Ok(records)
+}
+
+// 单个抢票任务的汇总
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TaskSummary {
+    pub task_id: String,
+    pub uid: i64,
+    pub project_id: String,
+    pub started_at: Option<i64>,
+    pub finished_at: Option<i64>,
+    pub success: Option<bool>, //None 表示没有结束记录（仍在运行或被中断）
+    pub message: String,
+    pub order_id: Option<String>,
+    pub attempts: u32,
+    pub error_codes: BTreeMap<i64, u32>, //错误码 -> 次数
+}
+
+// 把记录按 task_id 汇总，按开始时间排序
+pub fn summarize(records: &[HistoryRecord]) -> Vec<TaskSummary> {
+    let mut summaries: Vec<TaskSummary> = Vec::new();
+    let mut indexes: HashMap<&str, usize> = HashMap::new();
+    for record in records {
+        let index = match indexes.get(record.task_id.as_str()) {
+            Some(index) => *index,
+            None => {
+                indexes.insert(&record.task_id, summaries.len());
+                summaries.push(TaskSummary {
+                    task_id: record.task_id.clone(),
+                    uid: record.uid,
+                    project_id: record.project_id.clone(),
+                    started_at: None,
+                    finished_at: None,
+                    success: None,
+                    message: String::new(),
+                    order_id: None,
+                    attempts: 0,
+                    error_codes: BTreeMap::new(),
+                });
+                summaries.len() - 1
+            }
+        };
+        let summary = &mut summaries[index];
+        match record.event {
+            HistoryEvent::TaskStart => summary.started_at = Some(record.time),
+            HistoryEvent::TaskEnd => {
+                summary.finished_at = Some(record.time);
+                summary.success = Some(record.success);
+                summary.message = record.message.clone();
+                summary.order_id = record.order_id.clone();
+            }
+            _ => {
+                summary.attempts += 1;
+                if let Some(code) = record.code.filter(|c| *c != 0) {
+                    *summary.error_codes.entry(code).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+    summaries.sort_by_key(|s| s.started_at.unwrap_or(i64::MAX));
+    summaries
+}
@@ -1,14 +1,29 @@
 pub mod account;
 pub mod captcha;
 pub mod config;
+pub mod endpoints;
+pub mod history;
 pub mod http_utils;
+mod local_http;
+pub mod log_record;
 pub mod login;
+pub mod metrics;
+pub mod metrics_exporter;
+pub mod paths;
 pub mod push;
 pub mod record_log;
+pub mod redact;
+pub mod retry;
+pub mod schedule;
 pub mod show_orderlist;
+pub mod task_api;
+pub mod task_journal;
+pub mod task_log;
 pub mod taskmanager;
 pub mod ticket;
+pub mod ticket_error;
 pub mod utils;
+pub mod vault;
 
 pub mod cookie_manager;
 pub mod gen_cp;
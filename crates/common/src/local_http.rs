@@ -0,0 +1,134 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+// 指标接口和本地任务接口共用的极简 HTTP 服务：每个连接一个线程，读写都有超时
+
+// 单个连接的读写超时
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+// 请求头行数上限
+const MAX_HEADERS: usize = 64;
+
+pub(crate) struct Request {
+    pub method: String,
+    pub path: String,
+    headers: Vec<(String, String)>,
+}
+
+impl Request {
+    // 按名称取请求头（不区分大小写）
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+pub(crate) struct Response {
+    pub status: &'static str,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    pub fn json(status: &'static str, body: String) -> Self {
+        Self {
+            status,
+            content_type: "application/json; charset=utf-8",
+            body,
+        }
+    }
+
+    pub fn not_found() -> Self {
+        Self {
+            status: "404 Not Found",
+            content_type: "text/plain; charset=utf-8",
+            body: "not found\n".to_string(),
+        }
+    }
+}
+
+// 在后台线程中接受连接，每个连接交给单独的线程处理
+pub(crate) fn serve<F>(listener: TcpListener, name: &str, handler: F) -> Result<(), String>
+where
+    F: Fn(&Request) -> Response + Send + Sync + 'static,
+{
+    let handler = Arc::new(handler);
+    let conn_name = format!("{}-conn", name);
+    thread::Builder::new()
+        .name(name.to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let handler = handler.clone();
+                        let spawned =
+                            thread::Builder::new()
+                                .name(conn_name.clone())
+                                .spawn(move || {
+                                    if let Err(e) = handle_connection(stream, handler.as_ref()) {
+                                        log::debug!("本地接口请求处理失败: {}", e);
+                                    }
+                                });
+                        if let Err(e) = spawned {
+                            log::debug!("本地接口连接线程启动失败: {}", e);
+                        }
+                    }
+                    Err(e) => log::debug!("本地接口连接失败: {}", e),
+                }
+            }
+        })
+        .map(|_| ())
+        .map_err(|e| format!("启动 {} 线程失败: {}", name, e))
+}
+
+fn handle_connection<F>(mut stream: TcpStream, handler: &F) -> std::io::Result<()>
+where
+    F: Fn(&Request) -> Response,
+{
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let line = line.trim_end();
+        if line.is_empty() || headers.len() >= MAX_HEADERS {
+            break;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            headers.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let request = Request {
+        method: parts.next().unwrap_or("").to_string(),
+        path: parts
+            .next()
+            .unwrap_or("")
+            .split('?')
+            .next()
+            .unwrap_or("")
+            .to_string(),
+        headers,
+    };
+    let response = handler(&request);
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.content_type,
+        response.body.len(),
+        response.body
+    )?;
+    stream.flush()
+}
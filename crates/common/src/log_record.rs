@@ -0,0 +1,277 @@
+use log::kv::{self, Key, Value, VisitSource};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+// 结构化日志：每条日志携带任务 ID、账号、抢票阶段、错误码与分类，
+// 抢票日志和界面按字段过滤，不再依赖关键字匹配
+
+// 抢票阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogPhase {
+    Countdown,
+    Token,
+    Confirm,
+    Create,
+    PayCheck,
+}
+
+impl LogPhase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogPhase::Countdown => "countdown",
+            LogPhase::Token => "token",
+            LogPhase::Confirm => "confirm",
+            LogPhase::Create => "create",
+            LogPhase::PayCheck => "pay-check",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "countdown" => Some(LogPhase::Countdown),
+            "token" => Some(LogPhase::Token),
+            "confirm" => Some(LogPhase::Confirm),
+            "create" => Some(LogPhase::Create),
+            "pay-check" | "pay_check" => Some(LogPhase::PayCheck),
+            _ => None,
+        }
+    }
+}
+
+// 日志分类
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogCategory {
+    Grab,
+    Login,
+    Push,
+    #[default]
+    System,
+}
+
+impl LogCategory {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "grab" => Some(LogCategory::Grab),
+            "login" => Some(LogCategory::Login),
+            "push" => Some(LogCategory::Push),
+            "system" => Some(LogCategory::System),
+            _ => None,
+        }
+    }
+
+    // 未显式标注时按模块路径归类
+    fn from_target(target: &str) -> Self {
+        if target.contains("grab_ticket") {
+            LogCategory::Grab
+        } else if target.contains("login") {
+            LogCategory::Login
+        } else if target.contains("push") || target.contains("dungeon") {
+            LogCategory::Push
+        } else {
+            LogCategory::System
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogRecord {
+    pub time: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phase: Option<LogPhase>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<i64>,
+    #[serde(default)]
+    pub category: LogCategory,
+}
+
+impl LogRecord {
+    // 由 log 记录生成：显式的键值优先，其次是当前任务上下文
+    pub fn from_log(record: &log::Record, time: String, message: String) -> Self {
+        let mut fields = KvFields::default();
+        let _ = record.key_values().visit(&mut fields);
+        let context = current_context();
+
+        let category = fields.category.unwrap_or_else(|| {
+            if context.is_some() {
+                LogCategory::Grab
+            } else {
+                LogCategory::from_target(record.target())
+            }
+        });
+        let (task_id, uid, phase, code) = match context {
+            Some(ctx) => (
+                fields.task_id.or(Some(ctx.task_id)),
+                fields.uid.or(Some(ctx.uid)),
+                fields.phase.or(ctx.phase),
+                fields.code.or(ctx.code),
+            ),
+            None => (fields.task_id, fields.uid, fields.phase, fields.code),
+        };
+
+        Self {
+            time,
+            level: record.level().to_string(),
+            target: record.target().to_string(),
+            message,
+            task_id,
+            uid,
+            phase,
+            code,
+            category,
+        }
+    }
+
+    // 与原有文本日志格式一致
+    pub fn to_line(&self) -> String {
+        format!("[{}] {}: {}", self.time, self.level, self.message)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|_| self.to_line())
+    }
+
+    // 抢票日志窗口展示的分类
+    pub fn is_grab_related(&self) -> bool {
+        matches!(self.category, LogCategory::Grab | LogCategory::Login)
+    }
+}
+
+#[derive(Default)]
+struct KvFields {
+    task_id: Option<String>,
+    uid: Option<i64>,
+    phase: Option<LogPhase>,
+    code: Option<i64>,
+    category: Option<LogCategory>,
+}
+
+impl<'kvs> VisitSource<'kvs> for KvFields {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        match key.as_str() {
+            "task_id" => self.task_id = Some(value.to_string()),
+            "uid" => self.uid = value.to_i64(),
+            "phase" => self.phase = LogPhase::parse(&value.to_string()),
+            "code" => self.code = value.to_i64(),
+            "category" => self.category = LogCategory::parse(&value.to_string()),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+// 日志过滤条件，字段均为可选
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LogFilter {
+    #[serde(default)]
+    pub task_id: Option<String>,
+    #[serde(default)]
+    pub uid: Option<i64>,
+    #[serde(default)]
+    pub phase: Option<LogPhase>,
+    #[serde(default)]
+    pub category: Option<LogCategory>,
+    #[serde(default)]
+    pub code: Option<i64>,
+    // 最低级别，如 WARN 表示只看 WARN 与 ERROR
+    #[serde(default)]
+    pub level: Option<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+impl LogFilter {
+    pub fn matches(&self, record: &LogRecord) -> bool {
+        if let Some(task_id) = &self.task_id
+            && record.task_id.as_ref() != Some(task_id)
+        {
+            return false;
+        }
+        if self.uid.is_some() && record.uid != self.uid {
+            return false;
+        }
+        if self.phase.is_some() && record.phase != self.phase {
+            return false;
+        }
+        if let Some(category) = self.category
+            && record.category != category
+        {
+            return false;
+        }
+        if self.code.is_some() && record.code != self.code {
+            return false;
+        }
+        if let Some(level) = self
+            .level
+            .as_deref()
+            .and_then(|l| log::Level::from_str(l).ok())
+        {
+            match log::Level::from_str(&record.level) {
+                Ok(record_level) if record_level <= level => {}
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+// 抢票任务的日志上下文，任务内所有日志自动带上这些字段
+#[derive(Debug, Clone)]
+pub struct LogContext {
+    pub task_id: String,
+    pub uid: i64,
+    pub phase: Option<LogPhase>,
+    pub code: Option<i64>,
+}
+
+tokio::task_local! {
+    static GRAB_CONTEXT: Arc<Mutex<LogContext>>;
+}
+
+pub async fn with_grab_context<F: Future>(task_id: String, uid: i64, task: F) -> F::Output {
+    let context = LogContext {
+        task_id,
+        uid,
+        phase: None,
+        code: None,
+    };
+    GRAB_CONTEXT
+        .scope(Arc::new(Mutex::new(context)), task)
+        .await
+}
+
+// 进入新阶段时清空上一阶段的错误码
+pub fn set_phase(phase: LogPhase) {
+    let _ = GRAB_CONTEXT.try_with(|ctx| {
+        if let Ok(mut ctx) = ctx.lock() {
+            ctx.phase = Some(phase);
+            ctx.code = None;
+        }
+    });
+}
+
+// 记录最近一次接口返回的错误码，之后同阶段的日志都会带上
+pub fn set_code(code: Option<i64>) {
+    let _ = GRAB_CONTEXT.try_with(|ctx| {
+        if let Ok(mut ctx) = ctx.lock() {
+            ctx.code = code.filter(|c| *c != 0);
+        }
+    });
+}
+
+pub fn current_context() -> Option<LogContext> {
+    GRAB_CONTEXT
+        .try_with(|ctx| ctx.lock().ok().map(|ctx| ctx.clone()))
+        .ok()
+        .flatten()
+}
@@ -3,6 +3,7 @@ use crate::account::add_account;
 use crate::captcha::LocalCaptcha;
 use crate::captcha::captcha;
 use crate::config::CustomConfig;
+use crate::endpoints::ApiEndpoints;
 use crate::http_utils::{request_get, request_post};
 use reqwest::Client;
 use serde_json::json;
@@ -43,13 +44,10 @@ pub fn qrcode_login(client: &Client) -> Result<String, String> {
     // 创建一个临时的运行时来执行异步代码
     let rt = tokio::runtime::Runtime::new().unwrap();
     rt.block_on(async {
-        let response = request_get(
-            client,
-            "https://passport.bilibili.com/x/passport-login/web/qrcode/generate",
-            None,
-        )
-        .await
-        .map_err(|e| e.to_string())?;
+        let url = ApiEndpoints::current().passport_url("/x/passport-login/web/qrcode/generate");
+        let response = request_get(client, &url, None)
+            .await
+            .map_err(|e| e.to_string())?;
 
         let json = response
             .json::<serde_json::Value>()
@@ -71,13 +69,10 @@ pub struct Country {
 }
 
 pub async fn get_country_list(client: &Client) -> Result<Vec<Country>, String> {
-    let response = request_get(
-        client,
-        "https://passport.bilibili.com/web/generic/country/list",
-        None,
-    )
-    .await
-    .map_err(|e| e.to_string())?;
+    let url = ApiEndpoints::current().passport_url("/web/generic/country/list");
+    let response = request_get(client, &url, None)
+        .await
+        .map_err(|e| e.to_string())?;
 
     let json = response
         .json::<serde_json::Value>()
@@ -118,7 +113,8 @@ pub async fn send_loginsms(
     custom_config: CustomConfig,
     local_captcha: LocalCaptcha,
 ) -> Result<String, String> {
-    let response = request_get(client, "https://www.bilibili.com/", None)
+    let endpoints = ApiEndpoints::current();
+    let response = request_get(client, &endpoints.www_url("/"), None)
         .await
         .map_err(|e| e.to_string())?;
 
@@ -127,7 +123,7 @@ pub async fn send_loginsms(
     // 发送请求
     let response = request_get(
         client,
-        "https://passport.bilibili.com/x/passport-login/captcha",
+        &endpoints.passport_url("/x/passport-login/captcha"),
         None,
     )
     .await
@@ -169,7 +165,7 @@ pub async fn send_loginsms(
             log::debug!("验证码数据: {:?}", json_data);
             let send_sms = request_post(
                 client,
-                "https://passport.bilibili.com/x/passport-login/web/sms/send",
+                &endpoints.passport_url("/x/passport-login/web/sms/send"),
                 None,
                 Some(&json_data),
             )
@@ -218,7 +214,7 @@ pub async fn sms_login(
     log::debug!("短信登录数据: {:?}", data);
     let login_response = request_post(
         client,
-        "https://passport.bilibili.com/x/passport-login/web/login/sms",
+        &ApiEndpoints::current().passport_url("/x/passport-login/web/login/sms"),
         None,
         Some(&data),
     )
@@ -0,0 +1,348 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+// 进程内的运行指标，抢票处理和任务管理器负责更新，前端按需读取快照
+static METRICS: Lazy<Mutex<Registry>> = Lazy::new(|| Mutex::new(Registry::default()));
+
+// 延迟直方图分桶上限（毫秒），超出最后一档的计入 +Inf
+pub const LATENCY_BUCKETS_MS: [u64; 10] = [25, 50, 100, 200, 300, 500, 1000, 2000, 5000, 10000];
+
+#[derive(Default)]
+struct Registry {
+    attempts: BTreeMap<(String, Option<i64>), u64>, //(接口, 错误码) -> 次数，None 表示请求本身失败
+    latencies: BTreeMap<String, Histogram>,
+    tasks: BTreeMap<String, TaskCounts>,
+    push: BTreeMap<(String, bool), u64>, //(推送渠道, 是否成功) -> 次数
+    grab_success: u64,
+    grab_failure: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Histogram {
+    pub buckets: Vec<u64>, //与 LATENCY_BUCKETS_MS 对应的非累计计数，最后一个为 +Inf
+    pub count: u64,
+    pub sum_ms: u64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            buckets: vec![0; LATENCY_BUCKETS_MS.len() + 1],
+            count: 0,
+            sum_ms: 0,
+        }
+    }
+}
+
+impl Histogram {
+    fn observe(&mut self, ms: u64) {
+        let index = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|le| ms <= *le)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.buckets[index] += 1;
+        self.count += 1;
+        self.sum_ms += ms;
+    }
+
+    pub fn mean_ms(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.sum_ms as f64 / self.count as f64
+        }
+    }
+
+    // 估算分位数，返回所在分桶的上限；落在 +Inf 时返回 None
+    pub fn quantile_ms(&self, q: f64) -> Option<u64> {
+        if self.count == 0 {
+            return Some(0);
+        }
+        let target = (self.count as f64 * q).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (index, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= target {
+                return LATENCY_BUCKETS_MS.get(index).copied();
+            }
+        }
+        None
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct TaskCounts {
+    pub active: u64,
+    pub completed: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AttemptCount {
+    pub endpoint: String,
+    pub code: Option<i64>,
+    pub count: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PushCount {
+    pub channel: String,
+    pub success: bool,
+    pub count: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EndpointLatency {
+    pub endpoint: String,
+    pub histogram: Histogram,
+    pub mean_ms: f64,
+    pub p50_ms: Option<u64>,
+    pub p95_ms: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MetricsSnapshot {
+    pub attempts: u64,                   //接口请求总数
+    pub success: u64,                    //返回码为 0 的请求
+    pub failures: u64,                   //其余请求
+    pub error_codes: BTreeMap<i64, u64>, //非 0 错误码 -> 次数（不分接口）
+    pub attempts_by_code: Vec<AttemptCount>,
+    pub latencies: Vec<EndpointLatency>,
+    pub grab_success: u64, //下单成功的抢票任务
+    pub grab_failure: u64, //未抢到就结束的抢票任务
+    pub active_tasks: u64,
+    pub completed_tasks: u64,
+    pub tasks: BTreeMap<String, TaskCounts>, //任务类型 -> 计数
+    pub push: Vec<PushCount>,
+}
+
+fn with_registry(f: impl FnOnce(&mut Registry)) {
+    match METRICS.lock() {
+        Ok(mut registry) => f(&mut registry),
+        Err(e) => log::warn!("更新运行指标失败: {}", e),
+    }
+}
+
+// 记录一次接口请求的返回码和耗时
+pub fn observe_request(endpoint: &str, code: Option<i64>, latency: Duration) {
+    with_registry(|r| {
+        *r.attempts.entry((endpoint.to_string(), code)).or_insert(0) += 1;
+        r.latencies
+            .entry(endpoint.to_string())
+            .or_default()
+            .observe(latency.as_millis() as u64);
+    });
+}
+
+// 记录一个抢票任务的最终结果
+pub fn record_grab_result(success: bool) {
+    with_registry(|r| {
+        if success {
+            r.grab_success += 1;
+        } else {
+            r.grab_failure += 1;
+        }
+    });
+}
+
+// 记录一次推送渠道的发送结果
+pub fn record_push(channel: &str, success: bool) {
+    with_registry(|r| *r.push.entry((channel.to_string(), success)).or_insert(0) += 1);
+}
+
+// 任务开始时调用，返回的守卫被释放（正常结束或被取消）时计为完成
+pub fn track_task(kind: &str) -> TaskGuard {
+    with_registry(|r| r.tasks.entry(kind.to_string()).or_default().active += 1);
+    TaskGuard {
+        kind: kind.to_string(),
+    }
+}
+
+pub struct TaskGuard {
+    kind: String,
+}
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        with_registry(|r| {
+            let counts = r.tasks.entry(self.kind.clone()).or_default();
+            counts.active = counts.active.saturating_sub(1);
+            counts.completed += 1;
+        });
+    }
+}
+
+pub fn snapshot() -> MetricsSnapshot {
+    let Ok(r) = METRICS.lock() else {
+        return MetricsSnapshot::default();
+    };
+    let mut snapshot = MetricsSnapshot {
+        grab_success: r.grab_success,
+        grab_failure: r.grab_failure,
+        tasks: r.tasks.clone(),
+        ..Default::default()
+    };
+    for ((endpoint, code), count) in &r.attempts {
+        snapshot.attempts += count;
+        match code {
+            Some(0) => snapshot.success += count,
+            Some(code) => {
+                snapshot.failures += count;
+                *snapshot.error_codes.entry(*code).or_insert(0) += count;
+            }
+            None => snapshot.failures += count,
+        }
+        snapshot.attempts_by_code.push(AttemptCount {
+            endpoint: endpoint.clone(),
+            code: *code,
+            count: *count,
+        });
+    }
+    for (endpoint, histogram) in &r.latencies {
+        snapshot.latencies.push(EndpointLatency {
+            endpoint: endpoint.clone(),
+            histogram: histogram.clone(),
+            mean_ms: histogram.mean_ms(),
+            p50_ms: histogram.quantile_ms(0.5),
+            p95_ms: histogram.quantile_ms(0.95),
+        });
+    }
+    for counts in r.tasks.values() {
+        snapshot.active_tasks += counts.active;
+        snapshot.completed_tasks += counts.completed;
+    }
+    for ((channel, success), count) in &r.push {
+        snapshot.push.push(PushCount {
+            channel: channel.clone(),
+            success: *success,
+            count: *count,
+        });
+    }
+    snapshot
+}
+
+// 按 Prometheus 文本格式输出当前指标
+pub fn render_prometheus() -> String {
+    let s = snapshot();
+    let mut out = String::new();
+
+    metric_header(
+        &mut out,
+        "btr_tasks_active",
+        "gauge",
+        "Tasks currently running.",
+    );
+    for (kind, counts) in &s.tasks {
+        out.push_str(&format!(
+            "btr_tasks_active{{kind=\"{}\"}} {}\n",
+            escape(kind),
+            counts.active
+        ));
+    }
+    metric_header(
+        &mut out,
+        "btr_tasks_completed_total",
+        "counter",
+        "Tasks finished or cancelled.",
+    );
+    for (kind, counts) in &s.tasks {
+        out.push_str(&format!(
+            "btr_tasks_completed_total{{kind=\"{}\"}} {}\n",
+            escape(kind),
+            counts.completed
+        ));
+    }
+
+    metric_header(
+        &mut out,
+        "btr_grab_results_total",
+        "counter",
+        "Grab tasks by final result.",
+    );
+    out.push_str(&format!(
+        "btr_grab_results_total{{result=\"success\"}} {}\n",
+        s.grab_success
+    ));
+    out.push_str(&format!(
+        "btr_grab_results_total{{result=\"failure\"}} {}\n",
+        s.grab_failure
+    ));
+
+    metric_header(
+        &mut out,
+        "btr_api_attempts_total",
+        "counter",
+        "Ticket API calls by endpoint and error code.",
+    );
+    for attempt in &s.attempts_by_code {
+        let code = attempt.code.map_or("none".to_string(), |c| c.to_string());
+        out.push_str(&format!(
+            "btr_api_attempts_total{{endpoint=\"{}\",code=\"{}\"}} {}\n",
+            escape(&attempt.endpoint),
+            code,
+            attempt.count
+        ));
+    }
+
+    metric_header(
+        &mut out,
+        "btr_push_total",
+        "counter",
+        "Push notifications by channel and result.",
+    );
+    for push in &s.push {
+        out.push_str(&format!(
+            "btr_push_total{{channel=\"{}\",result=\"{}\"}} {}\n",
+            escape(&push.channel),
+            if push.success { "success" } else { "failure" },
+            push.count
+        ));
+    }
+
+    metric_header(
+        &mut out,
+        "btr_api_latency_seconds",
+        "histogram",
+        "Ticket API latency.",
+    );
+    for latency in &s.latencies {
+        let endpoint = escape(&latency.endpoint);
+        let mut cumulative = 0;
+        for (index, count) in latency.histogram.buckets.iter().enumerate() {
+            cumulative += count;
+            let le = LATENCY_BUCKETS_MS
+                .get(index)
+                .map_or("+Inf".to_string(), |ms| (*ms as f64 / 1000.0).to_string());
+            out.push_str(&format!(
+                "btr_api_latency_seconds_bucket{{endpoint=\"{}\",le=\"{}\"}} {}\n",
+                endpoint, le, cumulative
+            ));
+        }
+        out.push_str(&format!(
+            "btr_api_latency_seconds_sum{{endpoint=\"{}\"}} {}\n",
+            endpoint,
+            latency.histogram.sum_ms as f64 / 1000.0
+        ));
+        out.push_str(&format!(
+            "btr_api_latency_seconds_count{{endpoint=\"{}\"}} {}\n",
+            endpoint, latency.histogram.count
+        ));
+    }
+    out
+}
+
+fn metric_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    out.push_str(&format!(
+        "# HELP {} {}\n# TYPE {} {}\n",
+        name, help, name, kind
+    ));
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
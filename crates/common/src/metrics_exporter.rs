@@ -0,0 +1,29 @@
+use crate::config::MetricsExporterConfig;
+use crate::local_http::{self, Request, Response};
+use crate::metrics;
+use std::net::{SocketAddr, TcpListener};
+
+// 启动 /metrics 接口（配置未开启时不做任何事），返回实际监听地址。
+// 该接口只读，任务的查询和取消走 task_api
+pub fn start(config: &MetricsExporterConfig) -> Result<Option<SocketAddr>, String> {
+    if !config.enabled {
+        return Ok(None);
+    }
+    let listener = TcpListener::bind(&config.listen)
+        .map_err(|e| format!("指标接口监听 {} 失败: {}", config.listen, e))?;
+    let addr = listener.local_addr().map_err(|e| e.to_string())?;
+    local_http::serve(listener, "metrics-exporter", handle_request)?;
+    log::info!("Prometheus 指标接口已启动: http://{}/metrics", addr);
+    Ok(Some(addr))
+}
+
+fn handle_request(request: &Request) -> Response {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/metrics") => Response {
+            status: "200 OK",
+            content_type: "text/plain; version=0.0.4; charset=utf-8",
+            body: metrics::render_prometheus(),
+        },
+        _ => Response::not_found(),
+    }
+}
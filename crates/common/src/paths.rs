@@ -0,0 +1,38 @@
+use once_cell::sync::Lazy;
+use std::path::{Path, PathBuf};
+
+// 数据目录：config、Log、history、schedule.json 等都相对于这里，命令行和图形界面共用。
+// 设置了 BTR_HOME 时使用该目录；否则当前目录有 config 时用当前目录，
+// 程序所在目录有 config 时用程序目录，都没有时用当前目录
+static BASE_DIR: Lazy<PathBuf> = Lazy::new(resolve_base_dir);
+
+pub fn base_dir() -> &'static Path {
+    &BASE_DIR
+}
+
+// 数据目录下的文件或子目录
+pub fn data_path(relative: impl AsRef<Path>) -> PathBuf {
+    BASE_DIR.join(relative)
+}
+
+pub fn config_path() -> PathBuf {
+    data_path("config")
+}
+
+fn resolve_base_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("BTR_HOME").filter(|d| !d.is_empty()) {
+        return PathBuf::from(dir);
+    }
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    if cwd.join("config").exists() {
+        return cwd;
+    }
+    if let Some(exe_dir) = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+        && exe_dir.join("config").exists()
+    {
+        return exe_dir;
+    }
+    cwd
+}
@@ -1,14 +1,300 @@
-use crate::config::PushConfig;
+use crate::config::{
+    DungeonConfig, GotifyConfig, MatrixConfig, NtfyConfig, PushConfig, SmtpConfig, SmtpSecurity,
+    WebhookConfig,
+};
+use crate::metrics;
 use crate::taskmanager::{
     DungeonQrResult, PushRequest, PushType, TaskManager, TaskRequest, TaskResult,
 };
+use crate::ticket::{CheckFakeResultData, ConfirmTicketResult};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use futures_util::future::{BoxFuture, join_all};
 use futures_util::{SinkExt, StreamExt};
-use reqwest::Client;
+use image::Luma;
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::transport::smtp::client::{Tls, TlsParameters};
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message as Email, Tokio1Executor};
+use qrcode::QrCode;
+use reqwest::{Client, Method, RequestBuilder};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
 use url::Url;
 
+// 推送渠道：每个渠道按 PushConfig 中的配置独立发送，由 PushRegistry 并发调用并汇总结果
+
+// 一条推送通知
+#[derive(Clone, Debug, Default)]
+pub struct Notification {
+    pub title: String,
+    pub message: String,
+    pub jump_url: Option<String>, //点击通知后打开的链接
+    pub pay_url: Option<String>,  //支付二维码内容，支持图片的渠道会附带二维码
+    pub order: Option<OrderSummary>,
+}
+
+// 下单成功时的订单信息，供通知正文和 Webhook 模板使用
+#[derive(Clone, Debug, Default)]
+pub struct OrderSummary {
+    pub project: String,
+    pub screen: String,
+    pub ticket: String,
+    pub count: i32,
+    pub price: i64,     //票价，单位分
+    pub pay_money: i64, //应付总额，单位分
+    pub order_id: String,
+}
+
+impl OrderSummary {
+    pub fn from_confirm(order_id: impl ToString, confirm: &ConfirmTicketResult) -> Self {
+        Self {
+            project: confirm.project_name.clone(),
+            screen: confirm.screen_name.clone(),
+            ticket: confirm.ticket_info.name.clone(),
+            count: confirm.count,
+            price: confirm.ticket_info.price,
+            pay_money: confirm.pay_money,
+            order_id: order_id.to_string(),
+        }
+    }
+
+    // B站 App 内的订单详情页，可直接支付
+    pub fn order_url(&self) -> String {
+        format!(
+            "bilibili://mall/web?url=https://mall.bilibili.com/neul-next/ticket/orderDetail.html?order_id={}",
+            self.order_id
+        )
+    }
+}
+
+impl Notification {
+    pub fn new(title: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            message: message.into(),
+            ..Self::default()
+        }
+    }
+
+    // 下单成功的通知，没有支付信息时提示前往订单中心支付
+    pub fn order_success(order: OrderSummary, pay: Option<&CheckFakeResultData>) -> Self {
+        let pay_url = pay.and_then(|p| p.code_url.clone());
+        let mut message = format!(
+            "项目: {}\n场次: {}\n票种: {} x{}\n金额: ¥{}\n订单号: {}\n",
+            order.project,
+            order.screen,
+            order.ticket,
+            order.count,
+            yuan(order.pay_money),
+            order.order_id
+        );
+        if pay_url.is_some() {
+            message.push_str("请尽快支付！");
+        } else {
+            message.push_str("状态: 未获取到支付信息，请前往订单中心支付");
+        }
+        Self {
+            title: format!("抢票成功: {}", order.project),
+            message,
+            jump_url: Some(order.order_url()),
+            pay_url,
+            order: Some(order),
+        }
+    }
+}
+
+// 分转为元，保留两位小数
+fn yuan(fen: i64) -> String {
+    format!("{:.2}", fen as f64 / 100.0)
+}
+
+pub trait PushChannel: Send + Sync {
+    // 与 enabled_methods 中的名称一致
+    fn name(&self) -> &'static str;
+
+    // token、地址等必填项是否已填写
+    fn is_configured(&self) -> bool;
+
+    // 成功时返回渠道的结果说明
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<String, String>>;
+
+    // 推送结果中显示的名称，同一渠道有多个实例时用于区分
+    fn label(&self) -> String {
+        self.name().to_string()
+    }
+
+    // 单个渠道的超时时间，超时按失败处理，不影响其他渠道
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(20)
+    }
+}
+
+// 单个渠道的推送结果
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelReport {
+    pub channel: String,
+    pub success: bool,
+    pub message: String,
+    pub elapsed_ms: u64,
+}
+
+// 一次推送的结果，按渠道列出
+#[derive(Clone, Debug, Default)]
+pub struct DeliveryReport {
+    pub channels: Vec<ChannelReport>,
+    pub dungeon_target_id: Option<String>,
+}
+
+impl DeliveryReport {
+    // 至少一个渠道成功即视为推送成功
+    pub fn success(&self) -> bool {
+        self.channels.iter().any(|c| c.success)
+    }
+
+    pub fn summary(&self) -> String {
+        let succeeded = self.channels.iter().filter(|c| c.success).count();
+        let failed = self.channels.len() - succeeded;
+        if self.channels.is_empty() {
+            "没有已启用并配置好的推送渠道".to_string()
+        } else if failed == 0 {
+            format!("{} 个渠道推送成功", succeeded)
+        } else {
+            format!("{} 成功 / {} 失败", succeeded, failed)
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct PushRegistry {
+    channels: Vec<Box<dyn PushChannel>>,
+    dungeon_target: Arc<Mutex<Option<String>>>,
+}
+
+impl PushRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 按 enabled_methods 创建已启用且配置完整的渠道，各 HTTP 渠道共用一个客户端
+    pub fn from_config(config: &PushConfig, result_tx: Option<mpsc::Sender<TaskResult>>) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(20))
+            .build()
+            .unwrap_or_default();
+        let mut registry = Self::new();
+        let channels: Vec<Box<dyn PushChannel>> = vec![
+            Box::new(BarkChannel {
+                client: client.clone(),
+                token: config.bark_token.clone(),
+            }),
+            Box::new(PushPlusChannel {
+                client: client.clone(),
+                token: config.pushplus_token.clone(),
+            }),
+            Box::new(FangtangChannel {
+                client: client.clone(),
+                token: config.fangtang_token.clone(),
+            }),
+            Box::new(DingtalkChannel {
+                client: client.clone(),
+                token: config.dingtalk_token.clone(),
+            }),
+            Box::new(WeChatChannel {
+                client: client.clone(),
+                token: config.wechat_token.clone(),
+            }),
+            Box::new(GotifyChannel {
+                client: client.clone(),
+                config: config.gotify_config.clone(),
+            }),
+            Box::new(NtfyChannel {
+                client: client.clone(),
+                config: config.ntfy_config.clone(),
+            }),
+            Box::new(MatrixChannel {
+                client: client.clone(),
+                config: config.matrix_config.clone(),
+            }),
+            Box::new(SmtpChannel {
+                config: config.smtp_config.clone(),
+            }),
+            Box::new(DungeonChannel {
+                config: config.dungeon_config.clone(),
+                result_tx,
+                target_id: registry.dungeon_target.clone(),
+            }),
+        ];
+        let webhooks = config.webhooks.iter().enumerate().map(|(index, webhook)| {
+            Box::new(WebhookChannel {
+                client: client.clone(),
+                config: webhook.clone(),
+                index,
+            }) as Box<dyn PushChannel>
+        });
+        for channel in channels.into_iter().chain(webhooks) {
+            if !config.enabled_methods.iter().any(|m| m == channel.name()) {
+                continue;
+            }
+            if channel.is_configured() {
+                registry.register(channel);
+            } else {
+                log::warn!("推送渠道 {} 已启用但未填写配置，已跳过", channel.label());
+            }
+        }
+        registry
+    }
+
+    pub fn register(&mut self, channel: Box<dyn PushChannel>) {
+        self.channels.push(channel);
+    }
+
+    // 只保留名称满足条件的渠道
+    pub fn retain(&mut self, keep: impl Fn(&str) -> bool) {
+        self.channels.retain(|c| keep(c.name()));
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.channels.iter().map(|c| c.name()).collect()
+    }
+
+    // 所有渠道并发发送，每个渠道单独计时和超时
+    pub async fn send_all(&self, notification: &Notification) -> DeliveryReport {
+        let channels = join_all(self.channels.iter().map(|channel| async move {
+            let started = Instant::now();
+            let result =
+                match tokio::time::timeout(channel.timeout(), channel.send(notification)).await {
+                    Ok(result) => result,
+                    Err(_) => Err(format!("超时（{}秒）", channel.timeout().as_secs_f32())),
+                };
+            metrics::record_push(channel.name(), result.is_ok());
+            if let Err(e) = &result {
+                log::warn!("{} 推送出错: {}", channel.label(), e);
+            }
+            let (success, message) = match result {
+                Ok(message) => (true, message),
+                Err(message) => (false, message),
+            };
+            ChannelReport {
+                channel: channel.label(),
+                success,
+                message,
+                elapsed_ms: started.elapsed().as_millis() as u64,
+            }
+        }))
+        .await;
+        DeliveryReport {
+            channels,
+            dungeon_target_id: self.dungeon_target.lock().ok().and_then(|t| t.clone()),
+        }
+    }
+}
+
 impl PushConfig {
     pub fn push_all(
         &self,
@@ -39,483 +325,809 @@ impl PushConfig {
 
     pub async fn push_all_async(
         &self,
-        title: &str,
-        message: &str,
-        jump_url: &Option<String>,
+        notification: &Notification,
         result_tx: Option<mpsc::Sender<TaskResult>>,
-    ) -> (bool, String, Option<String>) {
-        let mut success_count = 0;
-        let mut failure_count = 0;
-        let mut failures = Vec::new();
-        let mut dungeon_target_id = None;
-
-        if self.enabled_methods.contains(&"bark".to_string()) && !self.bark_token.is_empty() {
-            let (success, msg) = self.push_bark(title, message).await;
-            if success {
-                success_count += 1;
-            } else {
-                failure_count += 1;
-                failures.push(format!("Bark推送出错: {}", msg));
-            }
-        }
+    ) -> DeliveryReport {
+        PushRegistry::from_config(self, result_tx)
+            .send_all(notification)
+            .await
+    }
+}
 
-        if self.enabled_methods.contains(&"pushplus".to_string()) && !self.pushplus_token.is_empty()
-        {
-            let (success, msg) = self.push_pushplus(title, message).await;
-            if success {
-                success_count += 1;
-            } else {
-                failure_count += 1;
-                failures.push(format!("PushPlus推送出错: {}", msg));
-            }
-        }
+// 发送请求并按状态码判断是否成功，各 HTTP 渠道共用
+async fn send_http(name: &str, request: RequestBuilder) -> Result<String, String> {
+    let resp = request
+        .send()
+        .await
+        .map_err(|e| format!("推送失败: {}", e))?;
+    let status = resp.status();
+    let text = resp
+        .text()
+        .await
+        .map_err(|e| format!("读取响应失败: {}", e))?;
+    log::debug!("{} 推送响应: 状态码 {}, 内容: {}", name, status, text);
+    if status.is_success() {
+        Ok("推送成功".to_string())
+    } else {
+        Err(format!("推送失败，状态码: {}", status))
+    }
+}
 
-        if self.enabled_methods.contains(&"fangtang".to_string()) && !self.fangtang_token.is_empty()
-        {
-            let (success, msg) = self.push_fangtang(title, message).await;
-            if success {
-                success_count += 1;
-            } else {
-                failure_count += 1;
-                failures.push(format!("Fangtang推送出错: {}", msg));
-            }
-        }
+pub struct BarkChannel {
+    client: Client,
+    token: String,
+}
 
-        if self.enabled_methods.contains(&"dingtalk".to_string()) && !self.dingtalk_token.is_empty()
-        {
-            let (success, msg) = self.push_dingtalk(title, message).await;
-            if success {
-                success_count += 1;
-            } else {
-                failure_count += 1;
-                failures.push(format!("Dingtalk推送出错: {}", msg));
-            }
-        }
+impl PushChannel for BarkChannel {
+    fn name(&self) -> &'static str {
+        "bark"
+    }
 
-        if self.enabled_methods.contains(&"wechat".to_string()) && !self.wechat_token.is_empty() {
-            let (success, msg) = self.push_wechat(title, message).await;
-            if success {
-                success_count += 1;
-            } else {
-                failure_count += 1;
-                failures.push(format!("WeChat推送出错: {}", msg));
-            }
-        }
+    fn is_configured(&self) -> bool {
+        !self.token.is_empty()
+    }
 
-        if self.enabled_methods.contains(&"gotify".to_string())
-            && !self.gotify_config.gotify_token.is_empty()
-        {
-            let (success, msg) = self.push_gotify(title, message, jump_url).await;
-            if success {
-                success_count += 1;
-            } else {
-                failure_count += 1;
-                failures.push(format!("Gotify推送出错: {}", msg));
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let mut data = json!({
+                "title": notification.title,
+                "body": notification.message,
+                "level": "timeSensitive",
+                "badge": 1,
+                "icon": "https://sr.mihoyo.com/favicon-mi.ico",
+                "group": "biliticket",
+                "isArchive": 1,
+            });
+            // 点击通知打开订单详情页支付
+            if let Some(jump_url) = &notification.jump_url {
+                data["url"] = json!(jump_url);
             }
-        }
+            let url = format!("https://api.day.app/{}/", self.token);
+            send_http("Bark", self.client.post(url).json(&data)).await
+        })
+    }
+}
 
-        if self.enabled_methods.contains(&"dungeon".to_string()) && self.dungeon_config.enabled {
-            let (success, msg, target_id) = self.push_dungeon(result_tx).await;
-            if success {
-                success_count += 1;
-                dungeon_target_id = target_id;
-            } else {
-                failure_count += 1;
-                failures.push(format!("Dungeon推送出错: {}", msg));
-            }
+pub struct PushPlusChannel {
+    client: Client,
+    token: String,
+}
+
+impl PushChannel for PushPlusChannel {
+    fn name(&self) -> &'static str {
+        "pushplus"
+    }
+
+    fn is_configured(&self) -> bool {
+        !self.token.is_empty()
+    }
+
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let data = json!({
+                "token": self.token,
+                "title": notification.title,
+                "content": notification.message,
+            });
+            let url = "http://www.pushplus.plus/send";
+            send_http("PushPlus", self.client.post(url).json(&data)).await
+        })
+    }
+}
+
+pub struct FangtangChannel {
+    client: Client,
+    token: String,
+}
+
+impl PushChannel for FangtangChannel {
+    fn name(&self) -> &'static str {
+        "fangtang"
+    }
+
+    fn is_configured(&self) -> bool {
+        !self.token.is_empty()
+    }
+
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let data = json!({
+                "title": notification.title,
+                "desp": notification.message,
+                "noip": 1
+            });
+            let url = format!("https://sctapi.ftqq.com/{}.send", self.token);
+            send_http("Fangtang", self.client.post(url).json(&data)).await
+        })
+    }
+}
+
+// 钉钉和企业微信机器人的文本消息格式相同
+fn robot_text(notification: &Notification) -> serde_json::Value {
+    json!({
+        "msgtype": "text",
+        "text": {
+            "content": format!("{} \n {}", notification.title, notification.message)
         }
+    })
+}
 
-        if success_count == 0 {
-            return (
-                false,
-                format!(
-                    "{} 成功 / {} 失败。失败详情: {}",
-                    success_count,
-                    failure_count,
-                    failures.join("; ")
-                ),
-                dungeon_target_id,
+pub struct DingtalkChannel {
+    client: Client,
+    token: String,
+}
+
+impl PushChannel for DingtalkChannel {
+    fn name(&self) -> &'static str {
+        "dingtalk"
+    }
+
+    fn is_configured(&self) -> bool {
+        !self.token.is_empty()
+    }
+
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let url = format!(
+                "https://oapi.dingtalk.com/robot/send?access_token={}",
+                self.token
             );
-        } else {
-            return (
-                true,
-                format!("{} 个渠道推送成功", success_count),
-                dungeon_target_id,
+            let request = self
+                .client
+                .post(url)
+                .json(&robot_text(notification))
+                .header("Charset", "UTF-8");
+            send_http("钉钉", request).await
+        })
+    }
+}
+
+pub struct WeChatChannel {
+    client: Client,
+    token: String,
+}
+
+impl PushChannel for WeChatChannel {
+    fn name(&self) -> &'static str {
+        "wechat"
+    }
+
+    fn is_configured(&self) -> bool {
+        !self.token.is_empty()
+    }
+
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let url = format!(
+                "https://qyapi.weixin.qq.com/cgi-bin/webhook/send?key={}",
+                self.token
             );
-        }
+            let request = self
+                .client
+                .post(url)
+                .json(&robot_text(notification))
+                .header("Charset", "UTF-8");
+            send_http("微信", request).await
+        })
     }
-    pub async fn push_gotify(
-        &self,
-        title: &str,
-        message: &str,
-        jump_url: &Option<String>,
-    ) -> (bool, String) {
-        let mut default_headers = reqwest::header::HeaderMap::new();
-        let jump_url_real = jump_url
-            .as_deref()
-            .unwrap_or("bilibili://mall/web?url=https://www.bilibili.com");
-
-        let push_target_url = if self.gotify_config.gotify_url.contains("http") {
-            self.gotify_config.gotify_url.clone()
-        } else {
-            format!("http://{}", self.gotify_config.gotify_url)
-        };
-        default_headers.insert(
-            "Content-Type",
-            reqwest::header::HeaderValue::from_static("application/json"),
-        );
-        default_headers.insert(
-            "Authorization",
-            reqwest::header::HeaderValue::from_str(&format!(
-                "Bearer {}",
-                self.gotify_config.gotify_token
-            ))
-            .unwrap(),
-        );
-        let client_builder = Client::builder()
-            .default_headers(default_headers)
-            .timeout(std::time::Duration::from_secs(20));
-        let data = serde_json::json!({
-            "message": message,
-            "title": title,
-            "priority": 9,
-            "extras": {
-            "client::notification": {
-                "click": {"url": jump_url_real},
-            },
-            "android::action": {
-                "onReceive": {"intentUrl": jump_url_real}
-            }
-        }
-        });
-        let client = match client_builder.build() {
-            Ok(client) => client,
-            Err(e) => return (false, format!("创建HTTP客户端失败: {}", e)),
-        };
-        let url = format!("{}/message", push_target_url);
-
-        match client.post(&url).json(&data).send().await {
-            Ok(resp) => {
-                let status = resp.status();
-                match resp.text().await {
-                    Ok(text) => {
-                        log::debug!("Gotify 推送响应: 状态码 {}, 内容: {}", status, text);
-                        if status.is_success() {
-                            (true, "推送成功".to_string())
-                        } else {
-                            (false, format!("推送失败，状态码: {}", status))
-                        }
-                    }
-                    Err(e) => (false, format!("读取响应失败: {}", e)),
-                }
-            }
-            Err(e) => (false, format!("推送失败: {}", e)),
-        }
+}
+
+// 没有订单链接时点击通知打开的页面
+const DEFAULT_JUMP_URL: &str = "bilibili://mall/web?url=https://www.bilibili.com";
+
+pub struct GotifyChannel {
+    client: Client,
+    config: GotifyConfig,
+}
+
+impl PushChannel for GotifyChannel {
+    fn name(&self) -> &'static str {
+        "gotify"
     }
-    pub async fn push_bark(&self, title: &str, message: &str) -> (bool, String) {
-        let client = Client::new();
-        let data = serde_json::json!({
-            "title":title,
-            "body":message,
-            "level":"timeSensitive",
-            "badge":1,
-            "icon":"https://sr.mihoyo.com/favicon-mi.ico",
-            "group":"biliticket",
-            "isArchive":1,
 
-        });
-        let url = format!("https://api.day.app/{}/", self.bark_token);
-        match client.post(&url).json(&data).send().await {
-            Ok(resp) => {
-                let status = resp.status();
-                match resp.text().await {
-                    Ok(text) => {
-                        log::debug!("Bark 推送响应: 状态码 {}, 内容: {}", status, text);
-                        if status.is_success() {
-                            (true, "推送成功".to_string())
-                        } else {
-                            (false, format!("推送失败，状态码: {}", status))
-                        }
+    fn is_configured(&self) -> bool {
+        !self.config.gotify_token.is_empty()
+    }
+
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let jump_url = notification.jump_url.as_deref().unwrap_or(DEFAULT_JUMP_URL);
+            let base_url = if self.config.gotify_url.contains("http") {
+                self.config.gotify_url.clone()
+            } else {
+                format!("http://{}", self.config.gotify_url)
+            };
+            let mut data = json!({
+                "message": notification.message,
+                "title": notification.title,
+                "priority": 9,
+                "extras": {
+                    "client::notification": {
+                        "click": {"url": jump_url},
+                    },
+                    "android::action": {
+                        "onReceive": {"intentUrl": jump_url}
                     }
-                    Err(e) => (false, format!("读取响应失败: {}", e)),
                 }
+            });
+            // 有支付链接时以 Markdown 显示支付二维码，二维码生成失败时只附支付链接
+            if let Some(pay_url) = &notification.pay_url {
+                let lines: Vec<&str> = notification.message.lines().collect();
+                let qr = match qr_data_uri(pay_url) {
+                    Ok(uri) => format!("![支付二维码]({})\n\n", uri),
+                    Err(e) => {
+                        log::warn!("生成支付二维码失败: {}", e);
+                        String::new()
+                    }
+                };
+                data["message"] = json!(format!(
+                    "{}\n\n{}[打开支付链接]({})",
+                    lines.join("  \n"),
+                    qr,
+                    pay_url
+                ));
+                data["extras"]["client::display"] = json!({"contentType": "text/markdown"});
             }
-            Err(e) => (false, format!("推送失败: {}", e)),
-        }
+            let request = self
+                .client
+                .post(format!("{}/message", base_url))
+                .bearer_auth(&self.config.gotify_token)
+                .json(&data);
+            send_http("Gotify", request).await
+        })
     }
+}
 
-    pub async fn push_pushplus(&self, title: &str, message: &str) -> (bool, String) {
-        let client = Client::new();
-        let url = "http://www.pushplus.plus/send";
-        let data = serde_json::json!({
-            "token":self.pushplus_token,
-            "title":title,
-            "content":message,
-        });
-        match client.post(url).json(&data).send().await {
-            Ok(resp) => {
-                let status = resp.status();
-                match resp.text().await {
-                    Ok(text) => {
-                        log::debug!("PushPlus 推送响应: 状态码 {}, 内容: {}", status, text);
-                        if status.is_success() {
-                            (true, "推送成功".to_string())
-                        } else {
-                            (false, format!("推送失败，状态码: {}", status))
-                        }
-                    }
-                    Err(e) => (false, format!("读取响应失败: {}", e)),
-                }
+// 以 JSON 方式发布到 ntfy 服务器根地址，点击通知打开与 Gotify 相同的链接
+pub struct NtfyChannel {
+    client: Client,
+    config: NtfyConfig,
+}
+
+impl PushChannel for NtfyChannel {
+    fn name(&self) -> &'static str {
+        "ntfy"
+    }
+
+    fn is_configured(&self) -> bool {
+        !self.config.topic_url.is_empty()
+    }
+
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let topic_url = self.config.topic_url.trim_end_matches('/');
+            let (server, topic) = topic_url
+                .rsplit_once('/')
+                .filter(|(server, topic)| server.contains("://") && !topic.is_empty())
+                .ok_or_else(|| format!("主题地址无效: {}", self.config.topic_url))?;
+            let data = json!({
+                "topic": topic,
+                "title": notification.title,
+                "message": notification.message,
+                "priority": self.config.priority.clamp(1, 5),
+                "click": notification.jump_url.as_deref().unwrap_or(DEFAULT_JUMP_URL),
+            });
+            let mut request = self.client.post(server).json(&data);
+            if !self.config.token.is_empty() {
+                request = request.bearer_auth(&self.config.token);
             }
-            Err(e) => (false, format!("推送失败: {}", e)),
+            send_http("ntfy", request).await
+        })
+    }
+}
+
+// 通过 Matrix 客户端接口向房间发送一条文本消息
+pub struct MatrixChannel {
+    client: Client,
+    config: MatrixConfig,
+}
+
+impl PushChannel for MatrixChannel {
+    fn name(&self) -> &'static str {
+        "matrix"
+    }
+
+    fn is_configured(&self) -> bool {
+        !self.config.homeserver.is_empty()
+            && !self.config.access_token.is_empty()
+            && !self.config.room_id.is_empty()
+    }
+
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let room_id: String =
+                url::form_urlencoded::byte_serialize(self.config.room_id.as_bytes()).collect();
+            // 事务 ID 用于服务端去重，每条消息不同
+            let url = format!(
+                "{}/_matrix/client/v3/rooms/{}/send/m.room.message/{}",
+                self.config.homeserver.trim_end_matches('/'),
+                room_id,
+                uuid::Uuid::new_v4().simple()
+            );
+            let lines: Vec<String> = notification.message.lines().map(escape_html).collect();
+            let data = json!({
+                "msgtype": "m.text",
+                "body": format!("{}\n{}", notification.title, notification.message),
+                "format": "org.matrix.custom.html",
+                "formatted_body": format!(
+                    "<b>{}</b><br>{}",
+                    escape_html(&notification.title),
+                    lines.join("<br>")
+                ),
+            });
+            let request = self
+                .client
+                .put(url)
+                .bearer_auth(&self.config.access_token)
+                .json(&data);
+            send_http("Matrix", request).await
+        })
+    }
+}
+
+// 按模板发送自定义 HTTP 请求，多个 Webhook 各自作为一个渠道
+pub struct WebhookChannel {
+    client: Client,
+    config: WebhookConfig,
+    index: usize,
+}
+
+impl PushChannel for WebhookChannel {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    fn label(&self) -> String {
+        if self.config.name.is_empty() {
+            format!("webhook#{}", self.index + 1)
+        } else {
+            format!("webhook:{}", self.config.name)
         }
     }
 
-    pub async fn push_fangtang(&self, title: &str, message: &str) -> (bool, String) {
-        let client = Client::new();
-        let url = format!("https://sctapi.ftqq.com/{}.send", self.fangtang_token);
-        let data = serde_json::json!({
-            "title":title,
-            "desp":message,
-            "noip":1
-        });
-        match client.post(url).json(&data).send().await {
-            Ok(resp) => {
-                let status = resp.status();
-                match resp.text().await {
-                    Ok(text) => {
-                        log::debug!("Fangtang 推送响应: 状态码 {}, 内容: {}", status, text);
-                        if status.is_success() {
-                            (true, "推送成功".to_string())
-                        } else {
-                            (false, format!("推送失败，状态码: {}", status))
-                        }
-                    }
-                    Err(e) => (false, format!("读取响应失败: {}", e)),
+    fn is_configured(&self) -> bool {
+        !self.config.url.is_empty()
+    }
+
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let method = Method::from_bytes(self.config.method.trim().to_uppercase().as_bytes())
+                .map_err(|_| format!("请求方法无效: {}", self.config.method))?;
+            let url = render_template(&self.config.url, notification, |v| {
+                url::form_urlencoded::byte_serialize(v.as_bytes()).collect()
+            });
+            let mut request = self.client.request(method, url);
+            for (key, value) in &self.config.headers {
+                request = request.header(key, render_template(value, notification, str::to_string));
+            }
+            if !self.config.body.is_empty() {
+                // 未指定 Content-Type 时按模板内容判断是否为 JSON
+                let content_type = self
+                    .config
+                    .headers
+                    .iter()
+                    .find(|(k, _)| k.eq_ignore_ascii_case("content-type"))
+                    .map(|(_, v)| v.as_str());
+                let json_body = match content_type {
+                    Some(v) => v.contains("json"),
+                    None => looks_like_json(&self.config.body),
+                };
+                if content_type.is_none() && json_body {
+                    request = request.header("Content-Type", "application/json");
                 }
+                let body = if json_body {
+                    render_template(&self.config.body, notification, json_escape)
+                } else {
+                    render_template(&self.config.body, notification, str::to_string)
+                };
+                request = request.body(body);
             }
-            Err(e) => (false, format!("推送失败: {}", e)),
-        }
+            send_http(&self.label(), request).await
+        })
     }
+}
 
-    pub async fn push_dingtalk(&self, title: &str, message: &str) -> (bool, String) {
-        let client = Client::new();
-        let url = format!(
-            "https://oapi.dingtalk.com/robot/send?access_token={}",
-            self.dingtalk_token
-        );
-        let data = serde_json::json!({
-            "msgtype":"text",
-            "text":{
-                "content":format!("{} \n {}", title, message)
-            }
+// 替换模板中的占位符，escape 用于按所在位置转义（URL 编码、JSON 字符串等）
+pub fn render_template(
+    template: &str,
+    notification: &Notification,
+    escape: impl Fn(&str) -> String,
+) -> String {
+    let order = notification.order.clone().unwrap_or_default();
+    let (count, price, pay_money) = match &notification.order {
+        Some(order) => (
+            order.count.to_string(),
+            yuan(order.price),
+            yuan(order.pay_money),
+        ),
+        None => Default::default(),
+    };
+    // 二维码只在模板用到时生成
+    let pay_qr = match &notification.pay_url {
+        Some(url) if template.contains("{{pay_qr}}") => qr_data_uri(url).unwrap_or_default(),
+        _ => String::new(),
+    };
+    let values = [
+        ("title", notification.title.as_str()),
+        ("message", notification.message.as_str()),
+        ("project", order.project.as_str()),
+        ("screen", order.screen.as_str()),
+        ("ticket", order.ticket.as_str()),
+        ("tier", order.ticket.as_str()),
+        ("count", count.as_str()),
+        ("price", price.as_str()),
+        ("pay_money", pay_money.as_str()),
+        ("order_id", order.order_id.as_str()),
+        ("pay_url", notification.pay_url.as_deref().unwrap_or("")),
+        ("pay_qr", pay_qr.as_str()),
+        ("jump_url", notification.jump_url.as_deref().unwrap_or("")),
+    ];
+    // 从左到右只扫描一遍模板，替换进来的值不会再被当作占位符
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let value = after.find("}}").and_then(|end| {
+            let key = &after[..end];
+            let (_, value) = values.iter().find(|(name, _)| *name == key)?;
+            Some((value, end))
         });
-        match client
-            .post(url)
-            .json(&data)
-            .header("Content-Type", "application/json")
-            .header("Charset", "UTF-8")
-            .send()
-            .await
-        {
-            Ok(resp) => {
-                let status = resp.status();
-                match resp.text().await {
-                    Ok(text) => {
-                        log::debug!("钉钉推送响应: 状态码 {}, 内容: {}", status, text);
-                        if status.is_success() {
-                            (true, "推送成功".to_string())
-                        } else {
-                            (false, format!("推送失败，状态码: {}", status))
-                        }
-                    }
-                    Err(e) => (false, format!("读取响应失败: {}", e)),
-                }
+        match value {
+            Some((value, end)) => {
+                rendered.push_str(&escape(value));
+                rest = &after[end + 2..];
+            }
+            // 未知占位符原样保留
+            None => {
+                rendered.push_str("{{");
+                rest = after;
             }
-            Err(e) => (false, format!("推送失败: {}", e)),
         }
     }
+    rendered.push_str(rest);
+    rendered
+}
 
-    pub async fn push_wechat(&self, title: &str, message: &str) -> (bool, String) {
-        let client = Client::new();
-        let url = format!(
-            "https://qyapi.weixin.qq.com/cgi-bin/webhook/send?key={}",
-            self.wechat_token
-        );
-        let data = serde_json::json!({
-            "msgtype":"text",
-            "text":{
-                "content":format!("{} \n {}", title, message)
+// 以 { 或 [ 开头，且不是以占位符开头
+fn looks_like_json(template: &str) -> bool {
+    let template = template.trim_start();
+    template.starts_with('[') || (template.starts_with('{') && !template.starts_with("{{"))
+}
+
+// 转义为 JSON 字符串内容，不含两侧引号
+fn json_escape(value: &str) -> String {
+    let quoted = serde_json::Value::from(value).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+// 发送 HTML 邮件，有支付链接时附带支付二维码
+pub struct SmtpChannel {
+    config: SmtpConfig,
+}
+
+impl PushChannel for SmtpChannel {
+    fn name(&self) -> &'static str {
+        "smtp"
+    }
+
+    fn is_configured(&self) -> bool {
+        !self.config.host.is_empty() && !self.config.to.is_empty()
+    }
+
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            send_smtp(&self.config, notification).await?;
+            Ok("推送成功".to_string())
+        })
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(30)
+    }
+}
+
+async fn send_smtp(smtp: &SmtpConfig, notification: &Notification) -> Result<(), String> {
+    let from = if smtp.from.is_empty() {
+        &smtp.username
+    } else {
+        &smtp.from
+    };
+    let mut builder = Email::builder()
+        .from(
+            from.parse::<Mailbox>()
+                .map_err(|e| format!("发件人地址无效: {}", e))?,
+        )
+        .subject(&notification.title);
+    for to in &smtp.to {
+        builder = builder.to(to
+            .parse::<Mailbox>()
+            .map_err(|e| format!("收件人地址无效 {}: {}", to, e))?);
+    }
+
+    // 二维码生成失败时只附支付链接，不影响邮件发送
+    let qr = notification
+        .pay_url
+        .as_deref()
+        .and_then(|url| match qr_png(url) {
+            Ok(png) => Some(png),
+            Err(e) => {
+                log::warn!("生成支付二维码失败: {}", e);
+                None
             }
         });
-        match client
-            .post(url)
-            .json(&data)
-            .header("Content-Type", "application/json")
-            .header("Charset", "UTF-8")
-            .send()
-            .await
-        {
-            Ok(resp) => {
-                let status = resp.status();
-                match resp.text().await {
-                    Ok(text) => {
-                        log::debug!("微信推送响应: 状态码 {}, 内容: {}", status, text);
-                        if status.is_success() {
-                            (true, "推送成功".to_string())
-                        } else {
-                            (false, format!("推送失败，状态码: {}", status))
-                        }
-                    }
-                    Err(e) => (false, format!("读取响应失败: {}", e)),
-                }
-            }
-            Err(e) => (false, format!("推送失败: {}", e)),
+    let html = SinglePart::html(smtp_html(
+        &notification.title,
+        &notification.message,
+        notification.pay_url.as_deref(),
+        qr.is_some(),
+    ));
+    let body = match qr {
+        Some(png) => {
+            let content_type =
+                ContentType::parse("image/png").map_err(|e| format!("生成邮件失败: {}", e))?;
+            MultiPart::related()
+                .singlepart(html)
+                .singlepart(Attachment::new_inline("pay_qr".to_string()).body(png, content_type))
         }
+        None => MultiPart::related().singlepart(html),
+    };
+    let email = builder
+        .multipart(
+            MultiPart::alternative()
+                .singlepart(SinglePart::plain(notification.message.clone()))
+                .multipart(body),
+        )
+        .map_err(|e| format!("生成邮件失败: {}", e))?;
+
+    let tls_parameters =
+        || TlsParameters::new(smtp.host.clone()).map_err(|e| format!("TLS 配置失败: {}", e));
+    let tls = match smtp.security {
+        SmtpSecurity::None => Tls::None,
+        SmtpSecurity::StartTls => Tls::Required(tls_parameters()?),
+        SmtpSecurity::Tls => Tls::Wrapper(tls_parameters()?),
+    };
+    let mut transport = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&smtp.host)
+        .port(smtp.port)
+        .tls(tls)
+        .timeout(Some(Duration::from_secs(20)));
+    if !smtp.username.is_empty() {
+        transport = transport.credentials(Credentials::new(
+            smtp.username.clone(),
+            smtp.password.clone(),
+        ));
     }
 
-    pub async fn push_dungeon(
-        &self,
-        result_tx: Option<mpsc::Sender<TaskResult>>,
-    ) -> (bool, String, Option<String>) {
-        let ws_url = "wss://ws.dungeon-lab.cn";
-        let url = match Url::parse(ws_url) {
-            Ok(u) => u,
-            Err(e) => return (false, format!("解析WebSocket URL失败: {}", e), None),
-        };
+    let response = transport
+        .build()
+        .send(email)
+        .await
+        .map_err(|e| format!("发送邮件失败: {}", e))?;
+    log::debug!("SMTP 推送响应: {:?}", response.code());
+    Ok(())
+}
 
-        log::info!("正在连接 Dungeon Socket 服务: {}", ws_url);
+// 连接 Dungeon Socket 服务，等待 App 扫码绑定后发送脉冲
+pub struct DungeonChannel {
+    config: DungeonConfig,
+    result_tx: Option<mpsc::Sender<TaskResult>>,
+    target_id: Arc<Mutex<Option<String>>>,
+}
 
-        let (ws_stream, _) = match connect_async(url).await {
-            Ok(s) => s,
-            Err(e) => {
-                log::error!("连接 Socket 服务失败: {}", e);
-                return (false, format!("连接 Socket 服务失败: {}", e), None);
+impl PushChannel for DungeonChannel {
+    fn name(&self) -> &'static str {
+        "dungeon"
+    }
+
+    fn is_configured(&self) -> bool {
+        self.config.enabled
+    }
+
+    fn send<'a>(
+        &'a self,
+        _notification: &'a Notification,
+    ) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(async move {
+            let target_id = pulse_dungeon(&self.config, self.result_tx.as_ref()).await?;
+            if let Ok(mut current) = self.target_id.lock() {
+                *current = Some(target_id);
             }
-        };
-
-        let (mut write, mut read) = ws_stream.split();
-        let mut client_id = String::new();
-        let mut target_id = String::new();
-        let mut bound = false;
-
-        let timeout = std::time::Duration::from_secs(60);
-        let start_time = std::time::Instant::now();
-
-        log::debug!("WebSocket 连接成功，等待服务器返回 Client ID...");
-
-        while start_time.elapsed() < timeout {
-            match tokio::time::timeout(std::time::Duration::from_secs(1), read.next()).await {
-                Ok(Some(Ok(msg))) => {
-                    if let Message::Text(text) = msg {
-                        if let Ok(v) = serde_json::from_str::<serde_json::Value>(&text) {
-                            let msg_type = v["type"].as_str().unwrap_or("");
-                            let message = v["message"].as_str().unwrap_or("");
-
-                            if msg_type == "bind" {
-                                if message == "targetId" {
-                                    client_id = v["clientId"].as_str().unwrap_or("").to_string();
-                                    log::debug!("获取到 Client ID: {}", client_id);
-
-                                    let qr_content = format!(
-                                        "https://www.dungeon-lab.com/app-download.php#DGLAB-SOCKET#wss://ws.dungeon-lab.cn/{}",
-                                        client_id
-                                    );
-                                    if let Some(ref tx) = result_tx {
-                                        let _ = tx
-                                            .send(TaskResult::DungeonQrResult(DungeonQrResult {
-                                                task_id: "".to_string(),
-                                                qr_url: qr_content,
-                                            }))
-                                            .await;
-                                    }
-                                } else if message == "200"
-                                    || !v["targetId"].as_str().unwrap_or("").is_empty()
-                                {
-                                    target_id = v["targetId"].as_str().unwrap_or("").to_string();
-                                    log::debug!("App 绑定成功! Target ID: {}", target_id);
-                                    bound = true;
-                                    break;
+            Ok("WebSocket 脉冲发送完成".to_string())
+        })
+    }
+
+    // 包含等待 App 绑定的 60 秒
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(90)
+    }
+}
+
+// 成功时返回绑定的 Target ID
+async fn pulse_dungeon(
+    config: &DungeonConfig,
+    result_tx: Option<&mpsc::Sender<TaskResult>>,
+) -> Result<String, String> {
+    let ws_url = "wss://ws.dungeon-lab.cn";
+    let url = match Url::parse(ws_url) {
+        Ok(u) => u,
+        Err(e) => return Err(format!("解析WebSocket URL失败: {}", e)),
+    };
+
+    log::info!("正在连接 Dungeon Socket 服务: {}", ws_url);
+
+    let (ws_stream, _) = match connect_async(url).await {
+        Ok(s) => s,
+        Err(e) => {
+            log::error!("连接 Socket 服务失败: {}", e);
+            return Err(format!("连接 Socket 服务失败: {}", e));
+        }
+    };
+
+    let (mut write, mut read) = ws_stream.split();
+    let mut client_id = String::new();
+    let mut target_id = String::new();
+    let mut bound = false;
+
+    let timeout = std::time::Duration::from_secs(60);
+    let start_time = std::time::Instant::now();
+
+    log::debug!("WebSocket 连接成功，等待服务器返回 Client ID...");
+
+    while start_time.elapsed() < timeout {
+        match tokio::time::timeout(std::time::Duration::from_secs(1), read.next()).await {
+            Ok(Some(Ok(msg))) => {
+                if let Message::Text(text) = msg {
+                    if let Ok(v) = serde_json::from_str::<serde_json::Value>(&text) {
+                        let msg_type = v["type"].as_str().unwrap_or("");
+                        let message = v["message"].as_str().unwrap_or("");
+
+                        if msg_type == "bind" {
+                            if message == "targetId" {
+                                client_id = v["clientId"].as_str().unwrap_or("").to_string();
+                                log::debug!("获取到 Client ID: {}", client_id);
+
+                                let qr_content = format!(
+                                    "https://www.dungeon-lab.com/app-download.php#DGLAB-SOCKET#wss://ws.dungeon-lab.cn/{}",
+                                    client_id
+                                );
+                                if let Some(tx) = result_tx {
+                                    let _ = tx
+                                        .send(TaskResult::DungeonQrResult(DungeonQrResult {
+                                            task_id: "".to_string(),
+                                            qr_url: qr_content,
+                                        }))
+                                        .await;
                                 }
+                            } else if message == "200"
+                                || !v["targetId"].as_str().unwrap_or("").is_empty()
+                            {
+                                target_id = v["targetId"].as_str().unwrap_or("").to_string();
+                                log::debug!("App 绑定成功! Target ID: {}", target_id);
+                                bound = true;
+                                break;
                             }
                         }
                     }
                 }
-                Ok(Some(Err(e))) => {
-                    return (false, format!("WS 读取错误: {}", e), None);
-                }
-                Ok(None) => {
-                    return (false, "WS 连接被服务器断开".to_string(), None);
-                }
-                Err(_) => {
-                    // Timeout
-                }
+            }
+            Ok(Some(Err(e))) => {
+                return Err(format!("WS 读取错误: {}", e));
+            }
+            Ok(None) => {
+                return Err("WS 连接被服务器断开".to_string());
+            }
+            Err(_) => {
+                // Timeout
             }
         }
+    }
 
-        if !bound {
-            return (
-                false,
-                "等待 App 绑定超时，请确保 App 已扫描二维码并连接".to_string(),
-                None,
-            );
-        }
+    if !bound {
+        return Err("等待 App 绑定超时，请确保 App 已扫描二维码并连接".to_string());
+    }
 
-        let channel_idx = self.dungeon_config.channel;
-        let channel_char = if channel_idx == 0 { "A" } else { "B" };
-        let clear_channel_idx = if channel_idx == 0 { "1" } else { "2" };
+    let channel_idx = config.channel;
+    let channel_char = if channel_idx == 0 { "A" } else { "B" };
+    let clear_channel_idx = if channel_idx == 0 { "1" } else { "2" };
 
-        let freq_val = (self.dungeon_config.frequency as u16).max(10).min(100);
-        let intensity = self.dungeon_config.intensity.min(100);
+    let freq_val = (config.frequency as u16).max(10).min(100);
+    let intensity = config.intensity.min(100);
 
-        let hex_str = format!(
-            "{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}",
-            freq_val, freq_val, freq_val, freq_val, intensity, intensity, intensity, intensity
-        );
+    let hex_str = format!(
+        "{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}",
+        freq_val, freq_val, freq_val, freq_val, intensity, intensity, intensity, intensity
+    );
 
-        let pulse_duration = self.dungeon_config.pulse_ms.max(100);
-        let num_chunks = (pulse_duration + 99) / 100;
-        let num_chunks = num_chunks.min(100);
+    let pulse_duration = config.pulse_ms.max(100);
+    let num_chunks = (pulse_duration + 99) / 100;
+    let num_chunks = num_chunks.min(100);
 
-        let mut wave_data = Vec::new();
-        for _ in 0..num_chunks {
-            wave_data.push(hex_str.clone());
-        }
+    let mut wave_data = Vec::new();
+    for _ in 0..num_chunks {
+        wave_data.push(hex_str.clone());
+    }
 
-        let clear_msg = json!({
+    let clear_msg = json!({
+        "type": "msg",
+        "clientId": client_id,
+        "targetId": target_id,
+        "message": format!("clear-{}", clear_channel_idx)
+    });
+
+    if let Err(e) = write.send(Message::Text(clear_msg.to_string())).await {
+        return Err(format!("发送清除指令失败: {}", e));
+    }
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    log::debug!("开始执行脉冲循环: {} 次", config.count);
+
+    for i in 0..config.count {
+        log::debug!("发送第 {}/{} 次脉冲", i + 1, config.count);
+        let pulse_msg = json!({
             "type": "msg",
             "clientId": client_id,
             "targetId": target_id,
-            "message": format!("clear-{}", clear_channel_idx)
+            "message": format!("pulse-{}:{}", channel_char, serde_json::to_string(&wave_data).unwrap())
         });
 
-        if let Err(e) = write.send(Message::Text(clear_msg.to_string())).await {
-            return (false, format!("发送清除指令失败: {}", e), None);
+        if let Err(e) = write.send(Message::Text(pulse_msg.to_string())).await {
+            log::error!("发送脉冲失败: {}", e);
+            break;
         }
-        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
 
-        log::debug!("开始执行脉冲循环: {} 次", self.dungeon_config.count);
+        let sleep_time = config.pulse_ms + config.pause_ms;
+        tokio::time::sleep(std::time::Duration::from_millis(sleep_time)).await;
+    }
 
-        for i in 0..self.dungeon_config.count {
-            log::debug!("发送第 {}/{} 次脉冲", i + 1, self.dungeon_config.count);
-            let pulse_msg = json!({
-                "type": "msg",
-                "clientId": client_id,
-                "targetId": target_id,
-                "message": format!("pulse-{}:{}", channel_char, serde_json::to_string(&wave_data).unwrap())
-            });
+    let _ = write.close().await;
 
-            if let Err(e) = write.send(Message::Text(pulse_msg.to_string())).await {
-                log::error!("发送脉冲失败: {}", e);
-                break;
-            }
+    Ok(target_id)
+}
 
-            let sleep_time = self.dungeon_config.pulse_ms + self.dungeon_config.pause_ms;
-            tokio::time::sleep(std::time::Duration::from_millis(sleep_time)).await;
-        }
+// 邮件正文：标题、逐行的订单信息，以及引用内嵌图片 cid:pay_qr 的支付二维码
+fn smtp_html(title: &str, message: &str, pay_url: Option<&str>, with_qr: bool) -> String {
+    let lines: Vec<String> = message.lines().map(escape_html).collect();
+    let mut html = format!(
+        "<html><body><h2>{}</h2><p>{}</p>",
+        escape_html(title),
+        lines.join("<br>")
+    );
+    match pay_url {
+        Some(url) if with_qr => html.push_str(&format!(
+            "<p>请尽快扫码支付，以免订单失效！</p><p><img src=\"cid:pay_qr\" alt=\"支付二维码\" width=\"240\" height=\"240\"></p><p><a href=\"{0}\">{0}</a></p>",
+            escape_html(url)
+        )),
+        Some(url) => html.push_str(&format!(
+            "<p>请尽快打开链接支付，以免订单失效！</p><p><a href=\"{0}\">{0}</a></p>",
+            escape_html(url)
+        )),
+        None => {}
+    }
+    html.push_str("</body></html>");
+    html
+}
 
-        let _ = write.close().await;
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
 
-        (true, "WebSocket 脉冲发送完成".to_string(), Some(target_id))
-    }
+fn qr_png(content: &str) -> Result<Vec<u8>, String> {
+    let code = QrCode::new(content.as_bytes()).map_err(|e| format!("生成二维码失败: {}", e))?;
+    let image = code.render::<Luma<u8>>().min_dimensions(240, 240).build();
+    let mut png = Vec::new();
+    image
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .map_err(|e| format!("转换图片失败: {}", e))?;
+    Ok(png)
+}
+
+// 可直接嵌入 Markdown、HTML 的 PNG 二维码
+fn qr_data_uri(content: &str) -> Result<String, String> {
+    Ok(format!(
+        "data:image/png;base64,{}",
+        STANDARD.encode(qr_png(content)?)
+    ))
 }
@@ -1,8 +1,13 @@
+use crate::log_record::{LogFilter, LogRecord};
+use crate::paths;
+use crate::redact;
+use crate::task_log;
 use log::{Level, LevelFilter, Metadata, Record, SetLoggerError};
 use once_cell::sync::Lazy;
+use std::collections::VecDeque;
 use std::fs::{self, File, OpenOptions};
 use std::io::Write;
-use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 
 // 日志文件处理相关内容
@@ -10,18 +15,35 @@ lazy_static::lazy_static! {
     static ref LOG_FILE: Mutex<Option<(String, File)>> = Mutex::new(None);
 }
 
+// 日志文件按 JSON 行写入，可设置环境变量 BTR_LOG_JSON=1 或调用 set_json_file(true)
+static JSON_FILE: Lazy<AtomicBool> = Lazy::new(|| {
+    AtomicBool::new(std::env::var("BTR_LOG_JSON").is_ok_and(|v| v == "1" || v == "true"))
+});
+
+pub fn set_json_file(enabled: bool) {
+    JSON_FILE.store(enabled, Ordering::Relaxed);
+}
+
+fn json_file() -> bool {
+    JSON_FILE.load(Ordering::Relaxed)
+}
+
 // 创建新的日志文件
 fn create_log_file() -> Option<(String, File)> {
     // 确保日志目录存在
-    let log_dir = Path::new("Log");
-    if let Err(e) = fs::create_dir_all(log_dir) {
+    let log_dir = paths::data_path("Log");
+    if let Err(e) = fs::create_dir_all(&log_dir) {
         eprintln!("无法创建日志目录: {}", e);
         return None;
     }
 
     // 创建带有时间戳的文件名
     let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
-    let filename = format!("Log/log_{}.log", timestamp);
+    let extension = if json_file() { "jsonl" } else { "log" };
+    let filename = log_dir
+        .join(format!("log_{}.{}", timestamp, extension))
+        .display()
+        .to_string();
 
     // 打开文件
     match OpenOptions::new()
@@ -41,11 +63,11 @@ fn create_log_file() -> Option<(String, File)> {
 fn write_to_log_file(message: &str) -> bool {
     let mut file_guard = LOG_FILE.lock().unwrap();
 
-    // 检查是否需要创建新的日志文件
+    // 检查是否需要创建新的日志文件（跨天或切换了文件格式）
     let create_new_file = match &*file_guard {
         Some((filename, _)) => {
             let current_date = chrono::Local::now().format("%Y%m%d").to_string();
-            !filename.contains(&current_date)
+            !filename.contains(&current_date) || filename.ends_with(".jsonl") != json_file()
         }
         None => true,
     };
@@ -151,6 +173,58 @@ where
     }
 }
 
+type RecordListener = Box<dyn Fn(&LogRecord) + Send + Sync>;
+static RECORD_LISTENERS: Lazy<Mutex<Vec<RecordListener>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+// 接收结构化日志，供界面按任务、阶段等字段过滤
+pub fn add_record_listener<F>(f: F)
+where
+    F: Fn(&LogRecord) + Send + Sync + 'static,
+{
+    if let Ok(mut listeners) = RECORD_LISTENERS.lock() {
+        listeners.push(Box::new(f));
+    }
+}
+
+const MAX_RECORDS: usize = 5000;
+
+// 最近的结构化日志
+static LOG_RECORDS: Lazy<Mutex<VecDeque<LogRecord>>> =
+    Lazy::new(|| Mutex::new(VecDeque::with_capacity(MAX_RECORDS)));
+
+fn push_record(record: LogRecord) {
+    if let Ok(mut records) = LOG_RECORDS.try_lock() {
+        if records.len() >= MAX_RECORDS {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+}
+
+// 按条件查询最近的结构化日志，limit 表示只取最后若干条
+pub fn query_records(filter: &LogFilter) -> Vec<LogRecord> {
+    let Ok(records) = LOG_RECORDS.lock() else {
+        return Vec::new();
+    };
+    let mut matched: Vec<LogRecord> = records
+        .iter()
+        .filter(|record| filter.matches(record))
+        .cloned()
+        .collect();
+    if let Some(limit) = filter.limit
+        && matched.len() > limit
+    {
+        matched.drain(0..matched.len() - limit);
+    }
+    matched
+}
+
+pub fn clear_records() {
+    if let Ok(mut records) = LOG_RECORDS.lock() {
+        records.clear();
+    }
+}
+
 pub static LOG_COLLECTOR: Lazy<Arc<Mutex<LogCollector>>> =
     Lazy::new(|| Arc::new(Mutex::new(LogCollector::new())));
 
@@ -165,8 +239,14 @@ impl log::Log for CollectorLogger {
 
     fn log(&self, record: &Record) {
         if self.enabled(record.metadata()) {
-            let timestamp = chrono::Local::now().format("%Y-%m-%d %H:%M:%S:%3f");
-            let log_message = format!("[{}] {}: {}", timestamp, record.level(), record.args());
+            let timestamp = chrono::Local::now()
+                .format("%Y-%m-%d %H:%M:%S:%3f")
+                .to_string();
+            // 先脱敏，之后写入收集器、监听器和文件的都是脱敏后的内容
+            let args_str = record.args().to_string();
+            let args_str = redact::redact(&args_str).into_owned();
+            let log_record = LogRecord::from_log(record, timestamp, args_str);
+            let log_message = log_record.to_line();
 
             {
                 if let Ok(mut collector) = LOG_COLLECTOR.try_lock() {
@@ -175,33 +255,11 @@ impl log::Log for CollectorLogger {
                 }
             }
 
-            // 检查是否为抢票相关日志
-            let args_str = record.args().to_string();
-            if args_str.contains("抢票")
-                || args_str.contains("token")
-                || args_str.contains("订单")
-                || args_str.contains("验证码")
-                || args_str.contains("倒计时")
-                || args_str.contains("项目")
-                || args_str.contains("场次")
-                || args_str.contains("购票人")
-                || args_str.contains("开始抢票")
-                || args_str.contains("获取token")
-                || args_str.contains("确认订单")
-                || args_str.contains("下单")
-                || args_str.contains("重试")
-                || args_str.contains("失败")
-                || args_str.contains("成功")
-                || args_str.contains("距离抢票时间")
-                || args_str.contains("获取购票人信息")
-                || args_str.contains("获取项目详情")
-                || args_str.contains("二维码")
-                || args_str.contains("短信")
-                || args_str.contains("登录")
+            // 按分类收集抢票相关日志
+            if log_record.is_grab_related()
+                && let Ok(mut grab_collector) = GRAB_LOG_COLLECTOR.try_lock()
             {
-                if let Ok(mut grab_collector) = GRAB_LOG_COLLECTOR.try_lock() {
-                    grab_collector.add(log_message.clone());
-                }
+                grab_collector.add(log_message.clone());
             }
 
             println!("{}", log_message);
@@ -212,9 +270,20 @@ impl log::Log for CollectorLogger {
                     listener(&log_message);
                 }
             }
+            if let Ok(listeners) = RECORD_LISTENERS.lock() {
+                for listener in listeners.iter() {
+                    listener(&log_record);
+                }
+            }
 
             // 单独处理文件写入，避免同时持有多个锁
-            let _ = write_to_log_file(&log_message);
+            if json_file() {
+                let _ = write_to_log_file(&log_record.to_json());
+            } else {
+                let _ = write_to_log_file(&log_message);
+            }
+            task_log::append(&log_record);
+            push_record(log_record);
         }
     }
 
@@ -0,0 +1,88 @@
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// 日志与 Debug 输出脱敏：Cookie、CSRF、各类 token、手机号、身份证号
+// 排查问题确实需要原文时，可设置环境变量 BTR_UNSAFE_DEBUG=1 或调用 set_unsafe_debug(true)
+
+static UNSAFE_DEBUG: Lazy<AtomicBool> = Lazy::new(|| {
+    AtomicBool::new(std::env::var("BTR_UNSAFE_DEBUG").is_ok_and(|v| v == "1" || v == "true"))
+});
+
+pub fn set_unsafe_debug(enabled: bool) {
+    UNSAFE_DEBUG.store(enabled, Ordering::Relaxed);
+    if enabled {
+        log::warn!("已开启不安全调试模式，日志中将包含 Cookie、token 等敏感信息，请勿外传");
+    }
+}
+
+pub fn unsafe_debug() -> bool {
+    UNSAFE_DEBUG.load(Ordering::Relaxed)
+}
+
+// 敏感 Cookie 与参数名后的值：SESSDATA=xxx、"token":"xxx"、bark_token: "xxx"、csrf=xxx
+static SECRET_VALUE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r#"(?i)((?:SESSDATA|bili_jct|DedeUserID__ckMd5|sid|buvid3|buvid4|_uuid|bili_ticket|ac_time_value|refresh_token|access_key|[a-z_]*token|csrf|cookie|secret|password|sign)"?\s*(?:=|:)\s*(?:Some\()?"?)([^"&;,\s})]+)"#,
+    )
+    .unwrap()
+});
+
+// 18 位身份证号（中文与数字之间没有 \b，所以用非数字做边界）
+static ID_NUMBER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(^|\D)(\d{3})\d{11}(\d{3}[\dXx])(\D|$)").unwrap());
+
+// 大陆手机号
+static PHONE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(^|\D)(1[3-9]\d)\d{4}(\d{4})(\D|$)").unwrap());
+
+// 对任意文本脱敏，开启不安全调试时原样返回
+pub fn redact(text: &str) -> Cow<'_, str> {
+    if unsafe_debug() {
+        return Cow::Borrowed(text);
+    }
+    redact_always(text)
+}
+
+pub fn redact_always(text: &str) -> Cow<'_, str> {
+    let text = SECRET_VALUE.replace_all(text, |c: &Captures| format!("{}***", &c[1]));
+    let text = replace_pii(text, &ID_NUMBER, "${1}${2}***********${3}${4}");
+    replace_pii(text, &PHONE, "${1}${2}****${3}${4}")
+}
+
+// 边界字符会被匹配吃掉，相邻的号码需要再替换一轮；替换后的内容含 * 不会再次匹配
+fn replace_pii<'a>(mut text: Cow<'a, str>, pattern: &Regex, replacement: &str) -> Cow<'a, str> {
+    while pattern.is_match(&text) {
+        text = Cow::Owned(pattern.replace_all(&text, replacement).into_owned());
+    }
+    text
+}
+
+// Debug 输出中的敏感字段，只保留长度
+pub fn secret(value: &str) -> String {
+    if unsafe_debug() || value.is_empty() {
+        value.to_string()
+    } else {
+        format!("<已隐藏 {} 字符>", value.chars().count())
+    }
+}
+
+// Debug 输出中的个人信息（手机号、证件号），保留首尾几位
+pub fn pii(value: &str) -> String {
+    if unsafe_debug() {
+        return value.to_string();
+    }
+    let chars: Vec<char> = value.chars().collect();
+    if chars.len() <= 4 {
+        return "*".repeat(chars.len());
+    }
+    let keep = if chars.len() >= 11 { 3 } else { 1 };
+    let tail = if chars.len() >= 11 { 4 } else { 1 };
+    format!(
+        "{}{}{}",
+        chars[..keep].iter().collect::<String>(),
+        "*".repeat(chars.len() - keep - tail),
+        chars[chars.len() - tail..].iter().collect::<String>()
+    )
+}
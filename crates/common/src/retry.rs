@@ -0,0 +1,257 @@
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::ticket_error::{ErrorClass, TicketApiError};
+
+// 抢票流程各阶段的重试策略：退避曲线、随机抖动、最大次数、总时限，以及按错误码覆盖的间隔和处理方式。
+// 最大次数和基础间隔留空时沿用 CustomConfig 中原有的 max_*_retry、retry_interval_ms 设置（下单阶段见 CustomConfig::order_retry_policy）
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryStage {
+    Token,     //获取 token
+    Confirm,   //确认订单
+    Order,     //下单
+    FakeCheck, //假票检测
+    Refresh,   //捡漏模式刷新项目
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backoff {
+    #[default]
+    Fixed, //每次都是基础间隔
+    Linear,      //基础间隔 × 第几次重试
+    Exponential, //基础间隔 × 2^(第几次重试 - 1)
+}
+
+// 某个错误码的特殊处理
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CodeOverride {
+    pub code: i64,
+    #[serde(default)]
+    pub delay_ms: Option<u64>, //该错误码的重试间隔，不参与退避
+    #[serde(default)]
+    pub class: Option<ErrorClass>, //改变该错误码的处理方式，如把停售当作可重试
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    pub max_attempts: Option<u32>, //0 表示不限
+    pub base_delay_ms: Option<u64>,
+    pub backoff: Backoff,
+    pub max_delay_ms: u64, //退避后的间隔上限，0 表示不限
+    pub jitter_ms: u64,    //在间隔上随机增加 0~jitter_ms 毫秒
+    pub deadline_ms: u64,  //该阶段从第一次尝试起的总时限，0 表示不限
+    pub overrides: Vec<CodeOverride>,
+}
+
+impl RetryPolicy {
+    fn with_delay(base_delay_ms: u64) -> Self {
+        Self {
+            base_delay_ms: Some(base_delay_ms),
+            ..Self::default()
+        }
+    }
+
+    fn override_for(&self, code: i64) -> Option<&CodeOverride> {
+        self.overrides.iter().find(|o| o.code == code)
+    }
+
+    // 错误的处理方式，优先使用错误码覆盖
+    pub fn classify(&self, error: TicketApiError) -> ErrorClass {
+        self.override_for(error.code())
+            .and_then(|o| o.class)
+            .unwrap_or_else(|| error.class())
+    }
+
+    // 第 attempt 次失败后的等待时间（attempt 从 1 开始）
+    pub fn delay(&self, attempt: u32, code: Option<i64>) -> Duration {
+        let base = self.base_delay_ms.unwrap_or(0);
+        let delay = match code.and_then(|c| self.override_for(c)?.delay_ms) {
+            Some(delay) => delay,
+            None => {
+                let step = attempt.max(1) as u64;
+                let delay = match self.backoff {
+                    Backoff::Fixed => base,
+                    Backoff::Linear => base.saturating_mul(step),
+                    Backoff::Exponential => base.saturating_mul(1u64 << (step - 1).min(32)),
+                };
+                if self.max_delay_ms > 0 {
+                    delay.min(self.max_delay_ms)
+                } else {
+                    delay
+                }
+            }
+        };
+        let jitter = if self.jitter_ms > 0 {
+            rand::thread_rng().gen_range(0..=self.jitter_ms)
+        } else {
+            0
+        };
+        Duration::from_millis(delay + jitter)
+    }
+}
+
+// 配置文件中每个阶段只需写要改的字段，未写的字段（包括内置的错误码覆盖）保留该阶段的默认值
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "RetryPoliciesOverlay")]
+pub struct RetryPolicies {
+    pub token: RetryPolicy,
+    pub confirm: RetryPolicy,
+    pub order: RetryPolicy,
+    pub fake_check: RetryPolicy,
+    pub refresh: RetryPolicy,
+}
+
+impl Default for RetryPolicies {
+    fn default() -> Self {
+        Self {
+            token: RetryPolicy::with_delay(1000),
+            confirm: RetryPolicy::with_delay(300),
+            order: RetryPolicy {
+                overrides: vec![
+                    CodeOverride {
+                        code: TicketApiError::SoldOut.code(),
+                        delay_ms: Some(600),
+                        class: None,
+                    },
+                    CodeOverride {
+                        code: TicketApiError::TooFast.code(),
+                        delay_ms: Some(4800),
+                        class: None,
+                    },
+                ],
+                ..RetryPolicy::default()
+            },
+            fake_check: RetryPolicy::with_delay(500),
+            refresh: RetryPolicy {
+                max_attempts: Some(0),
+                ..RetryPolicy::with_delay(2000)
+            },
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct RetryPolicyOverlay {
+    max_attempts: Option<u32>,
+    base_delay_ms: Option<u64>,
+    backoff: Option<Backoff>,
+    max_delay_ms: Option<u64>,
+    jitter_ms: Option<u64>,
+    deadline_ms: Option<u64>,
+    overrides: Vec<CodeOverride>,
+}
+
+impl RetryPolicyOverlay {
+    // 错误码覆盖按错误码合并，同一错误码以配置为准
+    fn apply(self, policy: &mut RetryPolicy) {
+        if self.max_attempts.is_some() {
+            policy.max_attempts = self.max_attempts;
+        }
+        if self.base_delay_ms.is_some() {
+            policy.base_delay_ms = self.base_delay_ms;
+        }
+        if let Some(backoff) = self.backoff {
+            policy.backoff = backoff;
+        }
+        if let Some(max_delay_ms) = self.max_delay_ms {
+            policy.max_delay_ms = max_delay_ms;
+        }
+        if let Some(jitter_ms) = self.jitter_ms {
+            policy.jitter_ms = jitter_ms;
+        }
+        if let Some(deadline_ms) = self.deadline_ms {
+            policy.deadline_ms = deadline_ms;
+        }
+        for code_override in self.overrides {
+            policy.overrides.retain(|o| o.code != code_override.code);
+            policy.overrides.push(code_override);
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct RetryPoliciesOverlay {
+    token: RetryPolicyOverlay,
+    confirm: RetryPolicyOverlay,
+    order: RetryPolicyOverlay,
+    fake_check: RetryPolicyOverlay,
+    refresh: RetryPolicyOverlay,
+}
+
+impl From<RetryPoliciesOverlay> for RetryPolicies {
+    fn from(overlay: RetryPoliciesOverlay) -> Self {
+        let mut policies = RetryPolicies::default();
+        overlay.token.apply(&mut policies.token);
+        overlay.confirm.apply(&mut policies.confirm);
+        overlay.order.apply(&mut policies.order);
+        overlay.fake_check.apply(&mut policies.fake_check);
+        overlay.refresh.apply(&mut policies.refresh);
+        policies
+    }
+}
+
+impl RetryPolicies {
+    pub fn get(&self, stage: RetryStage) -> &RetryPolicy {
+        match stage {
+            RetryStage::Token => &self.token,
+            RetryStage::Confirm => &self.confirm,
+            RetryStage::Order => &self.order,
+            RetryStage::FakeCheck => &self.fake_check,
+            RetryStage::Refresh => &self.refresh,
+        }
+    }
+}
+
+// 一个阶段的重试计数，失败时给出下一次的等待时间，次数或时限用尽时返回 None
+#[derive(Clone, Debug)]
+pub struct RetryState {
+    policy: RetryPolicy,
+    attempts: u32,
+    started: Instant,
+}
+
+impl RetryState {
+    pub fn new(policy: RetryPolicy) -> Self {
+        Self {
+            policy,
+            attempts: 0,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    pub fn reset(&mut self) {
+        self.attempts = 0;
+        self.started = Instant::now();
+    }
+
+    pub fn failed(&mut self, code: Option<i64>) -> Option<Duration> {
+        self.attempts += 1;
+        let max_attempts = self.policy.max_attempts.unwrap_or(0);
+        if max_attempts > 0 && self.attempts >= max_attempts {
+            return None;
+        }
+        let delay = self.policy.delay(self.attempts, code);
+        if self.policy.deadline_ms > 0
+            && self.started.elapsed() + delay > Duration::from_millis(self.policy.deadline_ms)
+        {
+            return None;
+        }
+        Some(delay)
+    }
+}
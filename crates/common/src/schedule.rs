@@ -0,0 +1,382 @@
+use crate::paths;
+use crate::task_journal::JournalEntry;
+use crate::vault;
+use chrono::Local;
+use fs2::FileExt;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+// 定时抢票：提前几天设定在某个本地时间或开售前 N 分钟提交抢票任务，
+// 触发前按设定的提前量自动执行预检（刷新项目、校验登录和购票人、测量时钟偏差）。
+// 任务列表保存在 schedule.json，抢票参数与任务日志相同，同样按配置加密
+
+static SCHEDULE_PATH: Lazy<Mutex<PathBuf>> =
+    Lazy::new(|| Mutex::new(paths::data_path("schedule.json")));
+static WRITE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+// 解析后的任务列表缓存，文件的修改时间和大小不变时直接复用，
+// 避免调度每秒检查时都重新读取和解密（口令加密时每次都要跑 argon2）
+struct CachedJobs {
+    path: PathBuf,
+    stamp: (SystemTime, u64),
+    jobs: Vec<ScheduledJob>,
+}
+
+static CACHE: Lazy<Mutex<Option<CachedJobs>>> = Lazy::new(|| Mutex::new(None));
+
+// 修改定时任务文件位置（测试或自定义数据目录时使用）
+pub fn set_schedule_path(path: impl Into<PathBuf>) {
+    if let Ok(mut current) = SCHEDULE_PATH.lock() {
+        *current = path.into();
+    }
+}
+
+pub fn schedule_path() -> PathBuf {
+    SCHEDULE_PATH
+        .lock()
+        .map(|p| p.clone())
+        .unwrap_or_else(|_| paths::data_path("schedule.json"))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreflightStep {
+    RefreshProject,
+    VerifyAccount,
+    SyncClock,
+}
+
+impl PreflightStep {
+    pub const ALL: [PreflightStep; 3] = [
+        PreflightStep::RefreshProject,
+        PreflightStep::VerifyAccount,
+        PreflightStep::SyncClock,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PreflightStep::RefreshProject => "刷新项目",
+            PreflightStep::VerifyAccount => "校验登录和购票人",
+            PreflightStep::SyncClock => "测量时钟偏差",
+        }
+    }
+}
+
+// 各预检步骤在触发前多少秒执行，0 表示不执行
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreflightOffsets {
+    #[serde(default)]
+    pub refresh_project: u64,
+    #[serde(default)]
+    pub verify_account: u64,
+    #[serde(default)]
+    pub sync_clock: u64,
+}
+
+impl Default for PreflightOffsets {
+    fn default() -> Self {
+        Self {
+            refresh_project: 600,
+            verify_account: 300,
+            sync_clock: 60,
+        }
+    }
+}
+
+impl PreflightOffsets {
+    pub fn offset(&self, step: PreflightStep) -> u64 {
+        match step {
+            PreflightStep::RefreshProject => self.refresh_project,
+            PreflightStep::VerifyAccount => self.verify_account,
+            PreflightStep::SyncClock => self.sync_clock,
+        }
+    }
+}
+
+// 触发时间：指定的本地时间，或开售前 N 分钟（开售时间取自项目详情）
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScheduleTime {
+    At { time: i64 }, //毫秒时间戳
+    BeforeSale { minutes: i64 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Scheduled,
+    Submitted,
+    Failed,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PreflightRecord {
+    pub step: PreflightStep,
+    pub success: bool,
+    pub message: String,
+    pub time: i64, //毫秒时间戳
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScheduledJob {
+    pub id: String,
+    pub grab: JournalEntry,
+    pub when: ScheduleTime,
+    #[serde(default)]
+    pub preflight: PreflightOffsets,
+    pub status: JobStatus,
+    #[serde(default)]
+    pub message: String,
+    #[serde(default)]
+    pub preflight_log: Vec<PreflightRecord>,
+    #[serde(default)]
+    pub clock_offset_ms: i64, //服务器时间 - 本地时间
+    #[serde(default)]
+    pub task_id: Option<String>,
+    pub created_at: i64,
+}
+
+impl ScheduledJob {
+    pub fn new(grab: JournalEntry, when: ScheduleTime, preflight: PreflightOffsets) -> Self {
+        let now = Local::now().timestamp_millis();
+        Self {
+            id: format!("job-{}-{}", grab.uid, now),
+            grab,
+            when,
+            preflight,
+            status: JobStatus::Scheduled,
+            message: String::new(),
+            preflight_log: Vec::new(),
+            clock_offset_ms: 0,
+            task_id: None,
+            created_at: now,
+        }
+    }
+
+    // 触发时间（毫秒），开售前 N 分钟且还没有项目详情时为 None
+    pub fn fire_at(&self) -> Option<i64> {
+        match self.when {
+            ScheduleTime::At { time } => Some(time),
+            ScheduleTime::BeforeSale { minutes } => {
+                let sale_begin = self.grab.project_info.as_ref()?.sale_begin;
+                let sale_begin_ms = if sale_begin > 10_000_000_000 {
+                    sale_begin
+                } else {
+                    sale_begin * 1000
+                };
+                Some(sale_begin_ms - minutes * 60_000)
+            }
+        }
+    }
+
+    // 开售时间以服务器时间为准，按测得的偏差换算；指定的本地时间直接按本地时钟
+    fn clock(&self, now_ms: i64) -> i64 {
+        match self.when {
+            ScheduleTime::At { .. } => now_ms,
+            ScheduleTime::BeforeSale { .. } => now_ms + self.clock_offset_ms,
+        }
+    }
+
+    pub fn is_due(&self, now_ms: i64) -> bool {
+        self.status == JobStatus::Scheduled
+            && self.fire_at().is_some_and(|at| self.clock(now_ms) >= at)
+    }
+
+    // 已到执行时间且还没执行过的预检步骤
+    pub fn due_preflight(&self, now_ms: i64) -> Vec<PreflightStep> {
+        if self.status != JobStatus::Scheduled {
+            return Vec::new();
+        }
+        // 没有开售时间时先刷新项目，失败后每分钟重试
+        let Some(fire_at) = self.fire_at() else {
+            let retry = self
+                .preflight_log
+                .iter()
+                .all(|r| r.step != PreflightStep::RefreshProject || now_ms - r.time >= 60_000);
+            return if retry {
+                vec![PreflightStep::RefreshProject]
+            } else {
+                Vec::new()
+            };
+        };
+        // 提前量之前的记录（如创建时补取项目详情）不算
+        PreflightStep::ALL
+            .into_iter()
+            .filter(|step| {
+                let offset = self.preflight.offset(*step);
+                let window = fire_at - offset as i64 * 1000;
+                offset > 0
+                    && self.clock(now_ms) >= window
+                    && !self
+                        .preflight_log
+                        .iter()
+                        .any(|r| r.step == *step && self.clock(r.time) >= window)
+            })
+            .collect()
+    }
+
+    pub fn record_preflight(&mut self, step: PreflightStep, result: Result<String, String>) {
+        let (success, message) = match result {
+            Ok(message) => (true, message),
+            Err(message) => (false, message),
+        };
+        self.message = format!("{}: {}", step.label(), message);
+        self.preflight_log.push(PreflightRecord {
+            step,
+            success,
+            message,
+            time: Local::now().timestamp_millis(),
+        });
+    }
+
+    // 修改触发时间和预检提前量，已提交的任务不能再修改
+    pub fn reschedule(
+        &mut self,
+        when: ScheduleTime,
+        preflight: PreflightOffsets,
+    ) -> Result<(), String> {
+        if self.status == JobStatus::Submitted {
+            return Err("定时任务已提交，无法修改".to_string());
+        }
+        self.when = when;
+        self.preflight = preflight;
+        self.status = JobStatus::Scheduled;
+        self.message.clear();
+        self.preflight_log.clear();
+        Ok(())
+    }
+}
+
+// 全部定时任务，按创建时间排序
+pub fn load() -> Result<Vec<ScheduledJob>, String> {
+    let path = schedule_path();
+    let Some(stamp) = file_stamp(&path) else {
+        return Ok(Vec::new());
+    };
+    if let Ok(cache) = CACHE.lock()
+        && let Some(cached) = cache.as_ref()
+        && cached.path == path
+        && cached.stamp == stamp
+    {
+        return Ok(cached.jobs.clone());
+    }
+    let jobs = read_jobs(&path)?;
+    remember(path, stamp, &jobs);
+    Ok(jobs)
+}
+
+fn read_jobs(path: &Path) -> Result<Vec<ScheduledJob>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let raw = vault::read_config(path).map_err(|e| format!("读取定时任务失败: {}", e))?;
+    let mut jobs: Vec<ScheduledJob> =
+        serde_json::from_str(&raw).map_err(|e| format!("解析定时任务失败: {}", e))?;
+    jobs.sort_by_key(|job| job.created_at);
+    Ok(jobs)
+}
+
+fn file_stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+fn remember(path: PathBuf, stamp: (SystemTime, u64), jobs: &[ScheduledJob]) {
+    if let Ok(mut cache) = CACHE.lock() {
+        *cache = Some(CachedJobs {
+            path,
+            stamp,
+            jobs: jobs.to_vec(),
+        });
+    }
+}
+
+pub fn find(id: &str) -> Result<ScheduledJob, String> {
+    load()?
+        .into_iter()
+        .find(|job| job.id == id)
+        .ok_or_else(|| format!("没有找到定时任务 {}", id))
+}
+
+pub fn add(job: ScheduledJob) -> Result<(), String> {
+    update(|jobs| {
+        jobs.retain(|j| j.id != job.id);
+        jobs.push(job);
+        Ok(())
+    })
+}
+
+pub fn remove(id: &str) -> Result<(), String> {
+    update(|jobs| {
+        let before = jobs.len();
+        jobs.retain(|job| job.id != id);
+        if jobs.len() == before {
+            return Err(format!("没有找到定时任务 {}", id));
+        }
+        Ok(())
+    })
+}
+
+// 在写锁内重新读取文件、修改一个任务并保存，返回修改后的任务。
+// 写锁跨进程生效，调度用它认领到期任务，图形界面和 cli schedule run 同时运行时只有一方能提交
+pub fn modify(
+    id: &str,
+    change: impl FnOnce(&mut ScheduledJob) -> Result<(), String>,
+) -> Result<ScheduledJob, String> {
+    let mut updated = None;
+    update(|jobs| {
+        let job = jobs
+            .iter_mut()
+            .find(|job| job.id == id)
+            .ok_or_else(|| format!("没有找到定时任务 {}", id))?;
+        change(job)?;
+        updated = Some(job.clone());
+        Ok(())
+    })?;
+    updated.ok_or_else(|| format!("没有找到定时任务 {}", id))
+}
+
+fn update(change: impl FnOnce(&mut Vec<ScheduledJob>) -> Result<(), String>) -> Result<(), String> {
+    let _guard = WRITE_LOCK.lock().map_err(|e| e.to_string())?;
+    let path = schedule_path();
+    let _file_lock = lock_file(&path)?;
+    let mut jobs = read_jobs(&path)?;
+    change(&mut jobs)?;
+    save(&path, &jobs)?;
+    if let Some(stamp) = file_stamp(&path) {
+        remember(path, stamp, &jobs);
+    }
+    Ok(())
+}
+
+// 对 schedule.json 旁的锁文件加排他锁（文件本身保存时会被替换），锁随返回的文件关闭释放
+fn lock_file(path: &Path) -> Result<fs::File, String> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let lock_path = path.with_extension("lock");
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .map_err(|e| format!("打开 {} 失败: {}", lock_path.display(), e))?;
+    file.lock_exclusive()
+        .map_err(|e| format!("锁定 {} 失败: {}", lock_path.display(), e))?;
+    Ok(file)
+}
+
+fn save(path: &Path, jobs: &[ScheduledJob]) -> Result<(), String> {
+    if jobs.is_empty() {
+        return match fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+            _ => Ok(()),
+        };
+    }
+    let json = serde_json::to_string_pretty(jobs).map_err(|e| e.to_string())?;
+    vault::write_sealed(path, &json)
+}
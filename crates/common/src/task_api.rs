@@ -0,0 +1,152 @@
+use crate::local_http::{self, Request, Response};
+use crate::paths;
+use crate::taskmanager::TaskControl;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+// 本地任务接口：供 cli tasks 查看和取消本进程中的任务。
+// 只监听 127.0.0.1 的随机端口，请求必须在 X-BTR-Token 头中带上本进程启动时生成的随机令牌。
+// 地址和令牌写入数据目录下的 task_api/<进程号>.json（仅当前用户可读），与指标接口相互独立
+
+pub const TOKEN_HEADER: &str = "X-BTR-Token";
+
+static ENDPOINT_DIR: Lazy<Mutex<PathBuf>> = Lazy::new(|| Mutex::new(paths::data_path("task_api")));
+
+// 修改接口信息目录（测试或自定义数据目录时使用）
+pub fn set_endpoint_dir(dir: impl Into<PathBuf>) {
+    if let Ok(mut current) = ENDPOINT_DIR.lock() {
+        *current = dir.into();
+    }
+}
+
+pub fn endpoint_dir() -> PathBuf {
+    ENDPOINT_DIR
+        .lock()
+        .map(|p| p.clone())
+        .unwrap_or_else(|_| paths::data_path("task_api"))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskApiEndpoint {
+    pub addr: String,
+    pub token: String,
+    pub pid: u32,
+}
+
+impl TaskApiEndpoint {
+    fn path(&self) -> PathBuf {
+        endpoint_dir().join(format!("{}.json", self.pid))
+    }
+
+    // 删除接口信息文件（程序退出或接口已不可用时）
+    pub fn remove(&self) {
+        let _ = fs::remove_file(self.path());
+    }
+}
+
+// 启动接口并写入接口信息文件
+pub fn start(control: Arc<dyn TaskControl>) -> Result<TaskApiEndpoint, String> {
+    let listener =
+        TcpListener::bind("127.0.0.1:0").map_err(|e| format!("本地任务接口监听失败: {}", e))?;
+    let addr = listener.local_addr().map_err(|e| e.to_string())?;
+    let endpoint = TaskApiEndpoint {
+        addr: addr.to_string(),
+        token: uuid::Uuid::new_v4().simple().to_string(),
+        pid: std::process::id(),
+    };
+    let token = endpoint.token.clone();
+    local_http::serve(listener, "task-api", move |request| {
+        handle_request(request, &token, control.as_ref())
+    })?;
+    write_endpoint(&endpoint)?;
+    log::debug!("本地任务接口已启动: {}", endpoint.addr);
+    Ok(endpoint)
+}
+
+// 列出正在运行的程序（图形界面、cli grab 等）的接口信息
+pub fn discover() -> Vec<TaskApiEndpoint> {
+    let Ok(entries) = fs::read_dir(endpoint_dir()) else {
+        return Vec::new();
+    };
+    let mut endpoints: Vec<TaskApiEndpoint> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .filter_map(|raw| serde_json::from_str(&raw).ok())
+        .collect();
+    endpoints.sort_by_key(|e| e.pid);
+    endpoints
+}
+
+fn write_endpoint(endpoint: &TaskApiEndpoint) -> Result<(), String> {
+    let path = endpoint.path();
+    fs::create_dir_all(endpoint_dir()).map_err(|e| format!("创建目录失败: {}", e))?;
+    // 先删除旧文件（进程号可能被复用），确保新建文件的权限生效
+    let _ = fs::remove_file(&path);
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(&path)
+        .map_err(|e| format!("写入 {} 失败: {}", path.display(), e))?;
+    let content = serde_json::to_string(endpoint).map_err(|e| e.to_string())?;
+    file.write_all(content.as_bytes())
+        .map_err(|e| format!("写入 {} 失败: {}", path.display(), e))
+}
+
+// 逐字节比较完，避免按耗时猜测令牌
+fn token_matches(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+// GET /tasks 列出任务，POST /tasks/<任务ID>/cancel 取消任务
+fn handle_request(request: &Request, token: &str, control: &dyn TaskControl) -> Response {
+    if !request
+        .header(TOKEN_HEADER)
+        .is_some_and(|given| token_matches(given, token))
+    {
+        return Response::json(
+            "401 Unauthorized",
+            serde_json::json!({ "success": false, "message": "令牌无效" }).to_string(),
+        );
+    }
+    match (request.method.as_str(), request.path.trim_end_matches('/')) {
+        ("GET", "/tasks") => Response::json(
+            "200 OK",
+            serde_json::to_string(&control.list_tasks()).unwrap_or_else(|_| "[]".to_string()),
+        ),
+        ("POST", path) => {
+            let Some(task_id) = path
+                .strip_prefix("/tasks/")
+                .and_then(|p| p.strip_suffix("/cancel"))
+            else {
+                return Response::not_found();
+            };
+            match control.cancel_task(task_id) {
+                Ok(()) => {
+                    Response::json("200 OK", serde_json::json!({ "success": true }).to_string())
+                }
+                Err(e) => Response::json(
+                    "409 Conflict",
+                    serde_json::json!({ "success": false, "message": e }).to_string(),
+                ),
+            }
+        }
+        _ => Response::not_found(),
+    }
+}
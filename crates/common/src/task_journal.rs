@@ -0,0 +1,274 @@
+use crate::account::Account;
+use crate::captcha::LocalCaptcha;
+use crate::config::{CustomConfig, PushConfig};
+use crate::paths;
+use crate::taskmanager::{GrabTicketRequest, TaskStatus};
+use crate::ticket::{BilibiliTicket, BuyerInfo, NoBindBuyerInfo, TicketInfo, TicketPreference};
+use crate::vault;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, mpsc};
+use std::thread;
+use std::time::Duration;
+
+// 抢票任务日志：提交时写入 task_journal.json，任务结束时移除，
+// 程序或电脑重启后可据此恢复未结束的任务。
+// 只保存账号 UID，恢复时用配置中的账号重新创建 cookie 管理器；
+// 文件包含购票人信息，配置已加密时按同样方式加密
+
+static JOURNAL_PATH: Lazy<Mutex<PathBuf>> =
+    Lazy::new(|| Mutex::new(paths::data_path("task_journal.json")));
+static WRITE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+// 修改任务日志文件位置（测试或自定义数据目录时使用）
+pub fn set_journal_path(path: impl Into<PathBuf>) {
+    if let Ok(mut current) = JOURNAL_PATH.lock() {
+        *current = path.into();
+    }
+}
+
+pub fn journal_path() -> PathBuf {
+    JOURNAL_PATH
+        .lock()
+        .map(|p| p.clone())
+        .unwrap_or_else(|_| paths::data_path("task_journal.json"))
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub task_id: String,
+    pub uid: i64,
+    pub project_id: String,
+    pub screen_id: String,
+    pub ticket_id: String,
+    pub count: i16,
+    pub grab_mode: u8,
+    pub is_hot: bool,
+    pub id_bind: usize,
+    pub buyer_info: Vec<BuyerInfo>,
+    #[serde(default)]
+    pub no_bind_buyer_info: Option<NoBindBuyerInfo>,
+    #[serde(default)]
+    pub project_info: Option<TicketInfo>,
+    #[serde(default)]
+    pub skip_words: Option<Vec<String>>,
+    #[serde(default)]
+    pub preferences: Vec<TicketPreference>,
+    pub ua: String,
+    pub status_delay: usize,
+    pub saved_at: i64, //毫秒时间戳
+}
+
+impl JournalEntry {
+    pub fn from_request(req: &GrabTicketRequest) -> Self {
+        Self {
+            task_id: req.task_id.clone(),
+            uid: req.uid,
+            project_id: req.project_id.clone(),
+            screen_id: req.screen_id.clone(),
+            ticket_id: req.ticket_id.clone(),
+            count: req.count,
+            grab_mode: req.grab_mode,
+            is_hot: req.is_hot,
+            id_bind: req.biliticket.id_bind,
+            buyer_info: req.buyer_info.clone(),
+            no_bind_buyer_info: req.biliticket.no_bind_buyer_info.clone(),
+            project_info: req.biliticket.project_info.clone(),
+            skip_words: req.skip_words.clone(),
+            preferences: req.preferences.clone(),
+            ua: req.biliticket.ua.clone(),
+            status_delay: req.biliticket.status_delay,
+            saved_at: chrono::Local::now().timestamp_millis(),
+        }
+    }
+
+    // 项目名称，没有项目详情时显示项目 ID
+    pub fn project_name(&self) -> String {
+        self.project_info
+            .as_ref()
+            .map(|info| info.name.clone())
+            .unwrap_or_else(|| self.project_id.clone())
+    }
+
+    // 用配置中的账号重建抢票请求，沿用原任务 ID；抢票参数使用当前配置
+    pub fn to_request(
+        &self,
+        account: &Account,
+        config: &CustomConfig,
+        push_config: &PushConfig,
+        local_captcha: LocalCaptcha,
+    ) -> Result<GrabTicketRequest, String> {
+        if account.uid != self.uid {
+            return Err(format!("账号不匹配: 任务属于 {}", self.uid));
+        }
+        let cookie_manager = account
+            .cookie_manager
+            .clone()
+            .ok_or_else(|| format!("账号 {} 未初始化，请重新登录", self.uid))?;
+
+        let biliticket = BilibiliTicket {
+            uid: self.uid,
+            method: 0,
+            ua: self.ua.clone(),
+            config: config.clone(),
+            account: account.clone(),
+            push_self: push_config.clone(),
+            status_delay: self.status_delay,
+            captcha_use_type: 0,
+            cookie_manager: Some(cookie_manager.clone()),
+            project_id: self.project_id.clone(),
+            screen_id: self.screen_id.clone(),
+            id_bind: self.id_bind,
+            project_info: self.project_info.clone(),
+            all_buyer_info: None,
+            buyer_info: (!self.buyer_info.is_empty()).then(|| self.buyer_info.clone()),
+            no_bind_buyer_info: self.no_bind_buyer_info.clone(),
+            select_ticket_id: (!self.ticket_id.is_empty()).then(|| self.ticket_id.clone()),
+            pay_money: None,
+            count: Some(self.count as i32),
+            device_id: String::new(),
+        };
+
+        Ok(GrabTicketRequest {
+            task_id: self.task_id.clone(),
+            uid: self.uid,
+            project_id: self.project_id.clone(),
+            screen_id: self.screen_id.clone(),
+            ticket_id: self.ticket_id.clone(),
+            count: self.count,
+            buyer_info: self.buyer_info.clone(),
+            cookie_manager,
+            biliticket,
+            grab_mode: self.grab_mode,
+            status: TaskStatus::Pending,
+            start_time: None,
+            is_hot: self.is_hot,
+            local_captcha,
+            skip_words: self.skip_words.clone(),
+            preferences: self.preferences.clone(),
+        })
+    }
+}
+
+// 全部未结束的任务，按保存时间排序；先等待已提交的修改写入文件
+pub fn load() -> Result<Vec<JournalEntry>, String> {
+    flush();
+    read_entries()
+}
+
+fn read_entries() -> Result<Vec<JournalEntry>, String> {
+    let path = journal_path();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let raw = vault::read_config(&path).map_err(|e| format!("读取任务日志失败: {}", e))?;
+    let mut entries: Vec<JournalEntry> =
+        serde_json::from_str(&raw).map_err(|e| format!("解析任务日志失败: {}", e))?;
+    entries.sort_by_key(|e| e.saved_at);
+    Ok(entries)
+}
+
+pub fn find(task_id: &str) -> Result<JournalEntry, String> {
+    load()?
+        .into_iter()
+        .find(|e| e.task_id == task_id)
+        .ok_or_else(|| format!("没有找到未完成的任务 {}", task_id))
+}
+
+enum WriterMessage {
+    Record(Box<JournalEntry>),
+    Remove(String),
+    Flush(mpsc::Sender<()>),
+}
+
+// 加密配置下每次读写都要派生密钥，由单独的线程按提交顺序改写文件，
+// 提交任务和任务结束时不会阻塞调用方或异步运行时
+static WRITER: Lazy<Option<mpsc::Sender<WriterMessage>>> = Lazy::new(|| {
+    let (tx, rx) = mpsc::channel::<WriterMessage>();
+    let spawned = thread::Builder::new()
+        .name("journal-writer".to_string())
+        .spawn(move || {
+            for message in rx {
+                match message {
+                    WriterMessage::Record(entry) => write_record(&entry),
+                    WriterMessage::Remove(task_id) => write_remove(&task_id),
+                    WriterMessage::Flush(done) => {
+                        let _ = done.send(());
+                    }
+                }
+            }
+        });
+    match spawned {
+        Ok(_) => Some(tx),
+        Err(e) => {
+            log::warn!("启动任务日志写入线程失败，改为直接写入: {}", e);
+            None
+        }
+    }
+});
+
+// 记录或更新一个任务，失败只记日志不影响抢票
+pub fn record(entry: &JournalEntry) {
+    let queued = WRITER.as_ref().is_some_and(|tx| {
+        tx.send(WriterMessage::Record(Box::new(entry.clone())))
+            .is_ok()
+    });
+    if !queued {
+        write_record(entry);
+    }
+}
+
+// 任务结束或放弃恢复时移除
+pub fn remove(task_id: &str) {
+    let queued = WRITER
+        .as_ref()
+        .is_some_and(|tx| tx.send(WriterMessage::Remove(task_id.to_string())).is_ok());
+    if !queued {
+        write_remove(task_id);
+    }
+}
+
+// 等待已提交的修改写入文件
+pub fn flush() {
+    if let Some(tx) = WRITER.as_ref() {
+        let (done_tx, done_rx) = mpsc::channel();
+        if tx.send(WriterMessage::Flush(done_tx)).is_ok() {
+            let _ = done_rx.recv_timeout(Duration::from_secs(5));
+        }
+    }
+}
+
+fn write_record(entry: &JournalEntry) {
+    if let Err(e) = update(|entries| {
+        entries.retain(|e| e.task_id != entry.task_id);
+        entries.push(entry.clone());
+    }) {
+        log::warn!("写入任务日志失败: {}", e);
+    }
+}
+
+fn write_remove(task_id: &str) {
+    if let Err(e) = update(|entries| entries.retain(|e| e.task_id != task_id)) {
+        log::warn!("更新任务日志失败: {}", e);
+    }
+}
+
+fn update(change: impl FnOnce(&mut Vec<JournalEntry>)) -> Result<(), String> {
+    let _guard = WRITE_LOCK.lock().map_err(|e| e.to_string())?;
+    let mut entries = read_entries()?;
+    change(&mut entries);
+    save(&journal_path(), &entries)
+}
+
+fn save(path: &Path, entries: &[JournalEntry]) -> Result<(), String> {
+    if entries.is_empty() {
+        return match fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+            _ => Ok(()),
+        };
+    }
+    let json = serde_json::to_string_pretty(entries).map_err(|e| e.to_string())?;
+    vault::write_sealed(path, &json)
+}
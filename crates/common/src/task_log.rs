@@ -0,0 +1,235 @@
+use crate::log_record::LogRecord;
+use crate::paths;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+// 按任务保存的日志：内存中每个任务一个有界环形缓冲，
+// 同时写入 Log/tasks/<任务ID>.jsonl，命令行可跨进程查看、跟踪和导出
+
+const TASK_LOG_CAPACITY: usize = 2000;
+const MAX_TASK_LOGS: usize = 64;
+// 磁盘上的任务日志文件最多保留的数量和时长，新建任务文件时清理
+const MAX_TASK_LOG_FILES: usize = 200;
+const TASK_LOG_FILE_RETENTION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+static TASK_LOG_DIR: Lazy<Mutex<PathBuf>> =
+    Lazy::new(|| Mutex::new(paths::data_path("Log").join("tasks")));
+
+// 修改任务日志目录（测试或自定义数据目录时使用）
+pub fn set_task_log_dir(dir: impl Into<PathBuf>) {
+    if let Ok(mut current) = TASK_LOG_DIR.lock() {
+        *current = dir.into();
+    }
+}
+
+pub fn task_log_dir() -> PathBuf {
+    TASK_LOG_DIR
+        .lock()
+        .map(|d| d.clone())
+        .unwrap_or_else(|_| paths::data_path("Log").join("tasks"))
+}
+
+// 任务 ID 用作文件名，过滤掉路径字符
+pub fn task_log_path(task_id: &str) -> PathBuf {
+    let name: String = task_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    task_log_dir().join(format!("{}.jsonl", name))
+}
+
+// 默认导出位置：Log/export/task_<任务ID>_<时间>.log
+pub fn default_export_path(task_id: &str) -> PathBuf {
+    let name = task_log_path(task_id)
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+    paths::data_path("Log")
+        .join("export")
+        .join(format!("task_{}_{}.log", name, timestamp))
+}
+
+struct TaskLog {
+    records: VecDeque<LogRecord>,
+    next_seq: u64, //已写入的总条数，用于增量拉取
+    file: Option<File>,
+    updated: Instant,
+}
+
+static TASK_LOGS: Lazy<Mutex<HashMap<String, TaskLog>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// 增量拉取结果，下次从 next_seq 继续
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskLogChunk {
+    pub task_id: String,
+    pub records: Vec<LogRecord>,
+    pub next_seq: u64,
+    pub truncated: bool, //请求的起点已被环形缓冲覆盖
+}
+
+// 由日志系统调用，只收带有任务 ID 的日志
+pub fn append(record: &LogRecord) {
+    let Some(task_id) = record.task_id.as_deref() else {
+        return;
+    };
+    let Ok(mut logs) = TASK_LOGS.lock() else {
+        return;
+    };
+
+    if !logs.contains_key(task_id) && logs.len() >= MAX_TASK_LOGS {
+        // 淘汰最久没有新日志的任务，磁盘文件保留
+        if let Some(oldest) = logs
+            .iter()
+            .min_by_key(|(_, log)| log.updated)
+            .map(|(id, _)| id.clone())
+        {
+            logs.remove(&oldest);
+        }
+    }
+
+    if !logs.contains_key(task_id) {
+        let active: Vec<PathBuf> = logs
+            .keys()
+            .map(|id| task_log_path(id))
+            .chain([task_log_path(task_id)])
+            .collect();
+        prune_files(&task_log_dir(), &active);
+    }
+    let log = logs.entry(task_id.to_string()).or_insert_with(|| TaskLog {
+        records: VecDeque::new(),
+        next_seq: 0,
+        file: open_task_file(task_id),
+        updated: Instant::now(),
+    });
+    if let Some(file) = log.file.as_mut() {
+        let _ = writeln!(file, "{}", record.to_json());
+    }
+    if log.records.len() >= TASK_LOG_CAPACITY {
+        log.records.pop_front();
+    }
+    log.records.push_back(record.clone());
+    log.next_seq += 1;
+    log.updated = Instant::now();
+}
+
+fn open_task_file(task_id: &str) -> Option<File> {
+    let path = task_log_path(task_id);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).ok()?;
+    }
+    OpenOptions::new().create(true).append(true).open(path).ok()
+}
+
+// 删除超过保留时长的任务日志文件，剩余的按修改时间只保留最新的若干个；正在写入和即将打开的文件不删
+fn prune_files(dir: &Path, active: &[PathBuf]) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let now = SystemTime::now();
+    let mut files: Vec<(SystemTime, PathBuf)> = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "jsonl") || active.contains(&path) {
+            continue;
+        }
+        let Ok(modified) = entry.metadata().and_then(|m| m.modified()) else {
+            continue;
+        };
+        let expired = now
+            .duration_since(modified)
+            .is_ok_and(|age| age >= TASK_LOG_FILE_RETENTION);
+        if expired {
+            let _ = fs::remove_file(&path);
+        } else {
+            files.push((modified, path));
+        }
+    }
+    let keep = MAX_TASK_LOG_FILES.saturating_sub(active.len());
+    if files.len() > keep {
+        files.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+        for (_, path) in files.drain(keep..) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+// 获取任务日志中序号不小于 since 的部分；内存中没有时从文件读取
+pub fn fetch(task_id: &str, since: u64) -> Result<TaskLogChunk, String> {
+    if let Ok(logs) = TASK_LOGS.lock()
+        && let Some(log) = logs.get(task_id)
+    {
+        let first_seq = log.next_seq - log.records.len() as u64;
+        let skip = since.saturating_sub(first_seq) as usize;
+        return Ok(TaskLogChunk {
+            task_id: task_id.to_string(),
+            records: log.records.iter().skip(skip).cloned().collect(),
+            next_seq: log.next_seq,
+            truncated: since < first_seq,
+        });
+    }
+
+    let records = read_file(task_id)?;
+    let next_seq = records.len() as u64;
+    Ok(TaskLogChunk {
+        task_id: task_id.to_string(),
+        records: records.into_iter().skip(since as usize).collect(),
+        next_seq,
+        truncated: false,
+    })
+}
+
+// 读取任务日志文件中的全部记录
+pub fn read_file(task_id: &str) -> Result<Vec<LogRecord>, String> {
+    let path = task_log_path(task_id);
+    let file = File::open(&path).map_err(|_| format!("没有找到任务 {} 的日志", task_id))?;
+    Ok(parse_lines(BufReader::new(file)))
+}
+
+fn parse_lines(reader: impl BufRead) -> Vec<LogRecord> {
+    reader
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect()
+}
+
+// 导出单个任务的完整日志，json 为 false 时使用与日志文件相同的文本格式
+pub fn export(task_id: &str, path: &Path, json: bool) -> Result<usize, String> {
+    let records = match read_file(task_id) {
+        Ok(records) => records,
+        Err(e) => {
+            let chunk = fetch(task_id, 0).map_err(|_| e)?;
+            chunk.records
+        }
+    };
+    if records.is_empty() {
+        return Err(format!("任务 {} 没有日志", task_id));
+    }
+
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|e| format!("创建目录失败: {}", e))?;
+    }
+    let mut file = File::create(path).map_err(|e| format!("创建导出文件失败: {}", e))?;
+    for record in &records {
+        let line = if json {
+            record.to_json()
+        } else {
+            record.to_line()
+        };
+        writeln!(file, "{}", line).map_err(|e| format!("写入导出文件失败: {}", e))?;
+    }
+    Ok(records.len())
+}
@@ -1,7 +1,10 @@
 use crate::captcha::LocalCaptcha;
-use crate::config::CustomConfig;
+use crate::config::{CustomConfig, TaskLimits};
 use crate::cookie_manager::CookieManager;
+use crate::log_record::LogPhase;
+use crate::push::ChannelReport;
 use crate::show_orderlist::OrderResponse;
+use crate::ticket_error::ErrorClass;
 use crate::{config, ticket::*};
 use config::PushConfig;
 use reqwest::Client;
@@ -10,7 +13,7 @@ use std::sync::Arc;
 use std::time::Instant;
 
 // 任务状态枚举
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum TaskStatus {
     Pending,
     Running,
@@ -19,6 +22,39 @@ pub enum TaskStatus {
     Cancelled,
 }
 
+impl TaskStatus {
+    // 已结束的任务不再更新状态
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            TaskStatus::Completed(_) | TaskStatus::Failed(_) | TaskStatus::Cancelled
+        )
+    }
+}
+
+// 抢票任务当前所处阶段
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskPhase {
+    WaitingForSale,  //等待开售
+    GettingToken,    //获取token
+    Confirming,      //确认订单
+    CreatingOrder,   //创建订单
+    AwaitingPayment, //等待支付
+}
+
+impl From<LogPhase> for TaskPhase {
+    fn from(phase: LogPhase) -> Self {
+        match phase {
+            LogPhase::Countdown => TaskPhase::WaitingForSale,
+            LogPhase::Token => TaskPhase::GettingToken,
+            LogPhase::Confirm => TaskPhase::Confirming,
+            LogPhase::Create => TaskPhase::CreatingOrder,
+            LogPhase::PayCheck => TaskPhase::AwaitingPayment,
+        }
+    }
+}
+
 // 票务结果
 #[derive(Clone, Serialize, Deserialize)]
 pub struct TicketResult {
@@ -42,6 +78,122 @@ pub enum Task {
     GrabTicketTask(GrabTicketTask),
 }
 
+impl Task {
+    pub fn status(&self) -> &TaskStatus {
+        match self {
+            Task::QrCodeLoginTask(t) => &t.status,
+            Task::LoginSmsRequestTask(t) => &t.status,
+            Task::PushTask(t) => &t.status,
+            Task::SubmitLoginSmsRequestTask(t) => &t.status,
+            Task::GetAllorderRequestTask(t) => &t.status,
+            Task::GetTicketInfoTask(t) => &t.status,
+            Task::GetBuyerInfoTask(t) => &t.status,
+            Task::GrabTicketTask(t) => &t.status,
+        }
+    }
+
+    pub fn set_status(&mut self, status: TaskStatus) {
+        match self {
+            Task::QrCodeLoginTask(t) => t.status = status,
+            Task::LoginSmsRequestTask(t) => t.status = status,
+            Task::PushTask(t) => t.status = status,
+            Task::SubmitLoginSmsRequestTask(t) => t.status = status,
+            Task::GetAllorderRequestTask(t) => t.status = status,
+            Task::GetTicketInfoTask(t) => t.status = status,
+            Task::GetBuyerInfoTask(t) => t.status = status,
+            Task::GrabTicketTask(t) => t.status = status,
+        }
+    }
+
+    // 只有抢票任务区分阶段
+    pub fn phase(&self) -> Option<TaskPhase> {
+        match self {
+            Task::GrabTicketTask(t) => t.phase,
+            _ => None,
+        }
+    }
+
+    pub fn kind(&self) -> TaskKind {
+        match self {
+            Task::QrCodeLoginTask(_) => TaskKind::QrCodeLogin,
+            Task::LoginSmsRequestTask(_) => TaskKind::LoginSms,
+            Task::PushTask(_) => TaskKind::Push,
+            Task::SubmitLoginSmsRequestTask(_) => TaskKind::SubmitLoginSms,
+            Task::GetAllorderRequestTask(_) => TaskKind::GetAllOrder,
+            Task::GetTicketInfoTask(_) => TaskKind::GetTicketInfo,
+            Task::GetBuyerInfoTask(_) => TaskKind::GetBuyerInfo,
+            Task::GrabTicketTask(_) => TaskKind::GrabTicket,
+        }
+    }
+
+    pub fn start_time(&self) -> Option<Instant> {
+        match self {
+            Task::QrCodeLoginTask(t) => t.start_time,
+            Task::LoginSmsRequestTask(t) => t.start_time,
+            Task::PushTask(t) => t.start_time,
+            Task::SubmitLoginSmsRequestTask(t) => t.start_time,
+            Task::GetAllorderRequestTask(t) => t.start_time,
+            Task::GetTicketInfoTask(t) => t.start_time,
+            Task::GetBuyerInfoTask(t) => t.start_time,
+            Task::GrabTicketTask(t) => t.start_time,
+        }
+    }
+
+    // finished_at 为任务结束时间，未结束时按当前时间计算已运行时长
+    pub fn summary(&self, task_id: &str, finished_at: Option<Instant>) -> TaskSummary {
+        let (uid, project_id) = match self {
+            Task::GetAllorderRequestTask(t) => (t.account_id.parse().ok(), None),
+            Task::GetTicketInfoTask(t) => (None, Some(t.project_id.clone())),
+            Task::GetBuyerInfoTask(t) => (Some(t.uid), None),
+            Task::GrabTicketTask(t) => (
+                Some(t.biliticket.account.uid),
+                Some(t.biliticket.project_id.clone()),
+            ),
+            _ => (None, None),
+        };
+        let elapsed = self
+            .start_time()
+            .map(|start| {
+                finished_at
+                    .unwrap_or_else(Instant::now)
+                    .saturating_duration_since(start)
+            })
+            .unwrap_or_default();
+        let started_at = self.start_time().map(|start| {
+            chrono::Local::now().timestamp_millis() - start.elapsed().as_millis() as i64
+        });
+        TaskSummary {
+            task_id: task_id.to_string(),
+            kind: self.kind().as_str().to_string(),
+            uid,
+            project_id,
+            status: self.status().clone(),
+            phase: self.phase(),
+            started_at,
+            elapsed_ms: elapsed.as_millis() as u64,
+        }
+    }
+}
+
+// 任务概要，用于列出任务
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TaskSummary {
+    pub task_id: String,
+    pub kind: String,
+    pub uid: Option<i64>,
+    pub project_id: Option<String>,
+    pub status: TaskStatus,
+    pub phase: Option<TaskPhase>,
+    pub started_at: Option<i64>, //毫秒时间戳
+    pub elapsed_ms: u64,
+}
+
+// 不持有任务管理器也能查询、取消任务，供本地接口使用
+pub trait TaskControl: Send + Sync {
+    fn list_tasks(&self) -> Vec<TaskSummary>;
+    fn cancel_task(&self, task_id: &str) -> Result<(), String>;
+}
+
 // 任务请求枚举
 pub enum TaskRequest {
     QrCodeLoginRequest(QrCodeLoginRequest),
@@ -54,6 +206,64 @@ pub enum TaskRequest {
     GrabTicketRequest(GrabTicketRequest),
 }
 
+impl TaskRequest {
+    pub fn kind(&self) -> TaskKind {
+        match self {
+            TaskRequest::QrCodeLoginRequest(_) => TaskKind::QrCodeLogin,
+            TaskRequest::LoginSmsRequest(_) => TaskKind::LoginSms,
+            TaskRequest::PushRequest(_) => TaskKind::Push,
+            TaskRequest::SubmitLoginSmsRequest(_) => TaskKind::SubmitLoginSms,
+            TaskRequest::GetAllorderRequest(_) => TaskKind::GetAllOrder,
+            TaskRequest::GetTicketInfoRequest(_) => TaskKind::GetTicketInfo,
+            TaskRequest::GetBuyerInfoRequest(_) => TaskKind::GetBuyerInfo,
+            TaskRequest::GrabTicketRequest(_) => TaskKind::GrabTicket,
+        }
+    }
+
+    // 请求自带的任务 ID，结果中会原样带回；登录、推送等请求没有该字段
+    pub fn task_id_mut(&mut self) -> Option<&mut String> {
+        match self {
+            TaskRequest::GetAllorderRequest(req) => Some(&mut req.task_id),
+            TaskRequest::GetTicketInfoRequest(req) => Some(&mut req.task_id),
+            TaskRequest::GetBuyerInfoRequest(req) => Some(&mut req.task_id),
+            TaskRequest::GrabTicketRequest(req) => Some(&mut req.task_id),
+            TaskRequest::QrCodeLoginRequest(_)
+            | TaskRequest::LoginSmsRequest(_)
+            | TaskRequest::PushRequest(_)
+            | TaskRequest::SubmitLoginSmsRequest(_) => None,
+        }
+    }
+}
+
+// 任务类型，决定排队优先级和并发上限
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TaskKind {
+    QrCodeLogin,
+    LoginSms,
+    Push,
+    SubmitLoginSms,
+    GetAllOrder,
+    GetTicketInfo,
+    GetBuyerInfo,
+    GrabTicket,
+}
+
+impl TaskKind {
+    // 与运行指标和任务列表中的名称一致
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskKind::QrCodeLogin => "qrcode_login",
+            TaskKind::LoginSms => "login_sms",
+            TaskKind::Push => "push",
+            TaskKind::SubmitLoginSms => "submit_login_sms",
+            TaskKind::GetAllOrder => "get_all_order",
+            TaskKind::GetTicketInfo => "get_ticket_info",
+            TaskKind::GetBuyerInfo => "get_buyer_info",
+            TaskKind::GrabTicket => "grab_ticket",
+        }
+    }
+}
+
 // 任务结果枚举
 #[derive(Clone, Serialize, Deserialize)]
 pub enum TaskResult {
@@ -68,6 +278,36 @@ pub enum TaskResult {
     DungeonQrResult(DungeonQrResult),
 }
 
+impl TaskResult {
+    // 结果对应的任务最终状态，中间状态（如等待扫码）返回 None
+    pub fn final_status(&self) -> Option<TaskStatus> {
+        let outcome = |success: bool, message: &str| {
+            if success {
+                TaskStatus::Completed(true)
+            } else {
+                TaskStatus::Failed(message.to_string())
+            }
+        };
+        match self {
+            TaskResult::QrCodeLoginResult(r) => match &r.status {
+                crate::login::QrCodeLoginStatus::Success(_) => Some(TaskStatus::Completed(true)),
+                crate::login::QrCodeLoginStatus::Failed(e) => Some(outcome(false, e)),
+                crate::login::QrCodeLoginStatus::Expired => Some(outcome(false, "二维码已过期")),
+                _ => None,
+            },
+            TaskResult::LoginSmsResult(r) => Some(outcome(r.success, &r.message)),
+            TaskResult::PushResult(r) => Some(outcome(r.success, &r.message)),
+            TaskResult::SubmitSmsLoginResult(r) => Some(outcome(r.success, &r.message)),
+            TaskResult::GetAllorderRequestResult(r) => Some(outcome(r.success, &r.message)),
+            TaskResult::GetTicketInfoResult(r) => Some(outcome(r.success, &r.message)),
+            TaskResult::GetBuyerInfoResult(r) => Some(outcome(r.success, &r.message)),
+            TaskResult::GrabTicketResult(r) if r.cancelled => Some(TaskStatus::Cancelled),
+            TaskResult::GrabTicketResult(r) => Some(outcome(r.success, &r.message)),
+            TaskResult::DungeonQrResult(_) => None,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct DungeonQrResult {
     pub task_id: String,
@@ -92,12 +332,14 @@ pub struct GrabTicketRequest {
     pub is_hot: bool,
     pub local_captcha: LocalCaptcha,
     pub skip_words: Option<Vec<String>>,
+    pub preferences: Vec<TicketPreference>, //候选票种，为空时只抢 screen_id/ticket_id
 }
 #[derive(Clone, Debug)]
 pub struct GrabTicketTask {
     pub task_id: String,
     pub biliticket: BilibiliTicket,
     pub status: TaskStatus,
+    pub phase: Option<TaskPhase>,
     pub client: Arc<Client>,
     pub start_time: Option<Instant>,
 }
@@ -111,6 +353,12 @@ pub struct GrabTicketResult {
     pub pay_token: Option<String>,
     pub confirm_result: Option<ConfirmTicketResult>,
     pub pay_result: Option<CheckFakeResultData>,
+    #[serde(default)]
+    pub cancelled: bool, //任务被取消后的最终结果
+    #[serde(default)]
+    pub error_code: Option<i64>, //失败时最后一次接口错误码
+    #[serde(default)]
+    pub error_class: Option<ErrorClass>, //失败时该错误的处理方式，界面据此判断是否需要停止或重新登录
 }
 //获取购票人信息
 #[derive(Clone, Debug)]
@@ -181,18 +429,44 @@ pub enum PushType {
     Fangtang,
     Dingtalk,
     WeChat,
+    Gotify,
+    Ntfy,
+    Matrix,
     Smtp,
+    Webhook,
     Dungeon,
 }
 
+impl PushType {
+    // 对应的推送渠道名称，All 表示全部已启用渠道
+    pub fn channel(&self) -> Option<&'static str> {
+        match self {
+            PushType::All => None,
+            PushType::Bark => Some("bark"),
+            PushType::PushPlus => Some("pushplus"),
+            PushType::Fangtang => Some("fangtang"),
+            PushType::Dingtalk => Some("dingtalk"),
+            PushType::WeChat => Some("wechat"),
+            PushType::Gotify => Some("gotify"),
+            PushType::Ntfy => Some("ntfy"),
+            PushType::Matrix => Some("matrix"),
+            PushType::Smtp => Some("smtp"),
+            PushType::Webhook => Some("webhook"),
+            PushType::Dungeon => Some("dungeon"),
+        }
+    }
+}
+
 // 推送结果结构体
 #[derive(Clone, Serialize, Deserialize)]
 pub struct PushRequestResult {
     pub task_id: String,
     pub success: bool,
-    pub message: String,
+    pub message: String, //汇总说明
     pub push_type: PushType,
     pub dungeon_target_id: Option<String>,
+    #[serde(default)]
+    pub channels: Vec<ChannelReport>, //各渠道的推送结果
 }
 
 #[derive(Clone)]
@@ -345,6 +619,15 @@ pub trait TaskManager: Send + 'static {
     // 获取任务状态
     fn get_task_status(&self, task_id: &str) -> Option<TaskStatus>;
 
+    // 获取抢票任务当前阶段
+    fn get_task_phase(&self, task_id: &str) -> Option<TaskPhase>;
+
+    // 列出全部任务（含尚未回收的已结束任务），按开始时间从新到旧
+    fn list_tasks(&self) -> Vec<TaskSummary>;
+
+    // 调整各类任务的并发上限，排队中的任务在下一次调度时按新上限启动
+    fn set_limits(&self, limits: TaskLimits);
+
     // 关闭任务管理器
     fn shutdown(&mut self);
 
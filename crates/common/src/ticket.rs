@@ -8,6 +8,8 @@ use serde_json::Value;
 use crate::account::Account;
 use crate::config::{self, CustomConfig};
 use crate::cookie_manager::CookieManager;
+use crate::redact;
+use crate::ticket_error::TicketApiError;
 use config::PushConfig;
 
 //成功下单结构体
@@ -85,6 +87,12 @@ pub struct TokenRiskParam {
     pub risk_param: Option<Value>,
 }
 
+impl TokenRiskParam {
+    pub fn error(&self) -> TicketApiError {
+        TicketApiError::from_code(self.code as i64)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct BilibiliTicket {
     pub uid: i64, //UID
@@ -278,6 +286,54 @@ pub struct ScreenTicketInfo {
     pub less_lv: i32, //最低等级需求
 }
 
+// 候选票种，按列表顺序尝试，前一个售罄后换下一个
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TicketPreference {
+    pub screen_id: String,
+    #[serde(default)]
+    pub ticket_id: Option<String>, //为空表示该场次的任意票种
+    #[serde(default)]
+    pub max_price: Option<usize>, //票价上限(分)
+}
+
+impl TicketPreference {
+    fn matches(&self, screen: &ScreenInfo, ticket: &ScreenTicketInfo) -> bool {
+        screen.id.to_string() == self.screen_id
+            && self
+                .ticket_id
+                .as_ref()
+                .is_none_or(|id| *id == ticket.id.to_string())
+            && self.max_price.is_none_or(|max| ticket.price <= max)
+    }
+
+    // 按偏好顺序列出项目中符合条件的票种，同一票种只出现一次。
+    // 优先返回可售的票种；都不可售时（如开售瞬间项目详情还未刷新）返回全部符合条件的票种
+    pub fn rank<'a>(
+        preferences: &[TicketPreference],
+        project: &'a TicketInfo,
+    ) -> Vec<(&'a ScreenInfo, &'a ScreenTicketInfo)> {
+        let mut matched: Vec<(&ScreenInfo, &ScreenTicketInfo)> = Vec::new();
+        for preference in preferences {
+            for screen in &project.screen_list {
+                for ticket in &screen.ticket_list {
+                    if preference.matches(screen, ticket)
+                        && !matched.iter().any(|(_, t)| t.id == ticket.id)
+                    {
+                        matched.push((screen, ticket));
+                    }
+                }
+            }
+        }
+        if matched
+            .iter()
+            .any(|(screen, ticket)| screen.clickable && ticket.clickable)
+        {
+            matched.retain(|(screen, ticket)| screen.clickable && ticket.clickable);
+        }
+        matched
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DescribeList {
     pub r#type: u8, // 使用 r# 前缀处理 Rust 关键字
@@ -319,7 +375,7 @@ pub struct InfoResponse {
     pub data: TicketInfo,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct BuyerInfo {
     pub id: i64,
     pub uid: i64,
@@ -360,9 +416,39 @@ pub struct BuyerInfoData {
     pub list: Vec<BuyerInfo>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct NoBindBuyerInfo {
     pub name: String,
     pub tel: String,
     pub uid: i64,
 }
+
+// 购票人的证件号和手机号在 Debug 输出中脱敏
+impl std::fmt::Debug for BuyerInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BuyerInfo")
+            .field("id", &self.id)
+            .field("uid", &self.uid)
+            .field("personal_id", &redact::pii(&self.personal_id))
+            .field("name", &self.name)
+            .field("tel", &redact::pii(&self.tel))
+            .field("id_type", &self.id_type)
+            .field("is_default", &self.is_default)
+            .field("id_card_front", &redact::secret(&self.id_card_front))
+            .field("id_card_back", &redact::secret(&self.id_card_back))
+            .field("verify_status", &self.verify_status)
+            .field("is_buyer_info_verified", &self.is_buyer_info_verified)
+            .field("is_buyer_valid", &self.is_buyer_valid)
+            .finish()
+    }
+}
+
+impl std::fmt::Debug for NoBindBuyerInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NoBindBuyerInfo")
+            .field("name", &self.name)
+            .field("tel", &redact::pii(&self.tel))
+            .field("uid", &self.uid)
+            .finish()
+    }
+}
@@ -0,0 +1,207 @@
+use serde::{Deserialize, Serialize};
+
+// B站会员购接口的错误码。取 token（order/prepare）和下单（order/create）返回的 errno/code，
+// 以及下单请求本身失败时使用的 HTTP 状态码和程序内部错误码
+
+// 遇到错误后的处理方式
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+    Retryable,    //原样重试
+    RefreshToken, //重新获取 token 后重试
+    NeedsCaptcha, //完成人机验证后重试
+    NeedsRelogin, //登录失效，需要重新登录
+    Fatal,        //终止抢票
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TicketApiError {
+    // 限流与库存
+    RateLimited,     //100001
+    TooManyRequests, //429
+    ServerBusy,      //900001
+    TooFast,         //3
+    SoldOut,         //100009
+    NarrowlyMissed,  //211
+    NullResponse,    //737
+    RequestFailed,   //412
+    // token 失效
+    TokenExpired,  //100041
+    TokenInvalid,  //100050
+    TokenMismatch, //900002
+    // 人机验证与登录
+    CaptchaRequired, //-401
+    RiskControl,     //401
+    NotLoggedIn,     //-101
+    CsrfFailed,      //-111
+    // 终止抢票
+    PurchaseLimit,      //1
+    SaleStopped,        //100016
+    TicketStopped,      //100017
+    SaleEnded,          //100039
+    PendingOrder,       //100079
+    DuplicateOrder,     //100003
+    UnpaidOrder,        //100048
+    WrongSelection,     //100080
+    WrongSelectionDate, //100082
+    SingleBuyerOnly,    //209001
+    NoBuyer,            //83000004
+    // 程序内部错误
+    InvalidParams,   //999
+    BindTypeInvalid, //919
+    Unknown(i64),
+}
+
+impl TicketApiError {
+    pub fn from_code(code: i64) -> Self {
+        match code {
+            100001 => Self::RateLimited,
+            429 => Self::TooManyRequests,
+            900001 => Self::ServerBusy,
+            3 => Self::TooFast,
+            100009 => Self::SoldOut,
+            211 => Self::NarrowlyMissed,
+            737 => Self::NullResponse,
+            412 => Self::RequestFailed,
+            100041 => Self::TokenExpired,
+            100050 => Self::TokenInvalid,
+            900002 => Self::TokenMismatch,
+            -401 => Self::CaptchaRequired,
+            401 => Self::RiskControl,
+            -101 => Self::NotLoggedIn,
+            -111 => Self::CsrfFailed,
+            1 => Self::PurchaseLimit,
+            100016 => Self::SaleStopped,
+            100017 => Self::TicketStopped,
+            100039 => Self::SaleEnded,
+            100079 => Self::PendingOrder,
+            100003 => Self::DuplicateOrder,
+            100048 => Self::UnpaidOrder,
+            100080 => Self::WrongSelection,
+            100082 => Self::WrongSelectionDate,
+            209001 => Self::SingleBuyerOnly,
+            83000004 => Self::NoBuyer,
+            999 => Self::InvalidParams,
+            919 => Self::BindTypeInvalid,
+            code => Self::Unknown(code),
+        }
+    }
+
+    pub fn code(&self) -> i64 {
+        match self {
+            Self::RateLimited => 100001,
+            Self::TooManyRequests => 429,
+            Self::ServerBusy => 900001,
+            Self::TooFast => 3,
+            Self::SoldOut => 100009,
+            Self::NarrowlyMissed => 211,
+            Self::NullResponse => 737,
+            Self::RequestFailed => 412,
+            Self::TokenExpired => 100041,
+            Self::TokenInvalid => 100050,
+            Self::TokenMismatch => 900002,
+            Self::CaptchaRequired => -401,
+            Self::RiskControl => 401,
+            Self::NotLoggedIn => -101,
+            Self::CsrfFailed => -111,
+            Self::PurchaseLimit => 1,
+            Self::SaleStopped => 100016,
+            Self::TicketStopped => 100017,
+            Self::SaleEnded => 100039,
+            Self::PendingOrder => 100079,
+            Self::DuplicateOrder => 100003,
+            Self::UnpaidOrder => 100048,
+            Self::WrongSelection => 100080,
+            Self::WrongSelectionDate => 100082,
+            Self::SingleBuyerOnly => 209001,
+            Self::NoBuyer => 83000004,
+            Self::InvalidParams => 999,
+            Self::BindTypeInvalid => 919,
+            Self::Unknown(code) => *code,
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::RateLimited | Self::TooManyRequests | Self::ServerBusy => "b站限速，正常现象",
+            Self::TooFast => "抢票速度过快，即将被硬控5秒",
+            Self::SoldOut => "当前票种库存不足",
+            Self::NarrowlyMissed => "很遗憾，差一点点抢到票，继续加油吧！",
+            Self::NullResponse => "B站返回了空数据，请查看上一条响应信息",
+            Self::RequestFailed => "请求失败或被风控拦截",
+            Self::TokenExpired | Self::TokenInvalid | Self::TokenMismatch => {
+                "token失效，即将重新获取token"
+            }
+            Self::CaptchaRequired | Self::RiskControl => "需要进行人机验证",
+            Self::NotLoggedIn => "账号未登录或登录已失效，请重新登录",
+            Self::CsrfFailed => "csrf校验失败，请重新登录",
+            Self::PurchaseLimit => {
+                "超人 请慢一点，这是仅限1人抢票的项目，或抢票格式有误，请重新提交任务"
+            }
+            Self::SaleStopped | Self::TicketStopped => "当前项目/类型/场次已停售",
+            Self::SaleEnded => "活动收摊啦,下次要快点哦",
+            Self::PendingOrder | Self::DuplicateOrder | Self::UnpaidOrder => {
+                "购票人存在待付款订单，请前往支付或取消后重新下单"
+            }
+            Self::WrongSelection | Self::WrongSelectionDate => "场次/项目/日期选择有误",
+            Self::SingleBuyerOnly => "当前项目只能选择一个购票人！不支持多选，请重新提交任务",
+            Self::NoBuyer => "没有配置购票人信息！请重新配置",
+            Self::InvalidParams => "程序内部错误！传参错误",
+            Self::BindTypeInvalid => {
+                "程序内部错误！该项目区分绑定非绑定项目错误，传入意外值，请尝试重新下单以及提出issue"
+            }
+            Self::Unknown(_) => "未知错误码，可以提出issue修复该问题",
+        }
+    }
+
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            Self::RateLimited
+            | Self::TooManyRequests
+            | Self::ServerBusy
+            | Self::TooFast
+            | Self::SoldOut
+            | Self::NarrowlyMissed
+            | Self::NullResponse
+            | Self::RequestFailed
+            | Self::InvalidParams
+            | Self::Unknown(_) => ErrorClass::Retryable,
+            Self::TokenExpired | Self::TokenInvalid | Self::TokenMismatch => {
+                ErrorClass::RefreshToken
+            }
+            Self::CaptchaRequired | Self::RiskControl => ErrorClass::NeedsCaptcha,
+            Self::NotLoggedIn | Self::CsrfFailed => ErrorClass::NeedsRelogin,
+            Self::PurchaseLimit
+            | Self::SaleStopped
+            | Self::TicketStopped
+            | Self::SaleEnded
+            | Self::PendingOrder
+            | Self::DuplicateOrder
+            | Self::UnpaidOrder
+            | Self::WrongSelection
+            | Self::WrongSelectionDate
+            | Self::SingleBuyerOnly
+            | Self::NoBuyer
+            | Self::BindTypeInvalid => ErrorClass::Fatal,
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        self.class() == ErrorClass::Retryable
+    }
+
+    // 当前场次或票种已售罄/停售，有候选票种时换下一个
+    pub fn is_sold_out(&self) -> bool {
+        matches!(
+            self,
+            Self::SoldOut | Self::SaleStopped | Self::TicketStopped | Self::SaleEnded
+        )
+    }
+}
+
+impl std::fmt::Display for TicketApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}（错误码 {}）", self.message(), self.code())
+    }
+}
@@ -1,3 +1,4 @@
+use crate::endpoints::ApiEndpoints;
 use reqwest::Client;
 
 // 单例锁实现，防止程序多开
@@ -20,9 +21,9 @@ pub fn ensure_single_instance() -> bool {
 }
 
 pub async fn get_now_time(client: &Client) -> i64 {
-    let url = "https://api.bilibili.com/x/click-interface/click/now";
+    let url = ApiEndpoints::current().api_url("/x/click-interface/click/now");
 
-    if let Ok(response) = client.get(url).send().await {
+    if let Ok(response) = client.get(&url).send().await {
         if let Ok(text) = response.text().await {
             if let Ok(json_data) = serde_json::from_str::<serde_json::Value>(&text) {
                 if let Some(now_sec) = json_data["data"]["now"].as_i64() {
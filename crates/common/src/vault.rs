@@ -0,0 +1,270 @@
+use crate::paths;
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use once_cell::sync::Lazy;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// 配置文件加密存储
+// 密钥来源按顺序：程序内设置的口令 > BTR_VAULT_PASSPHRASE > 密钥文件（BTR_VAULT_KEYFILE 或 ./config.key）
+// 有可用密钥时 save_config 会写成加密格式，旧的明文配置在下一次保存时自动迁移
+
+pub const DEFAULT_KEYFILE: &str = "config.key"; //相对于数据目录
+const VAULT_VERSION: u32 = 1;
+const KDF_PASSPHRASE: &str = "argon2id";
+const KDF_KEYFILE: &str = "keyfile";
+
+static PASSPHRASE: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Clone)]
+pub enum VaultKey {
+    Passphrase(String),
+    Keyfile([u8; 32]),
+}
+
+impl VaultKey {
+    fn kdf(&self) -> &'static str {
+        match self {
+            VaultKey::Passphrase(_) => KDF_PASSPHRASE,
+            VaultKey::Keyfile(_) => KDF_KEYFILE,
+        }
+    }
+
+    fn derive(&self, salt: &[u8]) -> Result<[u8; 32], String> {
+        match self {
+            VaultKey::Passphrase(passphrase) => {
+                let mut key = [0u8; 32];
+                argon2::Argon2::default()
+                    .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+                    .map_err(|e| format!("口令派生密钥失败: {}", e))?;
+                Ok(key)
+            }
+            VaultKey::Keyfile(key) => Ok(*key),
+        }
+    }
+}
+
+// 加密后的配置文件内容
+#[derive(Serialize, Deserialize)]
+struct VaultFile {
+    btr_vault: u32,
+    kdf: String,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VaultStatus {
+    pub encrypted: bool,     //配置文件当前是否为加密格式
+    pub kdf: Option<String>, //argon2id 为口令，keyfile 为密钥文件
+    pub key_available: bool, //是否已提供口令或密钥文件
+}
+
+// 设置本次运行使用的口令，传 None 清除
+pub fn set_passphrase(passphrase: Option<String>) {
+    if let Ok(mut current) = PASSPHRASE.lock() {
+        *current = passphrase.filter(|p| !p.is_empty());
+    }
+}
+
+// 是否提供了口令（程序内设置或 BTR_VAULT_PASSPHRASE），口令优先于密钥文件
+pub fn passphrase_set() -> bool {
+    PASSPHRASE.lock().ok().is_some_and(|p| p.is_some()) || env_passphrase_set()
+}
+
+// 是否通过环境变量 BTR_VAULT_PASSPHRASE 提供了口令，程序内无法清除
+pub fn env_passphrase_set() -> bool {
+    std::env::var("BTR_VAULT_PASSPHRASE").is_ok_and(|p| !p.is_empty())
+}
+
+pub fn keyfile_path() -> PathBuf {
+    std::env::var("BTR_VAULT_KEYFILE")
+        .ok()
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| paths::data_path(DEFAULT_KEYFILE))
+}
+
+// 当前可用的密钥，没有任何密钥来源时返回 None
+pub fn current_key() -> Result<Option<VaultKey>, String> {
+    if let Some(passphrase) = PASSPHRASE.lock().ok().and_then(|p| p.clone()) {
+        return Ok(Some(VaultKey::Passphrase(passphrase)));
+    }
+    if let Ok(passphrase) = std::env::var("BTR_VAULT_PASSPHRASE")
+        && !passphrase.is_empty()
+    {
+        return Ok(Some(VaultKey::Passphrase(passphrase)));
+    }
+    let path = keyfile_path();
+    if path.exists() {
+        return read_keyfile(&path).map(Some);
+    }
+    Ok(None)
+}
+
+fn read_keyfile(path: &Path) -> Result<VaultKey, String> {
+    let raw = fs::read_to_string(path).map_err(|e| format!("读取密钥文件失败: {}", e))?;
+    let bytes = STANDARD
+        .decode(raw.trim())
+        .map_err(|e| format!("密钥文件格式错误: {}", e))?;
+    let key: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| "密钥文件长度错误，应为 32 字节".to_string())?;
+    Ok(VaultKey::Keyfile(key))
+}
+
+// 生成新的随机密钥文件，已存在时不覆盖；文件仅当前用户可读写
+pub fn generate_keyfile(path: &Path) -> Result<(), String> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path).map_err(|e| match e.kind() {
+        io::ErrorKind::AlreadyExists => format!("密钥文件已存在: {}", path.display()),
+        _ => format!("写入密钥文件失败: {}", e),
+    })?;
+    let mut key = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut key);
+    file.write_all(STANDARD.encode(key).as_bytes())
+        .map_err(|e| format!("写入密钥文件失败: {}", e))?;
+    log::info!("已生成密钥文件: {}，请妥善备份", path.display());
+    Ok(())
+}
+
+pub fn is_encrypted(raw: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(raw)
+        .map(|v| v.get("btr_vault").is_some())
+        .unwrap_or(false)
+}
+
+pub fn encrypt(plaintext: &[u8], key: &VaultKey) -> Result<String, String> {
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let cipher = Aes256Gcm::new_from_slice(&key.derive(&salt)?).map_err(|e| e.to_string())?;
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|_| "加密配置失败".to_string())?;
+
+    serde_json::to_string_pretty(&VaultFile {
+        btr_vault: VAULT_VERSION,
+        kdf: key.kdf().to_string(),
+        salt: STANDARD.encode(salt),
+        nonce: STANDARD.encode(nonce),
+        ciphertext: STANDARD.encode(ciphertext),
+    })
+    .map_err(|e| e.to_string())
+}
+
+pub fn decrypt(raw: &str, key: &VaultKey) -> Result<Vec<u8>, String> {
+    let file: VaultFile =
+        serde_json::from_str(raw).map_err(|e| format!("加密配置格式错误: {}", e))?;
+    if file.btr_vault != VAULT_VERSION {
+        return Err(format!("不支持的加密配置版本: {}", file.btr_vault));
+    }
+    if file.kdf != key.kdf() {
+        return Err(match file.kdf.as_str() {
+            KDF_PASSPHRASE => "配置文件使用口令加密，请提供口令".to_string(),
+            _ => "配置文件使用密钥文件加密，请提供密钥文件".to_string(),
+        });
+    }
+    let decode = |field: &str| {
+        STANDARD
+            .decode(field)
+            .map_err(|e| format!("加密配置格式错误: {}", e))
+    };
+    let salt = decode(&file.salt)?;
+    let nonce = decode(&file.nonce)?;
+    let ciphertext = decode(&file.ciphertext)?;
+    if nonce.len() != 12 {
+        return Err("加密配置格式错误: nonce 长度不正确".to_string());
+    }
+
+    let cipher = Aes256Gcm::new_from_slice(&key.derive(&salt)?).map_err(|e| e.to_string())?;
+    cipher
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+        .map_err(|_| "解密配置失败，口令或密钥文件不正确".to_string())
+}
+
+// 读取配置文件内容，加密格式时用当前密钥解密
+pub fn read_config(path: &Path) -> Result<String, String> {
+    let raw = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    if !is_encrypted(&raw) {
+        return Ok(raw);
+    }
+    let key = current_key()?.ok_or_else(|| "配置文件已加密，请提供口令或密钥文件".to_string())?;
+    String::from_utf8(decrypt(&raw, &key)?).map_err(|e| e.to_string())
+}
+
+// 生成写入配置文件的内容
+// 已有加密配置时必须能用当前密钥解开才允许覆盖，避免口令输错后用默认配置冲掉原文件
+pub fn seal_config(path: &Path, json: &str) -> Result<String, String> {
+    let existing = fs::read_to_string(path)
+        .ok()
+        .filter(|raw| is_encrypted(raw));
+    match current_key()? {
+        Some(key) => {
+            if let Some(raw) = existing {
+                decrypt(&raw, &key).map_err(|e| format!("{}，拒绝覆盖已加密的配置", e))?;
+            }
+            encrypt(json.as_bytes(), &key)
+        }
+        None if existing.is_some() => {
+            Err("配置文件已加密但未提供口令或密钥文件，拒绝以明文覆盖".to_string())
+        }
+        None => Ok(json.to_string()),
+    }
+}
+
+// 加密后写入数据文件：先写临时文件再改名，避免写到一半中断留下损坏的文件
+pub fn write_sealed(path: &Path, json: &str) -> Result<(), String> {
+    let content = seal_config(path, json)?;
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let temp_path = path.with_extension("tmp");
+    write_private(&temp_path, content.as_bytes()).map_err(|e| e.to_string())?;
+    fs::rename(&temp_path, path).map_err(|e| e.to_string())
+}
+
+// 以 0600 权限新建文件写入，残留的临时文件先删除，避免沿用旧文件的权限
+pub(crate) fn write_private(path: &Path, content: &[u8]) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    file.write_all(content)?;
+    file.sync_all()
+}
+
+pub fn status(path: &Path) -> VaultStatus {
+    let kdf = fs::read_to_string(path)
+        .ok()
+        .and_then(|raw| serde_json::from_str::<VaultFile>(&raw).ok())
+        .map(|file| file.kdf);
+    VaultStatus {
+        encrypted: kdf.is_some(),
+        kdf,
+        key_available: matches!(current_key(), Ok(Some(_))),
+    }
+}
@@ -0,0 +1,75 @@
+use common::log_record::{self, LogCategory, LogFilter, LogPhase, LogRecord};
+
+fn build(target: &str, kvs: &[(&str, i64)], message: &str) -> LogRecord {
+    let args = format_args!("{}", message);
+    let record = log::Record::builder()
+        .level(log::Level::Warn)
+        .target(target)
+        .args(args)
+        .key_values(&kvs)
+        .build();
+    LogRecord::from_log(
+        &record,
+        "2024-07-06 18:00:00:000".to_string(),
+        message.to_string(),
+    )
+}
+
+#[tokio::test]
+async fn grab_context_fills_fields() {
+    let record = log_record::with_grab_context("task-1".to_string(), 42, async {
+        log_record::set_phase(LogPhase::Create);
+        log_record::set_code(Some(100009));
+        build("common::http_utils", &[], "库存不足，正在重试")
+    })
+    .await;
+
+    assert_eq!(record.task_id.as_deref(), Some("task-1"));
+    assert_eq!(record.uid, Some(42));
+    assert_eq!(record.phase, Some(LogPhase::Create));
+    assert_eq!(record.code, Some(100009));
+    assert_eq!(record.category, LogCategory::Grab);
+    assert_eq!(
+        record.to_line(),
+        "[2024-07-06 18:00:00:000] WARN: 库存不足，正在重试"
+    );
+
+    let json: serde_json::Value = serde_json::from_str(&record.to_json()).unwrap();
+    assert_eq!(json["phase"], "create");
+    assert_eq!(json["category"], "grab");
+
+    // 进入新阶段后错误码清空
+    let record = log_record::with_grab_context("task-1".to_string(), 42, async {
+        log_record::set_code(Some(100009));
+        log_record::set_phase(LogPhase::PayCheck);
+        build("common::http_utils", &[], "检测假票")
+    })
+    .await;
+    assert_eq!(record.phase, Some(LogPhase::PayCheck));
+    assert_eq!(record.code, None);
+}
+
+#[test]
+fn category_and_filter_without_context() {
+    let login = build("backend::taskmanager::login_handler", &[], "二维码已失效");
+    assert_eq!(login.category, LogCategory::Login);
+    assert!(login.task_id.is_none());
+
+    let tagged = build("common::utils", &[("code", 412)], "请求被拦截");
+    assert_eq!(tagged.code, Some(412));
+    assert_eq!(tagged.category, LogCategory::System);
+
+    let filter = LogFilter {
+        code: Some(412),
+        level: Some("WARN".to_string()),
+        ..Default::default()
+    };
+    assert!(filter.matches(&tagged));
+    assert!(!filter.matches(&login));
+
+    let errors_only = LogFilter {
+        level: Some("ERROR".to_string()),
+        ..Default::default()
+    };
+    assert!(!errors_only.matches(&tagged));
+}
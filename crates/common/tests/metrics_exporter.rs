@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+use common::config::MetricsExporterConfig;
+use common::{metrics, metrics_exporter};
+
+#[test]
+fn disabled_exporter_does_not_listen() {
+    let addr = metrics_exporter::start(&MetricsExporterConfig::default()).unwrap();
+    assert!(addr.is_none());
+}
+
+#[test]
+fn serves_prometheus_text() {
+    metrics::observe_request("order/createV2", Some(100009), Duration::from_millis(120));
+    metrics::observe_request("order/createV2", Some(0), Duration::from_millis(30));
+    metrics::record_push("bark", true);
+    metrics::record_push("gotify", false);
+    metrics::record_grab_result(true);
+    let _task = metrics::track_task("grab_ticket");
+
+    let addr = metrics_exporter::start(&MetricsExporterConfig {
+        enabled: true,
+        listen: "127.0.0.1:0".to_string(),
+    })
+    .unwrap()
+    .unwrap();
+
+    let response = reqwest::blocking::get(format!("http://{}/metrics", addr)).unwrap();
+    assert!(response.status().is_success());
+    let body = response.text().unwrap();
+
+    assert!(body.contains("btr_tasks_active{kind=\"grab_ticket\"} 1"));
+    assert!(body.contains("btr_api_attempts_total{endpoint=\"order/createV2\",code=\"100009\"} 1"));
+    assert!(body.contains("btr_push_total{channel=\"bark\",result=\"success\"} 1"));
+    assert!(body.contains("btr_push_total{channel=\"gotify\",result=\"failure\"} 1"));
+    assert!(body.contains("btr_grab_results_total{result=\"success\"} 1"));
+    assert!(
+        body.contains("btr_api_latency_seconds_bucket{endpoint=\"order/createV2\",le=\"0.05\"} 1")
+    );
+    assert!(
+        body.contains("btr_api_latency_seconds_bucket{endpoint=\"order/createV2\",le=\"+Inf\"} 2")
+    );
+    assert!(body.contains("btr_api_latency_seconds_count{endpoint=\"order/createV2\"} 2"));
+
+    let missing = reqwest::blocking::get(format!("http://{}/other", addr)).unwrap();
+    assert_eq!(missing.status().as_u16(), 404);
+    // 任务控制不在指标接口上
+    let tasks = reqwest::blocking::get(format!("http://{}/tasks", addr)).unwrap();
+    assert_eq!(tasks.status().as_u16(), 404);
+}
+
+#[test]
+fn idle_connection_does_not_block_others() {
+    let addr = metrics_exporter::start(&MetricsExporterConfig {
+        enabled: true,
+        listen: "127.0.0.1:0".to_string(),
+    })
+    .unwrap()
+    .unwrap();
+
+    // 只连上不发请求的客户端
+    let _idle = std::net::TcpStream::connect(addr).unwrap();
+    let client = reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(2))
+        .build()
+        .unwrap();
+    let response = client
+        .get(format!("http://{}/metrics", addr))
+        .send()
+        .unwrap();
+    assert!(response.status().is_success());
+}
@@ -0,0 +1,83 @@
+use common::account::Account;
+use common::redact;
+use common::ticket::BuyerInfo;
+
+#[test]
+fn masks_cookies_and_tokens() {
+    let text = "cookie: SESSDATA=abc123%2Cdef; bili_jct=0123456789abcdef; DedeUserID=10001";
+    let redacted = redact::redact(text);
+    assert!(!redacted.contains("abc123"));
+    assert!(!redacted.contains("0123456789abcdef"));
+    assert!(redacted.contains("bili_jct=***"));
+
+    let url = "https://show.bilibili.com/api/ticket/order/createstatus?project_id=1&token=secret_pay&timestamp=1";
+    let redacted = redact::redact(url);
+    assert!(redacted.contains("token=***&timestamp=1"));
+    assert!(redacted.contains("project_id=1"));
+
+    let json = r#"{"token":"abc","ptoken":"def","order_id":5001}"#;
+    assert_eq!(
+        redact::redact(json),
+        r#"{"token":"***","ptoken":"***","order_id":5001}"#
+    );
+
+    let debug = r#"PushConfig { bark_token: "bark-secret", pay_token: Some("pay-secret") }"#;
+    let redacted = redact::redact(debug);
+    assert!(!redacted.contains("bark-secret"));
+    assert!(!redacted.contains("pay-secret"));
+}
+
+#[test]
+fn masks_phone_and_id_numbers() {
+    assert_eq!(
+        redact::redact("手机号13800001234，证件号110101199001011234"),
+        "手机号138****1234，证件号110***********1234"
+    );
+    assert_eq!(
+        redact::redact("13800001234,13900005678"),
+        "138****1234,139****5678"
+    );
+    // 毫秒时间戳和订单号不应被当成手机号或证件号
+    assert_eq!(redact::redact("time=1717000000000"), "time=1717000000000");
+    assert_eq!(
+        redact::redact("order 1234567890123456789"),
+        "order 1234567890123456789"
+    );
+}
+
+#[test]
+fn debug_output_hides_secrets() {
+    let account = Account {
+        uid: 10001,
+        name: "测试".to_string(),
+        level: "6".to_string(),
+        cookie: "SESSDATA=abc; bili_jct=def".to_string(),
+        csrf: "def".to_string(),
+        is_login: true,
+        account_status: String::new(),
+        vip_label: String::new(),
+        vip_type: 0,
+        vip_status: 0,
+        is_active: true,
+        avatar_url: None,
+        cookie_manager: None,
+    };
+    let debug = format!("{:?}", account);
+    assert!(!debug.contains("SESSDATA=abc"));
+    assert!(debug.contains("uid: 10001"));
+
+    let buyer: BuyerInfo = serde_json::from_value(serde_json::json!({
+        "id": 1,
+        "uid": 10001,
+        "personal_id": "110101199001011234",
+        "name": "测试购票人",
+        "tel": "13800001234",
+        "id_type": 0,
+        "is_default": 1,
+    }))
+    .unwrap();
+    let debug = format!("{:?}", buyer);
+    assert!(!debug.contains("110101199001011234"));
+    assert!(!debug.contains("13800001234"));
+    assert!(debug.contains("138****1234"));
+}
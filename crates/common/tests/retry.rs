@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use common::config::CustomConfig;
+use common::retry::{Backoff, CodeOverride, RetryPolicies, RetryPolicy, RetryStage, RetryState};
+use common::ticket_error::{ErrorClass, TicketApiError};
+
+#[test]
+fn backoff_curves_and_code_overrides() {
+    let policy = RetryPolicy {
+        base_delay_ms: Some(100),
+        backoff: Backoff::Exponential,
+        max_delay_ms: 500,
+        overrides: vec![CodeOverride {
+            code: 100039,
+            delay_ms: Some(50),
+            class: Some(ErrorClass::Retryable),
+        }],
+        ..RetryPolicy::default()
+    };
+    assert_eq!(policy.delay(1, None), Duration::from_millis(100));
+    assert_eq!(policy.delay(3, None), Duration::from_millis(400));
+    assert_eq!(policy.delay(5, None), Duration::from_millis(500));
+    assert_eq!(policy.delay(5, Some(100039)), Duration::from_millis(50));
+    assert_eq!(
+        policy.classify(TicketApiError::SaleEnded),
+        ErrorClass::Retryable
+    );
+    assert_eq!(policy.classify(TicketApiError::NoBuyer), ErrorClass::Fatal);
+
+    let linear = RetryPolicy {
+        backoff: Backoff::Linear,
+        jitter_ms: 20,
+        ..policy
+    };
+    let delay = linear.delay(3, None);
+    assert!(delay >= Duration::from_millis(300) && delay <= Duration::from_millis(320));
+}
+
+#[test]
+fn stage_policy_falls_back_to_legacy_settings() {
+    let config = CustomConfig {
+        max_order_retry: 3,
+        retry_interval_ms: 25,
+        ..CustomConfig::default()
+    };
+    // 下单次数只在轮询式选择中生效
+    assert_eq!(config.retry_policy(RetryStage::Order).max_attempts, Some(0));
+    let order = config.order_retry_policy(true);
+    assert_eq!(order.max_attempts, Some(3));
+    assert_eq!(order.base_delay_ms, Some(25));
+    assert_eq!(
+        order.delay(1, Some(TicketApiError::SoldOut.code())),
+        Duration::from_millis(600)
+    );
+    assert_eq!(
+        config.retry_policy(RetryStage::Token).base_delay_ms,
+        Some(1000)
+    );
+
+    let mut state = RetryState::new(order);
+    assert!(state.failed(None).is_some());
+    assert!(state.failed(None).is_some());
+    assert!(state.failed(None).is_none());
+    state.reset();
+    assert_eq!(state.attempts(), 0);
+
+    let mut limited = RetryState::new(RetryPolicy {
+        base_delay_ms: Some(1000),
+        deadline_ms: 500,
+        ..RetryPolicy::default()
+    });
+    assert!(limited.failed(None).is_none());
+}
+
+#[test]
+fn partial_stage_config_keeps_builtin_defaults() {
+    let policies: RetryPolicies = serde_json::from_str(
+        r#"{
+            "order": {
+                "backoff": "exponential",
+                "overrides": [{ "code": 100039, "class": "retryable" }]
+            },
+            "token": { "jitter_ms": 50 }
+        }"#,
+    )
+    .unwrap();
+    let order = policies.get(RetryStage::Order);
+    assert_eq!(order.backoff, Backoff::Exponential);
+    assert_eq!(
+        order.delay(1, Some(TicketApiError::SoldOut.code())),
+        Duration::from_millis(600)
+    );
+    assert_eq!(
+        order.delay(1, Some(TicketApiError::TooFast.code())),
+        Duration::from_millis(4800)
+    );
+    assert_eq!(
+        order.classify(TicketApiError::SaleEnded),
+        ErrorClass::Retryable
+    );
+    assert_eq!(policies.get(RetryStage::Token).base_delay_ms, Some(1000));
+    assert_eq!(policies.get(RetryStage::Token).jitter_ms, 50);
+    assert_eq!(policies.get(RetryStage::Refresh).max_attempts, Some(0));
+
+    // 同一错误码以配置为准，保存后再读取结果不变
+    let custom: RetryPolicies = serde_json::from_str(
+        r#"{ "order": { "overrides": [{ "code": 100009, "delay_ms": 200 }] } }"#,
+    )
+    .unwrap();
+    assert_eq!(
+        custom
+            .get(RetryStage::Order)
+            .delay(1, Some(TicketApiError::SoldOut.code())),
+        Duration::from_millis(200)
+    );
+    let reloaded: RetryPolicies =
+        serde_json::from_str(&serde_json::to_string(&custom).unwrap()).unwrap();
+    assert_eq!(reloaded, custom);
+    let empty: RetryPolicies = serde_json::from_str("{}").unwrap();
+    assert_eq!(empty, RetryPolicies::default());
+}
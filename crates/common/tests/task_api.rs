@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use common::task_api::{self, TOKEN_HEADER};
+use common::taskmanager::{TaskControl, TaskPhase, TaskStatus, TaskSummary};
+
+struct StubControl;
+
+impl TaskControl for StubControl {
+    fn list_tasks(&self) -> Vec<TaskSummary> {
+        vec![TaskSummary {
+            task_id: "task-1".to_string(),
+            kind: "grab_ticket".to_string(),
+            uid: Some(42),
+            project_id: Some("1000".to_string()),
+            status: TaskStatus::Running,
+            phase: Some(TaskPhase::WaitingForSale),
+            started_at: Some(1_720_260_000_000),
+            elapsed_ms: 1500,
+        }]
+    }
+
+    fn cancel_task(&self, task_id: &str) -> Result<(), String> {
+        if task_id == "task-1" {
+            Ok(())
+        } else {
+            Err("任务不存在".to_string())
+        }
+    }
+}
+
+#[test]
+fn lists_and_cancels_tasks_with_token() {
+    let dir = std::env::temp_dir().join(format!("btr-test-task-api-{}", std::process::id()));
+    task_api::set_endpoint_dir(&dir);
+    let endpoint = task_api::start(Arc::new(StubControl)).unwrap();
+    assert!(endpoint.addr.starts_with("127.0.0.1:"));
+    assert_eq!(task_api::discover(), vec![endpoint.clone()]);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let file = dir.join(format!("{}.json", endpoint.pid));
+        let mode = std::fs::metadata(file).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    let client = reqwest::blocking::Client::new();
+    let url = |path: &str| format!("http://{}{}", endpoint.addr, path);
+
+    // 没有令牌或令牌错误
+    let anonymous = client.get(url("/tasks")).send().unwrap();
+    assert_eq!(anonymous.status().as_u16(), 401);
+    let forged = client
+        .post(url("/tasks/task-1/cancel"))
+        .header(TOKEN_HEADER, "0".repeat(endpoint.token.len()))
+        .send()
+        .unwrap();
+    assert_eq!(forged.status().as_u16(), 401);
+
+    let tasks: Vec<TaskSummary> = client
+        .get(url("/tasks"))
+        .header(TOKEN_HEADER, &endpoint.token)
+        .send()
+        .unwrap()
+        .json()
+        .unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].uid, Some(42));
+    assert_eq!(tasks[0].phase, Some(TaskPhase::WaitingForSale));
+
+    let ok = client
+        .post(url("/tasks/task-1/cancel"))
+        .header(TOKEN_HEADER, &endpoint.token)
+        .send()
+        .unwrap();
+    assert!(ok.status().is_success());
+    let missing = client
+        .post(url("/tasks/task-2/cancel"))
+        .header(TOKEN_HEADER, &endpoint.token)
+        .send()
+        .unwrap();
+    assert_eq!(missing.status().as_u16(), 409);
+
+    endpoint.remove();
+    assert!(task_api::discover().is_empty());
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,65 @@
+use common::log_record::{LogCategory, LogRecord};
+use common::task_log;
+
+fn record(task_id: &str, message: &str) -> LogRecord {
+    LogRecord {
+        time: "2024-07-06 18:00:00:000".to_string(),
+        level: "INFO".to_string(),
+        target: "backend::taskmanager::grab_ticket_handler".to_string(),
+        message: message.to_string(),
+        task_id: Some(task_id.to_string()),
+        uid: Some(1),
+        phase: None,
+        code: None,
+        category: LogCategory::Grab,
+    }
+}
+
+#[test]
+fn per_task_logs_are_separate_and_exportable() {
+    let dir = std::env::temp_dir().join(format!("btr-test-task-log-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    task_log::set_task_log_dir(dir.join("tasks"));
+
+    for i in 0..3 {
+        task_log::append(&record("task-a", &format!("a{}", i)));
+        task_log::append(&record("task-b", &format!("b{}", i)));
+    }
+    // 没有任务 ID 的日志不进入任何任务
+    let mut system = record("task-a", "system");
+    system.task_id = None;
+    task_log::append(&system);
+
+    let chunk = task_log::fetch("task-a", 0).unwrap();
+    let messages: Vec<_> = chunk.records.iter().map(|r| r.message.as_str()).collect();
+    assert_eq!(messages, ["a0", "a1", "a2"]);
+    assert_eq!(chunk.next_seq, 3);
+
+    // 增量拉取只返回新日志
+    task_log::append(&record("task-a", "a3"));
+    let chunk = task_log::fetch("task-a", chunk.next_seq).unwrap();
+    assert_eq!(chunk.records.len(), 1);
+    assert_eq!(chunk.records[0].message, "a3");
+    assert!(!chunk.truncated);
+
+    // 文件中保存了完整日志，供其他进程读取
+    assert_eq!(task_log::read_file("task-b").unwrap().len(), 3);
+    assert!(task_log::fetch("task-missing", 0).is_err());
+
+    let export = dir.join("export").join("task-a.log");
+    assert_eq!(task_log::export("task-a", &export, false).unwrap(), 4);
+    let text = std::fs::read_to_string(&export).unwrap();
+    assert_eq!(
+        text.lines().next(),
+        Some("[2024-07-06 18:00:00:000] INFO: a0")
+    );
+    assert!(!text.contains("b0"));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn task_log_path_is_sanitized() {
+    let path = task_log::task_log_path("../../etc/passwd");
+    assert_eq!(path.parent(), Some(task_log::task_log_dir().as_path()));
+}
@@ -0,0 +1,55 @@
+use common::log_record::{LogCategory, LogRecord};
+use common::task_log;
+use std::time::{Duration, SystemTime};
+
+fn record(task_id: &str) -> LogRecord {
+    LogRecord {
+        time: "2024-07-06 18:00:00:000".to_string(),
+        level: "INFO".to_string(),
+        target: "backend::taskmanager::grab_ticket_handler".to_string(),
+        message: "start".to_string(),
+        task_id: Some(task_id.to_string()),
+        uid: Some(1),
+        phase: None,
+        code: None,
+        category: LogCategory::Grab,
+    }
+}
+
+#[test]
+fn old_task_log_files_are_pruned() {
+    let dir = std::env::temp_dir().join(format!("btr-test-task-log-gc-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let tasks_dir = dir.join("tasks");
+    std::fs::create_dir_all(&tasks_dir).unwrap();
+    task_log::set_task_log_dir(&tasks_dir);
+
+    // 一个超过保留时长的旧文件，以及超过数量上限的普通文件
+    let expired = tasks_dir.join("expired.jsonl");
+    std::fs::write(&expired, "").unwrap();
+    std::fs::File::options()
+        .write(true)
+        .open(&expired)
+        .unwrap()
+        .set_modified(SystemTime::now() - Duration::from_secs(8 * 24 * 60 * 60))
+        .unwrap();
+    for i in 0..205 {
+        std::fs::write(tasks_dir.join(format!("old-{}.jsonl", i)), "").unwrap();
+    }
+    let other = tasks_dir.join("notes.txt");
+    std::fs::write(&other, "").unwrap();
+
+    task_log::append(&record("task-new"));
+
+    assert!(!expired.exists());
+    assert!(other.exists(), "只清理任务日志文件");
+    assert!(task_log::task_log_path("task-new").exists());
+    let count = std::fs::read_dir(&tasks_dir)
+        .unwrap()
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "jsonl"))
+        .count();
+    assert_eq!(count, 200);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
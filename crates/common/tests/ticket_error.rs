@@ -0,0 +1,30 @@
+use common::ticket_error::{ErrorClass, TicketApiError};
+
+#[test]
+fn known_codes_round_trip_with_classification() {
+    let cases = [
+        (100009, ErrorClass::Retryable),
+        (3, ErrorClass::Retryable),
+        (100041, ErrorClass::RefreshToken),
+        (900002, ErrorClass::RefreshToken),
+        (-401, ErrorClass::NeedsCaptcha),
+        (-101, ErrorClass::NeedsRelogin),
+        (100079, ErrorClass::Fatal),
+        (83000004, ErrorClass::Fatal),
+        (919, ErrorClass::Fatal),
+    ];
+    for (code, class) in cases {
+        let error = TicketApiError::from_code(code);
+        assert_ne!(error, TicketApiError::Unknown(code));
+        assert_eq!(error.code(), code);
+        assert_eq!(error.class(), class, "{}", code);
+    }
+}
+
+#[test]
+fn unknown_code_is_retried_and_keeps_the_code() {
+    let error = TicketApiError::from_code(123456);
+    assert_eq!(error, TicketApiError::Unknown(123456));
+    assert!(error.is_retryable());
+    assert!(error.to_string().contains("123456"));
+}
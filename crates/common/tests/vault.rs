@@ -0,0 +1,90 @@
+use common::vault::{self, VaultKey};
+
+#[test]
+fn passphrase_round_trip() {
+    let key = VaultKey::Passphrase("correct horse".to_string());
+    let sealed = vault::encrypt(br#"{"accounts":[]}"#, &key).unwrap();
+
+    assert!(vault::is_encrypted(&sealed));
+    assert!(!sealed.contains("accounts"));
+    assert_eq!(
+        vault::decrypt(&sealed, &key).unwrap(),
+        br#"{"accounts":[]}"#
+    );
+
+    let wrong = VaultKey::Passphrase("wrong".to_string());
+    assert!(vault::decrypt(&sealed, &wrong).is_err());
+    assert!(vault::decrypt(&sealed, &VaultKey::Keyfile([7; 32])).is_err());
+}
+
+#[test]
+fn keyfile_round_trip() {
+    let dir = std::env::temp_dir().join(format!("btr-test-vault-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let keyfile = dir.join("config.key");
+    let _ = std::fs::remove_file(&keyfile);
+
+    vault::generate_keyfile(&keyfile).unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&keyfile).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+    assert!(
+        vault::generate_keyfile(&keyfile).is_err(),
+        "不应覆盖已有密钥文件"
+    );
+
+    let key = VaultKey::Keyfile([1; 32]);
+    let sealed = vault::encrypt(b"secret", &key).unwrap();
+    assert_eq!(vault::decrypt(&sealed, &key).unwrap(), b"secret");
+    assert!(vault::decrypt(&sealed, &VaultKey::Keyfile([2; 32])).is_err());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn refuses_to_overwrite_with_wrong_key() {
+    let dir = std::env::temp_dir().join(format!("btr-test-vault-seal-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config");
+
+    // 明文配置：没有密钥时原样写出，有密钥时迁移为加密格式
+    std::fs::write(&path, "{}").unwrap();
+    assert_eq!(vault::seal_config(&path, "{}").unwrap(), "{}");
+    vault::set_passphrase(Some("first".to_string()));
+    let sealed = vault::seal_config(&path, "{}").unwrap();
+    assert!(vault::is_encrypted(&sealed));
+    std::fs::write(&path, &sealed).unwrap();
+    assert_eq!(vault::read_config(&path).unwrap(), "{}");
+
+    vault::set_passphrase(Some("second".to_string()));
+    assert!(vault::seal_config(&path, "{}").is_err());
+    assert!(vault::read_config(&path).is_err());
+
+    vault::set_passphrase(None);
+    assert!(vault::seal_config(&path, "{}").is_err());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[cfg(unix)]
+#[test]
+fn sealed_files_are_private() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = std::env::temp_dir().join(format!("btr-test-vault-mode-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("journal.json");
+    // 残留的临时文件权限较宽，不应被沿用
+    let temp_path = path.with_extension("tmp");
+    std::fs::write(&temp_path, "stale").unwrap();
+    std::fs::set_permissions(&temp_path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+    vault::write_sealed(&path, "[]").unwrap();
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+    assert!(!temp_path.exists());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -1,10 +1,15 @@
 use crate::state::AppState;
 use crate::utils::{create_client, current_timestamp, decode_permissions, decode_policy};
 use common::PushType;
-use common::config::Project;
+use common::config::{MatrixConfig, NtfyConfig, Project, SmtpConfig, TaskLimits, WebhookConfig};
+use common::log_record::{LogFilter, LogRecord};
+use common::metrics;
+use common::record_log;
+use common::task_log::{self, TaskLogChunk};
 use common::taskmanager::{PushRequest, TaskRequest};
 use common::{GRAB_LOG_COLLECTOR, LOG_COLLECTOR};
 use serde_json::{Value, json};
+use std::path::PathBuf;
 use tauri::State;
 
 #[tauri::command]
@@ -144,6 +149,34 @@ pub fn get_app_info(state: State<'_, AppState>) -> Result<Value, String> {
     }))
 }
 
+// 按任务、阶段、分类等字段查询结构化日志
+#[tauri::command]
+pub fn get_log_records(filter: Option<LogFilter>) -> Result<Vec<LogRecord>, String> {
+    Ok(record_log::query_records(&filter.unwrap_or_default()))
+}
+
+// 获取单个任务的日志，since 为上次返回的 next_seq，用于持续跟踪
+#[tauri::command]
+pub fn get_task_log(task_id: String, since: Option<u64>) -> Result<TaskLogChunk, String> {
+    task_log::fetch(&task_id, since.unwrap_or(0))
+}
+
+// 导出单个任务的日志，未指定路径时写入 Log/export 目录，返回导出文件路径
+#[tauri::command]
+pub fn export_task_log(task_id: String, path: Option<String>) -> Result<String, String> {
+    let path = path
+        .map(PathBuf::from)
+        .unwrap_or_else(|| task_log::default_export_path(&task_id));
+    let count = task_log::export(&task_id, &path, false)?;
+    log::info!(
+        "已导出任务 {} 的 {} 条日志到 {}",
+        task_id,
+        count,
+        path.display()
+    );
+    Ok(path.display().to_string())
+}
+
 #[tauri::command]
 pub fn clear_grab_logs() -> Result<(), String> {
     if let Ok(mut collector) = GRAB_LOG_COLLECTOR.lock() {
@@ -231,6 +264,7 @@ pub async fn get_state(state: State<'_, AppState>) -> Result<Value, String> {
         "show_screen_info": ticket.show_screen_info,
         "selected_screen_id": ticket.selected_screen_id,
         "selected_ticket_id": ticket.selected_ticket_id,
+        "ticket_preferences": ticket.ticket_preferences,
         "confirm_ticket_info": ticket.confirm_ticket_info,
         "show_add_buyer_window": ui.show_add_buyer_window,
         "show_orderlist_window": ui.show_orderlist_window,
@@ -323,13 +357,20 @@ pub fn get_monitor_stats(state: State<'_, AppState>) -> Result<serde_json::Value
         .lock()
         .map_err(|_| "runtime lock失败".to_string())?;
 
+    let stats = metrics::snapshot();
+
     Ok(json!({
-        "attempts": 0,
-        "success": 0,
-        "failures": 0,
+        "attempts": stats.attempts,
+        "success": stats.success,
+        "failures": stats.failures,
         "running": runtime.running_status.contains("运行") || runtime.running_status.contains("抢票"),
-        "active_tasks": 0,
-        "completed_tasks": 0
+        "active_tasks": stats.active_tasks,
+        "completed_tasks": stats.completed_tasks,
+        "grab_success": stats.grab_success,
+        "grab_failure": stats.grab_failure,
+        "error_codes": stats.error_codes,
+        "latencies": stats.latencies,
+        "tasks": stats.tasks
     }))
 }
 
@@ -360,6 +401,10 @@ pub async fn save_settings(
     wechat_token: String,
     gotify_url: String,
     gotify_token: String,
+    ntfy_config: NtfyConfig,
+    matrix_config: MatrixConfig,
+    smtp_config: SmtpConfig,
+    webhooks: Vec<WebhookConfig>,
     custom_ua: bool,
     user_agent: String,
     skip_words: Option<Vec<String>>,
@@ -368,6 +413,7 @@ pub async fn save_settings(
     max_fake_check_retry: u32,
     max_order_retry: u32,
     retry_interval_ms: u64,
+    task_limits: TaskLimits,
     dungeon_channel: u8,
     dungeon_intensity: u8,
     dungeon_frequency: u8,
@@ -375,7 +421,7 @@ pub async fn save_settings(
     dungeon_pause_ms: u64,
     dungeon_count: u8,
 ) -> Result<(), String> {
-    {
+    let limits = {
         let mut config = state
             .config
             .lock()
@@ -399,6 +445,7 @@ pub async fn save_settings(
         config.custom_config.max_fake_check_retry = max_fake_check_retry;
         config.custom_config.max_order_retry = max_order_retry;
         config.custom_config.retry_interval_ms = retry_interval_ms;
+        config.custom_config.task_limits = task_limits;
 
         config.push_config.enabled = enable_push;
         config.push_config.enabled_methods = enabled_methods.clone();
@@ -409,6 +456,10 @@ pub async fn save_settings(
         config.push_config.wechat_token = wechat_token;
         config.push_config.gotify_config.gotify_url = gotify_url;
         config.push_config.gotify_config.gotify_token = gotify_token;
+        config.push_config.ntfy_config = ntfy_config;
+        config.push_config.matrix_config = matrix_config;
+        config.push_config.smtp_config = smtp_config;
+        config.push_config.webhooks = webhooks;
 
         config.push_config.dungeon_config.channel = dungeon_channel;
         config.push_config.dungeon_config.intensity = dungeon_intensity;
@@ -439,7 +490,9 @@ pub async fn save_settings(
             log::error!("保存配置失败: {}", e);
             return Err(format!("保存配置失败: {}", e));
         }
-    }
+        config.custom_config.task_limits.clone()
+    };
+    state.apply_task_limits(limits)?;
 
     if enable_push && enabled_methods.contains(&"dungeon".to_string()) {
         let (dungeon_service, sender) = {
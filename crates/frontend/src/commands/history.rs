@@ -0,0 +1,20 @@
+use common::history::{self, HistoryQuery, HistoryRecord, TaskSummary};
+
+// 查询抢票历史中的每次接口调用
+#[tauri::command]
+pub fn get_grab_history(query: Option<HistoryQuery>) -> Result<Vec<HistoryRecord>, String> {
+    history::query(&query.unwrap_or_default())
+}
+
+// 按任务汇总的抢票历史
+#[tauri::command]
+pub fn get_grab_task_summaries(query: Option<HistoryQuery>) -> Result<Vec<TaskSummary>, String> {
+    let mut query = query.unwrap_or_default();
+    let limit = query.limit.take();
+    let mut summaries = history::summarize(&history::query(&query)?);
+    if let Some(limit) = limit {
+        let skip = summaries.len().saturating_sub(limit);
+        summaries.drain(..skip);
+    }
+    Ok(summaries)
+}
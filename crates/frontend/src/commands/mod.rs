@@ -1,5 +1,7 @@
 pub mod account;
 pub mod auth;
 pub mod general;
+pub mod history;
 pub mod task;
 pub mod ticket;
+pub mod vault;
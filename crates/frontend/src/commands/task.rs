@@ -1,7 +1,9 @@
 use crate::state::AppState;
+use common::schedule::{self, ScheduleTime, ScheduledJob};
+use common::task_journal::{self, JournalEntry};
 use common::taskmanager::{
     GetAllorderRequest, GetBuyerInfoRequest, GetTicketInfoRequest, GrabTicketRequest, TaskRequest,
-    TaskResult, TaskStatus,
+    TaskResult, TaskStatus, TaskSummary,
 };
 use common::ticket::BilibiliTicket;
 use serde_json::{Value, json};
@@ -162,7 +164,8 @@ pub fn poll_task_results(state: State<'_, AppState>) -> Result<Value, String> {
             TaskResult::PushResult(r) => json!({
                 "type": "PushResult",
                 "success": r.success,
-                "message": r.message
+                "message": r.message,
+                "channels": r.channels
             }),
             TaskResult::GetAllorderRequestResult(r) => json!({
                 "type": "GetAllorderRequestResult",
@@ -222,7 +225,10 @@ pub fn poll_task_results(state: State<'_, AppState>) -> Result<Value, String> {
                 "order_id": r.order_id,
                 "message": r.message,
                 "pay_result": r.pay_result,
-                "confirm_result": r.confirm_result
+                "confirm_result": r.confirm_result,
+                "cancelled": r.cancelled,
+                "error_code": r.error_code,
+                "error_class": r.error_class
             }),
             TaskResult::DungeonQrResult(r) => json!({
                 "type": "DungeonQrResult",
@@ -248,14 +254,39 @@ pub fn cancel_task(state: State<'_, AppState>, task_id: String) -> Result<(), St
     Ok(())
 }
 
+#[tauri::command]
+pub fn list_tasks(state: State<'_, AppState>) -> Result<Vec<TaskSummary>, String> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock failed".to_string())?;
+
+    Ok(runtime.task_manager.list_tasks())
+}
+
 #[tauri::command]
 pub fn start_grab_ticket(state: State<'_, AppState>) -> Result<String, String> {
+    let request = build_grab_request(&state)?;
+    let task_id = request.task_id.clone();
+
+    // 提交任务
+    let mut runtime = state.runtime.lock().map_err(|_| "runtime lock failed")?;
+    runtime
+        .task_manager
+        .submit_task(TaskRequest::GrabTicketRequest(request))
+        .map_err(|e| format!("提交抢票任务失败: {}", e))?;
+
+    Ok(task_id)
+}
+
+// 按当前选择的账号、项目和购票人生成抢票请求
+fn build_grab_request(state: &AppState) -> Result<GrabTicketRequest, String> {
     // Acquire all necessary locks
     let config = state.config.lock().map_err(|_| "config lock failed")?;
     let ticket = state.ticket.lock().map_err(|_| "ticket lock failed")?;
     let auth = state.auth.lock().map_err(|_| "auth lock failed")?;
     let ui = state.ui.lock().map_err(|_| "ui lock failed")?;
-    let mut runtime = state.runtime.lock().map_err(|_| "runtime lock failed")?;
+    let runtime = state.runtime.lock().map_err(|_| "runtime lock failed")?;
 
     // 验证必要信息
     if ticket.ticket_id.is_empty() {
@@ -346,8 +377,8 @@ pub fn start_grab_ticket(state: State<'_, AppState>) -> Result<String, String> {
     );
 
     // 创建抢票请求
-    let grab_request = TaskRequest::GrabTicketRequest(GrabTicketRequest {
-        task_id: task_id.clone(),
+    Ok(GrabTicketRequest {
+        task_id,
         uid: account.uid,
         project_id: ticket.ticket_id.clone(),
         screen_id: ticket
@@ -368,13 +399,157 @@ pub fn start_grab_ticket(state: State<'_, AppState>) -> Result<String, String> {
         is_hot: false,
         local_captcha: runtime.local_captcha.clone(),
         skip_words: config.skip_words.clone(),
-    });
+        preferences: ticket.ticket_preferences.clone(),
+    })
+}
 
-    // 提交任务
-    runtime
+// 上次未结束的抢票任务（程序或电脑重启前提交的）
+#[tauri::command]
+pub fn get_unfinished_tasks(state: State<'_, AppState>) -> Result<Value, String> {
+    let runtime = state
+        .runtime
+        .lock()
+        .map_err(|_| "runtime lock failed".to_string())?;
+    let running: Vec<String> = runtime
         .task_manager
-        .submit_task(grab_request)
-        .map_err(|e| format!("提交抢票任务失败: {}", e))?;
+        .list_tasks()
+        .into_iter()
+        .map(|t| t.task_id)
+        .collect();
 
+    let entries: Vec<Value> = task_journal::load()?
+        .into_iter()
+        .filter(|e| !running.contains(&e.task_id))
+        .map(|e| {
+            json!({
+                "task_id": e.task_id,
+                "uid": e.uid,
+                "project_id": e.project_id,
+                "project_name": e.project_name(),
+                "grab_mode": e.grab_mode,
+                "buyers": e.buyer_info.iter().map(|b| b.name.clone()).collect::<Vec<_>>(),
+                "sale_begin": e.project_info.as_ref().map(|p| p.sale_begin),
+                "saved_at": e.saved_at
+            })
+        })
+        .collect();
+    Ok(json!(entries))
+}
+
+#[tauri::command]
+pub fn resume_task(state: State<'_, AppState>, task_id: String) -> Result<String, String> {
+    let config = state.config.lock().map_err(|_| "config lock failed")?;
+    let mut runtime = state.runtime.lock().map_err(|_| "runtime lock failed")?;
+
+    let entry = task_journal::find(&task_id)?;
+    let account = config
+        .accounts
+        .iter()
+        .find(|acc| acc.uid == entry.uid)
+        .ok_or_else(|| format!("账号 {} 已不存在，无法恢复任务", entry.uid))?;
+    let request = entry.to_request(
+        account,
+        &config.custom_config,
+        &config.push_config,
+        runtime.local_captcha.clone(),
+    )?;
+
+    let task_id = runtime
+        .task_manager
+        .submit_task(TaskRequest::GrabTicketRequest(request))
+        .map_err(|e| format!("恢复抢票任务失败: {}", e))?;
+    log::info!("已恢复抢票任务: {}", task_id);
     Ok(task_id)
 }
+
+#[tauri::command]
+pub fn discard_unfinished_task(task_id: String) -> Result<(), String> {
+    task_journal::remove(&task_id);
+    log::info!("已放弃恢复任务: {}", task_id);
+    Ok(())
+}
+
+fn schedule_time(
+    at: Option<i64>,
+    before_sale_minutes: Option<i64>,
+) -> Result<ScheduleTime, String> {
+    match (at, before_sale_minutes) {
+        (Some(time), None) => {
+            if time <= chrono::Local::now().timestamp_millis() {
+                return Err("定时时间必须晚于当前时间".to_string());
+            }
+            Ok(ScheduleTime::At { time })
+        }
+        (None, Some(minutes)) if minutes >= 0 => Ok(ScheduleTime::BeforeSale { minutes }),
+        (None, Some(_)) => Err("开售前分钟数不能为负数".to_string()),
+        _ => Err("请设置定时时间或开售前分钟数".to_string()),
+    }
+}
+
+// 定时抢票任务列表
+#[tauri::command]
+pub fn list_scheduled_jobs() -> Result<Value, String> {
+    let jobs: Vec<Value> = schedule::load()?
+        .into_iter()
+        .map(|job| {
+            json!({
+                "id": job.id,
+                "uid": job.grab.uid,
+                "project_name": job.grab.project_name(),
+                "grab_mode": job.grab.grab_mode,
+                "buyers": job.grab.buyer_info.iter().map(|b| b.name.clone()).collect::<Vec<_>>(),
+                "when": job.when,
+                "fire_at": job.fire_at(),
+                "preflight": job.preflight,
+                "preflight_log": job.preflight_log,
+                "status": job.status,
+                "message": job.message,
+                "task_id": job.task_id,
+                "created_at": job.created_at
+            })
+        })
+        .collect();
+    Ok(json!(jobs))
+}
+
+// 用当前选择的账号、项目和购票人新建定时任务
+#[tauri::command]
+pub fn add_scheduled_job(
+    state: State<'_, AppState>,
+    at: Option<i64>,
+    before_sale_minutes: Option<i64>,
+) -> Result<String, String> {
+    let when = schedule_time(at, before_sale_minutes)?;
+    let request = build_grab_request(&state)?;
+    let preflight = state
+        .config
+        .lock()
+        .map_err(|_| "config lock failed")?
+        .config
+        .schedule_preflight;
+
+    let job = ScheduledJob::new(JournalEntry::from_request(&request), when, preflight);
+    let id = job.id.clone();
+    schedule::add(job)?;
+    log::info!("已添加定时任务: {}", id);
+    Ok(id)
+}
+
+#[tauri::command]
+pub fn update_scheduled_job(
+    id: String,
+    at: Option<i64>,
+    before_sale_minutes: Option<i64>,
+) -> Result<(), String> {
+    let when = schedule_time(at, before_sale_minutes)?;
+    schedule::modify(&id, |job| job.reschedule(when, job.preflight))?;
+    log::info!("已修改定时任务: {}", id);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn remove_scheduled_job(id: String) -> Result<(), String> {
+    schedule::remove(&id)?;
+    log::info!("已删除定时任务: {}", id);
+    Ok(())
+}
@@ -1,5 +1,5 @@
 use crate::state::AppState;
-use common::ticket::{BuyerInfo, NoBindBuyerInfo};
+use common::ticket::{BuyerInfo, NoBindBuyerInfo, TicketPreference};
 use tauri::State;
 
 #[tauri::command]
@@ -91,6 +91,19 @@ pub fn set_selected_ticket(state: State<'_, AppState>, id: Option<i64>) -> Resul
     Ok(())
 }
 
+#[tauri::command]
+pub fn set_ticket_preferences(
+    state: State<'_, AppState>,
+    preferences: Vec<TicketPreference>,
+) -> Result<(), String> {
+    let mut ticket = state
+        .ticket
+        .lock()
+        .map_err(|_| "ticket lock failed".to_string())?;
+    ticket.ticket_preferences = preferences;
+    Ok(())
+}
+
 #[tauri::command]
 pub fn set_selected_buyer_list(
     state: State<'_, AppState>,
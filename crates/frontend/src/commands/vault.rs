@@ -0,0 +1,98 @@
+use crate::state::{AppState, ConfigState};
+use common::config::BtrConfig as Config;
+use common::paths;
+use common::vault::{self, VaultStatus};
+use tauri::State;
+
+#[tauri::command]
+pub fn get_vault_status() -> Result<VaultStatus, String> {
+    Ok(vault::status(&paths::config_path()))
+}
+
+// 输入口令解锁加密配置，并重新载入账号等配置
+#[tauri::command]
+pub fn unlock_vault(state: State<'_, AppState>, passphrase: String) -> Result<VaultStatus, String> {
+    vault::set_passphrase(Some(passphrase));
+    let config = match Config::load_config() {
+        Ok(config) => config,
+        Err(e) => {
+            vault::set_passphrase(None);
+            return Err(e.to_string());
+        }
+    };
+    let limits = config.custom_config.task_limits.clone();
+    {
+        let mut config_state = state
+            .config
+            .lock()
+            .map_err(|_| "config lock failed".to_string())?;
+        replace_config(&mut config_state, config);
+    }
+    state.apply_task_limits(limits)?;
+    log::info!("加密配置已解锁");
+    Ok(vault::status(&paths::config_path()))
+}
+
+// 开启加密：提供口令时使用口令，否则生成密钥文件
+#[tauri::command]
+pub fn enable_vault(
+    state: State<'_, AppState>,
+    passphrase: Option<String>,
+) -> Result<VaultStatus, String> {
+    if vault::status(&paths::config_path()).encrypted {
+        return Err("配置文件已经是加密格式".to_string());
+    }
+    match passphrase.filter(|p| !p.is_empty()) {
+        Some(passphrase) => vault::set_passphrase(Some(passphrase)),
+        None => vault::generate_keyfile(&vault::keyfile_path())?,
+    }
+    let config_state = state
+        .config
+        .lock()
+        .map_err(|_| "config lock failed".to_string())?;
+    config_state
+        .config
+        .save_config()
+        .map_err(|e| format!("保存加密配置失败: {}", e))?;
+    log::info!("配置文件已加密");
+    Ok(vault::status(&paths::config_path()))
+}
+
+// 取消加密，以明文写回配置
+#[tauri::command]
+pub fn disable_vault(state: State<'_, AppState>) -> Result<VaultStatus, String> {
+    // 环境变量中的口令仍然有效，下次保存会重新加密
+    if vault::env_passphrase_set() {
+        return Err(
+            "已设置环境变量 BTR_VAULT_PASSPHRASE，请先取消该环境变量再关闭加密".to_string(),
+        );
+    }
+    let mut config_state = state
+        .config
+        .lock()
+        .map_err(|_| "config lock failed".to_string())?;
+    // 从磁盘重新解密，避免把未解锁时的默认配置写回
+    let config = Config::load_config().map_err(|e| e.to_string())?;
+    config
+        .save_config_plaintext()
+        .map_err(|e| format!("保存配置失败: {}", e))?;
+    replace_config(&mut config_state, config);
+    vault::set_passphrase(None);
+    let keyfile = vault::keyfile_path();
+    if keyfile.exists() {
+        std::fs::remove_file(&keyfile).map_err(|e| format!("删除密钥文件失败: {}", e))?;
+    }
+    log::info!("配置文件已取消加密");
+    Ok(vault::status(&paths::config_path()))
+}
+
+fn replace_config(config_state: &mut ConfigState, config: Config) {
+    config_state.accounts = config.accounts.clone();
+    config_state.push_config = config.push_config.clone();
+    config_state.custom_config = config.custom_config.clone();
+    config_state.skip_words = config.skip_words.clone();
+    config_state.config = config;
+    for account in &mut config_state.accounts {
+        account.ensure_client();
+    }
+}
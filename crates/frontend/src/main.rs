@@ -28,6 +28,10 @@ fn main() {
             common::record_log::add_log_listener(move |message| {
                 let _ = handle.emit("log-event", message);
             });
+            let record_handle = app.handle().clone();
+            common::record_log::add_record_listener(move |record| {
+                let _ = record_handle.emit("log-record", record);
+            });
 
             let handle_task = app.handle().clone();
             let state = handle_task.state::<AppState>();
@@ -74,6 +78,14 @@ fn main() {
             task::get_order_list,
             task::poll_task_results,
             task::cancel_task,
+            task::list_tasks,
+            task::get_unfinished_tasks,
+            task::resume_task,
+            task::discard_unfinished_task,
+            task::list_scheduled_jobs,
+            task::add_scheduled_job,
+            task::update_scheduled_job,
+            task::remove_scheduled_job,
             task::start_grab_ticket,
             ticket::set_ticket_id,
             ticket::set_grab_mode,
@@ -83,6 +95,7 @@ fn main() {
             ticket::set_show_orderlist_window,
             ticket::set_selected_screen,
             ticket::set_selected_ticket,
+            ticket::set_ticket_preferences,
             ticket::set_selected_buyer_list,
             ticket::set_buyer_type,
             ticket::set_no_bind_buyer_info,
@@ -90,6 +103,9 @@ fn main() {
             general::push_test,
             general::get_policy,
             general::get_logs,
+            general::get_log_records,
+            general::get_task_log,
+            general::export_task_log,
             general::get_app_info,
             general::clear_grab_logs,
             general::set_show_qr_windows,
@@ -104,6 +120,12 @@ fn main() {
             general::save_settings,
             general::connect_dungeon,
             general::clear_logs,
+            history::get_grab_history,
+            history::get_grab_task_summaries,
+            vault::get_vault_status,
+            vault::unlock_vault,
+            vault::enable_vault,
+            vault::disable_vault,
         ])
         .run(tauri::generate_context!())
         .expect("tauri run failed");
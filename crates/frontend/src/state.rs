@@ -4,15 +4,20 @@ use serde_json::Value;
 use std::sync::{Arc, Mutex};
 
 use backend::dungeon::DungeonService;
+use backend::scheduler::Scheduler;
 use backend::taskmanager::TaskManagerImpl;
 use common::account::Account;
 use common::captcha::LocalCaptcha;
-use common::config::{BtrConfig as Config, CustomConfig, PushConfig};
+use common::config::{BtrConfig as Config, CustomConfig, PushConfig, TaskLimits};
+use common::endpoints::ApiEndpoints;
 use common::login::LoginInput;
 use common::machine_id;
+use common::metrics_exporter;
+use common::record_log;
 use common::show_orderlist::OrderResponse;
+use common::task_api;
 use common::taskmanager::TaskManager;
-use common::ticket::{BilibiliTicket, TicketInfo};
+use common::ticket::{BilibiliTicket, TicketInfo, TicketPreference};
 use common::ticket::{BuyerInfo, NoBindBuyerInfo};
 
 use crate::utils::{create_client, default_user_agent};
@@ -49,6 +54,7 @@ pub struct TicketState {
     pub show_screen_info: Option<i64>,
     pub selected_screen_id: Option<i64>,
     pub selected_ticket_id: Option<i64>,
+    pub ticket_preferences: Vec<TicketPreference>, //候选票种
     pub ticket_info_last_request_time: Option<std::time::Instant>,
     pub confirm_ticket_info: Option<String>,
     pub selected_buyer_list: Option<Vec<BuyerInfo>>,
@@ -128,6 +134,13 @@ impl AppState {
             log::error!("加载配置失败，将使用默认配置: {}", e);
             Config::default()
         });
+        ApiEndpoints::set_current(config.api_endpoints.clone());
+        if let Err(e) = metrics_exporter::start(&config.metrics_exporter) {
+            log::error!("{}", e);
+        }
+        if config.json_log_file {
+            record_log::set_json_file(true);
+        }
 
         let mut auth_state = AuthState {
             login_method: "扫码登录".to_string(),
@@ -168,6 +181,7 @@ impl AppState {
             show_screen_info: None,
             selected_screen_id: None,
             selected_ticket_id: None,
+            ticket_preferences: Vec::new(),
             ticket_info_last_request_time: None,
             confirm_ticket_info: None,
             selected_buyer_list: None,
@@ -178,6 +192,12 @@ impl AppState {
         let (tx, rx) = mpsc::channel(100);
         let mut task_manager = Box::new(TaskManagerImpl::new());
         task_manager.set_result_sender(tx.clone());
+        task_manager.set_limits(config_state.custom_config.task_limits.clone());
+        if let Err(e) = task_api::start(Arc::new(task_manager.control())) {
+            log::warn!("{}", e);
+        }
+        let control = task_manager.control();
+        let local_captcha = LocalCaptcha::new();
 
         let dungeon_service = Arc::new(DungeonService::new());
         {
@@ -196,7 +216,7 @@ impl AppState {
             is_loading: false,
             logs: Vec::new(),
             task_manager,
-            local_captcha: LocalCaptcha::new(),
+            local_captcha: local_captcha.clone(),
             result_receiver: Some(rx),
             result_sender: Some(tx),
             dungeon_service: Some(dungeon_service),
@@ -222,12 +242,42 @@ impl AppState {
             announce4: None,
         };
 
+        let config = Arc::new(Mutex::new(config_state));
+
+        // 定时任务使用界面中的最新账号和抢票设置
+        let scheduler_config = config.clone();
+        Scheduler::new(
+            control,
+            Arc::new(move || -> Result<Config, String> {
+                let state = scheduler_config
+                    .lock()
+                    .map_err(|_| "config lock failed".to_string())?;
+                let mut config = state.config.clone();
+                config.accounts = state.accounts.clone();
+                config.custom_config = state.custom_config.clone();
+                config.push_config = state.push_config.clone();
+                Ok(config)
+            }),
+            local_captcha,
+        )
+        .start();
+
         Self {
-            config: Arc::new(Mutex::new(config_state)),
+            config,
             ticket: Arc::new(Mutex::new(ticket_state)),
             auth: Arc::new(Mutex::new(auth_state)),
             runtime: Arc::new(Mutex::new(runtime_state)),
             ui: Arc::new(Mutex::new(ui_state)),
         }
     }
+
+    // 保存设置或解锁配置后，按新配置调整任务并发上限
+    pub fn apply_task_limits(&self, limits: TaskLimits) -> Result<(), String> {
+        let runtime = self
+            .runtime
+            .lock()
+            .map_err(|_| "runtime lock failed".to_string())?;
+        runtime.task_manager.set_limits(limits);
+        Ok(())
+    }
 }
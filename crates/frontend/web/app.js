@@ -165,7 +165,11 @@ function handleGrabTicketResult(data) {
 			console.error("显示成功弹窗失败:", e);
 			showError("抢票成功，但显示详情失败: " + e.message);
 		}
-	} else if (data.message && data.message.includes("待付款订单")) {
+	} else if (data.cancelled) {
+		showWarning("抢票任务已取消");
+		stopGrab();
+	} else if (data.error_class === "fatal" || data.error_class === "needs_relogin") {
+		// 待付款订单、购买上限、登录失效等无法继续的错误
 		showError(data.message);
 		stopGrab();
 	}
@@ -453,7 +457,7 @@ function initializeEventListeners() {
 	document.getElementById("grab-mode")?.addEventListener("change", updateSkipWordsVisibility);
 	document.getElementById("enable-push")?.addEventListener("change", updatePushSettingsVisibility);
 
-	["bark", "pushplus", "fangtang", "dingtalk", "wechat", "gotify", "dungeon"].forEach(m => {
+	["bark", "pushplus", "fangtang", "dingtalk", "wechat", "gotify", "ntfy", "matrix", "smtp", "webhook", "dungeon"].forEach(m => {
 		document.getElementById(`push-method-${m}`)?.addEventListener("change", updatePushSettingsVisibility);
 	});
 }
@@ -522,6 +526,9 @@ function initializeApp() {
 			window.__TAURI__.event.listen('log-event', (event) => {
 				handleIncomingLog(event.payload);
 			});
+			window.__TAURI__.event.listen('log-record', (event) => {
+				indexLogRecord(event.payload);
+			});
 		}
 
 		init();
@@ -925,6 +932,8 @@ async function showScreenTicketSelector(ticketInfo) {
 		updateTicketList(parseInt(this.value));
 	};
 	updateTicketList(availableScreens[0].id);
+	ticketPreferences = [];
+	renderTicketPreferences();
 
 	const idBind = ticketInfo.id_bind;
 	const realNameSection = document.getElementById("real-name-buyer-section");
@@ -956,6 +965,50 @@ function updateTicketList(screenId) {
 	}).join("");
 }
 
+// 候选票种列表，确认时随场次票种一起保存
+let ticketPreferences = [];
+
+function addTicketPreference() {
+	const screenId = document.getElementById("screen-select").value;
+	const ticketId = document.getElementById("ticket-select").value;
+	if (!screenId || !ticketId) {
+		showWarning("请选择场次和票种");
+		return;
+	}
+	if (ticketPreferences.some((p) => p.screen_id === screenId && p.ticket_id === ticketId)) {
+		showWarning("该票种已在候选列表中");
+		return;
+	}
+	const maxPrice = parseFloat(document.getElementById("preference-max-price").value);
+	ticketPreferences.push({
+		screen_id: screenId,
+		ticket_id: ticketId,
+		max_price: Number.isFinite(maxPrice) ? Math.round(maxPrice * 100) : null,
+	});
+	renderTicketPreferences();
+}
+
+function removeTicketPreference(index) {
+	ticketPreferences.splice(index, 1);
+	renderTicketPreferences();
+}
+
+function renderTicketPreferences() {
+	const list = document.getElementById("preference-list");
+	const ticketInfo = window.currentTicketInfo;
+	list.innerHTML = ticketPreferences.map((p, index) => {
+		const screen = ticketInfo && ticketInfo.screen_list.find((s) => String(s.id) === p.screen_id);
+		const ticket = screen && screen.ticket_list.find((t) => String(t.id) === p.ticket_id);
+		const name = ticket ? `${screen.name} ${ticket.desc}` : `场次${p.screen_id} 票种${p.ticket_id}`;
+		const cap = p.max_price !== null ? ` (≤ ¥${(p.max_price / 100).toFixed(2)})` : "";
+		return `
+        <div style="display: flex; align-items: center; justify-content: space-between; padding: 6px 0;">
+            <span>${index + 1}. ${name}${cap}</span>
+            <button class="btn btn-danger" onclick="removeTicketPreference(${index})">移除</button>
+        </div>`;
+	}).join("");
+}
+
 async function saveNoBindBuyerInfo() {
 	const name = document.getElementById("no-bind-name").value.trim();
 	const tel = document.getElementById("no-bind-tel").value.trim();
@@ -1032,6 +1085,9 @@ async function confirmScreenTicketSelection() {
 		await invoke("set_selected_ticket", {
 			id: ticketId
 		});
+		await invoke("set_ticket_preferences", {
+			preferences: ticketPreferences
+		});
 		await invoke("set_buyer_type", {
 			buyerType: parseInt(buyerType)
 		});
@@ -1155,6 +1211,223 @@ async function stopGrab() {
 	}
 }
 
+const TASK_PHASE_LABELS = {
+	waiting_for_sale: "等待开售",
+	getting_token: "获取token",
+	confirming: "确认订单",
+	creating_order: "创建订单",
+	awaiting_payment: "等待支付"
+};
+
+function formatTaskStatus(status) {
+	if (typeof status === "string") {
+		return { Pending: "等待中", Running: "运行中", Cancelled: "已取消" }[status] || status;
+	}
+	if ("Completed" in status) return status.Completed ? "已完成" : "未成功";
+	if ("Failed" in status) return "失败: " + status.Failed;
+	return JSON.stringify(status);
+}
+
+async function loadTaskList() {
+	const list = document.getElementById("task-list");
+	if (!list || !invoke) return;
+	try {
+		const tasks = await invoke("list_tasks");
+		list.innerHTML = "";
+		if (tasks.length === 0) {
+			list.innerHTML = '<li style="padding: 20px; text-align: center; color: var(--text-secondary);">暂无任务</li>';
+			return;
+		}
+		tasks.forEach((task) => {
+			const active = task.status === "Pending" || task.status === "Running";
+			const meta = [
+				task.uid ? `UID: ${task.uid}` : null,
+				task.project_id ? `项目: ${task.project_id}` : null,
+				task.phase ? `阶段: ${TASK_PHASE_LABELS[task.phase] || task.phase}` : null,
+				`已运行 ${Math.round(task.elapsed_ms / 1000)} 秒`
+			].filter(Boolean).join(" | ");
+			const li = document.createElement("li");
+			li.className = "account-item";
+			li.innerHTML = `
+                <div class="account-info">
+                    <div class="account-name">${task.kind} · ${formatTaskStatus(task.status)}</div>
+                    <div class="account-meta">${task.task_id}</div>
+                    <div class="account-meta">${meta}</div>
+                </div>
+                <div>
+                    ${active ? `<button class="btn btn-danger" onclick="cancelListedTask('${task.task_id}')">取消</button>` : ""}
+                </div>
+            `;
+			list.appendChild(li);
+		});
+	} catch (error) {
+		list.innerHTML = `<div style="padding: 20px; text-align: center; color: var(--error-color);">加载失败: ${error}</div>`;
+	}
+}
+
+async function cancelListedTask(taskId) {
+	try {
+		await invoke("cancel_task", { taskId });
+		if (taskId === currentTaskId) await stopGrab();
+		showSuccess("已取消任务 " + taskId);
+	} catch (error) {
+		showError("取消失败: " + error);
+	}
+	await loadTaskList();
+}
+
+// 上次退出前未结束的抢票任务，提供恢复或放弃
+async function loadUnfinishedTasks() {
+	const card = document.getElementById("unfinished-tasks-card");
+	const list = document.getElementById("unfinished-task-list");
+	if (!card || !list || !invoke) return;
+	try {
+		const entries = await invoke("get_unfinished_tasks");
+		card.style.display = entries.length > 0 ? "block" : "none";
+		list.innerHTML = "";
+		entries.forEach((entry) => {
+			const sale = entry.sale_begin ? new Date(entry.sale_begin * 1000).toLocaleString() : "-";
+			const li = document.createElement("li");
+			li.className = "account-item";
+			li.innerHTML = `
+                <div class="account-info">
+                    <div class="account-name">${entry.project_name}</div>
+                    <div class="account-meta">UID: ${entry.uid} | 模式: ${entry.grab_mode} | 开售: ${sale}</div>
+                    <div class="account-meta">购票人: ${entry.buyers.join("、") || "-"} | ${entry.task_id}</div>
+                </div>
+                <div>
+                    <button class="btn btn-success" onclick="resumeUnfinishedTask('${entry.task_id}')">恢复</button>
+                    <button class="btn btn-danger" onclick="discardUnfinishedTask('${entry.task_id}')">放弃</button>
+                </div>
+            `;
+			list.appendChild(li);
+		});
+		if (entries.length > 0) {
+			showWarning(`有 ${entries.length} 个上次未结束的抢票任务，可在「运行」页恢复`);
+		}
+	} catch (error) {
+		console.error("读取未完成任务失败:", error);
+	}
+}
+
+async function resumeUnfinishedTask(taskId) {
+	try {
+		if (isGrabTaskRunning) {
+			showWarning("任务已在运行");
+			return;
+		}
+		currentTaskId = await invoke("resume_task", { taskId });
+		isGrabTaskRunning = true;
+		document.getElementById("start-grab-btn").disabled = true;
+		document.getElementById("stop-grab-btn").disabled = false;
+		document.getElementById("monitor-status").textContent = "运行中";
+		document.getElementById("monitor-status").style.color = "var(--success-color)";
+		showSuccess("已恢复抢票任务 " + taskId);
+	} catch (error) {
+		showError("恢复失败: " + error);
+	}
+	await loadUnfinishedTasks();
+	await loadTaskList();
+}
+
+async function discardUnfinishedTask(taskId) {
+	try {
+		await invoke("discard_unfinished_task", { taskId });
+	} catch (error) {
+		showError("操作失败: " + error);
+	}
+	await loadUnfinishedTasks();
+}
+
+const SCHEDULE_STATUS_LABELS = {
+	scheduled: "等待中",
+	submitted: "已提交",
+	failed: "失败"
+};
+
+function describeScheduleTime(when) {
+	if (when.type === "at") return "指定时间 " + new Date(when.time).toLocaleString();
+	return `开售前 ${when.minutes} 分钟`;
+}
+
+// 读取定时任务表单，返回 invoke 参数
+function readScheduleForm() {
+	if (document.getElementById("schedule-type").value === "at") {
+		const value = document.getElementById("schedule-at").value;
+		if (!value) throw new Error("请选择定时时间");
+		return { at: new Date(value).getTime(), beforeSaleMinutes: null };
+	}
+	const minutes = parseInt(document.getElementById("schedule-minutes").value, 10);
+	if (isNaN(minutes)) throw new Error("请输入开售前分钟数");
+	return { at: null, beforeSaleMinutes: minutes };
+}
+
+async function loadScheduledJobs() {
+	const list = document.getElementById("schedule-list");
+	if (!list || !invoke) return;
+	try {
+		const jobs = await invoke("list_scheduled_jobs");
+		list.innerHTML = "";
+		if (jobs.length === 0) {
+			list.innerHTML = '<li style="padding: 20px; text-align: center; color: var(--text-secondary);">暂无定时任务</li>';
+			return;
+		}
+		jobs.forEach((job) => {
+			const fireAt = job.fire_at ? new Date(job.fire_at).toLocaleString() : "等待获取开售时间";
+			const preflight = job.preflight_log
+				.map((r) => `${r.success ? "✓" : "✗"} ${r.message}`)
+				.join("；");
+			const li = document.createElement("li");
+			li.className = "account-item";
+			li.innerHTML = `
+                <div class="account-info">
+                    <div class="account-name">${job.project_name} · ${SCHEDULE_STATUS_LABELS[job.status] || job.status}</div>
+                    <div class="account-meta">UID: ${job.uid} | 模式: ${job.grab_mode} | ${describeScheduleTime(job.when)} | 触发: ${fireAt}</div>
+                    <div class="account-meta">购票人: ${job.buyers.join("、") || "-"} | ${job.id}</div>
+                    ${job.message ? `<div class="account-meta">${job.message}</div>` : ""}
+                    ${preflight ? `<div class="account-meta">预检: ${preflight}</div>` : ""}
+                </div>
+                <div>
+                    ${job.status !== "submitted" ? `<button class="btn btn-primary" onclick="updateScheduledJob('${job.id}')">按表单修改</button>` : ""}
+                    <button class="btn btn-danger" onclick="removeScheduledJob('${job.id}')">删除</button>
+                </div>
+            `;
+			list.appendChild(li);
+		});
+	} catch (error) {
+		list.innerHTML = `<div style="padding: 20px; text-align: center; color: var(--error-color);">加载失败: ${error}</div>`;
+	}
+}
+
+async function addScheduledJob() {
+	try {
+		const id = await invoke("add_scheduled_job", readScheduleForm());
+		showSuccess("已添加定时任务 " + id);
+	} catch (error) {
+		showError("添加定时任务失败: " + (error.message || error));
+	}
+	await loadScheduledJobs();
+}
+
+async function updateScheduledJob(id) {
+	try {
+		await invoke("update_scheduled_job", { id, ...readScheduleForm() });
+		showSuccess("已修改定时任务");
+	} catch (error) {
+		showError("修改定时任务失败: " + (error.message || error));
+	}
+	await loadScheduledJobs();
+}
+
+async function removeScheduledJob(id) {
+	try {
+		await invoke("remove_scheduled_job", { id });
+	} catch (error) {
+		showError("删除定时任务失败: " + error);
+	}
+	await loadScheduledJobs();
+}
+
 async function triggerDungeonBind() {
     try {
         if (!invoke) return;
@@ -1197,6 +1470,12 @@ async function loadSettings() {
 			document.getElementById("max-fake-check-retry").value = state.custom_config.max_fake_check_retry || "10";
 			document.getElementById("max-order-retry").value = state.custom_config.max_order_retry || "30";
 			document.getElementById("retry-interval-ms").value = state.custom_config.retry_interval_ms || "400";
+			const limits = state.custom_config.task_limits || {};
+			document.getElementById("limit-grab-ticket").value = limits.grab_ticket ?? 0;
+			document.getElementById("limit-get-all-order").value = limits.get_all_order ?? 2;
+			document.getElementById("limit-get-ticket-info").value = limits.get_ticket_info ?? 4;
+			document.getElementById("limit-get-buyer-info").value = limits.get_buyer_info ?? 2;
+			document.getElementById("limit-background-while-grabbing").value = limits.background_while_grabbing ?? 1;
 			document.getElementById("custom-ua").checked = state.custom_config.open_custom_ua || false;
 			document.getElementById("user-agent").value = state.custom_config.custom_ua || "";
 		}
@@ -1220,7 +1499,7 @@ async function loadSettings() {
 			}
 
 			if (state.push_config.enabled_methods) {
-				["bark", "pushplus", "fangtang", "dingtalk", "wechat", "gotify", "dungeon"].forEach(m => {
+				["bark", "pushplus", "fangtang", "dingtalk", "wechat", "gotify", "ntfy", "matrix", "smtp", "webhook", "dungeon"].forEach(m => {
 					const el = document.getElementById(`push-method-${m}`);
 					if (el) el.checked = state.push_config.enabled_methods.includes(m);
 				});
@@ -1229,6 +1508,36 @@ async function loadSettings() {
 				document.getElementById("gotify-url").value = state.push_config.gotify_config.gotify_url || "";
 				document.getElementById("gotify-token").value = state.push_config.gotify_config.gotify_token || "";
 			}
+			if (state.push_config.ntfy_config) {
+				const ntfy = state.push_config.ntfy_config;
+				document.getElementById("ntfy-topic-url").value = ntfy.topic_url || "";
+				document.getElementById("ntfy-token").value = ntfy.token || "";
+				document.getElementById("ntfy-priority").value = ntfy.priority || "5";
+			}
+			if (state.push_config.matrix_config) {
+				const matrix = state.push_config.matrix_config;
+				document.getElementById("matrix-homeserver").value = matrix.homeserver || "";
+				document.getElementById("matrix-access-token").value = matrix.access_token || "";
+				document.getElementById("matrix-room-id").value = matrix.room_id || "";
+			}
+			if (state.push_config.smtp_config) {
+				const smtp = state.push_config.smtp_config;
+				document.getElementById("smtp-host").value = smtp.host || "";
+				document.getElementById("smtp-port").value = smtp.port || "587";
+				document.getElementById("smtp-security").value = smtp.security || "start_tls";
+				document.getElementById("smtp-username").value = smtp.username || "";
+				document.getElementById("smtp-password").value = smtp.password || "";
+				document.getElementById("smtp-from").value = smtp.from || "";
+				document.getElementById("smtp-to").value = (smtp.to || []).join(", ");
+			}
+			webhooks = (state.push_config.webhooks || []).map((w) => ({
+				name: w.name || "",
+				url: w.url || "",
+				method: w.method || "POST",
+				headers: Object.entries(w.headers || {}).map(([k, v]) => `${k}: ${v}`).join("\n"),
+				body: w.body || "",
+			}));
+			renderWebhooks();
 		}
 		updatePushSettingsVisibility();
 		updateSkipWordsVisibility();
@@ -1239,7 +1548,7 @@ async function loadSettings() {
 
 function updatePushSettingsVisibility() {
 	const pushEnabled = document.getElementById("enable-push").checked;
-	const methods = ["bark", "pushplus", "fangtang", "dingtalk", "wechat", "gotify", "dungeon"];
+	const methods = ["bark", "pushplus", "fangtang", "dingtalk", "wechat", "gotify", "ntfy", "matrix", "smtp", "webhook", "dungeon"];
 
 	methods.forEach(m => {
 		const channelCheckbox = document.getElementById(`push-method-${m}`);
@@ -1255,6 +1564,75 @@ function updatePushSettingsVisibility() {
 	});
 }
 
+// Webhook 列表，请求头在界面上按 "名称: 值" 每行一个编辑
+let webhooks = [];
+
+const DEFAULT_WEBHOOK_BODY = '{"title": "{{title}}", "message": "{{message}}", "order_id": "{{order_id}}", "pay_url": "{{pay_url}}"}';
+
+function addWebhook() {
+	webhooks.push({ name: "", url: "", method: "POST", headers: "", body: DEFAULT_WEBHOOK_BODY });
+	renderWebhooks();
+}
+
+function removeWebhook(index) {
+	webhooks.splice(index, 1);
+	renderWebhooks();
+}
+
+function updateWebhook(index, field, value) {
+	webhooks[index][field] = value;
+}
+
+function parseWebhookHeaders(text) {
+	const headers = {};
+	text.split("\n").forEach((line) => {
+		const pos = line.indexOf(":");
+		if (pos > 0) headers[line.slice(0, pos).trim()] = line.slice(pos + 1).trim();
+	});
+	return headers;
+}
+
+function renderWebhooks() {
+	const list = document.getElementById("webhook-list");
+	if (!list) return;
+	list.innerHTML = webhooks.map((w, index) => `
+        <div class="p-12 bg-light rounded-6 mb-16">
+            <div class="settings-grid">
+                <div class="settings-item-inner">
+                    <label class="form-label">名称</label>
+                    <input type="text" class="form-input" data-field="name" placeholder="用于区分推送结果" oninput="updateWebhook(${index}, 'name', this.value)" />
+                </div>
+                <div class="settings-item-inner">
+                    <label class="form-label">请求方法</label>
+                    <select class="select-input w-full" data-field="method" onchange="updateWebhook(${index}, 'method', this.value)">
+                        <option value="POST">POST</option>
+                        <option value="PUT">PUT</option>
+                        <option value="GET">GET</option>
+                    </select>
+                </div>
+            </div>
+            <div class="settings-item-inner mt-8">
+                <label class="form-label">URL</label>
+                <input type="text" class="form-input" data-field="url" placeholder="https://relay.example.com/hook" oninput="updateWebhook(${index}, 'url', this.value)" />
+            </div>
+            <div class="settings-item-inner mt-8">
+                <label class="form-label">请求头（每行一个，名称: 值）</label>
+                <textarea class="form-input" data-field="headers" rows="2" placeholder="Authorization: Bearer xxx" oninput="updateWebhook(${index}, 'headers', this.value)"></textarea>
+            </div>
+            <div class="settings-item-inner mt-8">
+                <label class="form-label">请求体模板</label>
+                <textarea class="form-input" data-field="body" rows="3" oninput="updateWebhook(${index}, 'body', this.value)"></textarea>
+            </div>
+            <button class="btn btn-danger mt-8" onclick="removeWebhook(${index})">移除</button>
+        </div>`).join("");
+	// 用户输入的内容通过 value 赋值，避免拼进 HTML
+	Array.from(list.children).forEach((item, index) => {
+		item.querySelectorAll("[data-field]").forEach((el) => {
+			el.value = webhooks[index][el.dataset.field];
+		});
+	});
+}
+
 function updateSkipWordsVisibility() {
 	const mode = document.getElementById("grab-mode").value;
 	const container = document.getElementById("skip-words-settings");
@@ -1275,6 +1653,17 @@ async function saveSettings() {
 		const maxFakeCheckRetry = parseInt(document.getElementById("max-fake-check-retry").value) || 10;
 		const maxOrderRetry = parseInt(document.getElementById("max-order-retry").value) || 30;
 		const retryIntervalMs = parseInt(document.getElementById("retry-interval-ms").value) || 400;
+		const limitValue = (id, fallback) => {
+			const value = parseInt(document.getElementById(id).value);
+			return Number.isNaN(value) || value < 0 ? fallback : value;
+		};
+		const taskLimits = {
+			grab_ticket: limitValue("limit-grab-ticket", 0),
+			get_all_order: limitValue("limit-get-all-order", 2),
+			get_ticket_info: limitValue("limit-get-ticket-info", 4),
+			get_buyer_info: limitValue("limit-get-buyer-info", 2),
+			background_while_grabbing: limitValue("limit-background-while-grabbing", 1),
+		};
 
 		const customUa = document.getElementById("custom-ua").checked;
 		const userAgent = document.getElementById("user-agent").value;
@@ -1286,7 +1675,7 @@ async function saveSettings() {
 		const dungeonPauseMs = parseInt(document.getElementById("dungeon-pause-ms").value);
 		const dungeonCount = parseInt(document.getElementById("dungeon-count").value);
 
-		const enabledMethods = ["bark", "pushplus", "fangtang", "dingtalk", "wechat", "gotify", "dungeon"].filter(m => document.getElementById(`push-method-${m}`)?.checked);
+		const enabledMethods = ["bark", "pushplus", "fangtang", "dingtalk", "wechat", "gotify", "ntfy", "matrix", "smtp", "webhook", "dungeon"].filter(m => document.getElementById(`push-method-${m}`)?.checked);
 
 		await invoke("save_settings", {
 			grabMode,
@@ -1301,6 +1690,32 @@ async function saveSettings() {
 			wechatToken: document.getElementById("wechat-token").value,
 			gotifyUrl: document.getElementById("gotify-url").value,
 			gotifyToken: document.getElementById("gotify-token").value,
+			ntfyConfig: {
+				topic_url: document.getElementById("ntfy-topic-url").value.trim(),
+				token: document.getElementById("ntfy-token").value.trim(),
+				priority: parseInt(document.getElementById("ntfy-priority").value) || 5,
+			},
+			matrixConfig: {
+				homeserver: document.getElementById("matrix-homeserver").value.trim(),
+				access_token: document.getElementById("matrix-access-token").value.trim(),
+				room_id: document.getElementById("matrix-room-id").value.trim(),
+			},
+			smtpConfig: {
+				host: document.getElementById("smtp-host").value.trim(),
+				port: parseInt(document.getElementById("smtp-port").value) || 587,
+				security: document.getElementById("smtp-security").value,
+				username: document.getElementById("smtp-username").value.trim(),
+				password: document.getElementById("smtp-password").value,
+				from: document.getElementById("smtp-from").value.trim(),
+				to: document.getElementById("smtp-to").value.split(",").map(s => s.trim()).filter(s => s.length > 0),
+			},
+			webhooks: webhooks.map((w) => ({
+				name: w.name.trim(),
+				url: w.url.trim(),
+				method: w.method,
+				headers: parseWebhookHeaders(w.headers),
+				body: w.body,
+			})),
 			customUa,
 			userAgent,
 			skipWords: skipWords.length > 0 ? skipWords : null,
@@ -1309,6 +1724,7 @@ async function saveSettings() {
 			maxFakeCheckRetry,
 			maxOrderRetry,
 			retryIntervalMs,
+			taskLimits,
 			dungeonChannel,
 			dungeonIntensity,
 			dungeonFrequency,
@@ -1337,6 +1753,11 @@ function resetSettings() {
 	document.getElementById("max-fake-check-retry").value = "10";
 	document.getElementById("max-order-retry").value = "30";
 	document.getElementById("retry-interval-ms").value = "400";
+	document.getElementById("limit-grab-ticket").value = "0";
+	document.getElementById("limit-get-all-order").value = "2";
+	document.getElementById("limit-get-ticket-info").value = "4";
+	document.getElementById("limit-get-buyer-info").value = "2";
+	document.getElementById("limit-background-while-grabbing").value = "1";
 
 	document.getElementById("custom-ua").checked = true;
 	document.getElementById("user-agent").value = "Mozilla/5.0 (Linux; Android 6.0; Nexus 5 Build/MRA58N) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/134.0.0.0 Mobile Safari/537.36";
@@ -1348,8 +1769,23 @@ function resetSettings() {
 	document.getElementById("wechat-token").value = "";
 	document.getElementById("gotify-url").value = "";
 	document.getElementById("gotify-token").value = "";
-
-	["bark", "pushplus", "fangtang", "dingtalk", "wechat", "gotify", "dungeon"].forEach(m => {
+	document.getElementById("ntfy-topic-url").value = "";
+	document.getElementById("ntfy-token").value = "";
+	document.getElementById("ntfy-priority").value = "5";
+	document.getElementById("matrix-homeserver").value = "";
+	document.getElementById("matrix-access-token").value = "";
+	document.getElementById("matrix-room-id").value = "";
+	document.getElementById("smtp-host").value = "";
+	document.getElementById("smtp-port").value = "587";
+	document.getElementById("smtp-security").value = "start_tls";
+	document.getElementById("smtp-username").value = "";
+	document.getElementById("smtp-password").value = "";
+	document.getElementById("smtp-from").value = "";
+	document.getElementById("smtp-to").value = "";
+	webhooks = [];
+	renderWebhooks();
+
+	["bark", "pushplus", "fangtang", "dingtalk", "wechat", "gotify", "ntfy", "matrix", "smtp", "webhook", "dungeon"].forEach(m => {
 		const el = document.getElementById(`push-method-${m}`);
 		if (el) el.checked = false;
 	});
@@ -1409,7 +1845,11 @@ function switchTab(tabName) {
 	const target = document.getElementById(`tab-${tabName}`);
 	if (target) {
 		target.classList.add("active");
-		if (tabName === "grab") loadInitialLogs();
+		if (tabName === "grab") {
+			loadInitialLogs();
+			loadTaskList();
+			loadScheduledJobs();
+		}
 		else if (tabName === "projects") loadProjects();
 		else if (tabName === "settings") loadSettings();
 		else if (tabName === "accounts") reloadAccounts();
@@ -1438,7 +1878,9 @@ async function init() {
   updateUptime();
   await updateSystemInfo();
   await checkPolicy();
+  await unlockVaultIfNeeded();
   await loadAccounts();
+  await loadUnfinishedTasks();
   await loadSettings();
   await initLogs();
   await loadCountryList();
@@ -1453,6 +1895,26 @@ async function init() {
   }, 5000);
 }
 
+// 配置文件用口令加密时，启动后先解锁再加载账号
+async function unlockVaultIfNeeded() {
+    try {
+        if (!invoke) return;
+        const status = await invoke("get_vault_status");
+        while (status.encrypted && !status.key_available) {
+            const passphrase = prompt("配置文件已加密，请输入口令：");
+            if (passphrase === null) return;
+            try {
+                await invoke("unlock_vault", { passphrase });
+                return;
+            } catch (error) {
+                alert("解锁失败: " + error);
+            }
+        }
+    } catch (error) {
+        console.error("检查配置加密状态失败:", error);
+    }
+}
+
 async function checkPolicy() {
     try {
         if (!invoke) return;
@@ -1480,6 +1942,13 @@ let logStatsCounter = {
 	warn: 0,
 	error: 0
 };
+// 结构化日志，按文本行索引，用于按任务、阶段、分类过滤
+let logRecordIndex = new Map();
+let logFieldFilters = {
+	category: "",
+	task_id: "",
+	phase: ""
+};
 let autoScrollEnabled = true;
 let logFilters = {
 	info: true,
@@ -1497,6 +1966,28 @@ function getLogLevel(log) {
 	return "success";
 }
 
+function formatRecordLine(record) {
+	return `[${record.time}] ${record.level}: ${record.message}`;
+}
+
+function indexLogRecord(record) {
+	logRecordIndex.set(formatRecordLine(record), record);
+	if (logRecordIndex.size > 5000) {
+		logRecordIndex.delete(logRecordIndex.keys().next().value);
+	}
+}
+
+function matchesFieldFilters(log) {
+	const { category, task_id, phase } = logFieldFilters;
+	if (!category && !task_id && !phase) return true;
+	const record = logRecordIndex.get(log);
+	if (!record) return false;
+	if (category && record.category !== category) return false;
+	if (task_id && record.task_id !== task_id) return false;
+	if (phase && record.phase !== phase) return false;
+	return true;
+}
+
 function handleIncomingLog(log) {
 	if (!logSet.has(log)) {
 		logSet.add(log);
@@ -1541,6 +2032,7 @@ function appendLogEntry(log) {
 	else if (level === "warn" && logFilters.warn) visible = true;
 	else if (level === "error" && logFilters.error) visible = true;
 	else if (level === "success" && logFilters.success) visible = true;
+	if (visible && !matchesFieldFilters(log)) visible = false;
 
 	const searchTerm = document.getElementById("log-search")?.value.toLowerCase();
 	if (visible && searchTerm && !log.toLowerCase().includes(searchTerm)) {
@@ -1570,6 +2062,9 @@ async function loadInitialLogs() {
 	try {
 		if (!invoke) throw new Error("Tauri invoke function not available");
 		const logs = await invoke("get_logs");
+		const records = await invoke("get_log_records", { filter: {} });
+		logRecordIndex = new Map();
+		(records || []).forEach(indexLogRecord);
 		if (logs && logs.length > 0) {
 			allLogs = logs;
 			logSet = new Set(allLogs);
@@ -1597,6 +2092,7 @@ function updateLogsDisplay() {
 	const container = document.getElementById("grab-logs-container");
 	if (!container) return;
 	const filtered = allLogs.filter(log => {
+		if (!matchesFieldFilters(log)) return false;
 		const level = getLogLevel(log);
 		if (level === "info") return logFilters.info;
 		if (level === "debug") return logFilters.debug;
@@ -1667,6 +2163,20 @@ function toggleLogFilter(level) {
 	updateLogsDisplay();
 }
 
+async function exportTaskLog() {
+	const taskId = document.getElementById("log-task-filter")?.value.trim();
+	if (!taskId) {
+		showWarning("请先填写要导出的任务ID");
+		return;
+	}
+	try {
+		const path = await invoke("export_task_log", { taskId });
+		showSuccess("任务日志已导出到 " + path);
+	} catch (error) {
+		showError("导出失败: " + error);
+	}
+}
+
 function toggleAutoScroll() {
 	autoScrollEnabled = !autoScrollEnabled;
 	const btn = document.getElementById("auto-scroll-btn");
@@ -1677,9 +2187,23 @@ function setupLogsEventListeners() {
 	document.getElementById("refresh-grab-logs-btn")?.addEventListener("click", loadInitialLogs);
 	document.getElementById("clear-grab-logs-btn")?.addEventListener("click", clearAllLogs);
 	document.getElementById("auto-scroll-btn")?.addEventListener("click", toggleAutoScroll);
+	document.getElementById("export-task-log-btn")?.addEventListener("click", exportTaskLog);
+	document.getElementById("refresh-tasks-btn")?.addEventListener("click", loadTaskList);
+	document.getElementById("refresh-schedule-btn")?.addEventListener("click", loadScheduledJobs);
+	document.getElementById("add-schedule-btn")?.addEventListener("click", addScheduledJob);
+	document.getElementById("schedule-type")?.addEventListener("change", function() {
+		document.getElementById("schedule-minutes").classList.toggle("display-none", this.value === "at");
+		document.getElementById("schedule-at").classList.toggle("display-none", this.value !== "at");
+	});
 	["info", "debug", "warn", "error", "success"].forEach(lv => {
 		document.getElementById(`filter-${lv}-btn`)?.addEventListener("click", () => toggleLogFilter(lv));
 	});
+	[["log-category-filter", "category"], ["log-task-filter", "task_id"], ["log-phase-filter", "phase"]].forEach(([id, field]) => {
+		document.getElementById(id)?.addEventListener("change", function() {
+			logFieldFilters[field] = this.value.trim();
+			updateLogsDisplay();
+		});
+	});
 	document.getElementById("log-search")?.addEventListener("input", function() {
 		const term = this.value.toLowerCase();
 		document.querySelectorAll(".log-entry").forEach(el => el.style.display = el.textContent.toLowerCase().includes(term) ? "" : "none");
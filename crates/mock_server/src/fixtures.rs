@@ -0,0 +1,222 @@
+use serde_json::{Value, json};
+
+// 假数据使用的固定 ID
+pub const PROJECT_ID: i64 = 1001;
+pub const SCREEN_ID: i64 = 2001;
+pub const TICKET_ID: i64 = 3001;
+pub const BUYER_ID: i64 = 4001;
+pub const ORDER_ID: i64 = 5001;
+pub const UID: i64 = 10001;
+pub const PAY_TOKEN: &str = "mock_pay_token";
+
+pub fn now_secs() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+// project/getV2，clickable 控制场次与票种是否可售
+pub fn project(clickable: bool, sale_begin: i64, hot_project: bool) -> Value {
+    let sale_flag = if clickable {
+        json!({ "number": 2, "display_name": "预售中" })
+    } else {
+        json!({ "number": 4, "display_name": "已售罄" })
+    };
+    let ticket = json!({
+        "saleStart": sale_begin,
+        "saleEnd": sale_begin + 86400,
+        "id": TICKET_ID,
+        "project_id": PROJECT_ID,
+        "price": 10000,
+        "desc": "普通票",
+        "sale_start": "",
+        "sale_end": "",
+        "type": 1,
+        "sale_type": 1,
+        "is_sale": 0,
+        "num": 1,
+        "sale_flag": sale_flag,
+        "clickable": clickable,
+        "sale_flag_number": sale_flag["number"],
+        "screen_name": "模拟场次",
+    });
+    let screen = json!({
+        "sale_flag": sale_flag,
+        "id": SCREEN_ID,
+        "start_time": sale_begin + 86400,
+        "name": "模拟场次",
+        "ticket_type": 1,
+        "screen_type": 1,
+        "delivery_type": 1,
+        "pick_seat": 0,
+        "ticket_list": [ticket],
+        "clickable": clickable,
+        "sale_end": sale_begin + 86400,
+        "sale_start": sale_begin,
+        "sale_flag_number": sale_flag["number"],
+        "show_date": "",
+    });
+    json!({
+        "errno": 0,
+        "errtag": 0,
+        "msg": "",
+        "data": {
+            "id": PROJECT_ID,
+            "name": "模拟演出",
+            "is_sale": 1,
+            "start_time": sale_begin + 86400,
+            "end_time": sale_begin + 90000,
+            "pick_seat": 0,
+            "project_type": 1,
+            "express_fee": 0,
+            "sale_begin": sale_begin,
+            "sale_end": sale_begin + 86400,
+            "count_down": 0,
+            "screen_list": [screen],
+            "sale_flag_number": sale_flag["number"],
+            "sale_flag": sale_flag["display_name"],
+            "is_free": false,
+            "performance_desc": null,
+            "id_bind": 1,
+            "hotProject": hot_project,
+        }
+    })
+}
+
+// order/prepare
+pub fn prepare_ok() -> Value {
+    json!({
+        "errno": 0,
+        "msg": "",
+        "data": { "token": "mock_token", "ptoken": "mock_ptoken" }
+    })
+}
+
+pub fn prepare_err(code: i64) -> Value {
+    json!({ "errno": code, "msg": format!("模拟错误 {}", code), "data": {} })
+}
+
+// order/prepare 触发风控
+pub fn prepare_risk() -> Value {
+    json!({
+        "errno": -401,
+        "msg": "需要验证",
+        "data": {
+            "ga_data": {
+                "riskParams": {
+                    "mid": UID.to_string(),
+                    "decision_type": "1",
+                    "buvid": "mock_buvid",
+                    "ip": "127.0.0.1",
+                    "scene": "neul_ticket",
+                    "ua": "mock",
+                    "v_voucher": "mock_voucher",
+                }
+            }
+        }
+    })
+}
+
+// gaia-vgate/v1/register，返回客户端不支持的验证类型，使风控处理失败
+pub fn risk_register_unsupported() -> Value {
+    json!({ "code": 0, "message": "0", "data": { "type": "phone" } })
+}
+
+// order/confirmInfo
+pub fn confirm_ok() -> Value {
+    json!({
+        "errno": 0,
+        "msg": "",
+        "data": {
+            "count": 1,
+            "pay_money": 10000,
+            "project_name": "模拟演出",
+            "screen_name": "模拟场次",
+            "ticket_info": { "name": "普通票", "count": 1, "price": 10000 }
+        }
+    })
+}
+
+pub fn confirm_err(code: i64) -> Value {
+    json!({ "errno": code, "msg": format!("模拟错误 {}", code), "data": {} })
+}
+
+// order/createV2
+pub fn create_ok() -> Value {
+    json!({
+        "errno": 0,
+        "msg": "",
+        "data": { "orderId": ORDER_ID, "orderCreateTime": now_secs(), "token": PAY_TOKEN }
+    })
+}
+
+pub fn create_err(code: i64) -> Value {
+    json!({ "errno": code, "msg": format!("模拟错误 {}", code), "data": {} })
+}
+
+// order/createstatus
+pub fn create_status_ok() -> Value {
+    json!({
+        "errno": 0,
+        "msg": "",
+        "data": {
+            "payParam": { "sign": "mock_sign", "code_url": "https://example.invalid/pay" }
+        }
+    })
+}
+
+// buyer/list
+pub fn buyer_list() -> Value {
+    json!({
+        "errno": 0,
+        "msg": "",
+        "data": {
+            "list": [{
+                "id": BUYER_ID,
+                "uid": UID,
+                "personal_id": "110101199001011234",
+                "name": "测试购票人",
+                "tel": "13800000000",
+                "id_type": 0,
+                "is_default": 1,
+            }]
+        }
+    })
+}
+
+// ordercenter/ticketList
+pub fn order_list() -> Value {
+    json!({
+        "errno": 0,
+        "errtag": 0,
+        "msg": "",
+        "data": {
+            "total": 1,
+            "list": [{
+                "order_id": ORDER_ID.to_string(),
+                "order_type": 1,
+                "item_id": PROJECT_ID,
+                "item_info": {
+                    "name": "模拟演出",
+                    "image": null,
+                    "screen_id": SCREEN_ID.to_string(),
+                    "screen_name": "模拟场次",
+                    "screen_start_time": "",
+                    "screen_end_time": "",
+                },
+                "total_money": 10000,
+                "count": 1,
+                "pay_money": 10000,
+                "pay_channel": null,
+                "status": 1,
+                "sub_status": 1,
+                "ctime": "",
+                "img": { "url": "" },
+                "sub_status_name": "待支付",
+            }]
+        }
+    })
+}
+
+// click/now
+pub fn now() -> Value {
+    json!({ "code": 0, "message": "0", "data": { "now": now_secs() } })
+}
@@ -0,0 +1,140 @@
+pub mod fixtures;
+pub mod scenario;
+pub mod smtp;
+pub mod webhook;
+
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use axum::Router;
+use axum::extract::State;
+use axum::http::{StatusCode, Uri};
+use axum::response::{IntoResponse, Json};
+use common::endpoints::ApiEndpoints;
+use tokio::sync::oneshot;
+
+pub use scenario::{Scenario, Script};
+pub use smtp::{ReceivedMail, SmtpSink};
+pub use webhook::{ReceivedRequest, WebhookSink};
+
+// 一次请求记录
+#[derive(Clone, Debug)]
+pub struct Hit {
+    pub path: String,
+    pub query: String,
+    pub body: String,
+    pub at: SystemTime,
+}
+
+struct MockState {
+    scenario: Mutex<Scenario>,
+    hits: Mutex<Vec<Hit>>,
+}
+
+// 本地模拟 B站接口服务，drop 时自动关闭
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<MockState>,
+    shutdown_tx: Option<oneshot::Sender<()>>,
+}
+
+impl MockServer {
+    // 监听随机端口
+    pub async fn start(scenario: Scenario) -> std::io::Result<Self> {
+        Self::bind("127.0.0.1:0", scenario).await
+    }
+
+    pub async fn bind(addr: &str, scenario: Scenario) -> std::io::Result<Self> {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(MockState {
+            scenario: Mutex::new(scenario),
+            hits: Mutex::new(Vec::new()),
+        });
+        let app = Router::new()
+            .fallback(handle_request)
+            .with_state(state.clone());
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let result = axum::serve(listener, app)
+                .with_graceful_shutdown(async {
+                    let _ = shutdown_rx.await;
+                })
+                .await;
+            if let Err(e) = result {
+                log::error!("模拟服务异常退出: {}", e);
+            }
+        });
+        log::info!("模拟服务已启动: http://{}", addr);
+
+        Ok(Self {
+            addr,
+            state,
+            shutdown_tx: Some(shutdown_tx),
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    // 全部接口指向本服务
+    pub fn endpoints(&self) -> ApiEndpoints {
+        ApiEndpoints::with_base(&self.base_url())
+    }
+
+    // 运行中替换场景
+    pub fn set_scenario(&self, scenario: Scenario) {
+        *self.state.scenario.lock().unwrap() = scenario;
+    }
+
+    pub fn hits(&self) -> Vec<Hit> {
+        self.state.hits.lock().unwrap().clone()
+    }
+
+    pub fn hits_for(&self, path: &str) -> Vec<Hit> {
+        self.hits().into_iter().filter(|h| h.path == path).collect()
+    }
+
+    pub fn hit_count(&self, path: &str) -> usize {
+        self.hits_for(path).len()
+    }
+
+    pub fn shutdown(&mut self) {
+        if let Some(tx) = self.shutdown_tx.take() {
+            let _ = tx.send(());
+        }
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+async fn handle_request(
+    State(state): State<Arc<MockState>>,
+    uri: Uri,
+    body: String,
+) -> impl IntoResponse {
+    let path = uri.path().to_string();
+    log::debug!("模拟服务收到请求: {}", uri);
+    state.hits.lock().unwrap().push(Hit {
+        path: path.clone(),
+        query: uri.query().unwrap_or("").to_string(),
+        body,
+        at: SystemTime::now(),
+    });
+
+    match state.scenario.lock().unwrap().respond(&path) {
+        Some(value) => (StatusCode::OK, Json(value)).into_response(),
+        None => (StatusCode::NOT_FOUND, format!("未模拟的接口: {}", path)).into_response(),
+    }
+}
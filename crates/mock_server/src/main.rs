@@ -0,0 +1,35 @@
+use mock_server::{MockServer, Scenario};
+
+// 用法: mock_server [场景名] [监听地址]
+// 配合配置文件中的 api_endpoints 使用，可让图形界面或 cli 离线走完整抢票流程
+#[tokio::main]
+async fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let mut args = std::env::args().skip(1);
+    let name = args.next().unwrap_or_else(|| "on_sale".to_string());
+    let addr = args.next().unwrap_or_else(|| "127.0.0.1:8080".to_string());
+
+    let scenario = match Scenario::by_name(&name) {
+        Some(scenario) => scenario,
+        None => {
+            eprintln!("未知场景: {}，可选: {}", name, Scenario::NAMES.join(", "));
+            std::process::exit(1);
+        }
+    };
+
+    let server = match MockServer::bind(&addr, scenario).await {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("启动模拟服务失败: {}", e);
+            std::process::exit(1);
+        }
+    };
+    println!("模拟服务已启动: {} (场景: {})", server.base_url(), name);
+    println!(
+        "在 config 中设置 \"api_endpoints\" 的 show/api/passport/www 为 {} 即可使用",
+        server.base_url()
+    );
+
+    let _ = tokio::signal::ctrl_c().await;
+}
@@ -0,0 +1,152 @@
+use std::collections::VecDeque;
+
+use serde_json::Value;
+
+use crate::fixtures;
+
+// 单个接口的响应脚本：按顺序返回，最后一个响应重复使用
+#[derive(Clone, Debug)]
+pub struct Script {
+    responses: VecDeque<Value>,
+}
+
+impl Script {
+    pub fn new(responses: Vec<Value>) -> Self {
+        Self {
+            responses: responses.into(),
+        }
+    }
+
+    pub fn once(response: Value) -> Self {
+        Self::new(vec![response])
+    }
+
+    // 前 times 次返回 first，之后一直返回 then
+    pub fn repeat_then(first: Value, times: usize, then: Value) -> Self {
+        let mut responses = vec![first; times];
+        responses.push(then);
+        Self::new(responses)
+    }
+
+    pub fn next_response(&mut self) -> Value {
+        if self.responses.len() > 1 {
+            self.responses.pop_front().unwrap_or_default()
+        } else {
+            self.responses.front().cloned().unwrap_or_default()
+        }
+    }
+}
+
+// 一次模拟场景，包含各接口的响应脚本
+#[derive(Clone, Debug)]
+pub struct Scenario {
+    pub name: String,
+    pub project: Script,
+    pub prepare: Script,
+    pub confirm: Script,
+    pub create: Script,
+    pub create_status: Script,
+    pub risk_register: Script,
+    pub buyers: Script,
+    pub orders: Script,
+}
+
+impl Default for Scenario {
+    // 正常开售，一次下单成功
+    fn default() -> Self {
+        Self {
+            name: "on_sale".to_string(),
+            project: Script::once(fixtures::project(true, fixtures::now_secs() - 60, false)),
+            prepare: Script::once(fixtures::prepare_ok()),
+            confirm: Script::once(fixtures::confirm_ok()),
+            create: Script::once(fixtures::create_ok()),
+            create_status: Script::once(fixtures::create_status_ok()),
+            risk_register: Script::once(fixtures::risk_register_unsupported()),
+            buyers: Script::once(fixtures::buyer_list()),
+            orders: Script::once(fixtures::order_list()),
+        }
+    }
+}
+
+impl Scenario {
+    pub const NAMES: [&'static str; 5] = [
+        "on_sale",
+        "sold_out_then_restock",
+        "stock_shortage_then_success",
+        "risk_then_success",
+        "sale_starts_in_5s",
+    ];
+
+    pub fn on_sale() -> Self {
+        Self::default()
+    }
+
+    // 第一次查询项目时全部售罄，之后回流
+    pub fn sold_out_then_restock() -> Self {
+        let sale_begin = fixtures::now_secs() - 60;
+        Self {
+            name: "sold_out_then_restock".to_string(),
+            project: Script::repeat_then(
+                fixtures::project(false, sale_begin, false),
+                1,
+                fixtures::project(true, sale_begin, false),
+            ),
+            ..Self::default()
+        }
+    }
+
+    // 下单接口先返回 times 次 100009（库存不足），随后成功
+    pub fn stock_shortage_then_success(times: usize) -> Self {
+        Self {
+            name: "stock_shortage_then_success".to_string(),
+            create: Script::repeat_then(fixtures::create_err(100009), times, fixtures::create_ok()),
+            ..Self::default()
+        }
+    }
+
+    // 获取 token 时先触发一次 -401 风控，之后正常
+    pub fn risk_then_success() -> Self {
+        Self {
+            name: "risk_then_success".to_string(),
+            prepare: Script::repeat_then(fixtures::prepare_risk(), 1, fixtures::prepare_ok()),
+            ..Self::default()
+        }
+    }
+
+    // secs 秒后开售
+    pub fn sale_starts_in(secs: i64) -> Self {
+        Self {
+            name: format!("sale_starts_in_{}s", secs),
+            project: Script::once(fixtures::project(true, fixtures::now_secs() + secs, false)),
+            ..Self::default()
+        }
+    }
+
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "on_sale" => Some(Self::on_sale()),
+            "sold_out_then_restock" => Some(Self::sold_out_then_restock()),
+            "stock_shortage_then_success" => Some(Self::stock_shortage_then_success(3)),
+            "risk_then_success" => Some(Self::risk_then_success()),
+            "sale_starts_in_5s" => Some(Self::sale_starts_in(5)),
+            _ => None,
+        }
+    }
+
+    // 按请求路径取下一个响应，未知路径返回 None
+    pub fn respond(&mut self, path: &str) -> Option<Value> {
+        let script = match path {
+            "/api/ticket/project/getV2" => &mut self.project,
+            "/api/ticket/order/prepare" => &mut self.prepare,
+            "/api/ticket/order/confirmInfo" => &mut self.confirm,
+            "/api/ticket/order/createV2" => &mut self.create,
+            "/api/ticket/order/createstatus" => &mut self.create_status,
+            "/api/ticket/buyer/list" => &mut self.buyers,
+            "/api/ticket/ordercenter/ticketList" => &mut self.orders,
+            "/x/gaia-vgate/v1/register" => &mut self.risk_register,
+            "/x/click-interface/click/now" => return Some(fixtures::now()),
+            _ => return None,
+        };
+        Some(script.next_response())
+    }
+}
@@ -0,0 +1,123 @@
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+// 收到的一封邮件
+#[derive(Clone, Debug, Default)]
+pub struct ReceivedMail {
+    pub auth: Option<String>, //AUTH PLAIN 的 base64 凭据
+    pub from: String,
+    pub to: Vec<String>,
+    pub data: String, //DATA 段原文，不含结尾的 "."
+}
+
+// 本地 SMTP 收件服务，只支持明文连接，收到的邮件保存在内存中，drop 时关闭
+pub struct SmtpSink {
+    addr: SocketAddr,
+    mails: Arc<Mutex<Vec<ReceivedMail>>>,
+    handle: JoinHandle<()>,
+}
+
+impl SmtpSink {
+    // 监听随机端口
+    pub async fn start() -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let mails = Arc::new(Mutex::new(Vec::new()));
+        let sink_mails = mails.clone();
+        let handle = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let mails = sink_mails.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_session(stream, mails).await {
+                        log::warn!("SMTP 会话异常结束: {}", e);
+                    }
+                });
+            }
+        });
+        log::info!("模拟 SMTP 服务已启动: {}", addr);
+        Ok(Self {
+            addr,
+            mails,
+            handle,
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn mails(&self) -> Vec<ReceivedMail> {
+        self.mails.lock().unwrap().clone()
+    }
+}
+
+impl Drop for SmtpSink {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+async fn handle_session(
+    stream: TcpStream,
+    mails: Arc<Mutex<Vec<ReceivedMail>>>,
+) -> std::io::Result<()> {
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+    let mut mail = ReceivedMail::default();
+    let mut auth = None;
+
+    write.write_all(b"220 mock smtp ready\r\n").await?;
+    while let Some(line) = lines.next_line().await? {
+        let upper = line.to_ascii_uppercase();
+        let reply: &[u8] = if upper.starts_with("EHLO") {
+            b"250-mock\r\n250-AUTH PLAIN\r\n250 8BITMIME\r\n"
+        } else if upper.starts_with("HELO") {
+            b"250 mock\r\n"
+        } else if upper.starts_with("AUTH PLAIN") {
+            auth = line.get(11..).map(|s| s.trim().to_string());
+            b"235 authenticated\r\n"
+        } else if upper.starts_with("MAIL FROM:") {
+            mail = ReceivedMail {
+                auth: auth.clone(),
+                from: address(&line[10..]),
+                ..ReceivedMail::default()
+            };
+            b"250 ok\r\n"
+        } else if upper.starts_with("RCPT TO:") {
+            mail.to.push(address(&line[8..]));
+            b"250 ok\r\n"
+        } else if upper == "DATA" {
+            write.write_all(b"354 end with .\r\n").await?;
+            let mut data = Vec::new();
+            while let Some(line) = lines.next_line().await? {
+                if line == "." {
+                    break;
+                }
+                // 去掉客户端为以 "." 开头的行添加的点
+                data.push(line.strip_prefix('.').unwrap_or(&line).to_string());
+            }
+            mail.data = data.join("\r\n");
+            mails.lock().unwrap().push(std::mem::take(&mut mail));
+            b"250 queued\r\n"
+        } else if upper == "QUIT" {
+            write.write_all(b"221 bye\r\n").await?;
+            return Ok(());
+        } else {
+            // RSET、NOOP 等
+            b"250 ok\r\n"
+        };
+        write.write_all(reply).await?;
+    }
+    Ok(())
+}
+
+// "<a@b.c> SIZE=123" → "a@b.c"
+fn address(arg: &str) -> String {
+    let arg = arg.trim();
+    let end = arg.find('>').unwrap_or(arg.len());
+    arg[..end].trim_start_matches('<').to_string()
+}
@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use axum::Router;
+use axum::extract::State;
+use axum::http::{HeaderMap, Method, StatusCode, Uri};
+use tokio::task::JoinHandle;
+
+// 收到的一次 Webhook 请求
+#[derive(Clone, Debug, Default)]
+pub struct ReceivedRequest {
+    pub method: String,
+    pub path: String,
+    pub query: String,
+    pub headers: BTreeMap<String, String>, //名称为小写
+    pub body: String,
+}
+
+struct SinkState {
+    status: StatusCode,
+    requests: Mutex<Vec<ReceivedRequest>>,
+}
+
+// 本地 HTTP 收件服务，任意路径都返回固定状态码，请求保存在内存中，drop 时关闭
+pub struct WebhookSink {
+    addr: SocketAddr,
+    state: Arc<SinkState>,
+    handle: JoinHandle<()>,
+}
+
+impl WebhookSink {
+    // 监听随机端口，返回 200
+    pub async fn start() -> std::io::Result<Self> {
+        Self::with_status(200).await
+    }
+
+    pub async fn with_status(status: u16) -> std::io::Result<Self> {
+        let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(SinkState {
+            status,
+            requests: Mutex::new(Vec::new()),
+        });
+        let app = Router::new()
+            .fallback(handle_request)
+            .with_state(state.clone());
+        let handle = tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                log::error!("模拟 Webhook 服务异常退出: {}", e);
+            }
+        });
+        log::info!("模拟 Webhook 服务已启动: http://{}", addr);
+        Ok(Self {
+            addr,
+            state,
+            handle,
+        })
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    pub fn requests(&self) -> Vec<ReceivedRequest> {
+        self.state.requests.lock().unwrap().clone()
+    }
+}
+
+impl Drop for WebhookSink {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+async fn handle_request(
+    State(state): State<Arc<SinkState>>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: String,
+) -> StatusCode {
+    state.requests.lock().unwrap().push(ReceivedRequest {
+        method: method.to_string(),
+        path: uri.path().to_string(),
+        query: uri.query().unwrap_or("").to_string(),
+        headers: headers
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
+            .collect(),
+        body,
+    });
+    state.status
+}